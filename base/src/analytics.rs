@@ -0,0 +1,224 @@
+use crate::day::Day;
+use crate::report::Period;
+use crate::task::{State as TaskState, Task};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use time::Date;
+
+/// The number of tasks in each state. Tasks with subtasks are counted by
+/// their subtasks. Waiting tasks are someone else's work, they're left out
+/// of the total and the completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateCounts {
+    pub completed: usize,
+    pub in_progress: usize,
+    pub blocked: usize,
+    pub incomplete: usize,
+    pub waiting: usize,
+}
+
+impl StateCounts {
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        let mut counts = Self::default();
+        for task in tasks {
+            match task.subtasks.is_empty() {
+                true => counts.add(&task.state),
+                false => task
+                    .subtasks
+                    .iter()
+                    .for_each(|subtask| counts.add(&subtask.state)),
+            }
+        }
+        counts
+    }
+
+    fn add(&mut self, state: &TaskState) {
+        match state {
+            TaskState::Completed => self.completed += 1,
+            TaskState::InProgress => self.in_progress += 1,
+            TaskState::Blocked => self.blocked += 1,
+            TaskState::Incomplete => self.incomplete += 1,
+            TaskState::Waiting => self.waiting += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &StateCounts) {
+        self.completed += other.completed;
+        self.in_progress += other.in_progress;
+        self.blocked += other.blocked;
+        self.incomplete += other.incomplete;
+        self.waiting += other.waiting;
+    }
+
+    pub fn total(&self) -> usize {
+        self.completed + self.in_progress + self.blocked + self.incomplete
+    }
+
+    /// The percentage of completed tasks, `None` without tasks.
+    pub fn completion(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            total => Some(self.completed as f64 * 100.0 / total as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayStats {
+    pub date: Date,
+    pub counts: StateCounts,
+}
+
+/// The counts of the days in the week added up, so a task carried over
+/// for three days counts three times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekStats {
+    pub period: Period,
+    pub counts: StateCounts,
+}
+
+/// A task from the first day it shows up until the day it was completed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskLifetime {
+    pub name: String,
+    pub created: Date,
+    pub completed: Date,
+}
+
+impl TaskLifetime {
+    pub fn days(&self) -> i64 {
+        (self.completed - self.created).whole_days()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Analytics {
+    pub days: Vec<DayStats>,
+    pub weeks: Vec<WeekStats>,
+    pub lifetimes: Vec<TaskLifetime>,
+    /// Tasks that haven't been completed yet, with the day they showed up.
+    pub open: Vec<(String, Date)>,
+}
+
+impl Analytics {
+    /// The days are expected in order, like in the workspace.
+    pub fn new(days: &[Day]) -> Self {
+        let mut analytics = Self::default();
+
+        for day in days {
+            let counts = StateCounts::from_tasks(&day.tasks);
+            analytics.days.push(DayStats {
+                date: day.date,
+                counts,
+            });
+
+            let period = Period::week(day.date);
+            match analytics.weeks.last_mut() {
+                Some(week) if week.period == period => week.counts.merge(&counts),
+                _ => analytics.weeks.push(WeekStats { period, counts }),
+            }
+
+            // Carried over tasks keep their name, which ties the days together.
+            for task in &day.tasks {
+                let open = analytics
+                    .open
+                    .iter()
+                    .position(|(name, _)| name == &task.name);
+                match (open, task.state == TaskState::Completed) {
+                    (Some(index), true) => {
+                        let (name, created) = analytics.open.remove(index);
+                        analytics.lifetimes.push(TaskLifetime {
+                            name,
+                            created,
+                            completed: day.date,
+                        });
+                    }
+                    (None, true) => analytics.lifetimes.push(TaskLifetime {
+                        name: task.name.clone(),
+                        created: day.date,
+                        completed: day.date,
+                    }),
+                    (None, false) => analytics.open.push((task.name.clone(), day.date)),
+                    (Some(_), false) => {}
+                }
+            }
+        }
+
+        analytics
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_workspace(workspace: &Workspace) -> Result<Self, crate::Error> {
+        let days = workspace.read_days(|_| true)?;
+        Ok(Self::new(&days))
+    }
+
+    /// The counts of all days added up.
+    pub fn totals(&self) -> StateCounts {
+        let mut totals = StateCounts::default();
+        self.days.iter().for_each(|day| totals.merge(&day.counts));
+        totals
+    }
+
+    /// The average number of days from a task showing up to its completion.
+    pub fn average_lifetime(&self) -> Option<f64> {
+        match self.lifetimes.len() {
+            0 => None,
+            count => {
+                let days: i64 = self.lifetimes.iter().map(TaskLifetime::days).sum();
+                Some(days as f64 / count as f64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+    use crate::tests::helpers::day;
+
+    #[test]
+    fn test_analytics() {
+        let days = vec![
+            day(
+                "2024-07-05",
+                "* [x] Standup\n* [ ] Deploy\n* [ ] Release\n  * [x] Changelog\n  * [#] Tag\n\
+                 * [>] Budget >@bob\n",
+            ),
+            day("2024-07-08", "* [x] Deploy\n* [~] Release\n"),
+        ];
+        let analytics = Analytics::new(&days);
+
+        assert_eq!(
+            analytics.days[0].counts,
+            StateCounts {
+                completed: 2,
+                in_progress: 0,
+                blocked: 1,
+                incomplete: 1,
+                waiting: 1,
+            }
+        );
+        // Waiting on someone else isn't part of the completion.
+        assert_eq!(analytics.days[0].counts.completion(), Some(50.0));
+        assert_eq!(analytics.weeks.len(), 2);
+        assert_eq!(analytics.weeks[1].period.name, "2024-W28");
+        assert_eq!(analytics.totals().total(), 6);
+
+        assert_eq!(analytics.lifetimes.len(), 2);
+        assert_eq!(analytics.lifetimes[1].name, "Deploy");
+        assert_eq!(analytics.lifetimes[1].days(), 3);
+        assert_eq!(analytics.average_lifetime(), Some(1.5));
+        assert_eq!(
+            analytics.open,
+            vec![
+                ("Release".to_string(), parse_date("2024-07-05").unwrap()),
+                (
+                    "Budget >@bob".to_string(),
+                    parse_date("2024-07-05").unwrap()
+                )
+            ]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Backups of the workspace, the config and the state for `w0rk backup`, as
+//! a `.tar.zst` archive with a directory per source.
+use crate::day::{date_from_path, is_locked};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use time::OffsetDateTime;
+
+const BACKUP_PREFIX: &str = "w0rk-";
+const BACKUP_EXTENSION: &str = ".tar.zst";
+
+type Archive = tar::Archive<zstd::Decoder<'static, BufReader<File>>>;
+
+/// The file name of a backup made at the time, like
+/// `w0rk-20240701-153000.tar.zst`. They sort by time.
+pub fn backup_name(now: OffsetDateTime) -> String {
+    format!(
+        "{}{:04}{:02}{:02}-{:02}{:02}{:02}{}",
+        BACKUP_PREFIX,
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        BACKUP_EXTENSION
+    )
+}
+
+/// Writes a backup to the directory with each source under its name, like
+/// `work/2024-07-01.md` for `("work", work_dir)`. A source can be a file or
+/// a directory. The backup directory itself is left out, so it can be inside
+/// one of the sources.
+pub fn create(
+    dir: &Path,
+    sources: &[(&str, &Path)],
+    now: OffsetDateTime,
+) -> Result<PathBuf, crate::Error> {
+    std::fs::create_dir_all(dir).map_err(crate::Error::in_file(dir))?;
+    // Compared to the canonical paths of the directories in the sources, a
+    // relative or symlinked backup directory is still left out.
+    let skip = dir.canonicalize().map_err(crate::Error::in_file(dir))?;
+    let path = dir.join(backup_name(now));
+    let file = File::create(&path).map_err(crate::Error::in_file(&path))?;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+
+    for (name, source) in sources {
+        if source.is_dir() {
+            append_dir(&mut archive, Path::new(name), source, &skip)?;
+        } else if let (true, Some(file_name)) = (source.is_file(), source.file_name()) {
+            archive
+                .append_path_with_name(source, Path::new(name).join(file_name))
+                .map_err(crate::Error::in_file(source))?;
+        }
+    }
+    archive.into_inner()?.finish()?;
+    Ok(path)
+}
+
+fn append_dir<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    name: &Path,
+    dir: &Path,
+    skip: &Path,
+) -> Result<(), crate::Error> {
+    for entry in std::fs::read_dir(dir).map_err(crate::Error::in_file(dir))? {
+        let path = entry?.path();
+        let name = name.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            if path.canonicalize().is_ok_and(|path| path == skip) {
+                continue;
+            }
+            append_dir(archive, &name, &path, skip)?;
+        } else if path.is_file() {
+            archive
+                .append_path_with_name(&path, &name)
+                .map_err(crate::Error::in_file(&path))?;
+        }
+    }
+    Ok(())
+}
+
+/// The backups in the directory, oldest first.
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>, crate::Error> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(crate::Error::in_file(dir))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION)
+                })
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+/// Removes all but the newest `keep` backups in the directory, returning the
+/// removed ones.
+pub fn prune(dir: &Path, keep: usize) -> Result<Vec<PathBuf>, crate::Error> {
+    let backups = list(dir)?;
+    let removed = backups[..backups.len().saturating_sub(keep)].to_vec();
+    for path in &removed {
+        std::fs::remove_file(path).map_err(crate::Error::in_file(path))?;
+    }
+    Ok(removed)
+}
+
+/// Unpacks the files under `name` in the backup to the directory, the other
+/// way around from `create`. Existing files are only overwritten with
+/// `force`, without it nothing is written when one of them exists. Returns
+/// the restored files. Each file is written to a temporary file first and
+/// renamed over the existing one, like the day files. Existing days locked
+/// by `lock_after` are left as they are, see `is_locked`.
+pub fn restore(
+    backup: &Path,
+    name: &str,
+    to: &Path,
+    force: bool,
+    lock_after: Option<u32>,
+) -> Result<Vec<PathBuf>, crate::Error> {
+    if !force {
+        let mut archive = open(backup)?;
+        for entry in archive.entries()? {
+            if let Some(path) = target(&entry?, name, to)? {
+                if path.exists() {
+                    return Err(crate::Error::AlreadyExists(path));
+                }
+            }
+        }
+    }
+
+    let mut restored = Vec::new();
+    let mut archive = open(backup)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if let Some(path) = target(&entry, name, to)? {
+            let locked = date_from_path(&path).is_ok_and(|date| is_locked(&date, lock_after));
+            if locked && path.exists() {
+                continue;
+            }
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(crate::Error::in_file(&path))?;
+            crate::day::write_day_file(&path, content)?;
+            restored.push(path);
+        }
+    }
+    Ok(restored)
+}
+
+fn open(backup: &Path) -> Result<Archive, crate::Error> {
+    let file = File::open(backup).map_err(crate::Error::in_file(backup))?;
+    Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+}
+
+/// Where a file under `name` in the backup goes, `None` for other entries.
+fn target<R: std::io::Read>(
+    entry: &tar::Entry<R>,
+    name: &str,
+    to: &Path,
+) -> Result<Option<PathBuf>, crate::Error> {
+    if !entry.header().entry_type().is_file() {
+        return Ok(None);
+    }
+    let path = entry.path()?;
+    let relative = match path.strip_prefix(name) {
+        Ok(relative) => relative,
+        Err(_) => return Ok(None),
+    };
+    // Don't let a crafted backup write outside of the directory.
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(crate::Error::InvalidBackup(format!(
+            "the path {} is outside of the directory",
+            path.display()
+        )));
+    }
+    Ok(Some(to.join(relative)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, hour: u8) -> OffsetDateTime {
+        crate::parse_date(date)
+            .unwrap()
+            .with_hms(hour, 0, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let dir = std::env::temp_dir().join("w0rk-test-backup");
+        let _ = std::fs::remove_dir_all(&dir);
+        let (work_dir, state_dir) = (dir.join("work"), dir.join("state"));
+        std::fs::create_dir_all(work_dir.join("reports")).unwrap();
+        std::fs::create_dir_all(&state_dir).unwrap();
+        std::fs::write(work_dir.join("2024-07-01.md"), "* [ ] Deploy\n").unwrap();
+        std::fs::write(work_dir.join("reports/2024-W27.md"), "# Week 27\n").unwrap();
+        std::fs::write(state_dir.join("slack.json"), "{}").unwrap();
+        std::fs::write(dir.join("config.toml"), "work_dir = \"work\"\n").unwrap();
+        // Not the canonical path of the backups in the state.
+        let backups = state_dir.join("../state/backups");
+        let sources = [
+            ("config", dir.join("config.toml")),
+            ("work", work_dir.clone()),
+            ("state", state_dir.clone()),
+        ];
+        let sources: Vec<(&str, &Path)> = sources
+            .iter()
+            .map(|(name, path)| (*name, path.as_path()))
+            .collect();
+
+        for hour in [9, 10, 11] {
+            create(&backups, &sources, at("2024-07-01", hour)).unwrap();
+        }
+        assert_eq!(prune(&backups, 2).unwrap().len(), 1);
+        let names: Vec<String> = list(&backups)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "w0rk-20240701-100000.tar.zst",
+                "w0rk-20240701-110000.tar.zst"
+            ]
+        );
+
+        let backup = list(&backups).unwrap().pop().unwrap();
+        let restored = dir.join("restored");
+        let mut files = restore(&backup, "work", &restored, false, None).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                restored.join("2024-07-01.md"),
+                restored.join("reports/2024-W27.md")
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(restored.join("2024-07-01.md")).unwrap(),
+            "* [ ] Deploy\n"
+        );
+        // The backups aren't in the backup of the state.
+        assert_eq!(
+            restore(&backup, "state", &dir.join("restored-state"), false, None).unwrap(),
+            vec![dir.join("restored-state/slack.json")]
+        );
+        assert!(matches!(
+            restore(&backup, "work", &restored, false, None),
+            Err(crate::Error::AlreadyExists(_))
+        ));
+        assert_eq!(
+            restore(&backup, "work", &restored, true, None)
+                .unwrap()
+                .len(),
+            2
+        );
+        // A locked day stays as it is.
+        assert_eq!(
+            restore(&backup, "work", &restored, true, Some(0)).unwrap(),
+            vec![restored.join("reports/2024-W27.md")]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! The tasks of a day on a board with a column per state, for `w0rk board`.
+//! Moving a task to another column changes its state.
+use crate::day::Day;
+use crate::task::State as TaskState;
+
+/// The columns of the board, left to right.
+pub const COLUMNS: [TaskState; 5] = [
+    TaskState::Incomplete,
+    TaskState::InProgress,
+    TaskState::Blocked,
+    TaskState::Waiting,
+    TaskState::Completed,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+pub struct Board {
+    pub day: Day,
+    column: usize,
+    row: usize,
+}
+
+impl Board {
+    /// A board with the first task of the first column with tasks selected.
+    pub fn new(day: Day) -> Self {
+        let mut board = Self {
+            day,
+            column: 0,
+            row: 0,
+        };
+        board.column = (0..COLUMNS.len())
+            .find(|column| !board.column_tasks(*column).is_empty())
+            .unwrap_or_default();
+        board
+    }
+
+    /// The indexes in `day.tasks` of the tasks in the column, in order.
+    pub fn column_tasks(&self, column: usize) -> Vec<usize> {
+        self.day
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| COLUMNS.get(column) == Some(&task.state))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The column and the row in it of the selection.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// The index in `day.tasks` of the selected task, if its column has any.
+    pub fn selected(&self) -> Option<usize> {
+        self.column_tasks(self.column).get(self.row).copied()
+    }
+
+    /// Moves the selection, staying on the same row when changing columns
+    /// if the other column has as many tasks.
+    pub fn select(&mut self, direction: Direction) {
+        match direction {
+            Direction::Up => self.row = self.row.saturating_sub(1),
+            Direction::Down => self.row += 1,
+            Direction::Left => self.column = self.column.saturating_sub(1),
+            Direction::Right => self.column = (self.column + 1).min(COLUMNS.len() - 1),
+        }
+        let len = self.column_tasks(self.column).len();
+        self.row = self.row.min(len.saturating_sub(1));
+    }
+
+    /// Moves the selected task to the column on the left or the right,
+    /// setting its state, and keeps it selected. Returns whether the day
+    /// changed.
+    pub fn move_selected(&mut self, direction: Direction) -> bool {
+        let column = match direction {
+            Direction::Left if self.column > 0 => self.column - 1,
+            Direction::Right if self.column + 1 < COLUMNS.len() => self.column + 1,
+            _ => return false,
+        };
+        let index = match self.selected() {
+            Some(index) => index,
+            None => return false,
+        };
+        self.day.tasks[index].set_state(COLUMNS[column].clone());
+        self.day.update_dependencies();
+        // An open task with an open dependency is blocked right away.
+        self.column = COLUMNS
+            .iter()
+            .position(|state| state == &self.day.tasks[index].state)
+            .unwrap_or(column);
+        self.row = self
+            .column_tasks(self.column)
+            .iter()
+            .position(|task| *task == index)
+            .unwrap_or_default();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn board() -> Board {
+        let content = "* [~] Review the PR\n\
+                       * [ ] Write the docs\n\
+                       * [ ] Plan the offsite\n  * [ ] Book the venue\n\
+                       * [#] Upgrade the database\n";
+        let (day, _) = Day::parse(Path::new("2024-06-03.md"), content).unwrap();
+        Board::new(day)
+    }
+
+    #[test]
+    fn test_select() {
+        let mut board = board();
+        assert_eq!(board.column_tasks(0), vec![1, 2]);
+        assert_eq!(board.selected(), Some(1));
+
+        board.select(Direction::Down);
+        board.select(Direction::Down);
+        assert_eq!(board.cursor(), (0, 1));
+        board.select(Direction::Right);
+        assert_eq!(board.cursor(), (1, 0));
+        assert_eq!(board.selected(), Some(0));
+        board.select(Direction::Right);
+        board.select(Direction::Right);
+        board.select(Direction::Right);
+        board.select(Direction::Right);
+        assert_eq!(board.cursor(), (4, 0));
+        assert_eq!(board.selected(), None);
+    }
+
+    #[test]
+    fn test_move_selected() {
+        let mut board = board();
+        board.select(Direction::Down);
+        assert!(!board.move_selected(Direction::Left));
+        assert!(board.move_selected(Direction::Right));
+        assert_eq!(board.day.tasks[2].state, TaskState::InProgress);
+        assert_eq!(board.column_tasks(1), vec![0, 2]);
+        assert_eq!(board.cursor(), (1, 1));
+        assert_eq!(board.selected(), Some(2));
+
+        assert!(board.move_selected(Direction::Right));
+        assert!(board.move_selected(Direction::Right));
+        assert_eq!(board.day.tasks[2].state, TaskState::Waiting);
+        assert!(board.move_selected(Direction::Right));
+        assert_eq!(board.day.tasks[2].state, TaskState::Completed);
+        assert!(!board.move_selected(Direction::Right));
+        assert!(board
+            .day
+            .to_content()
+            .contains("* [x] Plan the offsite\n  * [x] Book the venue"));
+    }
+}
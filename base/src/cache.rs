@@ -0,0 +1,135 @@
+use crate::day::Day;
+use crate::task::State as TaskState;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+pub(crate) const CACHE_FILE: &str = "days.json";
+
+/// Parsed days in the state dir, so commands that read the whole workspace
+/// only parse the files that changed since the last run.
+pub struct DayCache {
+    path: PathBuf,
+    /// The checkboxes the days are parsed with, see `CheckboxConfig`.
+    checkboxes: BTreeMap<String, TaskState>,
+    /// Read on the first day asked for, commands that don't read days don't
+    /// pay for it.
+    entries: OnceLock<Mutex<HashMap<PathBuf, Entry>>>,
+    changed: AtomicBool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    /// The version of w0rk that wrote the cache, another version starts over
+    /// as the parsing might have changed.
+    version: String,
+    /// The checkboxes it was parsed with, other ones start over too.
+    #[serde(default)]
+    checkboxes: BTreeMap<String, TaskState>,
+    days: HashMap<PathBuf, Entry>,
+}
+
+/// A day, valid as long as its file has the same modified time and size.
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    modified: SystemTime,
+    size: u64,
+    day: Day,
+}
+
+impl DayCache {
+    /// Opens the cache in the state dir for days parsed with the
+    /// checkboxes, it's read when the first day is asked for.
+    pub fn open(state_dir: &Path, checkboxes: BTreeMap<String, TaskState>) -> Self {
+        Self {
+            path: state_dir.join(CACHE_FILE),
+            checkboxes,
+            entries: OnceLock::new(),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// The cached days. A missing or unreadable cache is an empty one, it's
+    /// never worth failing a command over.
+    fn entries(&self) -> &Mutex<HashMap<PathBuf, Entry>> {
+        self.entries.get_or_init(|| {
+            let days = std::fs::read_to_string(&self.path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+                .filter(|cache| cache.version == env!("CARGO_PKG_VERSION"))
+                .filter(|cache| cache.checkboxes == self.checkboxes)
+                .map(|cache| cache.days)
+                .unwrap_or_default();
+            Mutex::new(days)
+        })
+    }
+
+    /// The day from the cache when the file didn't change, otherwise it's
+    /// parsed and cached.
+    pub fn day(&self, path: &Path) -> Result<Day, crate::Error> {
+        let metadata = std::fs::metadata(path)?;
+        let (modified, size) = (metadata.modified()?, metadata.len());
+        if let Some(entry) = self.entries().lock().unwrap().get(path) {
+            if entry.modified == modified && entry.size == size {
+                return Ok(entry.day.clone());
+            }
+        }
+
+        let (day, _) = Day::read(path, None, &self.checkboxes)?;
+        self.entries().lock().unwrap().insert(
+            path.to_owned(),
+            Entry {
+                modified,
+                size,
+                day: day.clone(),
+            },
+        );
+        self.changed.store(true, Ordering::Relaxed);
+        Ok(day)
+    }
+
+    /// Writes the cache when days were parsed, leaving out deleted files. It's
+    /// written next to the cache first and moved over it, so a command that
+    /// stops halfway doesn't leave a broken cache.
+    pub fn save(&self) -> Result<(), crate::Error> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mut days = self.entries().lock().unwrap().clone();
+        days.retain(|path, _| path.exists());
+        let cache = CacheFile {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            checkboxes: self.checkboxes.clone(),
+            days,
+        };
+        crate::day::write_day_file(&self.path, serde_json::to_string(&cache)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_cache() {
+        let dir = std::env::temp_dir().join("w0rk-test-day-cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("2024-07-01.md");
+        std::fs::write(&path, "* [ ] Deploy\n").unwrap();
+
+        let cache = DayCache::open(&dir, BTreeMap::new());
+        assert_eq!(cache.day(&path).unwrap().tasks[0].name, "Deploy");
+        cache.save().unwrap();
+
+        let cache = DayCache::open(&dir, BTreeMap::new());
+        assert!(cache.entries.get().is_none());
+        assert!(cache.entries().lock().unwrap().contains_key(&path));
+        std::fs::write(&path, "* [ ] Deploy production\n").unwrap();
+        assert_eq!(cache.day(&path).unwrap().tasks[0].name, "Deploy production");
+        assert!(cache.changed.load(Ordering::Relaxed));
+    }
+}
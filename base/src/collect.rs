@@ -0,0 +1,119 @@
+use crate::day::Day;
+use crate::task::{State as TaskState, Task};
+use crate::workspace::Workspace;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    static ref OPEN_ITEM_REGEX: Regex = Regex::new(r"^\s*[-*+]\s\[ \]\s+(?<text>.+?)\s*$").unwrap();
+}
+
+/// An open `- [ ]` item found in a markdown file outside the workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collected {
+    pub path: PathBuf,
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub text: String,
+}
+
+impl Collected {
+    /// The task for the day file, with a `@from:<path>` backlink. Without
+    /// the line, which changes with every edit above the item.
+    pub fn task(&self) -> Task {
+        Task {
+            name: format!("{} @from:{}", self.text, self.path.display()),
+            state: TaskState::Incomplete,
+            subtasks: Vec::new(),
+        }
+    }
+}
+
+/// The open items in the files matching the glob pattern, leaving out the
+/// files of the workspace itself. With a filter only the items it matches.
+pub fn collect(
+    workspace: &Workspace,
+    pattern: &str,
+    filter: Option<&Regex>,
+) -> Result<Vec<Collected>, crate::Error> {
+    let work_dir = workspace.path.canonicalize()?;
+    let mut collected = Vec::new();
+    for path in glob::glob(pattern)? {
+        let path = match path {
+            Ok(path) if path.is_file() => path.canonicalize()?,
+            _ => continue,
+        };
+        if path.starts_with(&work_dir) {
+            continue;
+        }
+        collected.extend(
+            open_items(
+                &path,
+                &std::fs::read_to_string(&path).map_err(crate::Error::in_file(&path))?,
+            )
+            .into_iter()
+            .filter(|item| filter.is_none_or(|filter| filter.is_match(&item.text))),
+        );
+    }
+    Ok(collected)
+}
+
+fn open_items(path: &Path, content: &str) -> Vec<Collected> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let captures = OPEN_ITEM_REGEX.captures(line)?;
+            Some(Collected {
+                path: path.to_path_buf(),
+                line: index + 1,
+                text: captures["text"].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Adds the items to today, creating the day if needed. Items that were
+/// collected before, by their backlink, are skipped. Returns the new tasks.
+pub fn add_to_today(workspace: &Workspace, items: &[Collected]) -> Result<Vec<Task>, crate::Error> {
+    let mut today: Day = workspace.today_or_create()?;
+    let mut added = Vec::new();
+    for task in items.iter().map(Collected::task) {
+        if today
+            .tasks
+            .iter()
+            .any(|existing| existing.name == task.name)
+        {
+            continue;
+        }
+        today.tasks.push(task.clone());
+        added.push(task);
+    }
+    if !added.is_empty() {
+        today.write()?;
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_items() {
+        let content = "# Project\n\n\
+                       - [ ] Write the README #docs\n\
+                       - [x] Pick a name\n  \
+                         * [ ] Ask legal #docs\n\
+                       Not a [ ] task\n";
+        let items = open_items(Path::new("/notes/project.md"), content);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].line, 5);
+        assert_eq!(
+            items[0].task().name,
+            "Write the README #docs @from:/notes/project.md"
+        );
+    }
+}
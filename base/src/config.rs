@@ -1,30 +1,705 @@
+use crate::task::State as TaskState;
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use time::format_description::{parse_owned, OwnedFormatItem};
+use time::{Date, Time};
 
+#[cfg(feature = "fs")]
 pub const RECURRING_FILE: &str = ".recurring.md";
+#[cfg(feature = "fs")]
+pub const RECURRING_STATE_FILE: &str = ".recurring.json";
+/// Removed days, in the work dir. `DaysList` only lists the files in the
+/// work dir itself, so it leaves them out.
+#[cfg(feature = "fs")]
+pub const TRASH_DIR: &str = ".trash";
 pub const DAY_EXTENTION: &str = "md";
+pub const TASKPAPER_EXTENSION: &str = "taskpaper";
 
 lazy_static! {
     pub static ref DAY_FORMAT: OwnedFormatItem = parse_owned::<2>("[year]-[month]-[day]").unwrap();
+    pub static ref TIME_FORMAT: OwnedFormatItem = parse_owned::<2>("[hour]:[minute]").unwrap();
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub work_dir: PathBuf,
-    pub slack: Option<SlackConfig>,
+    /// The syntax of new day files, existing ones are read in either.
+    #[serde(default)]
+    pub dialect: Dialect,
+    /// Where new day files go in the work dir.
+    #[serde(default)]
+    pub layout: Layout,
+    /// Use an Obsidian daily notes folder as the work dir.
+    pub obsidian: Option<ObsidianConfig>,
+    /// Applied by every output before the rewrites of the sink itself, see
+    /// `Day::rewrite`.
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub slack: Vec<SlackConfig>,
+    pub notion: Option<NotionConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub teams: Option<TeamsConfig>,
+    pub mattermost: Option<MattermostConfig>,
+    pub matrix: Option<MatrixConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub email: Option<EmailConfig>,
+    pub caldav: Option<CaldavConfig>,
+    /// Proxy for all sinks, e.g. `http://proxy:8080` or `socks5://proxy:1080`.
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    pub notifications: Option<NotificationConfig>,
+    pub server: Option<ServerConfig>,
+    pub encryption: Option<EncryptionConfig>,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
+    #[serde(default)]
+    pub style: StyleConfig,
+    #[serde(default)]
+    pub checkboxes: CheckboxConfig,
+    /// Days more than this many days ago can't be changed, see
+    /// `Workspace::with_lock_after`.
+    pub lock_after_days: Option<u32>,
+    pub git: Option<GitConfig>,
+}
+
+/// Local repositories to add your commits of the day from, as a Shipped
+/// section in the notes. See `git::set_shipped`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    pub repos: Vec<PathBuf>,
+    /// The name or email of your commits, `user.email` of the repository by
+    /// default.
+    pub author: Option<String>,
+}
+
+/// Checkboxes of other tools and people, like `[X]` or `[.]`, to read as a
+/// state instead of keeping the line in the notes. See `Workspace::with_checkboxes`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CheckboxConfig {
+    /// Read `[X]` as completed and `[.]`, `[o]` and `[/]` as in progress.
+    pub lenient: bool,
+    /// The states of more checkboxes, by the character between the brackets.
+    pub states: BTreeMap<String, TaskState>,
+}
+
+impl CheckboxConfig {
+    /// The states of the checkboxes to read besides the usual ones.
+    pub fn table(&self) -> BTreeMap<String, TaskState> {
+        let mut table = BTreeMap::new();
+        if self.lenient {
+            table.insert("X".to_string(), TaskState::Completed);
+            for checkbox in [".", "o", "/"] {
+                table.insert(checkbox.to_string(), TaskState::InProgress);
+            }
+        }
+        table.extend(self.states.clone());
+        table
+    }
+}
+
+/// How tasks are written to the day files and shown, see
+/// `Workspace::with_style`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct StyleConfig {
+    pub bullet: Bullet,
+    /// `[ ]` for open tasks, or `[]` without the space.
+    pub spaced_checkbox: bool,
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        Self {
+            bullet: Bullet::Star,
+            spaced_checkbox: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Bullet {
+    #[default]
+    #[serde(rename = "*")]
+    Star,
+    #[serde(rename = "-")]
+    Dash,
+}
+
+impl std::fmt::Display for Bullet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bullet::Star => write!(f, "*"),
+            Bullet::Dash => write!(f, "-"),
+        }
+    }
+}
+
+/// The lengths of the phases of `w0rk pomo`, in minutes.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work: u32,
+    pub short_break: u32,
+    pub long_break: u32,
+    /// Take a long break after this many pomodoros.
+    pub long_break_after: u32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_after: 4,
+        }
+    }
+}
+
+/// `w0rk backup`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Where the backups go, `backups` in the state dir by default.
+    pub dir: Option<PathBuf>,
+    /// Remove all but this many of the newest backups after a backup.
+    pub keep: Option<usize>,
+}
+
+/// Day files encrypted with age, with an identity in the OS keyring.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// The name of the identity in the keyring, `w0rk auth generate <name>`
+    /// makes one.
+    pub identity_ref: String,
 }
 
+/// `w0rk serve`, requests need an `Authorization: Bearer <token>` header.
 #[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    pub token: String,
+    #[serde(default = "default_server_addr")]
+    pub addr: String,
+}
+
+fn default_server_addr() -> String {
+    "127.0.0.1:7878".to_string()
+}
+
+/// Scheduling of `w0rk daemon`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Seconds between syncs.
+    pub interval: u64,
+    /// Up to this many seconds are added to every interval, so syncs of
+    /// several machines don't all hit the APIs at the same moment.
+    pub jitter: u64,
+    /// Also sync when a day file changes.
+    pub watch: bool,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            interval: 300,
+            jitter: 30,
+            watch: true,
+        }
+    }
+}
+
+/// Desktop notifications shown by `w0rk daemon`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Notify once about each task of today that is due.
+    pub due: bool,
+    /// Notify once about tasks that have been blocked for more days.
+    pub blocked_after: Option<i64>,
+    /// Notify once about each task whose `@blocked-by` dependencies are all
+    /// completed.
+    pub unblocked: bool,
+    /// Notify about the open tasks of today after this time (UTC, like the
+    /// day files).
+    #[serde(deserialize_with = "optional_time_of_day")]
+    pub end_of_day: Option<Time>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            due: true,
+            blocked_after: None,
+            unblocked: true,
+            end_of_day: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct SlackConfig {
+    /// Set to false to skip this sink when syncing, without removing it.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
     pub token: String,
+    /// The name of a token in the OS keyring, instead of `token`.
+    pub token_ref: Option<String>,
+    /// A channel ID, or `@username` to post in a direct message with that user.
+    #[serde(alias = "target")]
     pub channel: String,
+    /// Names the destination in the output and its state, needed when two
+    /// destinations post to the same channel.
+    pub name: Option<String>,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+    /// Post the notes of the day as a reply in the thread of the daily message.
+    #[serde(default)]
+    pub thread_notes: bool,
+    /// Only sync tasks with one of these tags, all tasks are synced when empty.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Replace the message of a removed day with a notice instead of deleting it.
+    #[serde(default)]
+    pub tombstone: bool,
+    #[serde(default)]
+    pub emoji: EmojiConfig,
+    /// Post stock Unicode emoji instead of the `:name:` ones of `emoji`:
+    /// always when true, never when false, and when unset for the ones that
+    /// aren't custom emoji of the workspace, checked with `emoji.list`.
+    pub unicode_emoji: Option<bool>,
+    /// Set the Slack status to the first in-progress task, needs a user token.
+    #[serde(default)]
+    pub status: bool,
+    #[serde(default = "default_status_emoji")]
+    pub status_emoji: String,
+    #[serde(default)]
+    pub reminders: Vec<ReminderConfig>,
+    /// Post open tasks as replies in the thread of the daily message, a ✅
+    /// reaction from you on a reply completes the task.
+    #[serde(default)]
+    pub reactions: bool,
+    #[serde(default)]
+    pub mode: SlackMode,
+    /// Add the tasks that have been open for at least this many days to
+    /// the message of the day.
+    pub stale_after: Option<i64>,
+    /// Add the Shipped section of the notes, see `git`, to the message of
+    /// the day.
+    #[serde(default)]
+    pub shipped: bool,
+}
+
+impl SlackConfig {
+    /// The name of the destination, its channel unless it's named.
+    pub fn key(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.channel)
+    }
+}
+
+/// The syntax of the day files, picked by their extension or name.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    /// `* [x] Deploy #infra` in `2024-07-01.md`.
+    #[default]
+    Markdown,
+    /// `- Deploy @infra @done(2024-07-01)` in `2024-07-01.taskpaper`.
+    Taskpaper,
+    /// `- DONE Deploy #infra` in Logseq's `journals/2024_07_01.md`.
+    Logseq,
+}
+
+impl Dialect {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Dialect::Markdown | Dialect::Logseq => DAY_EXTENTION,
+            Dialect::Taskpaper => TASKPAPER_EXTENSION,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == TASKPAPER_EXTENSION => Dialect::Taskpaper,
+            _ if crate::logseq::is_journal(path) => Dialect::Logseq,
+            _ => Dialect::Markdown,
+        }
+    }
+}
+
+/// The folders of the day files in the work dir. Days are read from any
+/// layout, this is for new ones and `w0rk migrate layout`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// `2024-07-01.md` in the work dir itself.
+    #[default]
+    Flat,
+    /// `2024/07/2024-07-01.md`, a folder per year and month.
+    Monthly,
+}
+
+impl Layout {
+    /// The folder of the day of the date, relative to the work dir.
+    pub fn folder(&self, date: &Date) -> PathBuf {
+        match self {
+            Layout::Flat => PathBuf::new(),
+            Layout::Monthly => PathBuf::from(format!("{:04}", date.year()))
+                .join(format!("{:02}", u8::from(date.month()))),
+        }
+    }
+}
+
+/// The daily notes settings of Obsidian, for the names of new day files.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ObsidianConfig {
+    /// The date format of the daily notes plugin, in Moment.js syntax. It
+    /// needs `YYYY-MM-DD` in it, that's how w0rk finds the date of a file.
+    #[serde(default = "default_obsidian_date_format")]
+    pub date_format: String,
+}
+
+fn default_obsidian_date_format() -> String {
+    "YYYY-MM-DD".to_string()
+}
+
+impl ObsidianConfig {
+    /// The Moment.js date format as a `time` format description.
+    pub fn file_format(&self) -> Result<OwnedFormatItem, crate::Error> {
+        let moment = self.date_format.as_str();
+        if !moment.contains("YYYY-MM-DD") {
+            return Err(crate::Error::Config(format!(
+                "the Obsidian date_format \"{}\" needs YYYY-MM-DD in it, e.g. \"YYYY-MM-DD dddd\"",
+                moment
+            )));
+        }
+        let tokens = [
+            ("YYYY", "[year]"),
+            ("MMMM", "[month repr:long]"),
+            ("MMM", "[month repr:short]"),
+            ("MM", "[month]"),
+            ("M", "[month padding:none]"),
+            ("DD", "[day]"),
+            ("D", "[day padding:none]"),
+            ("dddd", "[weekday]"),
+            ("ddd", "[weekday repr:short]"),
+        ];
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('[', "\\[");
+        let mut format = String::new();
+        let mut rest = moment;
+        while let Some(c) = rest.chars().next() {
+            // Moment.js escapes text with brackets, `[Week] W`.
+            if let Some(text) = rest.strip_prefix('[') {
+                let end = text.find(']').unwrap_or(text.len());
+                format.push_str(&escape(&text[..end]));
+                rest = text.get(end + 1..).unwrap_or_default();
+            } else if let Some((token, item)) = tokens.iter().find(|(t, _)| rest.starts_with(t)) {
+                format.push_str(item);
+                rest = &rest[token.len()..];
+            } else {
+                format.push_str(&escape(&c.to_string()));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        parse_owned::<2>(&format).map_err(|err| {
+            crate::Error::Config(format!(
+                "invalid Obsidian date_format \"{}\": {}",
+                moment, err
+            ))
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SlackMode {
+    /// One message with all tasks of the day.
+    #[default]
+    Day,
+    /// A message per top-level task below a message with the date, so
+    /// every task gets a thread of its own.
+    Task,
+}
+
+/// A reminder posted once a day after the given time (UTC, like the day files).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReminderConfig {
+    #[serde(deserialize_with = "time_of_day")]
+    pub at: Time,
+    pub kind: ReminderKind,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReminderKind {
+    /// Reminds to plan the day when there's no file for today yet.
+    Plan,
+    /// Lists the tasks of today that aren't completed yet.
+    Incomplete,
+}
+
+fn time_of_day<'de, D>(deserializer: D) -> Result<Time, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    Time::parse(&value, &TIME_FORMAT).map_err(|_| {
+        serde::de::Error::custom(format!("invalid time \"{}\", expected HH:MM", value))
+    })
+}
+
+fn optional_time_of_day<'de, D>(deserializer: D) -> Result<Option<Time>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    time_of_day(deserializer).map(Some)
+}
+
+fn default_status_emoji() -> String {
+    ":hammer_and_wrench:".to_string()
+}
+
+/// The emoji shown for each task state, stock emoji unless configured,
+/// e.g. `":todo_done:"` for custom workspace emoji.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct EmojiConfig {
+    pub completed: String,
+    pub in_progress: String,
+    pub blocked: String,
+    pub incomplete: String,
+    pub waiting: String,
+}
+
+impl EmojiConfig {
+    pub fn for_state(&self, state: &TaskState) -> &str {
+        match state {
+            TaskState::Blocked => &self.blocked,
+            TaskState::Completed => &self.completed,
+            TaskState::InProgress => &self.in_progress,
+            TaskState::Incomplete => &self.incomplete,
+            TaskState::Waiting => &self.waiting,
+        }
+    }
+
+    /// The emoji with the `:name:` ones for which `exists` is false replaced
+    /// by the stock emoji of their state.
+    pub fn with_fallback(&self, exists: impl Fn(&str) -> bool) -> Self {
+        let pick = |emoji: &String, stock: String| match emoji
+            .strip_prefix(':')
+            .and_then(|e| e.strip_suffix(':'))
+        {
+            Some(name) if !exists(name) => stock,
+            _ => emoji.clone(),
+        };
+        let stock = Self::default();
+        Self {
+            completed: pick(&self.completed, stock.completed),
+            in_progress: pick(&self.in_progress, stock.in_progress),
+            blocked: pick(&self.blocked, stock.blocked),
+            incomplete: pick(&self.incomplete, stock.incomplete),
+            waiting: pick(&self.waiting, stock.waiting),
+        }
+    }
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        Self {
+            completed: "✅".to_string(),
+            in_progress: "🔄".to_string(),
+            blocked: "⏸️".to_string(),
+            incomplete: "⬜".to_string(),
+            waiting: "⏳".to_string(),
+        }
+    }
+}
+
+// Allows a single sink config as well as a list, so one config can post to several destinations.
+// A visitor instead of an untagged enum, so errors within the sink config aren't swallowed.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+
+    struct OneOrMany<T>(std::marker::PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> serde::de::Visitor<'de> for OneOrMany<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a sink config or a list of sink configs")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            Ok(vec![T::deserialize(MapAccessDeserializer::new(map))?])
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq))
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany(std::marker::PhantomData))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotionConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub token: String,
+    pub database_id: String,
+    #[serde(default = "default_notion_title_property")]
+    pub title_property: String,
+    pub date_property: Option<String>,
+}
+
+/// Sinks are enabled unless they have `enabled = false`.
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_notion_title_property() -> String {
+    "Name".to_string()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DiscordConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub webhook_url: String,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TeamsConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// The URL of an incoming webhook of the channel.
+    pub webhook_url: String,
     #[serde(default)]
     pub rewrites: Vec<Rewrite>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MattermostConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub url: String,
+    pub token: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MatrixConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub homeserver: String,
+    pub access_token: String,
+    pub room_id: String,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub token: String,
+    pub chat_id: String,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub digest: DigestPeriod,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestPeriod {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CaldavConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct Rewrite {
     pub from: Regex,
@@ -37,6 +712,7 @@ impl<'de> Deserialize<'de> for Rewrite {
         D: serde::Deserializer<'de>,
     {
         #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
         struct Helper {
             from: String,
             to: String,
@@ -68,27 +744,563 @@ impl Rewrite {
     }
 }
 
+impl PartialEq for Rewrite {
+    fn eq(&self, other: &Self) -> bool {
+        self.from.as_str() == other.from.as_str() && self.to == other.to
+    }
+}
+
+/// The text with the rewrites applied in order.
+pub fn rewrite(text: &str, rewrites: &[Rewrite]) -> String {
+    let mut text = text.to_string();
+    for rewrite in rewrites {
+        rewrite.rewrite(&mut text);
+    }
+    text
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             work_dir: "./work_dir".into(),
-            slack: None,
+            dialect: Dialect::default(),
+            layout: Layout::default(),
+            obsidian: None,
+            rewrites: Vec::new(),
+            slack: Vec::new(),
+            notion: None,
+            discord: None,
+            teams: None,
+            mattermost: None,
+            matrix: None,
+            telegram: None,
+            webhook: None,
+            email: None,
+            caldav: None,
+            proxy: None,
+            daemon: DaemonConfig::default(),
+            notifications: None,
+            server: None,
+            encryption: None,
+            backup: BackupConfig::default(),
+            pomodoro: PomodoroConfig::default(),
+            style: StyleConfig::default(),
+            checkboxes: CheckboxConfig::default(),
+            lock_after_days: None,
+            git: None,
         }
     }
 }
 
+/// The config file names that are looked for, in order of preference.
+#[cfg(feature = "fs")]
+pub const CONFIG_FILES: [&str; 4] = ["config.toml", "config.yaml", "config.yml", "config.json"];
+
 impl Config {
+    /// Reads a JSON, TOML or YAML config, depending on the extension.
+    /// Unknown keys are an error, so typos don't silently drop settings.
+    #[cfg(feature = "fs")]
     pub fn from_path(path: &Path) -> Result<Self, crate::Error> {
         let config_file = std::fs::read_to_string(path)?;
-        let config: Config = serde_json::from_str(&config_file)?;
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let mut config = Self::from_content(&config_file, extension.unwrap_or_default())?;
+        // A relative work dir is next to the config, not in the current dir.
+        if config.work_dir.is_relative() {
+            if let Some(dir) = path.parent() {
+                config.work_dir = dir.join(&config.work_dir);
+            }
+        }
+        config.resolve_tokens()?;
+        Ok(config)
+    }
+
+    /// Parses a config in the format of the extension, JSON for unknown
+    /// ones. Tokens in the keyring are left for `resolve_tokens`.
+    pub fn from_content(content: &str, extension: &str) -> Result<Self, crate::Error> {
+        let config = match extension {
+            "toml" => parse(toml::Deserializer::new(content))?,
+            "yaml" | "yml" => parse(serde_yaml::Deserializer::from_str(content))?,
+            _ => parse(&mut serde_json::Deserializer::from_str(content))?,
+        };
+        if let Some(obsidian) = &config.obsidian {
+            obsidian.file_format()?;
+        }
+        // A work phase of no time would finish pomodoros in a loop.
+        if config.pomodoro.work == 0 {
+            return Err(crate::Error::Config(
+                "the pomodoro `work` needs to be at least a minute".to_string(),
+            ));
+        }
+        for (i, slack) in config.slack.iter().enumerate() {
+            if config.slack[..i]
+                .iter()
+                .any(|other| other.key() == slack.key())
+            {
+                return Err(crate::Error::Config(format!(
+                    "two slack configs for {}, give them a `name`",
+                    slack.key()
+                )));
+            }
+        }
         Ok(config)
     }
+
+    #[cfg(feature = "fs")]
+    /// The workspace in `work_dir`, creating days in the dialect and with
+    /// the file names of the config.
+    pub fn workspace(&self) -> Result<Workspace, crate::Error> {
+        self.configure(Workspace::open(&self.work_dir, self.key()?)?)
+    }
+
+    /// The workspace like `workspace`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn workspace_async(&self) -> Result<Workspace, crate::Error> {
+        self.configure(Workspace::open_async(&self.work_dir, self.key()?).await?)
+    }
+
+    #[cfg(feature = "fs")]
+    fn configure(&self, workspace: Workspace) -> Result<Workspace, crate::Error> {
+        let workspace = workspace
+            .with_dialect(self.dialect)
+            .with_layout(self.layout)
+            .with_style(self.style)
+            .with_checkboxes(self.checkboxes.table())
+            .with_rewrites(self.rewrites.clone())
+            .with_lock_after(self.lock_after_days);
+        Ok(match &self.obsidian {
+            Some(obsidian) => workspace.with_file_format(obsidian.file_format()?),
+            None => workspace,
+        })
+    }
+
+    /// The key of the encrypted day files with the identity from the
+    /// keyring, `None` without `encryption`. Used by `workspace`.
+    #[cfg(feature = "fs")]
+    pub fn key(&self) -> Result<Option<crate::Key>, crate::Error> {
+        let Some(encryption) = &self.encryption else {
+            return Ok(None);
+        };
+        #[cfg(feature = "encryption")]
+        return crate::Key::parse(&crate::secrets::get(&encryption.identity_ref)?).map(Some);
+        #[cfg(not(feature = "encryption"))]
+        Err(crate::Error::Config(format!(
+            "w0rk was built without encryption, can't read the day files with {}",
+            encryption.identity_ref
+        )))
+    }
+
+    /// Reads the tokens referenced with `token_ref` from the OS keyring.
+    #[cfg(feature = "fs")]
+    pub fn resolve_tokens(&mut self) -> Result<(), crate::Error> {
+        for slack in self.slack.iter_mut() {
+            match (&slack.token_ref, slack.token.is_empty()) {
+                (Some(_), false) => {
+                    return Err(crate::Error::Config(format!(
+                        "slack config for {} has both a `token` and a `token_ref`",
+                        slack.channel
+                    )))
+                }
+                (Some(token_ref), true) => slack.token = crate::secrets::get(token_ref)?,
+                (None, true) => {
+                    return Err(crate::Error::Config(format!(
+                        "slack config for {} needs a `token` or a `token_ref`",
+                        slack.channel
+                    )))
+                }
+                (None, false) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// A commented TOML config to start from, for `w0rk config init`.
+    pub fn template(work_dir: &Path) -> String {
+        let work_dir = toml::Value::String(work_dir.to_string_lossy().to_string());
+        include_str!("config.template.toml").replace("{work_dir}", &work_dir.to_string())
+    }
+
+    /// The first config file in the directory that exists, `config.json` if none do.
+    #[cfg(feature = "fs")]
+    pub fn find_path(dir: &Path) -> PathBuf {
+        CONFIG_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join("config.json"))
+    }
+
+    /// The other config files in the directory, which aren't read as
+    /// the one of `find_path` comes first.
+    #[cfg(feature = "fs")]
+    pub fn shadowed_paths(dir: &Path) -> Vec<PathBuf> {
+        let path = Self::find_path(dir);
+        CONFIG_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|other| other != &path && other.exists())
+            .collect()
+    }
+}
+
+fn parse<'de, D>(deserializer: D) -> Result<Config, crate::Error>
+where
+    D: serde::Deserializer<'de>,
+    D::Error: std::fmt::Display,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        crate::Error::Config(describe_error(&path, &err.into_inner().to_string()))
+    })
+}
+
+lazy_static! {
+    static ref UNKNOWN_FIELD: Regex =
+        Regex::new(r"unknown field `([^`]*)`, expected (?:one of )?(.*?)(?: at line| at \w|\n|$)")
+            .unwrap();
+    static ref FIELD_NAME: Regex = Regex::new(r"`([^`]*)`").unwrap();
+}
+
+// Names the section of the error and suggests the closest field for an
+// unknown one, e.g. "unknown field `chanel` in slack config, did you mean `channel`?".
+fn describe_error(path: &str, message: &str) -> String {
+    let captures = match UNKNOWN_FIELD.captures(message) {
+        Some(captures) => captures,
+        None => {
+            return match path {
+                "" | "." | "?" => message.trim().to_string(),
+                path => format!("{} (at `{}`)", message.trim(), path),
+            }
+        }
+    };
+    let field = &captures[1];
+
+    // The path ends with the unknown field, the section is what contains it.
+    let mut segments: Vec<&str> = path
+        .split('.')
+        .filter(|segment| !segment.is_empty() && *segment != "?")
+        .map(|segment| segment.split('[').next().unwrap_or(segment))
+        .collect();
+    if segments.last() == Some(&field) {
+        segments.pop();
+    }
+    let section = segments.join(".");
+    let section = match section.is_empty() {
+        true => "config".to_string(),
+        false => format!("{} config", section),
+    };
+    let suggestion = FIELD_NAME
+        .captures_iter(&captures[2])
+        .map(|expected| expected[1].to_string())
+        .filter(|expected| edit_distance(field, expected) <= 2.max(field.len() / 3))
+        .min_by_key(|expected| edit_distance(field, expected));
+    let position = message
+        .find(" at line ")
+        .map(|start| message[start + 1..].lines().next().unwrap_or_default())
+        .map(|position| format!(" ({})", position.trim()))
+        .unwrap_or_default();
+
+    match suggestion {
+        Some(expected) => format!(
+            "unknown field `{}` in {}, did you mean `{}`?{}",
+            field, section, expected, position
+        ),
+        None => format!(
+            "unknown field `{}` in {}, expected one of {}{}",
+            field, section, &captures[2], position
+        ),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + (a != *b) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_from_path_formats() {
+        let dir = std::env::temp_dir().join("w0rk-test-config-formats");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(
+            dir.join("config.toml"),
+            r##"
+# Comments are allowed
+work_dir = "/tmp/work"
+
+[[slack]]
+token = "a"
+channel = "b"
+rewrites = [{ from = "#(\\d+)", to = "PR $1" }]
+"##,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("config.yaml"),
+            "work_dir: /tmp/work\nslack:\n  token: a\n  channel: b\n",
+        )
+        .unwrap();
+
+        assert_eq!(Config::find_path(&dir), dir.join("config.toml"));
+        assert_eq!(Config::shadowed_paths(&dir), vec![dir.join("config.yaml")]);
+        let config = Config::from_path(&dir.join("config.toml")).unwrap();
+        assert_eq!(config.slack[0].rewrites.len(), 1);
+        let config = Config::from_path(&dir.join("config.yaml")).unwrap();
+        assert_eq!(config.slack[0].channel, "b");
+        assert_eq!(config.work_dir, PathBuf::from("/tmp/work"));
+
+        std::fs::write(dir.join("config.json"), r#"{"work_dir": "work"}"#).unwrap();
+        let config = Config::from_path(&dir.join("config.json")).unwrap();
+        assert_eq!(config.work_dir, dir.join("work"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_global_rewrites() {
+        let dir = std::env::temp_dir().join("w0rk-test-config-global-rewrites");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [ ] Review #12\n").unwrap();
+
+        let config = Config::from_content(
+            &format!(
+                r##"{{
+                    "work_dir": {:?},
+                    "rewrites": [{{"from": "#(\\d+)", "to": "PR $1"}}],
+                    "slack": {{"token": "a", "channel": "b"}}
+                }}"##,
+                dir
+            ),
+            "json",
+        )
+        .unwrap();
+        // The sinks only have their own, the days have the global ones.
+        assert!(config.slack[0].rewrites.is_empty());
+        let workspace = config.workspace().unwrap();
+        let day = workspace.read_day(&dir.join("2024-07-01.md")).unwrap();
+        assert_eq!(day.display_name(&day.tasks[0]), "Review PR 12");
+        assert_eq!(day.tasks[0].name, "Review #12");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_slack_token() {
+        let mut config = Config {
+            slack: vec![serde_json::from_str(r#"{"channel": "b"}"#).unwrap()],
+            ..Config::default()
+        };
+        assert_eq!(
+            config.resolve_tokens().unwrap_err().to_string(),
+            "Invalid config: slack config for b needs a `token` or a `token_ref`"
+        );
+
+        config.slack[0].token = "a".to_string();
+        config.slack[0].token_ref = Some("slack".to_string());
+        assert!(config.resolve_tokens().is_err());
+
+        config.slack[0].token_ref = None;
+        assert!(config.resolve_tokens().is_ok());
+    }
+
+    #[test]
+    fn test_obsidian_file_format() {
+        let date = crate::parse_date("2024-07-01").unwrap();
+        let obsidian = |format: &str| ObsidianConfig {
+            date_format: format.to_string(),
+        };
+        let format = obsidian("YYYY-MM-DD dddd [w]").file_format().unwrap();
+        assert_eq!(date.format(&format).unwrap(), "2024-07-01 Monday w");
+        let format = obsidian("YYYY-MM-DD").file_format().unwrap();
+        assert_eq!(date.format(&format).unwrap(), "2024-07-01");
+        assert!(obsidian("DD-MM-YYYY").file_format().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_template() {
+        let dir = std::env::temp_dir().join("w0rk-test-config-template");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.toml");
+        std::fs::write(&path, Config::template(Path::new("/tmp/work"))).unwrap();
+        let config = Config::from_path(&path).unwrap();
+        assert_eq!(config.work_dir, PathBuf::from("/tmp/work"));
+        assert!(config.slack.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_unknown_fields() {
+        let dir = std::env::temp_dir().join("w0rk-test-config-unknown");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.json");
+        std::fs::write(
+            &path,
+            r#"{"work_dir": "/tmp", "slack": {"token": "a", "chanel": "b"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Config::from_path(&path).unwrap_err().to_string(),
+            "Invalid config: unknown field `chanel` in slack config, did you mean `channel`? (at line 1 column 53)"
+        );
+
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "work_dir = \"/tmp\"\n[daemon]\nintervall = 60\n").unwrap();
+        assert_eq!(
+            Config::from_path(&path).unwrap_err().to_string(),
+            "Invalid config: unknown field `intervall` in daemon config, did you mean `interval`? (at line 3, column 1)"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_describe_error() {
+        assert_eq!(
+            describe_error(
+                "foo",
+                "unknown field `foo`, expected one of `work_dir`, `slack`"
+            ),
+            "unknown field `foo` in config, expected one of `work_dir`, `slack`"
+        );
+        assert_eq!(
+            describe_error("slack[1].reminders[0].at", "invalid time"),
+            "invalid time (at `slack[1].reminders[0].at`)"
+        );
+    }
+
+    #[test]
+    fn test_emoji_config() {
+        let config: SlackConfig = serde_json::from_str(
+            r#"{"token": "a", "channel": "b", "emoji": {"completed": ":todo_done:"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.emoji.for_state(&TaskState::Completed), ":todo_done:");
+        assert_eq!(config.emoji.for_state(&TaskState::Incomplete), "⬜");
+        assert_eq!(config.unicode_emoji, None);
+
+        let emoji = EmojiConfig {
+            in_progress: ":todo_doing:".to_string(),
+            ..config.emoji.clone()
+        };
+        let fallback = emoji.with_fallback(|name| name == "todo_done");
+        assert_eq!(fallback.for_state(&TaskState::Completed), ":todo_done:");
+        assert_eq!(fallback.for_state(&TaskState::InProgress), "🔄");
+        assert_eq!(fallback.for_state(&TaskState::Incomplete), "⬜");
+    }
+
+    #[test]
+    fn test_reminders() {
+        let config: SlackConfig = serde_json::from_str(
+            r#"{"token": "a", "channel": "b", "reminders": [{"at": "17:30", "kind": "incomplete"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.reminders,
+            vec![ReminderConfig {
+                at: Time::from_hms(17, 30, 0).unwrap(),
+                kind: ReminderKind::Incomplete,
+            }]
+        );
+
+        let result = serde_json::from_str::<SlackConfig>(
+            r#"{"token": "a", "channel": "b", "reminders": [{"at": "5pm", "kind": "plan"}]}"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_notifications() {
+        let config: Config = serde_json::from_str(
+            r#"{"work_dir": "/tmp", "notifications": {"blocked_after": 3, "end_of_day": "17:30"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.notifications,
+            Some(NotificationConfig {
+                due: true,
+                blocked_after: Some(3),
+                unblocked: true,
+                end_of_day: Some(Time::from_hms(17, 30, 0).unwrap()),
+            })
+        );
+
+        let config: Config =
+            serde_json::from_str(r#"{"work_dir": "/tmp", "notifications": {"due": false}}"#)
+                .unwrap();
+        assert_eq!(config.notifications.unwrap().end_of_day, None);
+    }
+
+    #[test]
+    fn test_slack_one_or_many() {
+        let config: Config = serde_json::from_str(
+            r#"{"work_dir": "/tmp", "slack": {"token": "a", "channel": "b"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.slack.len(), 1);
+
+        let config: Config = serde_json::from_str(
+            r#"{"work_dir": "/tmp", "slack": [{"token": "a", "channel": "b"}, {"token": "c", "channel": "d", "tags": ["public"]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.slack.len(), 2);
+        assert_eq!(config.slack[1].tags, vec!["public"]);
+        assert!(config.slack[0].enabled);
+
+        let config: Config = serde_json::from_str(
+            r#"{"work_dir": "/tmp", "slack": {"token": "a", "channel": "b", "enabled": false}}"#,
+        )
+        .unwrap();
+        assert!(!config.slack[0].enabled);
+
+        let config: Config = serde_json::from_str(r#"{"work_dir": "/tmp"}"#).unwrap();
+        assert!(config.slack.is_empty());
+
+        let config: Config = serde_json::from_str(
+            r#"{"work_dir": "/tmp", "slack": {"token": "a", "target": "@manager"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.slack[0].channel, "@manager");
+
+        let twice = r#"{"work_dir": "/tmp", "slack": [{"token": "a", "channel": "b"}, {"token": "c", "channel": "b"}]}"#;
+        assert!(matches!(
+            Config::from_content(twice, "json"),
+            Err(crate::Error::Config(_))
+        ));
+        let named = r#"{"work_dir": "/tmp", "slack": [{"token": "a", "channel": "b"}, {"token": "c", "channel": "b", "name": "other"}]}"#;
+        let config = Config::from_content(named, "json").unwrap();
+        assert_eq!(config.slack[0].key(), "b");
+        assert_eq!(config.slack[1].key(), "other");
+
+        assert!(matches!(
+            Config::from_content(r#"{"work_dir": "/tmp", "pomodoro": {"work": 0}}"#, "json"),
+            Err(crate::Error::Config(_))
+        ));
+    }
+
     #[test]
     fn test_rewrite() {
         let mut text = String::from("Skip validations when setting removing flag on site #13462");
@@ -0,0 +1,122 @@
+//! Dates for the command line as people say them: `yesterday`,
+//! `last friday`, `next mon` or `3 days ago`, next to `YYYY-MM-DD`.
+use crate::day::parse_date;
+use lazy_static::lazy_static;
+use regex::Regex;
+use time::{Date, Duration, OffsetDateTime, Weekday};
+
+lazy_static! {
+    static ref RELATIVE_REGEX: Regex = Regex::new(
+        r"^(?:(?<ago>\d+) (?<ago_unit>day|week)s? ago|in (?<in>\d+) (?<in_unit>day|week)s?)$"
+    )
+    .unwrap();
+}
+
+/// Parses a date relative to today, see `parse_natural_date_on`.
+pub fn parse_natural_date(value: &str) -> Result<Date, crate::Error> {
+    parse_natural_date_on(value, OffsetDateTime::now_utc().date())
+}
+
+/// Parses `YYYY-MM-DD`, `today`, `yesterday`, `tomorrow`, a weekday
+/// (`friday` or `fri` is the last one, today included), `last friday`,
+/// `next mon`, `3 days ago`, `2 weeks ago` or `in 3 days`.
+pub fn parse_natural_date_on(value: &str, today: Date) -> Result<Date, crate::Error> {
+    let invalid = || crate::Error::InvalidDate(value.to_string());
+    if let Ok(date) = parse_date(value) {
+        return Ok(date);
+    }
+    let value = value.trim().to_lowercase();
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    let date = match value.as_str() {
+        "today" => Some(today),
+        "yesterday" => today.previous_day(),
+        "tomorrow" => today.next_day(),
+        _ => match value.split_once(' ') {
+            Some(("last", weekday)) => {
+                let days = days_since(today, parse_weekday(weekday).ok_or_else(invalid)?);
+                today.checked_sub(Duration::days(if days == 0 { 7 } else { days }))
+            }
+            Some(("next", weekday)) => {
+                let days = days_since(today, parse_weekday(weekday).ok_or_else(invalid)?);
+                today.checked_add(Duration::days(7 - days))
+            }
+            _ => match parse_weekday(&value) {
+                Some(weekday) => today.checked_sub(Duration::days(days_since(today, weekday))),
+                None => relative(&value, today),
+            },
+        },
+    };
+    date.ok_or_else(invalid)
+}
+
+/// `3 days ago` or `in 2 weeks`.
+fn relative(value: &str, today: Date) -> Option<Date> {
+    let captures = RELATIVE_REGEX.captures(value)?;
+    let (count, unit, sign) = match (captures.name("ago"), captures.name("in")) {
+        (Some(count), _) => (count, &captures["ago_unit"], -1),
+        (_, Some(count)) => (count, &captures["in_unit"], 1),
+        _ => return None,
+    };
+    let days = count.as_str().parse::<i64>().ok()?
+        * match unit {
+            "week" => 7,
+            _ => 1,
+        };
+    today.checked_add(Duration::days(sign * days))
+}
+
+/// The number of days since the last weekday, 0 when today is one.
+fn days_since(today: Date, weekday: Weekday) -> i64 {
+    let today = today.weekday().number_days_from_monday() as i64;
+    (today - weekday.number_days_from_monday() as i64).rem_euclid(7)
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    let weekdays = [
+        ("monday", Weekday::Monday),
+        ("tuesday", Weekday::Tuesday),
+        ("wednesday", Weekday::Wednesday),
+        ("thursday", Weekday::Thursday),
+        ("friday", Weekday::Friday),
+        ("saturday", Weekday::Saturday),
+        ("sunday", Weekday::Sunday),
+    ];
+    weekdays
+        .into_iter()
+        .find(|(name, _)| value.len() >= 3 && name.starts_with(value))
+        .map(|(_, weekday)| weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_natural_date() {
+        // A Wednesday.
+        let today = parse_date("2024-07-03").unwrap();
+        let parse = |value| {
+            parse_natural_date_on(value, today)
+                .map(|date| date.to_string())
+                .unwrap_or_default()
+        };
+        assert_eq!(parse("2024-06-01"), "2024-06-01");
+        assert_eq!(parse("today"), "2024-07-03");
+        assert_eq!(parse("Yesterday"), "2024-07-02");
+        assert_eq!(parse("tomorrow"), "2024-07-04");
+        assert_eq!(parse("friday"), "2024-06-28");
+        assert_eq!(parse("wed"), "2024-07-03");
+        assert_eq!(parse("last friday"), "2024-06-28");
+        assert_eq!(parse("last wednesday"), "2024-06-26");
+        assert_eq!(parse("next mon"), "2024-07-08");
+        assert_eq!(parse("next wed"), "2024-07-10");
+        assert_eq!(parse("3 days ago"), "2024-06-30");
+        assert_eq!(parse("1 day ago"), "2024-07-02");
+        assert_eq!(parse("2 weeks  ago"), "2024-06-19");
+        assert_eq!(parse("in 2 days"), "2024-07-05");
+
+        assert!(parse_natural_date_on("someday", today).is_err());
+        assert!(parse_natural_date_on("last fr", today).is_err());
+        assert!(parse_natural_date_on("2024-02-30", today).is_err());
+    }
+}
@@ -1,43 +1,117 @@
-use crate::config::{DAY_EXTENTION, DAY_FORMAT, RECURRING_FILE};
-use crate::task::Task;
+use crate::config::{Dialect, Rewrite, StyleConfig, DAY_FORMAT};
+#[cfg(feature = "fs")]
+use crate::config::{DAY_EXTENTION, RECURRING_FILE, TASKPAPER_EXTENSION};
+use crate::task::{State as TaskState, Task};
+use crate::Key;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use time::format_description::OwnedFormatItem;
 use time::Date;
 
+lazy_static! {
+    // Lines that look like a task, to warn about the ones that don't parse.
+    // The checkbox has one character at most and is followed by a space, so
+    // links like `- [text](url)` aren't tasks.
+    static ref SUSPECT_TASK_REGEX: Regex =
+        Regex::new(r"^(?<bullet>[-*+])\s*\[(?<state>[^\]]?)\](?:\s+(?<name>.*))?$").unwrap();
+    static ref FILE_DATE_REGEX: Regex = Regex::new(r"\d{4}[-_]\d{2}[-_]\d{2}").unwrap();
+    // The bullet and checkbox of a task line, also used by lint and the
+    // language server. `+` isn't a task bullet, it's matched for lint to fix.
+    pub(crate) static ref CHECKBOX_REGEX: Regex =
+        Regex::new(r"^(?<prefix>(?<indent>\s*)(?<bullet>[*+-])\s?\[)(?<state>[^\]]?)\]").unwrap();
+}
+
+/// The day files of a workspace, by date.
+#[cfg(feature = "fs")]
+#[derive(Default)]
 pub struct DaysList(Vec<DayListing>);
 
+#[cfg(feature = "fs")]
 pub type DayListing = (Date, PathBuf);
 
+#[cfg(feature = "fs")]
 impl DaysList {
     pub fn from_path(path: &Path) -> Result<Self, crate::Error> {
-        let mut days: Vec<DayListing> = path
-            .read_dir()?
-            .filter_map(Result::ok)
-            .filter(|de| {
-                de.path().is_file()
-                    && de.path().extension() == Some(OsStr::new(DAY_EXTENTION))
-                    && de.path().file_name() != Some(OsStr::new(RECURRING_FILE))
-            })
-            .filter_map(|de| {
-                date_from_path(&de.path())
-                    .map(|date| (date, de.path().to_owned()))
-                    .ok()
+        Ok(Self::from_files(list_files(path)?))
+    }
+
+    /// Lists the directory like `from_path`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn from_path_async(path: &Path) -> Result<Self, crate::Error> {
+        let mut files = Vec::new();
+        let mut dirs = vec![(path.to_path_buf(), 0)];
+        while let Some((dir, depth)) = dirs.pop() {
+            let mut entries = tokio::fs::read_dir(&dir)
+                .await
+                .map_err(crate::Error::in_file(&dir))?;
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(crate::Error::in_file(&dir))?
+            {
+                match entry.file_type().await {
+                    Ok(kind) if kind.is_file() => files.push(entry.path()),
+                    Ok(kind) if kind.is_dir() && depth < 2 && is_date_folder(&entry.path()) => {
+                        dirs.push((entry.path(), depth + 1))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Self::from_files(files))
+    }
+
+    /// The day files among the files, sorted by date.
+    fn from_files(files: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut days: Vec<DayListing> = files
+            .into_iter()
+            .filter(|path| {
+                [DAY_EXTENTION, TASKPAPER_EXTENSION]
+                    .iter()
+                    .any(|ext| path.extension() == Some(OsStr::new(ext)))
+                    && path.file_name() != Some(OsStr::new(RECURRING_FILE))
             })
+            .filter_map(|path| date_from_path(&path).map(|date| (date, path)).ok())
             .collect();
         days.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        Ok(Self(days))
+        Self(days)
+    }
+
+    /// Only the days with a file name in one of the formats, like the
+    /// daily notes of Obsidian, leaving out other notes with a date.
+    pub(crate) fn named(mut self, formats: &[&OwnedFormatItem]) -> Self {
+        self.0.retain(|(date, path)| {
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            formats
+                .iter()
+                .any(|format| date.format(format).ok().as_deref() == stem)
+        });
+        self
     }
 
     pub fn last(&self) -> Option<&DayListing> {
         self.0.last()
     }
 
+    /// The last day on the date or before it, not one planned ahead with
+    /// `w0rk plan`.
+    pub fn last_until(&self, date: &Date) -> Option<&DayListing> {
+        self.0.iter().rev().find(|(day, _)| day <= date)
+    }
+
     pub fn iter(&self) -> std::slice::Iter<DayListing> {
         self.0.iter()
     }
 }
 
+#[cfg(feature = "fs")]
 impl IntoIterator for DaysList {
     type Item = DayListing;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -47,11 +121,49 @@ impl IntoIterator for DaysList {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Day {
     pub path: PathBuf,
     pub date: Date,
+    /// Frontmatter and headings above the first task, kept above the tasks.
+    pub preamble: String,
     pub tasks: Vec<Task>,
     pub notes: String,
+    /// The task lines as they were in the file, so `to_content` writes the
+    /// tasks that didn't change like they were written.
+    #[serde(default)]
+    pub(crate) sources: Vec<Source>,
+    /// The key of the workspace the day was read from, to write it
+    /// encrypted again.
+    #[serde(skip)]
+    pub(crate) key: Option<Key>,
+    /// The style new and renamed tasks are written in, the workspace's.
+    #[serde(skip)]
+    pub style: StyleConfig,
+    /// The days after which the day can't be written, the workspace's.
+    #[serde(skip)]
+    pub lock_after: Option<u32>,
+    /// The rewrites of the names and notes as they're shown, the
+    /// workspace's.
+    #[serde(skip)]
+    pub rewrites: Vec<Rewrite>,
+    /// The checkboxes the day is read with besides the usual ones, the
+    /// workspace's. See `CheckboxConfig`.
+    #[serde(skip)]
+    pub checkboxes: BTreeMap<String, TaskState>,
+}
+
+/// A task or subtask line of a day file, see `Day::sources`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Source {
+    pub subtask: bool,
+    pub state: TaskState,
+    pub name: String,
+    pub line: String,
+    /// The lines below the task that belong to it, like the child blocks
+    /// of a Logseq block that aren't tasks, written below it again.
+    #[serde(default)]
+    pub children: String,
 }
 
 impl Day {
@@ -59,55 +171,517 @@ impl Day {
         Ok(Self {
             path: path.into(),
             date: date_from_path(path)?,
+            preamble: String::new(),
             tasks: Vec::new(),
             notes: String::new(),
+            sources: Vec::new(),
+            key: None,
+            style: StyleConfig::default(),
+            lock_after: None,
+            rewrites: Vec::new(),
+            checkboxes: BTreeMap::new(),
         })
     }
 
+    #[cfg(feature = "fs")]
     pub fn from_path(path: &Path) -> Result<Self, crate::Error> {
-        let content = std::fs::read_to_string(path)?;
-        let (tasks, notes) = parse_day_content(&content);
-        Ok(Self {
+        Self::from_path_with_diagnostics(path).map(|(day, _)| day)
+    }
+
+    /// Reads the day like `from_path`, with the lines that look like tasks
+    /// but aren't, like `* [y] Deploy`. Those are kept as notes.
+    #[cfg(feature = "fs")]
+    pub fn from_path_with_diagnostics(
+        path: &Path,
+    ) -> Result<(Self, Vec<Diagnostic>), crate::Error> {
+        Self::read(path, None, &BTreeMap::new())
+    }
+
+    /// Reads the day like `from_path_with_diagnostics`, decrypting it with
+    /// the key and keeping it to write the day encrypted. The checkboxes are
+    /// read besides the usual ones, see `parse_with_checkboxes`.
+    #[cfg(feature = "fs")]
+    pub fn read(
+        path: &Path,
+        key: Option<&Key>,
+        checkboxes: &BTreeMap<String, TaskState>,
+    ) -> Result<(Self, Vec<Diagnostic>), crate::Error> {
+        let content = read_day_file(path, key)?;
+        let (mut day, diagnostics) = Self::parse_with_checkboxes(path, &content, checkboxes)?;
+        day.key = key.cloned();
+        Ok((day, diagnostics))
+    }
+
+    /// Reads the day like `from_path`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn from_path_async(path: &Path) -> Result<Self, crate::Error> {
+        Self::read_async(path, None, &BTreeMap::new()).await
+    }
+
+    /// Reads the day like `read`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub(crate) async fn read_async(
+        path: &Path,
+        key: Option<&Key>,
+        checkboxes: &BTreeMap<String, TaskState>,
+    ) -> Result<Self, crate::Error> {
+        let content = tokio::fs::read(path)
+            .await
+            .map_err(crate::Error::in_file(path))?;
+        let content = decode(content, key).map_err(crate::Error::in_file(path))?;
+        let (mut day, _) = Self::parse_with_checkboxes(path, &content, checkboxes)?;
+        day.key = key.cloned();
+        Ok(day)
+    }
+
+    /// Parses the content of the day file at the path, the path is only
+    /// used for the date and the dialect. Returns the diagnostics like
+    /// `from_path_with_diagnostics`.
+    pub fn parse(path: &Path, content: &str) -> Result<(Self, Vec<Diagnostic>), crate::Error> {
+        Self::parse_with_checkboxes(path, content, &BTreeMap::new())
+    }
+
+    /// Parses the day like `parse`, reading the checkboxes in the table as
+    /// their state besides the usual ones, see `CheckboxConfig`.
+    pub fn parse_with_checkboxes(
+        path: &Path,
+        content: &str,
+        checkboxes: &BTreeMap<String, TaskState>,
+    ) -> Result<(Self, Vec<Diagnostic>), crate::Error> {
+        let mut diagnostics = Vec::new();
+        let mut sources = Vec::new();
+        let (preamble, tasks, notes) = match Dialect::from_path(path) {
+            Dialect::Markdown => {
+                parse_day_content(content, checkboxes, &mut diagnostics, &mut sources)
+            }
+            Dialect::Taskpaper => crate::taskpaper::parse(content, &mut sources),
+            Dialect::Logseq => crate::logseq::parse(content, &mut sources),
+        };
+        let day = Self {
             path: path.into(),
             date: date_from_path(path)?,
+            preamble,
             tasks,
             notes,
+            sources,
+            key: None,
+            style: StyleConfig::default(),
+            lock_after: None,
+            rewrites: Vec::new(),
+            checkboxes: checkboxes.clone(),
+        };
+        Ok((day, diagnostics))
+    }
+
+    /// Marks every task (or subtask) with the given name as completed, updating
+    /// the parent's state. Returns whether any task changed.
+    pub fn complete_task(&mut self, name: &str) -> bool {
+        let mut changed = false;
+        for task in self.tasks.iter_mut() {
+            if task.name == name && task.state != TaskState::Completed {
+                task.set_state(TaskState::Completed);
+                changed = true;
+            }
+            if let Some(index) = task
+                .subtasks
+                .iter()
+                .position(|subtask| subtask.name == name && subtask.state != TaskState::Completed)
+            {
+                task.mark_subtask_complete(index);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Sets the state of the task at `index`, or of its subtask when given,
+    /// updating the parent's state, see `Task::set_state`. Returns whether
+    /// the task exists.
+    pub fn set_task_state(
+        &mut self,
+        index: usize,
+        subtask: Option<usize>,
+        state: TaskState,
+    ) -> bool {
+        let task = match self.tasks.get_mut(index) {
+            Some(task) => task,
+            None => return false,
+        };
+        match subtask {
+            Some(subtask) => match task.subtasks.get_mut(subtask) {
+                Some(subtask) => {
+                    subtask.state = state;
+                    task.update_state_from_subtasks();
+                    true
+                }
+                None => false,
+            },
+            None => {
+                task.set_state(state);
+                true
+            }
+        }
+    }
+
+    /// The `@blocked-by:` dependencies of the task that aren't completed in
+    /// the day. One that's not in the day stays open, `w0rk lint` reports
+    /// it.
+    pub fn open_dependencies(&self, task: &Task) -> Vec<String> {
+        task.blocked_by()
+            .into_iter()
+            .filter(|dependency| {
+                !self.task_entries().any(|entry| {
+                    entry.task.is_dependency(dependency) && entry.task.state == TaskState::Completed
+                })
+            })
+            .collect()
+    }
+
+    /// Removes the `@blocked-by:` annotations of the task and its subtasks
+    /// for the dependencies completed in the day, which aren't carried over
+    /// with it. A blocked task without dependencies left is opened.
+    pub fn remove_completed_dependencies(&self, task: &mut Task) {
+        let open = self.open_dependencies(task);
+        let dependencies = task.blocked_by();
+        if !dependencies.is_empty() {
+            for dependency in dependencies
+                .iter()
+                .filter(|dependency| !open.contains(dependency))
+            {
+                task.remove_dependency(dependency);
+            }
+            if open.is_empty() && task.state == TaskState::Blocked {
+                task.state = TaskState::Incomplete;
+            }
+        }
+        for subtask in task.subtasks.iter_mut() {
+            self.remove_completed_dependencies(subtask);
+        }
+    }
+
+    /// Blocks the open tasks and subtasks with an open dependency, see
+    /// `open_dependencies`, and opens the blocked ones whose dependencies
+    /// are all completed. Returns the names of the opened tasks.
+    pub fn update_dependencies(&mut self) -> Vec<String> {
+        // Whether each entry is blocked, `None` without dependencies.
+        let mut blocked = self
+            .task_entries()
+            .map(|entry| match entry.task.blocked_by().is_empty() {
+                true => None,
+                false => Some(!self.open_dependencies(entry.task).is_empty()),
+            })
+            .collect::<Vec<Option<bool>>>()
+            .into_iter();
+
+        let mut opened = Vec::new();
+        let mut update = |task: &mut Task, blocked: Option<bool>| match (&task.state, blocked) {
+            (TaskState::Incomplete, Some(true)) => task.state = TaskState::Blocked,
+            (TaskState::Blocked, Some(false)) => {
+                task.state = TaskState::Incomplete;
+                opened.push(task.name.clone());
+            }
+            _ => {}
+        };
+        for task in self.tasks.iter_mut() {
+            update(task, blocked.next().flatten());
+            for subtask in task.subtasks.iter_mut() {
+                update(subtask, blocked.next().flatten());
+            }
+        }
+        opened
+    }
+
+    /// The index in `tasks` of the task at the position in the file, the
+    /// first task is 1.
+    pub fn task_index(&self, position: usize) -> Result<usize, crate::Error> {
+        position
+            .checked_sub(1)
+            .filter(|index| *index < self.tasks.len())
+            .ok_or(crate::Error::NoTaskAt(position))
+    }
+
+    /// The tasks of the day with their date, each task followed by its
+    /// subtasks.
+    pub fn task_entries(&self) -> impl Iterator<Item = TaskEntry<'_>> {
+        self.tasks.iter().flat_map(move |task| {
+            let entry = TaskEntry {
+                date: self.date,
+                task,
+                parent: None,
+            };
+            std::iter::once(entry).chain(task.subtasks.iter().map(move |subtask| TaskEntry {
+                date: self.date,
+                task: subtask,
+                parent: Some(task),
+            }))
         })
     }
 
-    pub fn write(&self) -> Result<(), crate::Error> {
-        let content = self
+    /// A copy of the day with only the tasks matching the predicate. Tasks
+    /// with subtasks are kept with their matching subtasks when the task
+    /// itself doesn't match.
+    pub fn filtered<P>(&self, predicate: P) -> Day
+    where
+        P: Fn(&Task) -> bool,
+    {
+        let tasks = self
             .tasks
             .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<String>>()
-            .join("");
-        let content = format!("{}\n{}", content, self.notes);
-        std::fs::write(&self.path, content)?;
-        Ok(())
+            .filter_map(|task| {
+                if predicate(task) {
+                    return Some(task.clone());
+                }
+                let subtasks: Vec<Task> = task
+                    .subtasks
+                    .iter()
+                    .filter(|subtask| predicate(subtask))
+                    .cloned()
+                    .collect();
+                match subtasks.is_empty() {
+                    true => None,
+                    false => Some(Task {
+                        subtasks,
+                        ..task.clone()
+                    }),
+                }
+            })
+            .collect();
+
+        Day {
+            path: self.path.clone(),
+            date: self.date,
+            preamble: self.preamble.clone(),
+            tasks,
+            notes: self.notes.clone(),
+            sources: self.sources.clone(),
+            key: self.key.clone(),
+            style: self.style,
+            lock_after: self.lock_after,
+            rewrites: self.rewrites.clone(),
+            checkboxes: self.checkboxes.clone(),
+        }
+    }
+
+    /// The text with the rewrites of the config, like the names and notes
+    /// of the day in the reports and sinks.
+    pub fn rewrite(&self, text: &str) -> String {
+        crate::config::rewrite(text, &self.rewrites)
     }
+
+    /// The name of the task as it's shown, see `Task::display_name`, with
+    /// the rewrites of the config.
+    pub fn display_name(&self, task: &Task) -> String {
+        self.rewrite(&task.display_name())
+    }
+
+    /// Whether the day is too old to be written, see `is_locked`.
+    pub fn is_locked(&self) -> bool {
+        is_locked(&self.date, self.lock_after)
+    }
+
+    /// Writes the day file, encrypted when it was read from an encrypted
+    /// workspace, see `write_day_file`. Fails for locked days.
+    #[cfg(feature = "fs")]
+    pub fn write(&self) -> Result<(), crate::Error> {
+        if self.is_locked() {
+            return Err(crate::Error::DayIsLocked(self.date));
+        }
+        write_day_content(&self.path, self.to_content(), self.key.as_ref())
+    }
+
+    /// The day to share, for `w0rk show`: its date, the tasks with the
+    /// markers of the style and pinned ones first, and the notes, with the
+    /// names as they're shown.
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# {}\n\n", self.date);
+        let mut tasks: Vec<&Task> = self.tasks.iter().collect();
+        tasks.sort_by_key(|task| !task.is_pinned());
+        for task in tasks {
+            text.push_str(&format!(
+                "{} {}\n",
+                task.state.marker(&self.style),
+                self.display_name(task)
+            ));
+            for subtask in &task.subtasks {
+                text.push_str(&format!(
+                    "  {} {}\n",
+                    subtask.state.marker(&self.style),
+                    self.display_name(subtask)
+                ));
+            }
+        }
+        let notes = self.notes.trim();
+        if !notes.is_empty() {
+            text.push_str(&format!("\n{}\n", self.rewrite(notes)));
+        }
+        text
+    }
+
+    /// The content of the day file, in the dialect of its path.
+    pub fn to_content(&self) -> String {
+        let content = match Dialect::from_path(&self.path) {
+            Dialect::Markdown => {
+                let mut sources: Vec<&Source> = self.sources.iter().collect();
+                let mut tasks = String::new();
+                for task in &self.tasks {
+                    tasks.push_str(&task_line(task, false, &self.style, &mut sources));
+                    for subtask in &task.subtasks {
+                        tasks.push_str(&task_line(subtask, true, &self.style, &mut sources));
+                    }
+                }
+                format!("{}\n{}", tasks, self.notes.trim_start_matches('\n'))
+            }
+            Dialect::Taskpaper => {
+                crate::taskpaper::to_string(&self.tasks, &self.notes, self.date, &self.sources)
+            }
+            Dialect::Logseq => crate::logseq::to_string(&self.tasks, &self.notes, &self.sources),
+        };
+        format!("{}{}", self.preamble, content)
+    }
+}
+
+/// Whether the day of the date is locked, when it's more than `lock_after`
+/// days before today. None are locked without it, see `lock_after_days`.
+pub fn is_locked(date: &Date, lock_after: Option<u32>) -> bool {
+    is_locked_on(date, &time::OffsetDateTime::now_utc().date(), lock_after)
+}
+
+fn is_locked_on(date: &Date, today: &Date, lock_after: Option<u32>) -> bool {
+    lock_after.is_some_and(|days| (*today - *date).whole_days() > i64::from(days))
 }
 
-fn parse_day_content(content: &str) -> (Vec<Task>, String) {
+/// The line of the task, the one it was read from when it didn't change
+/// since, or that line with the new state when only the state changed.
+/// Those are taken from the sources, so each is used once.
+fn task_line(
+    task: &Task,
+    subtask: bool,
+    style: &StyleConfig,
+    sources: &mut Vec<&Source>,
+) -> String {
+    let same = |source: &&Source| source.subtask == subtask && source.name == task.name;
+    if let Some(index) = sources
+        .iter()
+        .position(|source| same(source) && source.state == task.state)
+    {
+        return format!("{}\n", sources.remove(index).line);
+    }
+    if let Some(index) = sources.iter().position(same) {
+        let line = &sources.remove(index).line;
+        let state = format!("${{prefix}}{}]", task.state);
+        return format!("{}\n", CHECKBOX_REGEX.replace(line, state.as_str()));
+    }
+    format!(
+        "{}{} {}\n",
+        if subtask { "  " } else { "" },
+        task.state.marker(style),
+        task.name
+    )
+}
+
+/// A task and the day it's on, see `Day::task_entries`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskEntry<'a> {
+    pub date: Date,
+    pub task: &'a Task,
+    /// The task this is a subtask of.
+    pub parent: Option<&'a Task>,
+}
+
+/// A line of a day file that looks like a task but isn't one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub content: String,
+    pub reason: String,
+}
+
+/// Splits a day file in the lines above the first task, the tasks and the
+/// notes. Frontmatter, fenced code blocks, quotes (like Obsidian's
+/// callouts) and meeting blocks are never tasks. Lines that look like tasks
+/// but don't parse are added to the diagnostics.
+fn parse_day_content(
+    content: &str,
+    checkboxes: &BTreeMap<String, TaskState>,
+    diagnostics: &mut Vec<Diagnostic>,
+    sources: &mut Vec<Source>,
+) -> (String, Vec<Task>, String) {
+    let mut preamble = String::new();
     let mut tasks: Vec<Task> = Vec::new();
     let mut notes = String::new();
+    let mut frontmatter = false;
+    let mut fence: Option<&str> = None;
+    // The heading level of the meeting block the line is in.
+    let mut meeting: Option<usize> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let text = match tasks.is_empty() {
+            true => &mut preamble,
+            false => &mut notes,
+        };
+        let start = line.trim_start();
+        let verbatim = if index == 0 && line.trim_end() == "---" {
+            frontmatter = true;
+            true
+        } else if frontmatter {
+            frontmatter = line.trim_end() != "---";
+            true
+        } else if let Some(marker) = ["```", "~~~"].into_iter().find(|m| start.starts_with(m)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                Some(open) => Some(open),
+                None => Some(marker),
+            };
+            true
+        } else if fence.is_some() {
+            true
+        } else if let Some((level, starts_meeting)) = crate::meeting::heading(line) {
+            meeting = match starts_meeting {
+                true => Some(level),
+                false => meeting.filter(|open| level > *open),
+            };
+            true
+        } else {
+            meeting.is_some() || start.starts_with('>')
+        };
+        if verbatim {
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        }
 
-    for line in content.lines() {
         let (subtask, trimmed_line) = match line.starts_with("  ") || line.starts_with('\t') {
             true => (true, line.trim_start_matches("  ").trim_start_matches('\t')),
             false => (false, line),
         };
 
         // Attempt to parse the line as a task
-        let task: Task = match trimmed_line.try_into() {
+        let task = match Task::parse(trimmed_line, checkboxes) {
             Ok(task) => task,
             Err(_) => {
-                notes.push_str(line);
+                if let Some(reason) = diagnose(trimmed_line.trim_start(), checkboxes) {
+                    diagnostics.push(Diagnostic {
+                        line: index + 1,
+                        content: line.to_string(),
+                        reason,
+                    });
+                }
+                text.push_str(line);
+                text.push('\n');
                 continue;
             }
         };
 
         // Check if it's a subtask, if so add it to the last task's subtasks, if present
+        let subtask = subtask && !tasks.is_empty();
+        sources.push(Source {
+            subtask,
+            state: task.state.clone(),
+            name: task.name.clone(),
+            line: line.to_string(),
+            children: String::new(),
+        });
         if subtask {
             if let Some(last_task) = tasks.last_mut() {
                 last_task.subtasks.push(task);
@@ -119,24 +693,153 @@ fn parse_day_content(content: &str) -> (Vec<Task>, String) {
         tasks.push(task);
     }
 
-    (tasks, notes)
+    // Without tasks there's nothing to keep the text above of.
+    if tasks.is_empty() {
+        sources.clear();
+        return (String::new(), tasks, preamble);
+    }
+    (preamble, tasks, notes)
+}
+
+/// Reads a day file, decrypting it with the key when it's encrypted.
+#[cfg(feature = "fs")]
+pub(crate) fn read_day_file(path: &Path, key: Option<&Key>) -> Result<String, crate::Error> {
+    let content = std::fs::read(path).map_err(crate::Error::in_file(path))?;
+    decode(content, key).map_err(crate::Error::in_file(path))
+}
+
+/// The files in the directory and in its year and month folders, like
+/// `2024/07/2024-07-01.md`, see `Layout`.
+#[cfg(feature = "fs")]
+pub(crate) fn list_files(dir: &Path) -> Result<Vec<PathBuf>, crate::Error> {
+    let mut files = Vec::new();
+    let mut dirs = vec![(dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        for path in dir
+            .read_dir()
+            .map_err(crate::Error::in_file(&dir))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+        {
+            if path.is_file() {
+                files.push(path);
+            } else if path.is_dir() && depth < 2 && is_date_folder(&path) {
+                dirs.push((path, depth + 1));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Whether the folder is a year or a month folder, named with digits.
+#[cfg(feature = "fs")]
+fn is_date_folder(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()))
 }
 
-fn date_from_path(path: &Path) -> Result<Date, crate::Error> {
+/// Writes the content to a file next to the day file first and moves that
+/// over it, so the day file is never left half written.
+#[cfg(feature = "fs")]
+pub(crate) fn write_day_file(path: &Path, content: impl AsRef<[u8]>) -> Result<(), crate::Error> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+        std::fs::create_dir_all(parent).map_err(crate::Error::in_file(parent))?;
+    }
+    std::fs::write(&temp_path, content)
+        .and_then(|_| std::fs::rename(&temp_path, path))
+        .map_err(|err| {
+            // Don't leave a half written file next to the day.
+            let _ = std::fs::remove_file(&temp_path);
+            crate::Error::in_file(path)(err)
+        })
+}
+
+/// Writes the content to the day file, encrypted when there's a key.
+#[cfg(feature = "fs")]
+pub(crate) fn write_day_content(
+    path: &Path,
+    content: String,
+    key: Option<&Key>,
+) -> Result<(), crate::Error> {
+    match key {
+        #[cfg(feature = "encryption")]
+        Some(key) => write_day_file(path, key.encrypt(&content)?),
+        _ => write_day_file(path, content),
+    }
+}
+
+#[cfg(feature = "fs")]
+pub(crate) fn decode(content: Vec<u8>, key: Option<&Key>) -> Result<String, crate::Error> {
+    if is_encrypted(&content) {
+        return match key {
+            #[cfg(feature = "encryption")]
+            Some(key) => key.decrypt(&content),
+            _ => Err(crate::Error::Encrypted),
+        };
+    }
+    String::from_utf8(content)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err).into())
+}
+
+/// Whether the content is encrypted with age, armored or not.
+#[cfg(feature = "fs")]
+pub(crate) fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(b"age-encryption.org/")
+        || content.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----")
+}
+
+/// Why a line that failed to parse as a task looks like one anyway.
+fn diagnose(line: &str, checkboxes: &BTreeMap<String, TaskState>) -> Option<String> {
+    let captures = SUSPECT_TASK_REGEX.captures(line)?;
+    let state = &captures["state"];
+    let reason = if &captures["bullet"] == "+" {
+        "`+` is not a task bullet, use `*` or `-`".to_string()
+    } else if crate::task::parse_state(state, checkboxes).is_err() {
+        format!(
+            "unknown state `[{}]`, expected `[ ]`, `[x]`, `[~]`, `[#]` or `[>]`",
+            state
+        )
+    } else if captures
+        .name("name")
+        .is_none_or(|name| name.as_str().trim().is_empty())
+    {
+        "the task has no name".to_string()
+    } else {
+        "expected `* [ ] <name>`".to_string()
+    };
+    Some(reason)
+}
+
+/// The first `YYYY-MM-DD` date in the file name, so names like
+/// `2024-07-01 Monday.md` from Obsidian's daily notes work too, or
+/// `YYYY_MM_DD` for Logseq's journals.
+pub(crate) fn date_from_path(path: &Path) -> Result<Date, crate::Error> {
+    let invalid = || crate::Error::InvalidDayPath(path.to_string_lossy().to_string());
     let file_stem = path
         .file_stem()
         .and_then(|stem| stem.to_str())
-        .ok_or_else(|| crate::Error::InvalidDayPath(path.to_string_lossy().to_string()))?;
-    Date::parse(file_stem, &DAY_FORMAT).map_err(|err| err.into())
+        .ok_or_else(invalid)?;
+    let date = FILE_DATE_REGEX.find(file_stem).ok_or_else(invalid)?;
+    parse_date(&date.as_str().replace('_', "-")).map_err(crate::Error::in_file(path))
+}
+
+/// Parses a `YYYY-MM-DD` date, the format used for the day files.
+pub fn parse_date(value: &str) -> Result<Date, crate::Error> {
+    Date::parse(value, &DAY_FORMAT).map_err(|err| err.into())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "fs")]
     use crate::tests::helpers::test_fixtures_path;
     use time::Month;
 
     #[test]
+    #[cfg(feature = "fs")]
     fn test_day_list_from_path() {
         let path = test_fixtures_path().join("work");
         let days_list = DaysList::from_path(&path).expect("Could not create days list");
@@ -148,6 +851,22 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_day_list_last_until() {
+        let days_list = DaysList::from_files(
+            ["2024-07-01.md", "2024-07-02.md", "2024-07-04.md"].map(PathBuf::from),
+        );
+        let last_until = |date: &str| {
+            days_list
+                .last_until(&parse_date(date).unwrap())
+                .map(|(date, _)| date.to_string())
+        };
+        assert_eq!(last_until("2024-07-03").as_deref(), Some("2024-07-02"));
+        assert_eq!(last_until("2024-07-04").as_deref(), Some("2024-07-04"));
+        assert_eq!(last_until("2024-06-30"), None);
+    }
+
     #[test]
     fn test_date_from_path() {
         let path = Path::new("2021-01-01.md");
@@ -158,17 +877,269 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_date_from_path_error() {
+        let err = Day::from_path(Path::new("/work/2024-13-01.md"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("/work/2024-13-01.md: "));
+        let err = date_from_path(Path::new("/work/2024-13-01.md")).unwrap_err();
+        assert!(matches!(err, crate::Error::File { .. }));
+    }
+
+    #[test]
+    fn test_obsidian_date_from_path() {
+        let date = date_from_path(Path::new("Daily 2021-01-01 Friday.md")).unwrap();
+        assert_eq!(date, parse_date("2021-01-01").unwrap());
+        assert!(date_from_path(Path::new("Friday.md")).is_err());
+    }
+
+    #[test]
+    fn test_is_locked_on() {
+        let today = parse_date("2024-07-31").unwrap();
+        let date = parse_date("2024-07-01").unwrap();
+        assert!(!is_locked_on(&date, &today, None));
+        assert!(!is_locked_on(&date, &today, Some(30)));
+        assert!(is_locked_on(&date, &today, Some(29)));
+        assert!(!is_locked_on(&today, &today, Some(0)));
+    }
+
+    #[test]
+    fn test_complete_task() {
+        let mut day = Day::new(Path::new("2021-01-01.md")).expect("Could not create day");
+        let (_, tasks, _) = parse_day_content(
+            "* [ ] Logs\n  * [x] Log subtask\n  * [ ] Other subtask\n",
+            &BTreeMap::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+        day.tasks = tasks;
+
+        assert!(day.complete_task("Other subtask"));
+        assert_eq!(day.tasks[0].state, TaskState::Completed);
+        assert!(!day.complete_task("Other subtask"));
+        assert!(!day.complete_task("Missing"));
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_notes_round_trip() {
+        let path = std::env::temp_dir().join("2021-01-02.md");
+        std::fs::write(&path, "* [ ] Logs\n\nFirst line\n\nSecond line\n").unwrap();
+
+        let day = Day::from_path(&path).expect("Could not read day");
+        assert_eq!(day.notes, "\nFirst line\n\nSecond line\n");
+        day.write().expect("Could not write day");
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "* [ ] Logs\n\nFirst line\n\nSecond line\n"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_unchanged_lines() {
+        let content = "- [ ]  Deploy \n-[x] Review\n\t- [ ] Tests\n\t- [ ] Docs\n";
+        let mut day = Day::parse(Path::new("2024-07-01.md"), content).unwrap().0;
+        assert_eq!(day.to_content(), format!("{}\n", content));
+
+        day.tasks[1].subtasks[1].state = TaskState::Completed;
+        day.tasks.swap(0, 1);
+        day.tasks.push("* [ ] Release".try_into().unwrap());
+        assert_eq!(
+            day.to_content(),
+            "-[x] Review\n\t- [ ] Tests\n\t- [x] Docs\n- [ ]  Deploy \n* [ ] Release\n\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_obsidian_round_trip() {
+        let path = std::env::temp_dir().join("2021-01-03 Sunday.md");
+        let content = "---\n\
+                       tags: [daily]\n\
+                       ---\n\
+                       ## Tasks\n\
+                       - [ ] Call [[Alice|Alice B]] about [[Project X#Budget]] 📅 2021-01-05\n\
+                       \n\
+                       > [!todo] Later\n\
+                       > - [ ] Not a task\n\
+                       ```\n\
+                       - [ ] Not a task either\n\
+                       ```\n";
+        std::fs::write(&path, content).unwrap();
+
+        let day = Day::from_path(&path).expect("Could not read day");
+        assert_eq!(day.tasks.len(), 1);
+        assert_eq!(day.tasks[0].due(), parse_date("2021-01-05").ok());
+        assert_eq!(day.preamble, "---\ntags: [daily]\n---\n## Tasks\n");
+        day.write().expect("Could not write day");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_set_task_state() {
+        let mut day = Day::new(Path::new("2021-01-01.md")).expect("Could not create day");
+        let (_, tasks, _) = parse_day_content(
+            "* [ ] Deploy\n* [ ] Release\n  * [ ] Changelog\n",
+            &BTreeMap::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+        day.tasks = tasks;
+
+        assert!(day.set_task_state(0, None, TaskState::Blocked));
+        assert_eq!(day.tasks[0].state, TaskState::Blocked);
+        assert!(day.set_task_state(1, Some(0), TaskState::Completed));
+        assert_eq!(day.tasks[1].state, TaskState::Completed);
+        assert!(!day.set_task_state(1, Some(1), TaskState::Completed));
+
+        day.tasks[1].subtasks[0].state = TaskState::Incomplete;
+        day.tasks[1].state = TaskState::Incomplete;
+        assert!(day.set_task_state(1, None, TaskState::Completed));
+        assert_eq!(day.tasks[1].subtasks[0].state, TaskState::Completed);
+        assert!(!day.set_task_state(2, None, TaskState::Completed));
+    }
+
+    #[test]
+    fn test_update_dependencies() {
+        let (mut day, _) = Day::parse(
+            Path::new("2021-01-01.md"),
+            "* [ ] Deploy @id:deploy\n* [ ] Announce @blocked-by:deploy\n\
+             * [~] Release\n  * [ ] Tag @blocked-by:\"Write the changelog\"\n\
+             * [ ] Write the changelog\n",
+        )
+        .unwrap();
+
+        assert!(day.update_dependencies().is_empty());
+        assert_eq!(day.tasks[1].state, TaskState::Blocked);
+        assert_eq!(day.tasks[2].subtasks[0].state, TaskState::Blocked);
+
+        day.set_task_state(0, None, TaskState::Completed);
+        assert_eq!(
+            day.update_dependencies(),
+            vec!["Announce @blocked-by:deploy"]
+        );
+        assert_eq!(day.tasks[1].state, TaskState::Incomplete);
+        // A dependency that's not in the day stays open.
+        let mut changelog = day.tasks.pop().unwrap();
+        assert!(day.update_dependencies().is_empty());
+        assert_eq!(day.tasks[2].subtasks[0].state, TaskState::Blocked);
+
+        // Completed dependencies aren't carried over to the next day, and
+        // neither are their annotations.
+        changelog.state = TaskState::Completed;
+        day.tasks.push(changelog);
+        let mut release = day.tasks[2].clone();
+        day.remove_completed_dependencies(&mut release);
+        assert_eq!(release.subtasks[0].name, "Tag");
+        assert_eq!(release.subtasks[0].state, TaskState::Incomplete);
+    }
+
+    #[test]
+    fn test_task_index() {
+        let (day, _) =
+            Day::parse(Path::new("2021-01-01.md"), "* [ ] Deploy\n* [ ] Review\n").unwrap();
+        assert_eq!(day.task_index(2).unwrap(), 1);
+        assert!(matches!(day.task_index(0), Err(crate::Error::NoTaskAt(0))));
+        assert!(matches!(day.task_index(3), Err(crate::Error::NoTaskAt(3))));
+    }
+
+    #[test]
+    fn test_filtered() {
+        let mut day = Day::new(Path::new("2021-01-01.md")).expect("Could not create day");
+        let (_, tasks, _) = parse_day_content(
+            "* [ ] Deploy #public\n* [ ] Private\n* [ ] Release\n  * [ ] Announce #public\n  * [ ] Other\n",
+            &BTreeMap::new(),
+            &mut Vec::new(), &mut Vec::new(),
+        );
+        day.tasks = tasks;
+
+        let filtered = day.filtered(|task| task.has_tag("public"));
+        assert_eq!(filtered.tasks.len(), 2);
+        assert_eq!(filtered.tasks[0].name, "Deploy #public");
+        assert_eq!(filtered.tasks[1].name, "Release");
+        assert_eq!(filtered.tasks[1].subtasks.len(), 1);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let (mut day, _) = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [x] Deploy\n  * [ ] Build #12\n* [ ] Review !pin\n\nShipped #12.\n",
+        )
+        .unwrap();
+        day.rewrites = serde_json::from_str(r##"[{"from": "#(\\d+)", "to": "PR $1"}]"##).unwrap();
+
+        assert_eq!(
+            day.to_markdown(),
+            "# 2024-07-01\n\n* [ ] Review\n* [x] Deploy\n  * [ ] Build PR 12\n\nShipped PR 12.\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_to_content() {
+        let content = "---\ntags: [daily]\n---\n* [x] Deploy\n  * [x] Build\n* [y] Review\n";
+        let (day, diagnostics) = Day::parse(Path::new("journal/2024-07-01.md"), content).unwrap();
+
+        assert_eq!(day.date, parse_date("2024-07-01").unwrap());
+        assert_eq!(diagnostics[0].line, 6);
+        assert_eq!(day.to_content(), content.replace("* [y]", "\n* [y]"));
+    }
+
     #[test]
     fn test_parse_day_content() {
         let content = r#"
 * [ ] Logs
   * [ ] Log subtask
       "#;
-        let (tasks, _) = parse_day_content(content);
+        let (_, tasks, _) =
+            parse_day_content(content, &BTreeMap::new(), &mut Vec::new(), &mut Vec::new());
 
         assert_eq!(tasks.len(), 1);
         assert_eq!(tasks[0].name, "Logs");
         assert_eq!(tasks[0].subtasks.len(), 1);
         assert_eq!(tasks[0].subtasks[0].name, "Log subtask");
     }
+
+    #[test]
+    fn test_parse_day_content_diagnostics() {
+        let content = "* [ ] Deploy\n\
+                       * [y] Review\n\
+                       \x20\x20+ [ ] Tag\n\
+                       - [x]\n\
+                       *  [ ] Release\n\
+                       ```\n\
+                       * [?] In a code block\n\
+                       ```\n\
+                       Not a [task]\n\
+                       - [Docs](https://example.com)\n\
+                       - [a](https://example.com)\n";
+        let mut diagnostics = Vec::new();
+        let (_, tasks, notes) =
+            parse_day_content(content, &BTreeMap::new(), &mut diagnostics, &mut Vec::new());
+
+        assert_eq!(tasks.len(), 1);
+        assert!(notes.contains("* [y] Review"));
+        let reasons: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (
+                    2,
+                    "unknown state `[y]`, expected `[ ]`, `[x]`, `[~]`, `[#]` or `[>]`"
+                ),
+                (3, "`+` is not a task bullet, use `*` or `-`"),
+                (4, "the task has no name"),
+                (5, "expected `* [ ] <name>`"),
+            ]
+        );
+    }
 }
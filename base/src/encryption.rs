@@ -0,0 +1,130 @@
+//! Day files encrypted with age. A workspace opened with the `Key` from
+//! the config decrypts the day files it reads and encrypts the ones it
+//! writes, see `Config::workspace`.
+use age::secrecy::ExposeSecret;
+use age::x25519::Identity;
+use std::path::{Path, PathBuf};
+
+/// The identity the day files of a workspace are encrypted with, an
+/// `AGE-SECRET-KEY-1...` key like `age-keygen` makes.
+#[derive(Clone)]
+pub struct Key(Identity);
+
+impl Key {
+    pub fn parse(identity: &str) -> Result<Self, crate::Error> {
+        identity
+            .trim()
+            .parse::<Identity>()
+            .map(Self)
+            .map_err(|err| crate::Error::Encryption(err.to_string()))
+    }
+
+    pub(crate) fn encrypt(&self, content: &str) -> Result<Vec<u8>, crate::Error> {
+        encrypt_with(&self.0, content)
+    }
+
+    /// Decrypts an encrypted day file, see `is_encrypted`.
+    pub(crate) fn decrypt(&self, content: &[u8]) -> Result<String, crate::Error> {
+        decrypt_with(&self.0, content)
+    }
+}
+
+/// A new identity for `encryption` in the config.
+pub fn generate_identity() -> String {
+    Identity::generate().to_string().expose_secret().to_string()
+}
+
+/// Rewrites the day file encrypted, or in plain text when `encrypt` is
+/// false, without changing its content. Returns whether the file changed.
+pub fn convert_file(path: &Path, key: &Key, encrypt: bool) -> Result<bool, crate::Error> {
+    let raw = std::fs::read(path).map_err(crate::Error::in_file(path))?;
+    if crate::day::is_encrypted(&raw) == encrypt {
+        return Ok(false);
+    }
+    let content = crate::day::read_day_file(path, Some(key))?;
+    let content = match encrypt {
+        true => key.encrypt(&content)?,
+        false => content.into_bytes(),
+    };
+    crate::day::write_day_file(path, content)?;
+    Ok(true)
+}
+
+/// Removes the indexes and the cache in the state dir, which keep the tasks
+/// in plain text. They're not used for encrypted day files. Returns the
+/// removed ones.
+pub fn remove_plain_text_stores(state_dir: &Path) -> Result<Vec<PathBuf>, crate::Error> {
+    let mut stores = vec![state_dir.join(crate::cache::CACHE_FILE)];
+    #[cfg(feature = "index")]
+    stores.push(state_dir.join(crate::index::INDEX_FILE));
+    #[cfg(feature = "search")]
+    stores.push(state_dir.join(crate::search::SEARCH_DIR));
+
+    let mut removed = Vec::new();
+    for path in stores {
+        let result = match path.is_dir() {
+            true => std::fs::remove_dir_all(&path),
+            false => std::fs::remove_file(&path),
+        };
+        match result {
+            Ok(()) => removed.push(path),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(crate::Error::in_file(&path)(err)),
+        }
+    }
+    Ok(removed)
+}
+
+fn encrypt_with(identity: &Identity, content: &str) -> Result<Vec<u8>, crate::Error> {
+    age::encrypt_and_armor(&identity.to_public(), content.as_bytes())
+        .map(String::into_bytes)
+        .map_err(|err| crate::Error::Encryption(err.to_string()))
+}
+
+fn decrypt_with(identity: &Identity, content: &[u8]) -> Result<String, crate::Error> {
+    let content =
+        age::decrypt(identity, content).map_err(|err| crate::Error::Encryption(err.to_string()))?;
+    String::from_utf8(content).map_err(|err| crate::Error::Encryption(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_with() {
+        let identity: Identity = generate_identity().parse().unwrap();
+        let content = "* [ ] Call Acme about the contract\n";
+
+        let encrypted = encrypt_with(&identity, content).unwrap();
+        assert!(crate::day::is_encrypted(&encrypted));
+        assert!(!String::from_utf8_lossy(&encrypted).contains("Acme"));
+        assert_eq!(decrypt_with(&identity, &encrypted).unwrap(), content);
+
+        let other = Identity::generate();
+        assert!(matches!(
+            decrypt_with(&other, &encrypted),
+            Err(crate::Error::Encryption(_))
+        ));
+        assert!(Key::parse("AGE-SECRET-KEY-1NOPE").is_err());
+    }
+
+    #[test]
+    fn test_read_encrypted_day() {
+        let dir = std::env::temp_dir().join("w0rk-test-encrypted-day");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let identity = Identity::generate();
+        let path = dir.join("2024-07-01.md");
+        std::fs::write(&path, encrypt_with(&identity, "* [ ] Deploy\n").unwrap()).unwrap();
+
+        // Without the identity the day can't be read, rather than reading
+        // the encrypted file as notes.
+        match crate::Day::from_path(&path) {
+            Err(crate::Error::File { source, .. }) => {
+                assert!(matches!(*source, crate::Error::Encrypted))
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+}
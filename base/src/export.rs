@@ -0,0 +1,281 @@
+use crate::day::{parse_date, Day};
+use crate::report::DebtReport;
+use crate::task::{State as TaskState, Task, ANNOTATION_REGEX, TAG_REGEX};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use regex::Captures;
+use serde::Serialize;
+use time::Date;
+
+/// The dates from `from` up to and including `to`, either end can be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateRange {
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: &Date) -> bool {
+        self.from.is_none_or(|from| date >= &from) && self.to.is_none_or(|to| date <= &to)
+    }
+}
+
+/// Parses `2024-07-01..2024-07-31`, `2024-07-01..` or `..2024-07-31`.
+pub fn parse_range(value: &str) -> Result<DateRange, crate::Error> {
+    let (from, to) = value
+        .split_once("..")
+        .ok_or_else(|| crate::Error::InvalidDateRange(value.to_string()))?;
+    let date = |date: &str| match date.trim() {
+        "" => Ok(None),
+        date => parse_date(date)
+            .map(Some)
+            .map_err(|_| crate::Error::InvalidDateRange(value.to_string())),
+    };
+    Ok(DateRange {
+        from: date(from)?,
+        to: date(to)?,
+    })
+}
+
+/// A task of a day, subtasks get a row of their own.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskRecord {
+    pub date: String,
+    pub name: String,
+    pub state: TaskState,
+    pub tags: Vec<String>,
+    pub people: Vec<String>,
+    /// The name of the parent task for subtasks.
+    pub subtask_of: Option<String>,
+}
+
+impl TaskRecord {
+    fn new(date: Date, task: &Task, parent: Option<&Task>) -> Self {
+        Self {
+            date: date.to_string(),
+            name: task.name.clone(),
+            state: task.state.clone(),
+            tags: task.tags(),
+            people: task.people(),
+            subtask_of: parent.map(|parent| parent.name.clone()),
+        }
+    }
+}
+
+pub fn records(days: &[Day]) -> Vec<TaskRecord> {
+    let mut records = Vec::new();
+    for day in days {
+        for task in &day.tasks {
+            records.push(TaskRecord::new(day.date, task, None));
+            for subtask in &task.subtasks {
+                records.push(TaskRecord::new(day.date, subtask, Some(task)));
+            }
+        }
+    }
+    records
+}
+
+#[cfg(feature = "fs")]
+/// The tasks of every day in the range.
+pub fn workspace_records(
+    workspace: &Workspace,
+    range: &DateRange,
+) -> Result<Vec<TaskRecord>, crate::Error> {
+    let days = workspace.read_days(|date| range.contains(date))?;
+    Ok(records(&days))
+}
+
+/// One row per task with a header, tags and people separated by spaces.
+pub fn to_csv(records: &[TaskRecord]) -> String {
+    let mut csv = "date,name,state,tags,people,subtask_of\n".to_string();
+    for record in records {
+        let state = serde_json::to_value(&record.state)
+            .ok()
+            .and_then(|state| state.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let row = [
+            record.date.as_str(),
+            record.name.as_str(),
+            state.as_str(),
+            &record.tags.join(" "),
+            &record.people.join(" "),
+            record.subtask_of.as_deref().unwrap_or_default(),
+        ]
+        .map(csv_field)
+        .join(",");
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    csv
+}
+
+/// One JSON object per line.
+pub fn to_jsonl(records: &[TaskRecord]) -> Result<String, crate::Error> {
+    let mut jsonl = String::new();
+    for record in records {
+        jsonl.push_str(&serde_json::to_string(record)?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+/// The open tasks of the last day in todo.txt syntax, open subtasks instead
+/// of their task. Tasks in progress get priority `(A)`, blocked tasks `(C)`,
+/// the creation date is the day the task first showed up. Tags become
+/// `+projects`, or `@contexts` when they're in `contexts`, and annotations
+/// like `@due:2024-07-12` become `due:2024-07-12`.
+pub fn to_todotxt(days: &[Day], contexts: &[String]) -> String {
+    let Some(last) = days.last() else {
+        return String::new();
+    };
+    let first_seen: Vec<(String, Date)> = DebtReport::new(days)
+        .tasks
+        .into_iter()
+        .map(|task| (task.name, task.first_seen))
+        .collect();
+    let created = |task: &Task| {
+        let name = task.recurring_name();
+        first_seen
+            .iter()
+            .find(|(seen, _)| seen == &name)
+            .map_or(last.date, |(_, date)| *date)
+    };
+
+    let mut lines = String::new();
+    for task in &last.tasks {
+        if task.subtasks.is_empty() {
+            if task.state != TaskState::Completed {
+                lines.push_str(&todotxt_line(
+                    &task.state,
+                    created(task),
+                    &task.name,
+                    contexts,
+                ));
+            }
+            continue;
+        }
+        for subtask in &task.subtasks {
+            if subtask.state != TaskState::Completed {
+                let name = format!("{}: {}", task.name, subtask.name);
+                lines.push_str(&todotxt_line(
+                    &subtask.state,
+                    created(task),
+                    &name,
+                    contexts,
+                ));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(feature = "fs")]
+pub fn workspace_todotxt(
+    workspace: &Workspace,
+    contexts: &[String],
+) -> Result<String, crate::Error> {
+    let days = workspace.read_days(|_| true)?;
+    Ok(to_todotxt(&days, contexts))
+}
+
+fn todotxt_line(state: &TaskState, created: Date, name: &str, contexts: &[String]) -> String {
+    let priority = match state {
+        TaskState::InProgress => "(A) ",
+        TaskState::Blocked => "(C) ",
+        _ => "",
+    };
+    let text = TAG_REGEX.replace_all(name, |captures: &Captures| {
+        let tag = &captures["tag"];
+        let sigil = match contexts
+            .iter()
+            .any(|c| c.trim_start_matches('#').eq_ignore_ascii_case(tag))
+        {
+            true => '@',
+            false => '+',
+        };
+        captures[0].replacen(&format!("#{}", tag), &format!("{}{}", sigil, tag), 1)
+    });
+    let text = ANNOTATION_REGEX.replace_all(&text, "$space$key:$value");
+    format!("{}{} {}\n", priority, created, text)
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let range = parse_range("2024-07-01..2024-07-31").unwrap();
+        assert!(range.contains(&parse_date("2024-07-31").unwrap()));
+        assert!(!range.contains(&parse_date("2024-08-01").unwrap()));
+
+        let range = parse_range("..2024-07-31").unwrap();
+        assert_eq!(range.from, None);
+        assert!(range.contains(&parse_date("2020-01-01").unwrap()));
+
+        assert!(parse_range("2024-07-01").is_err());
+        assert!(parse_range("2024-07-01..tomorrow").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_export() {
+        let dir = std::env::temp_dir().join("w0rk-test-export");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("2024-07-01.md");
+        std::fs::write(
+            &path,
+            "* [~] Release #infra with @alice\n  * [x] Changelog, \"final\"\n",
+        )
+        .unwrap();
+        let records = records(&[Day::from_path(&path).unwrap()]);
+
+        assert_eq!(
+            to_csv(&records),
+            "date,name,state,tags,people,subtask_of\n\
+             2024-07-01,Release #infra with @alice,in_progress,infra,alice,\n\
+             2024-07-01,\"Changelog, \"\"final\"\"\",completed,,,Release #infra with @alice\n"
+        );
+        assert_eq!(
+            to_jsonl(&records).unwrap().lines().next().unwrap(),
+            r#"{"date":"2024-07-01","name":"Release #infra with @alice","state":"in_progress","tags":["infra"],"people":["alice"],"subtask_of":null}"#
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_todotxt() {
+        let dir = std::env::temp_dir().join("w0rk-test-todotxt");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut days = Vec::new();
+        for (date, content) in [
+            ("2024-07-01", "* [ ] Taxes #home\n* [x] Deploy\n"),
+            (
+                "2024-07-02",
+                "* [ ] Taxes #home\n* [~] Upgrade #infra @due:2024-07-12\n* [ ] Release\n  * [x] Changelog\n  * [#] Tag\n",
+            ),
+        ] {
+            let path = dir.join(format!("{}.md", date));
+            std::fs::write(&path, content).unwrap();
+            days.push(Day::from_path(&path).unwrap());
+        }
+
+        assert_eq!(
+            to_todotxt(&days, &["home".to_string()]),
+            "2024-07-01 Taxes @home\n\
+             (A) 2024-07-02 Upgrade +infra due:2024-07-12\n\
+             (C) 2024-07-02 Release: Tag\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
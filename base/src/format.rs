@@ -0,0 +1,139 @@
+//! Day files in their canonical form for `w0rk fmt`: the bullets and
+//! checkboxes of the style in the config (`* [ ]` by default), subtasks
+//! indented by two spaces and no trailing whitespace. Fenced code blocks are
+//! left as they are.
+//! The day is parsed and written again, like every command that changes it
+//! does, so the canonical form is what w0rk writes itself.
+use crate::config::StyleConfig;
+use crate::day::{read_day_file, write_day_content, Day};
+use crate::lint::fix_checkbox;
+use crate::task::State as TaskState;
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The content of the day file at the path in its canonical form, read with
+/// the checkboxes. Lines that almost are tasks, like `* [X]` or `+ []`, get
+/// the checkbox fix of `w0rk lint --fix` first.
+pub fn format_content(
+    path: &Path,
+    content: &str,
+    style: &StyleConfig,
+    checkboxes: &BTreeMap<String, TaskState>,
+) -> Result<String, crate::Error> {
+    let (_, diagnostics) = Day::parse_with_checkboxes(path, content, checkboxes)?;
+    let fenced = fenced_lines(content);
+    let mut lines: Vec<String> = content.lines().map(ToString::to_string).collect();
+    for diagnostic in diagnostics {
+        if fenced[diagnostic.line - 1] {
+            continue;
+        }
+        if let Some(fixed) = fix_checkbox(&diagnostic.content) {
+            lines[diagnostic.line - 1] = fixed;
+        }
+    }
+    let (mut day, _) = Day::parse_with_checkboxes(path, &lines.join("\n"), checkboxes)?;
+    // Every task line is written again, not only the ones that changed.
+    day.sources.clear();
+    day.style = *style;
+    let content = day.to_content();
+    Ok(content
+        .lines()
+        .zip(fenced_lines(&content))
+        .map(|(line, fenced)| match fenced {
+            true => format!("{}\n", line),
+            false => format!("{}\n", line.trim_end()),
+        })
+        .collect())
+}
+
+/// Whether each line of the content is inside a fenced code block, between
+/// two lines starting with ```` ``` ```` or `~~~`.
+fn fenced_lines(content: &str) -> Vec<bool> {
+    let mut fence: Option<&str> = None;
+    content
+        .lines()
+        .map(|line| {
+            let marker = ["```", "~~~"]
+                .into_iter()
+                .find(|marker| line.trim_start().starts_with(marker));
+            match (fence, marker) {
+                (None, Some(marker)) => {
+                    fence = Some(marker);
+                    false
+                }
+                (Some(open), Some(marker)) if open == marker => {
+                    fence = None;
+                    false
+                }
+                (fence, _) => fence.is_some(),
+            }
+        })
+        .collect()
+}
+
+/// Formats the day files of the workspace, returning the ones that changed.
+/// With `check` nothing is written, only the files that would change are
+/// returned. Locked days are left as they are.
+pub fn format(workspace: &Workspace, check: bool) -> Result<Vec<PathBuf>, crate::Error> {
+    let mut changed = Vec::new();
+    for (date, path) in workspace.day_list.iter() {
+        if workspace.is_locked(date) {
+            continue;
+        }
+        let content = read_day_file(path, workspace.key())?;
+        let formatted = format_content(path, &content, &workspace.style, &workspace.checkboxes)
+            .map_err(crate::Error::in_file(path))?;
+        if formatted == content {
+            continue;
+        }
+        if !check {
+            write_day_content(path, formatted, workspace.key())?;
+        }
+        changed.push(path.clone());
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_content() {
+        let path = Path::new("2024-07-01.md");
+        let style = StyleConfig::default();
+        let content = "# Monday  \n- [x]Deploy   \n    - [ ] Release\n+ [] Review\n* [X] Announce\n\nNotes \n";
+        let formatted = format_content(path, content, &style, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            formatted,
+            "# Monday\n* [x] Deploy\n  * [ ] Release\n* [ ] Review\n* [x] Announce\n\nNotes\n"
+        );
+        assert_eq!(
+            format_content(path, &formatted, &style, &BTreeMap::new()).unwrap(),
+            formatted
+        );
+
+        // The code keeps its trailing whitespace, and isn't fixed like tasks.
+        let content = "* [ ] Deploy\n\n```yaml  \nkey: value  \n+ [] item\n```\n";
+        assert_eq!(
+            format_content(path, content, &style, &BTreeMap::new()).unwrap(),
+            "* [ ] Deploy\n\n```yaml\nkey: value  \n+ [] item\n```\n"
+        );
+
+        let style = StyleConfig {
+            bullet: crate::Bullet::Dash,
+            spaced_checkbox: false,
+        };
+        assert_eq!(
+            format_content(
+                path,
+                "* [ ] Deploy\n  * [x] Tests\n\nNotes\n",
+                &style,
+                &BTreeMap::new()
+            )
+            .unwrap(),
+            "- [] Deploy\n  - [x] Tests\n\nNotes\n"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Your commits of the day in local repositories, as a section in the notes
+//! of the day:
+//!
+//! ```markdown
+//! ## Shipped
+//! - w0rk: Fix the parser (a1b2c3d)
+//! ```
+//!
+//! In a TaskPaper day it's a `Shipped:` project with the commits indented
+//! below it, in a Logseq journal a `- ## Shipped` block with the commits as
+//! its children. The section runs to the next heading, project or
+//! top-level block and is written again on every `w0rk sync` and
+//! `w0rk review`, see `GitConfig`.
+use crate::config::{Dialect, GitConfig};
+use crate::day::Day;
+use std::path::Path;
+use std::process::Command;
+use time::Date;
+
+/// The Shipped section in the dialect of a day file.
+struct Section(Dialect);
+
+impl Section {
+    fn of(day: &Day) -> Self {
+        Section(Dialect::from_path(&day.path))
+    }
+
+    fn heading(&self) -> &'static str {
+        match self.0 {
+            Dialect::Markdown => "## Shipped",
+            Dialect::Taskpaper => "Shipped:",
+            Dialect::Logseq => "- ## Shipped",
+        }
+    }
+
+    /// The line of the commit in the section.
+    fn item(&self, commit: &Commit) -> String {
+        let prefix = match self.0 {
+            Dialect::Markdown => "- ",
+            Dialect::Taskpaper => "\t",
+            Dialect::Logseq => "\t- ",
+        };
+        format!(
+            "{}{}: {} ({})\n",
+            prefix, commit.repo, commit.subject, commit.hash
+        )
+    }
+
+    /// What goes between the section and the rest of the notes, Logseq
+    /// blocks don't have blank lines between them.
+    fn separator(&self) -> &'static str {
+        match self.0 {
+            Dialect::Logseq => "\n",
+            _ => "\n\n",
+        }
+    }
+
+    fn is_heading(&self, line: &str) -> bool {
+        line.trim_end() == self.heading()
+    }
+
+    /// Whether the line ends the section: the next heading in Markdown, the
+    /// next line that isn't indented in the other dialects.
+    fn is_end(&self, line: &str) -> bool {
+        match self.0 {
+            Dialect::Markdown => line.starts_with('#'),
+            _ => !line.trim().is_empty() && !line.starts_with(char::is_whitespace),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    /// The name of the repository's folder.
+    pub repo: String,
+    pub hash: String,
+    pub subject: String,
+}
+
+/// The commits of the date by the author of the config, in the order of the
+/// repositories and oldest first. Merges are left out. A repository that
+/// can't be read, like a missing one or one without `user.email`, is
+/// skipped and its error returned with the commits of the others.
+pub fn commits(config: &GitConfig, date: Date) -> (Vec<Commit>, Vec<crate::Error>) {
+    let mut commits = Vec::new();
+    let mut errors = Vec::new();
+    for repo in &config.repos {
+        match repo_commits(repo, config.author.as_deref(), date) {
+            Ok(repo_commits) => commits.extend(repo_commits),
+            Err(err) => errors.push(err),
+        }
+    }
+    (commits, errors)
+}
+
+fn repo_commits(
+    repo: &Path,
+    author: Option<&str>,
+    date: Date,
+) -> Result<Vec<Commit>, crate::Error> {
+    let author = match author {
+        Some(author) => author.to_string(),
+        None => git(repo, &["config", "user.email"])?.trim().to_string(),
+    };
+    let next = date.next_day().unwrap_or(date);
+    let log = git(
+        repo,
+        &[
+            "log",
+            "--no-merges",
+            "--reverse",
+            &format!("--author={}", author),
+            &format!("--since={} 00:00", date),
+            &format!("--until={} 00:00", next),
+            "--format=%h %s",
+        ],
+    )?;
+    let name = repo.file_name().unwrap_or_default().to_string_lossy();
+    Ok(log
+        .lines()
+        .filter_map(|line| {
+            let (hash, subject) = line.split_once(' ')?;
+            Some(Commit {
+                repo: name.to_string(),
+                hash: hash.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect())
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String, crate::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|err| crate::Error::Git(format!("{}: {}", repo.to_string_lossy(), err)))?;
+    if !output.status.success() {
+        return Err(crate::Error::Git(format!(
+            "{}: {}",
+            repo.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The commits in the Shipped section of the day, the lines without their
+/// indentation.
+pub fn shipped(day: &Day) -> Vec<String> {
+    let section = Section::of(day);
+    // Without tasks the TaskPaper and Logseq sections are in the preamble.
+    let text = match day.notes.lines().any(|line| section.is_heading(line)) {
+        true => &day.notes,
+        false => &day.preamble,
+    };
+    let mut lines = text.lines().skip_while(|line| !section.is_heading(line));
+    if lines.next().is_none() {
+        return Vec::new();
+    }
+    lines
+        .take_while(|line| !section.is_end(line))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// Writes the commits as the Shipped section of the notes of the day, in
+/// its dialect and at the end unless it's there already. Returns whether
+/// the day changed, without commits it stays as it is.
+pub fn set_shipped(day: &mut Day, commits: &[Commit]) -> bool {
+    if commits.is_empty() {
+        return false;
+    }
+    let section = Section::of(day);
+    let mut lines = format!("{}\n", section.heading());
+    for commit in commits {
+        lines.push_str(&section.item(commit));
+    }
+    let separator = section.separator();
+
+    let has_heading = |text: &str| text.lines().any(|line| section.is_heading(line));
+    let text = match !has_heading(&day.notes) && has_heading(&day.preamble) {
+        true => &mut day.preamble,
+        false => &mut day.notes,
+    };
+    let current: Vec<&str> = text.lines().collect();
+    let new = match current.iter().position(|line| section.is_heading(line)) {
+        Some(start) => {
+            let end = current[start + 1..]
+                .iter()
+                .position(|line| section.is_end(line))
+                .map_or(current.len(), |end| start + 1 + end);
+            let before = current[..start].join("\n");
+            let after = current[end..].join("\n");
+            let before = match before.trim_end() {
+                "" => String::new(),
+                before => format!("{}{}", before, separator),
+            };
+            match after.trim() {
+                "" => format!("{}{}", before, lines),
+                after => format!("{}{}{}{}\n", before, lines.trim_end(), separator, after),
+            }
+        }
+        None => match text.trim_end() {
+            "" => lines,
+            text => format!("{}{}{}", text, separator, lines),
+        },
+    };
+    let changed = &new != text;
+    *text = new;
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_shipped() {
+        let mut day = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [x] Deploy\n\nWent well.\n\n## Retro\nMore tests.\n",
+        )
+        .unwrap()
+        .0;
+        let commit = |hash: &str, subject: &str| Commit {
+            repo: "w0rk".to_string(),
+            hash: hash.to_string(),
+            subject: subject.to_string(),
+        };
+
+        assert!(!set_shipped(&mut day, &[]));
+        assert!(set_shipped(
+            &mut day,
+            &[commit("a1b2c3d", "Fix the parser")]
+        ));
+        assert_eq!(
+            day.notes,
+            "\nWent well.\n\n## Retro\nMore tests.\n\n## Shipped\n- w0rk: Fix the parser (a1b2c3d)\n"
+        );
+
+        let commits = [
+            commit("a1b2c3d", "Fix the parser"),
+            commit("e4f5a6b", "Add git"),
+        ];
+        day.notes = "## Shipped\n- w0rk: Old (0000000)\n\n## Retro\nMore tests.\n".to_string();
+        assert!(set_shipped(&mut day, &commits));
+        assert!(!set_shipped(&mut day, &commits));
+        assert_eq!(
+            day.notes,
+            "## Shipped\n- w0rk: Fix the parser (a1b2c3d)\n- w0rk: Add git (e4f5a6b)\n\n\
+             ## Retro\nMore tests.\n"
+        );
+        assert_eq!(
+            shipped(&day),
+            vec![
+                "- w0rk: Fix the parser (a1b2c3d)",
+                "- w0rk: Add git (e4f5a6b)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commits_skip_missing_repos() {
+        let config = GitConfig {
+            repos: vec!["/nonexistent/w0rk".into()],
+            author: Some("me@example.com".to_string()),
+        };
+        let (commits, errors) = commits(
+            &config,
+            Date::from_calendar_date(2024, time::Month::July, 1).unwrap(),
+        );
+        assert!(commits.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("/nonexistent/w0rk"));
+    }
+
+    #[test]
+    fn test_set_shipped_dialects() {
+        let commits = [Commit {
+            repo: "w0rk".to_string(),
+            hash: "a1b2c3d".to_string(),
+            subject: "Fix the parser".to_string(),
+        }];
+
+        let mut day = Day::parse(
+            Path::new("2024-07-01.taskpaper"),
+            "- Deploy @done(2024-07-01)\n\nShipped:\n\tw0rk: Old (0000000)\n\nRetro:\n\tMore tests.\n",
+        )
+        .unwrap()
+        .0;
+        assert!(set_shipped(&mut day, &commits));
+        assert!(!set_shipped(&mut day, &commits));
+        assert_eq!(
+            day.to_content(),
+            "- Deploy @done(2024-07-01)\n\nShipped:\n\tw0rk: Fix the parser (a1b2c3d)\n\n\
+             Retro:\n\tMore tests.\n"
+        );
+        assert_eq!(shipped(&day), vec!["w0rk: Fix the parser (a1b2c3d)"]);
+        assert_eq!(day.tasks.len(), 1);
+
+        let mut day = Day::parse(
+            Path::new("journals/2024_07_01.md"),
+            "- DONE Deploy\n- Went well\n\t- Mostly\n",
+        )
+        .unwrap()
+        .0;
+        assert!(set_shipped(&mut day, &commits));
+        assert!(!set_shipped(&mut day, &commits));
+        assert_eq!(
+            day.to_content(),
+            "- DONE Deploy\n- Went well\n\t- Mostly\n- ## Shipped\n\t- w0rk: Fix the parser (a1b2c3d)\n"
+        );
+        assert_eq!(shipped(&day), vec!["- w0rk: Fix the parser (a1b2c3d)"]);
+
+        // Without tasks the section is in the preamble, and written again there.
+        let mut day = Day::parse(
+            Path::new("2024-07-01.taskpaper"),
+            "Shipped:\n\tw0rk: Old (0000000)\n",
+        )
+        .unwrap()
+        .0;
+        assert!(set_shipped(&mut day, &commits));
+        assert_eq!(
+            day.to_content(),
+            "Shipped:\n\tw0rk: Fix the parser (a1b2c3d)\n\n"
+        );
+    }
+}
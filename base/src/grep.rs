@@ -0,0 +1,142 @@
+//! Regex search through the day files as they are, notes and all, for
+//! `w0rk grep`.
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+#[cfg(feature = "fs")]
+use rayon::prelude::*;
+use regex::Regex;
+use std::path::PathBuf;
+use time::Date;
+
+/// A day file with matching lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepFile {
+    pub date: Date,
+    pub path: PathBuf,
+    /// Groups of adjacent lines, see `grep_content`.
+    pub hunks: Vec<Vec<GrepLine>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepLine {
+    /// The line number, starting at 1.
+    pub number: usize,
+    pub content: String,
+    /// Whether the line matches or is context.
+    pub matched: bool,
+}
+
+/// The matching lines of the content with up to `context` lines before and
+/// after them, in groups of adjacent lines.
+pub fn grep_content(content: &str, regex: &Regex, context: usize) -> Vec<Vec<GrepLine>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut hunks: Vec<Vec<GrepLine>> = Vec::new();
+    // The index after the last line in a hunk.
+    let mut end = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if !regex.is_match(line) {
+            continue;
+        }
+        let start = index.saturating_sub(context);
+        if start > end || hunks.is_empty() {
+            hunks.push(Vec::new());
+            end = start;
+        }
+        let hunk = hunks.last_mut().expect("a hunk was added");
+        let last = (index + context + 1).min(lines.len());
+        for (number, line) in lines.iter().enumerate().take(last).skip(end) {
+            hunk.push(GrepLine {
+                number: number + 1,
+                content: line.to_string(),
+                matched: regex.is_match(line),
+            });
+        }
+        end = end.max(last);
+    }
+    hunks
+}
+
+#[cfg(feature = "fs")]
+/// The day files with matching lines, in order of their date.
+pub fn grep(
+    workspace: &Workspace,
+    regex: &Regex,
+    context: usize,
+) -> Result<Vec<GrepFile>, crate::Error> {
+    workspace
+        .day_list
+        .iter()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|(date, path)| {
+            let content = crate::day::read_day_file(path, workspace.key())?;
+            Ok(GrepFile {
+                date: *date,
+                path: path.clone(),
+                hunks: grep_content(&content, regex, context),
+            })
+        })
+        .collect::<Vec<Result<GrepFile, crate::Error>>>()
+        .into_iter()
+        .filter(|file| file.as_ref().map_or(true, |file| !file.hunks.is_empty()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(hunks: &[Vec<GrepLine>]) -> Vec<Vec<(usize, bool)>> {
+        hunks
+            .iter()
+            .map(|hunk| {
+                hunk.iter()
+                    .map(|line| (line.number, line.matched))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grep_content() {
+        let content = "* [ ] Deploy\n* [ ] Review\n\nTalked to Alice\nabout the migration\n\
+                       \n\n\nAlice is out next week\n";
+        let regex = Regex::new("(?i)alice").unwrap();
+
+        assert_eq!(
+            numbers(&grep_content(content, &regex, 0)),
+            vec![vec![(4, true)], vec![(9, true)]]
+        );
+        assert_eq!(
+            numbers(&grep_content(content, &regex, 1)),
+            vec![
+                vec![(3, false), (4, true), (5, false)],
+                vec![(8, false), (9, true)]
+            ]
+        );
+        // Overlapping context is merged into one group.
+        assert_eq!(grep_content(content, &regex, 2).len(), 1);
+        assert_eq!(grep_content(content, &regex, 2)[0].len(), 8);
+        assert!(grep_content(content, &Regex::new("Bob").unwrap(), 2).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_grep() {
+        let dir = std::env::temp_dir().join("w0rk-test-grep");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("2024-06-03.md"),
+            "* [ ] Deploy\n\nThe migration plan\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [ ] Rotate keys\n").unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+
+        let files = grep(&workspace, &Regex::new("migra").unwrap(), 1).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, dir.join("2024-06-03.md"));
+        assert_eq!(files[0].hunks[0][1].content, "The migration plan");
+    }
+}
@@ -0,0 +1,346 @@
+use crate::analytics::Analytics;
+use crate::day::Day;
+use crate::task::{State as TaskState, Task, TAG_REGEX};
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use time::{Date, Duration, Month};
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;color:#222}\
+a{color:#0b63c5;text-decoration:none}a:hover{text-decoration:underline}\
+ul.tasks{list-style:none;padding-left:0}ul.tasks ul{list-style:none}\
+li.completed>span.name{text-decoration:line-through;color:#777}\
+table.month{border-collapse:collapse;margin-bottom:1.5rem}table.month td,table.month th{width:2.5rem;height:2rem;text-align:center}\
+td.day a{display:block;font-weight:bold;background:#eef4fb;border-radius:4px}\
+pre.notes{white-space:pre-wrap;background:#f6f6f6;padding:1rem}nav{margin-bottom:1rem}";
+
+const SEARCH_SCRIPT: &str = "const input=document.getElementById('q'),results=document.getElementById('results');\
+fetch('search.json').then(r=>r.json()).then(index=>{input.addEventListener('input',()=>{\
+const q=input.value.toLowerCase();results.innerHTML='';if(q.length<2)return;\
+index.filter(e=>e.text.toLowerCase().includes(q)).forEach(e=>{const li=document.createElement('li');\
+const a=document.createElement('a');a.href=e.url;a.textContent=e.date;li.appendChild(a);\
+li.appendChild(document.createTextNode(' '+e.text.split('\\n').find(l=>l.toLowerCase().includes(q))));\
+results.appendChild(li);});});});";
+
+/// Renders the workspace into a static site: a calendar index, a page per
+/// day, a page per tag and a search page. Returns the number of pages.
+/// Encrypted workspaces aren't published.
+pub fn publish(workspace: &Workspace, out_dir: &Path) -> Result<usize, crate::Error> {
+    if workspace.is_encrypted() {
+        return Err(crate::Error::NotEncrypted("Publishing".to_string()));
+    }
+    let days = workspace.read_days(|_| true)?;
+
+    fs::create_dir_all(out_dir.join("days"))?;
+    fs::create_dir_all(out_dir.join("tags"))?;
+    fs::write(out_dir.join("style.css"), STYLE)?;
+    let mut pages = 0;
+
+    for (index, day) in days.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| days[index].date);
+        let next = days.get(index + 1).map(|day| day.date);
+        fs::write(
+            out_dir.join("days").join(format!("{}.html", day.date)),
+            day_page(day, previous, next),
+        )?;
+        pages += 1;
+    }
+
+    for (tag, tasks) in tagged_tasks(&days) {
+        fs::write(
+            out_dir.join("tags").join(format!("{}.html", tag)),
+            tag_page(&tag, &tasks),
+        )?;
+        pages += 1;
+    }
+
+    fs::write(
+        out_dir.join("index.html"),
+        index_page(&workspace.name, &days),
+    )?;
+    fs::write(out_dir.join("search.html"), search_page())?;
+    fs::write(out_dir.join("search.json"), search_index(&days)?)?;
+    Ok(pages + 2)
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <link rel=\"stylesheet\" href=\"{root}style.css\">\n</head>\n<body>\n\
+         <nav><a href=\"{root}index.html\">Calendar</a> · <a href=\"{root}search.html\">Search</a></nav>\n\
+         {body}</body>\n</html>\n",
+        title = escape(title),
+        root = root,
+        body = body
+    )
+}
+
+pub fn day_page(day: &Day, previous: Option<Date>, next: Option<Date>) -> String {
+    let mut body = format!("<h1>{}</h1>\n<p>", day.date);
+    if let Some(previous) = previous {
+        write!(body, "<a href=\"{}.html\">← {}</a> ", previous, previous).unwrap();
+    }
+    if let Some(next) = next {
+        write!(body, "<a href=\"{}.html\">{} →</a>", next, next).unwrap();
+    }
+    body.push_str("</p>\n<ul class=\"tasks\">\n");
+    for task in &day.tasks {
+        body.push_str(&task_item(task));
+    }
+    body.push_str("</ul>\n");
+    if !day.notes.trim().is_empty() {
+        writeln!(
+            body,
+            "<pre class=\"notes\">{}</pre>",
+            escape(day.notes.trim())
+        )
+        .unwrap();
+    }
+    page(&day.date.to_string(), "../", &body)
+}
+
+fn task_item(task: &Task) -> String {
+    let mut item = format!(
+        "<li class=\"{}\">{} <span class=\"name\">{}</span>",
+        state_class(&task.state),
+        state_symbol(&task.state),
+        link_tags(&escape(&task.name))
+    );
+    if !task.subtasks.is_empty() {
+        item.push_str("\n<ul>\n");
+        for subtask in &task.subtasks {
+            item.push_str(&task_item(subtask));
+        }
+        item.push_str("</ul>\n");
+    }
+    item.push_str("</li>\n");
+    item
+}
+
+fn tag_page(tag: &str, tasks: &[(Date, Task)]) -> String {
+    let mut body = format!("<h1>#{}</h1>\n<ul class=\"tasks\">\n", escape(tag));
+    for (date, task) in tasks {
+        writeln!(
+            body,
+            "<li class=\"{}\">{} <a href=\"../days/{}.html\">{}</a> <span class=\"name\">{}</span></li>",
+            state_class(&task.state),
+            state_symbol(&task.state),
+            date,
+            date,
+            escape(&task.name)
+        )
+        .unwrap();
+    }
+    body.push_str("</ul>\n");
+    page(&format!("#{}", tag), "../", &body)
+}
+
+fn index_page(name: &str, days: &[Day]) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape(name));
+
+    let analytics = Analytics::new(days);
+    let totals = analytics.totals();
+    if let Some(completion) = totals.completion() {
+        write!(body, "<p>{:.0}% of tasks completed", completion).unwrap();
+        if let Some(lifetime) = analytics.average_lifetime() {
+            write!(body, ", {:.1} days from start to done on average", lifetime).unwrap();
+        }
+        body.push_str(".</p>\n");
+    }
+
+    let tags = tagged_tasks(days);
+    if !tags.is_empty() {
+        body.push_str("<p>Tags: ");
+        let links: Vec<String> = tags
+            .keys()
+            .map(|tag| format!("<a href=\"tags/{}.html\">#{}</a>", tag, tag))
+            .collect();
+        body.push_str(&links.join(" "));
+        body.push_str("</p>\n");
+    }
+
+    let mut months: Vec<(i32, Month)> = days
+        .iter()
+        .map(|day| (day.date.year(), day.date.month()))
+        .collect();
+    months.dedup();
+    for (year, month) in months.into_iter().rev() {
+        body.push_str(&month_table(year, month, days));
+    }
+    page(name, "", &body)
+}
+
+// A calendar of the month with links to the days that have a file.
+fn month_table(year: i32, month: Month, days: &[Day]) -> String {
+    let first = Date::from_calendar_date(year, month, 1).expect("every month has a first day");
+    let mut table = format!(
+        "<h2>{} {}</h2>\n<table class=\"month\">\n<tr><th>Mo</th><th>Tu</th><th>We</th><th>Th</th><th>Fr</th><th>Sa</th><th>Su</th></tr>\n<tr>",
+        month, year
+    );
+    for _ in 0..first.weekday().number_days_from_monday() {
+        table.push_str("<td></td>");
+    }
+    let mut date = first;
+    while date.month() == month {
+        match days.iter().any(|day| day.date == date) {
+            true => write!(
+                table,
+                "<td class=\"day\"><a href=\"days/{}.html\">{}</a></td>",
+                date,
+                date.day()
+            ),
+            false => write!(table, "<td>{}</td>", date.day()),
+        }
+        .unwrap();
+        if date.weekday() == time::Weekday::Sunday {
+            table.push_str("</tr>\n<tr>");
+        }
+        date += Duration::days(1);
+    }
+    table.push_str("</tr>\n</table>\n");
+    table
+}
+
+fn search_page() -> String {
+    let body = format!(
+        "<h1>Search</h1>\n<input id=\"q\" type=\"search\" placeholder=\"Search tasks and notes\" autofocus>\n\
+         <ul id=\"results\"></ul>\n<script>{}</script>\n",
+        SEARCH_SCRIPT
+    );
+    page("Search", "", &body)
+}
+
+/// An entry per day with the text of its tasks and notes, for the search page.
+fn search_index(days: &[Day]) -> Result<String, crate::Error> {
+    let entries: Vec<serde_json::Value> = days
+        .iter()
+        .map(|day| {
+            let mut text = String::new();
+            for task in &day.tasks {
+                text.push_str(&task.to_markdown(&day.style));
+            }
+            text.push_str(day.notes.trim());
+            serde_json::json!({
+                "date": day.date.to_string(),
+                "url": format!("days/{}.html", day.date),
+                "text": text,
+            })
+        })
+        .collect();
+    Ok(serde_json::to_string(&entries)?)
+}
+
+// The tasks and subtasks per lowercased tag.
+fn tagged_tasks(days: &[Day]) -> BTreeMap<String, Vec<(Date, Task)>> {
+    let mut tags: BTreeMap<String, Vec<(Date, Task)>> = BTreeMap::new();
+    for day in days {
+        for task in day
+            .tasks
+            .iter()
+            .chain(day.tasks.iter().flat_map(|t| &t.subtasks))
+        {
+            for tag in task.tags() {
+                tags.entry(tag.to_lowercase())
+                    .or_default()
+                    .push((day.date, task.clone()));
+            }
+        }
+    }
+    tags
+}
+
+fn link_tags(name: &str) -> String {
+    TAG_REGEX
+        .replace_all(name, |captures: &regex::Captures| {
+            let tag = &captures["tag"];
+            // The match starts with the whitespace before the tag, if any.
+            let prefix = &captures[0][..captures[0].len() - tag.len() - 1];
+            format!(
+                "{}<a href=\"../tags/{}.html\">#{}</a>",
+                prefix,
+                tag.to_lowercase(),
+                tag
+            )
+        })
+        .to_string()
+}
+
+fn state_class(state: &TaskState) -> &'static str {
+    match state {
+        TaskState::Completed => "completed",
+        TaskState::InProgress => "in-progress",
+        TaskState::Blocked => "blocked",
+        TaskState::Incomplete => "incomplete",
+        TaskState::Waiting => "waiting",
+    }
+}
+
+fn state_symbol(state: &TaskState) -> &'static str {
+    match state {
+        TaskState::Completed => "☑",
+        TaskState::InProgress => "◐",
+        TaskState::Blocked => "⊘",
+        TaskState::Incomplete => "☐",
+        TaskState::Waiting => "⧖",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish() {
+        let dir = std::env::temp_dir().join("w0rk-test-html");
+        let _ = fs::remove_dir_all(&dir);
+        let work_dir = dir.join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+        fs::write(
+            work_dir.join("2024-07-01.md"),
+            "* [x] Deploy <api> #infra\n* [ ] Release\n  * [ ] Changelog #docs\nShipped it.\n",
+        )
+        .unwrap();
+        fs::write(work_dir.join("2024-07-02.md"), "* [ ] Release\n").unwrap();
+
+        let workspace = Workspace::from_path(&work_dir).unwrap();
+        let out_dir = dir.join("site");
+        assert_eq!(publish(&workspace, &out_dir).unwrap(), 6);
+
+        let day = fs::read_to_string(out_dir.join("days/2024-07-01.html")).unwrap();
+        assert!(day.contains(
+            "<li class=\"completed\">☑ <span class=\"name\">Deploy &lt;api&gt; <a href=\"../tags/infra.html\">#infra</a></span></li>"
+        ));
+        assert!(day.contains("<a href=\"2024-07-02.html\">2024-07-02 →</a>"));
+        assert!(day.contains("<pre class=\"notes\">Shipped it.</pre>"));
+
+        let index = fs::read_to_string(out_dir.join("index.html")).unwrap();
+        assert!(index.contains("<h2>July 2024</h2>"));
+        assert!(index.contains("<td class=\"day\"><a href=\"days/2024-07-01.html\">1</a></td>"));
+        assert!(out_dir.join("tags/docs.html").exists());
+
+        let search: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(out_dir.join("search.json")).unwrap())
+                .unwrap();
+        assert_eq!(search[1]["url"], "days/2024-07-02.html");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_month_table() {
+        let days = vec![];
+        let table = month_table(2024, Month::July, &days);
+        // July 2024 starts on a Monday and ends on a Wednesday.
+        assert!(table.contains("<tr><td>1</td>"));
+        assert!(table.ends_with("<td>31</td></tr>\n</table>\n"));
+    }
+}
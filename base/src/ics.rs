@@ -0,0 +1,296 @@
+use crate::config::{rewrite, Rewrite};
+use crate::recurring_task::{Interval, RecurringTask, RecurringTasks};
+use crate::task::{State as TaskState, Task};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use lazy_static::lazy_static;
+use time::format_description::{parse_owned, OwnedFormatItem};
+use time::{Date, OffsetDateTime};
+
+lazy_static! {
+    static ref ICS_DATE_FORMAT: OwnedFormatItem = parse_owned::<2>("[year][month][day]").unwrap();
+    static ref ICS_STAMP_FORMAT: OwnedFormatItem =
+        parse_owned::<2>("[year][month][day]T[hour][minute][second]Z").unwrap();
+}
+
+#[cfg(feature = "fs")]
+/// Exports the tasks of the most recent day until today that have a due
+/// date as `VTODO`s and the recurring tasks as repeating all-day `VEVENT`s.
+pub fn export(workspace: &Workspace) -> Result<String, crate::Error> {
+    let today = OffsetDateTime::now_utc();
+    let tasks = match workspace.day_list.last_until(&today.date()) {
+        Some((_, path)) => workspace.read_day(path)?.tasks,
+        None => Vec::new(),
+    };
+
+    to_calendar(
+        &tasks,
+        &workspace.rewrites,
+        &workspace.recurring_tasks,
+        today.date(),
+        today,
+    )
+}
+
+/// The summaries of the tasks get the rewrites, their UIDs are made from
+/// the names as they are so they stay the same when the rewrites change.
+pub fn to_calendar(
+    tasks: &[Task],
+    rewrites: &[Rewrite],
+    recurring_tasks: &RecurringTasks,
+    start: Date,
+    stamp: OffsetDateTime,
+) -> Result<String, crate::Error> {
+    let stamp = stamp.format(&ICS_STAMP_FORMAT)?;
+    let mut lines = calendar_header();
+
+    for task in tasks
+        .iter()
+        .flat_map(|task| std::iter::once(task).chain(task.subtasks.iter()))
+    {
+        if let Some(due) = task.due() {
+            let uid = format!("{}-{}@w0rk", due, slug(&summary(task, &[])));
+            lines.extend(vtodo(task, rewrites, &uid, &stamp)?);
+        }
+    }
+
+    for recurring_task in recurring_tasks.iter() {
+        lines.extend(vevent(recurring_task, start, &stamp)?);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    Ok(to_content(&lines))
+}
+
+fn vtodo(
+    task: &Task,
+    rewrites: &[Rewrite],
+    uid: &str,
+    stamp: &str,
+) -> Result<Vec<String>, crate::Error> {
+    let status = match task.state {
+        TaskState::Completed => "COMPLETED",
+        TaskState::InProgress => "IN-PROCESS",
+        TaskState::Incomplete | TaskState::Blocked | TaskState::Waiting => "NEEDS-ACTION",
+    };
+    let summary = summary(task, rewrites);
+
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", stamp),
+        format!("SUMMARY:{}", escape(&summary)),
+    ];
+    if let Some(due) = task.due() {
+        lines.push(format!("DUE;VALUE=DATE:{}", due.format(&ICS_DATE_FORMAT)?));
+    }
+    lines.push(format!("STATUS:{}", status));
+    lines.push("END:VTODO".to_string());
+
+    Ok(lines)
+}
+
+/// A calendar with a single `VTODO` for the task, as stored on CalDAV servers.
+pub fn todo_calendar(
+    task: &Task,
+    rewrites: &[Rewrite],
+    uid: &str,
+    stamp: OffsetDateTime,
+) -> Result<String, crate::Error> {
+    let stamp = stamp.format(&ICS_STAMP_FORMAT)?;
+    let mut lines = calendar_header();
+    lines.extend(vtodo(task, rewrites, uid, &stamp)?);
+    lines.push("END:VCALENDAR".to_string());
+
+    Ok(to_content(&lines))
+}
+
+/// The state of the first `VTODO` in a calendar, based on its `STATUS` property.
+pub fn todo_state(calendar: &str) -> Option<TaskState> {
+    let mut in_todo = false;
+    for line in calendar.replace("\r\n ", "").replace("\n ", "").lines() {
+        match line.trim_end() {
+            "BEGIN:VTODO" => in_todo = true,
+            "END:VTODO" => return None,
+            line if in_todo && line.starts_with("STATUS") => {
+                return match line.rsplit(':').next() {
+                    Some("COMPLETED") => Some(TaskState::Completed),
+                    Some("IN-PROCESS") => Some(TaskState::InProgress),
+                    Some("NEEDS-ACTION") => Some(TaskState::Incomplete),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn summary(task: &Task, rewrites: &[Rewrite]) -> String {
+    let name = task.display_name();
+    let name = match task.due() {
+        Some(due) => name
+            .replace(&format!("@due:{}", due), "")
+            .trim()
+            .to_string(),
+        None => name,
+    };
+    rewrite(&name, rewrites)
+}
+
+fn calendar_header() -> Vec<String> {
+    vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//matsimitsu//w0rk//EN".to_string(),
+    ]
+}
+
+fn to_content(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<String>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn vevent(task: &RecurringTask, start: Date, stamp: &str) -> Result<Vec<String>, crate::Error> {
+    // A task to complete a number of times a week or month has no rule.
+    let Some(rrule) = rrule(&task.interval) else {
+        return Ok(Vec::new());
+    };
+    // Start on the first date the task is due, so the event lines up with the rule.
+    let mut dtstart = start;
+    while !task.is_due(&dtstart) {
+        dtstart = dtstart.next_day().unwrap_or(dtstart);
+    }
+
+    Ok(vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:recurring-{}-{}@w0rk", task.interval, slug(&task.name)),
+        format!("DTSTAMP:{}", stamp),
+        format!("SUMMARY:{}", escape(&task.name)),
+        format!("DTSTART;VALUE=DATE:{}", dtstart.format(&ICS_DATE_FORMAT)?),
+        format!("RRULE:{}", rrule),
+        "END:VEVENT".to_string(),
+    ])
+}
+
+fn rrule(interval: &Interval) -> Option<&'static str> {
+    match interval {
+        Interval::Daily => Some("FREQ=DAILY"),
+        Interval::Weekly => Some("FREQ=WEEKLY;BYDAY=MO"),
+        Interval::Monthly => Some("FREQ=MONTHLY;BYMONTHDAY=1"),
+        Interval::Weekday => Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"),
+        Interval::Weekend => Some("FREQ=WEEKLY;BYDAY=SA,SU"),
+        Interval::Monday => Some("FREQ=WEEKLY;BYDAY=MO"),
+        Interval::Tuesday => Some("FREQ=WEEKLY;BYDAY=TU"),
+        Interval::Wednesday => Some("FREQ=WEEKLY;BYDAY=WE"),
+        Interval::Thursday => Some("FREQ=WEEKLY;BYDAY=TH"),
+        Interval::Friday => Some("FREQ=WEEKLY;BYDAY=FR"),
+        Interval::Saturday => Some("FREQ=WEEKLY;BYDAY=SA"),
+        Interval::Sunday => Some("FREQ=WEEKLY;BYDAY=SU"),
+        Interval::TimesPerWeek(_) | Interval::TimesPerMonth(_) => None,
+    }
+}
+
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Content lines longer than 75 octets have to be folded onto continuation lines.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    #[test]
+    fn test_to_calendar() {
+        let tasks: Vec<Task> = vec![
+            "* [ ] Send invoice, to client @due:2024-07-03"
+                .try_into()
+                .unwrap(),
+            "* [ ] No due date".try_into().unwrap(),
+        ];
+        let recurring_tasks =
+            RecurringTasks::from(vec!["* [ ] @friday Write report".try_into().unwrap()]);
+        let start = Date::from_calendar_date(2024, Month::July, 1).unwrap();
+        let stamp = start.midnight().assume_utc();
+
+        let rewrites: Vec<Rewrite> =
+            serde_json::from_str(r#"[{"from": "client", "to": "ACME"}]"#).unwrap();
+
+        let calendar = to_calendar(&tasks, &rewrites, &recurring_tasks, start, stamp).unwrap();
+
+        assert_eq!(
+            calendar,
+            [
+                "BEGIN:VCALENDAR",
+                "VERSION:2.0",
+                "PRODID:-//matsimitsu//w0rk//EN",
+                "BEGIN:VTODO",
+                "UID:2024-07-03-send-invoice-to-client@w0rk",
+                "DTSTAMP:20240701T000000Z",
+                "SUMMARY:Send invoice\\, to ACME",
+                "DUE;VALUE=DATE:20240703",
+                "STATUS:NEEDS-ACTION",
+                "END:VTODO",
+                "BEGIN:VEVENT",
+                "UID:recurring-friday-write-report@w0rk",
+                "DTSTAMP:20240701T000000Z",
+                "SUMMARY:Write report",
+                "DTSTART;VALUE=DATE:20240705",
+                "RRULE:FREQ=WEEKLY;BYDAY=FR",
+                "END:VEVENT",
+                "END:VCALENDAR",
+                "",
+            ]
+            .join("\r\n")
+        );
+    }
+
+    #[test]
+    fn test_todo_state() {
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:1\r\nSTATUS:COMPLETED\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        assert_eq!(todo_state(calendar), Some(TaskState::Completed));
+
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:1\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        assert_eq!(todo_state(calendar), None);
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = "a".repeat(80);
+        assert_eq!(
+            fold(&line),
+            format!("{}\r\n {}", "a".repeat(75), "a".repeat(5))
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use crate::config::DAY_FORMAT;
+#[cfg(feature = "fs")]
+use crate::config::RECURRING_FILE;
+#[cfg(feature = "fs")]
+use crate::day::Day;
+#[cfg(feature = "fs")]
+use crate::planner::is_due;
+use crate::recurring_task::{Interval, RecurringTask};
+use crate::task::{State as TaskState, Task};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use time::OffsetDateTime;
+use time::{Date, Weekday};
+
+pub mod org;
+pub mod reminders;
+pub mod things;
+pub mod todoist;
+
+/// Tasks read from another app, by the day they belong to, and the recurring
+/// tasks to add to the workspace.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub days: BTreeMap<Date, Vec<Task>>,
+    pub recurring: Vec<RecurringTask>,
+    /// What couldn't be imported as is, like an unsupported recurrence.
+    pub warnings: Vec<String>,
+}
+
+/// What [`Import::write`] changed in the workspace.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub days_created: usize,
+    pub days_updated: usize,
+    pub tasks: usize,
+    pub recurring: usize,
+}
+
+impl Import {
+    pub fn add(&mut self, date: Date, task: Task) {
+        self.days.entry(date).or_default().push(task);
+    }
+
+    /// Adds the subtask to the task with the parent's name on that day,
+    /// adding the parent first if it isn't there yet.
+    pub fn add_subtask(&mut self, date: Date, parent: &str, subtask: Task) {
+        let tasks = self.days.entry(date).or_default();
+        let index = match tasks.iter().position(|task| task.name == parent) {
+            Some(index) => index,
+            None => {
+                tasks.push(Task {
+                    name: parent.to_string(),
+                    state: TaskState::Incomplete,
+                    subtasks: Vec::new(),
+                });
+                tasks.len() - 1
+            }
+        };
+        tasks[index].add_subtask(subtask);
+    }
+
+    pub fn add_recurring(&mut self, name: String, interval: Interval) {
+        if !self.recurring.iter().any(|task| task.name == name) {
+            self.recurring.push(RecurringTask { name, interval });
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    /// Adds the tasks to the day files, creating the days that don't exist
+    /// yet, and the recurring tasks to the recurring file. Tasks that are
+    /// already there by name are skipped, so importing twice is harmless.
+    pub fn write(&self, workspace: &Workspace) -> Result<ImportSummary, crate::Error> {
+        let mut summary = ImportSummary::default();
+        let today = OffsetDateTime::now_utc().date();
+
+        for (date, tasks) in &self.days {
+            let path = workspace.day_path(date)?;
+            let mut day = match (path.exists(), date == &today) {
+                (true, _) => {
+                    summary.days_updated += 1;
+                    workspace.read_day(&path)?
+                }
+                // Today is created like any other day, with the open tasks
+                // and recurring tasks of the workspace, and the imported ones.
+                (false, true) => {
+                    summary.days_created += 1;
+                    let mut day = workspace.new_day()?;
+                    // Frequency tasks are due until they're completed that
+                    // many times in the week or month, like when planning.
+                    let since = self
+                        .recurring
+                        .iter()
+                        .filter_map(|task| task.interval.frequency(date))
+                        .map(|(_, since)| since)
+                        .min();
+                    let previous_days = match since {
+                        Some(since) => workspace.read_days(|day| day >= &since && day < date)?,
+                        None => Vec::new(),
+                    };
+                    for recurring in self
+                        .recurring
+                        .iter()
+                        .filter(|task| is_due(&task.interval, &task.name, &previous_days, date))
+                    {
+                        if !day.tasks.iter().any(|task| recurring.matches(task)) {
+                            day.tasks.push(recurring.into());
+                        }
+                    }
+                    day
+                }
+                (false, false) => {
+                    summary.days_created += 1;
+                    let mut day = Day::new(&path)?;
+                    day.key = workspace.key().cloned();
+                    workspace.configure_day(&mut day);
+                    day
+                }
+            };
+            for task in tasks {
+                if day.tasks.iter().any(|existing| existing.name == task.name) {
+                    continue;
+                }
+                day.tasks.push(task.clone());
+                summary.tasks += 1;
+            }
+            day.write()?;
+        }
+
+        let new_recurring: Vec<&RecurringTask> = self
+            .recurring
+            .iter()
+            .filter(|task| {
+                !workspace
+                    .recurring_tasks
+                    .iter()
+                    .any(|existing| existing.name == task.name)
+            })
+            .collect();
+        if !new_recurring.is_empty() {
+            let path = workspace.path.join(RECURRING_FILE);
+            let mut content = std::fs::read_to_string(&path).unwrap_or_default();
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            for task in new_recurring {
+                content.push_str(&format!("{}\n", task));
+                summary.recurring += 1;
+            }
+            std::fs::write(&path, content)?;
+        }
+
+        Ok(summary)
+    }
+}
+
+pub(crate) fn task(name: String, state: TaskState) -> Task {
+    Task {
+        name,
+        state,
+        subtasks: Vec::new(),
+    }
+}
+
+/// The name with a `@due:` annotation for the date, if any.
+pub(crate) fn with_due(name: &str, due: Option<Date>) -> String {
+    match due {
+        Some(date) => format!("{} @due:{}", name, date),
+        None => name.to_string(),
+    }
+}
+
+/// A tag for a project, list or label name, `Home Office` becomes
+/// `Home-Office`. `None` when there's nothing left to make a tag of.
+pub(crate) fn tag(name: &str) -> Option<String> {
+    let tag: String = name
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    match tag.chars().next() {
+        Some(c) if c.is_alphabetic() => Some(tag),
+        _ => None,
+    }
+}
+
+/// The interval that comes closest to a repeat rule, an iCalendar `RRULE`
+/// like `FREQ=WEEKLY;BYDAY=MO` or words like `every weekday`. Rules w0rk
+/// can't get close to, like yearly ones, are `None`. Weekly rules without
+/// a day repeat on the weekday of the date, if there is one.
+pub(crate) fn nearest_interval(rule: &str, date: Option<Date>) -> Option<Interval> {
+    let rule = rule.trim().to_ascii_lowercase();
+    let rule = rule.trim_start_matches("rrule:");
+    if rule.contains("freq=") {
+        let part = |name: &str| {
+            rule.split(';')
+                .find_map(|part| part.strip_prefix(name)?.strip_prefix('='))
+                .unwrap_or_default()
+        };
+        let days: Vec<&str> = part("byday").split(',').filter(|d| !d.is_empty()).collect();
+        return match part("freq") {
+            "daily" => Some(Interval::Daily),
+            "weekly" => match days[..] {
+                [] => date.map(weekday_interval).or(Some(Interval::Weekly)),
+                [day] => Interval::try_from(day_name(day)).ok(),
+                ["sa", "su"] | ["su", "sa"] => Some(Interval::Weekend),
+                _ if days.len() == 5 && !days.contains(&"sa") && !days.contains(&"su") => {
+                    Some(Interval::Weekday)
+                }
+                _ => Some(Interval::Daily),
+            },
+            "monthly" => Some(Interval::Monthly),
+            _ => None,
+        };
+    }
+
+    let rule = rule.trim_start_matches("every").trim();
+    match rule {
+        "day" | "daily" => Some(Interval::Daily),
+        "week" | "weekly" => date.map(weekday_interval).or(Some(Interval::Weekly)),
+        "month" | "monthly" => Some(Interval::Monthly),
+        "weekday" | "weekdays" | "workday" => Some(Interval::Weekday),
+        "weekend" | "weekends" => Some(Interval::Weekend),
+        rule => Interval::try_from(rule.trim_end_matches('s')).ok(),
+    }
+}
+
+fn day_name(day: &str) -> &str {
+    match day {
+        "mo" => "monday",
+        "tu" => "tuesday",
+        "we" => "wednesday",
+        "th" => "thursday",
+        "fr" => "friday",
+        "sa" => "saturday",
+        "su" => "sunday",
+        day => day,
+    }
+}
+
+pub(crate) fn weekday_interval(date: Date) -> Interval {
+    match date.weekday() {
+        Weekday::Monday => Interval::Monday,
+        Weekday::Tuesday => Interval::Tuesday,
+        Weekday::Wednesday => Interval::Wednesday,
+        Weekday::Thursday => Interval::Thursday,
+        Weekday::Friday => Interval::Friday,
+        Weekday::Saturday => Interval::Saturday,
+        Weekday::Sunday => Interval::Sunday,
+    }
+}
+
+/// Reads a date from the start of a timestamp, like `2024-07-01T09:30:00Z`.
+pub(crate) fn date_prefix(value: &str) -> Option<Date> {
+    value
+        .get(..10)
+        .and_then(|date| Date::parse(date, &DAY_FORMAT).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_write() {
+        let dir = std::env::temp_dir().join("w0rk-test-import");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [ ] Deploy\n").unwrap();
+        std::fs::write(dir.join(RECURRING_FILE), "* [] @daily Standup").unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+
+        let mut import = Import::default();
+        let date = parse_date("2024-07-01").unwrap();
+        import.add(date, "* [ ] Deploy".try_into().unwrap());
+        import.add_subtask(date, "Release", "* [x] Changelog".try_into().unwrap());
+        import.add(
+            parse_date("2024-06-30").unwrap(),
+            "* [x] Plan".try_into().unwrap(),
+        );
+        import.add_recurring("Standup".to_string(), Interval::Daily);
+        import.add_recurring("Invoices".to_string(), Interval::Monthly);
+
+        assert_eq!(
+            import.write(&workspace).unwrap(),
+            ImportSummary {
+                days_created: 1,
+                days_updated: 1,
+                tasks: 2,
+                recurring: 1,
+            }
+        );
+        let day = Day::from_path(&dir.join("2024-07-01.md")).unwrap();
+        assert_eq!(day.tasks[1].name, "Release");
+        assert_eq!(day.tasks[1].state, TaskState::Completed);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(RECURRING_FILE)).unwrap(),
+            "* [] @daily Standup\n* [] @monthly Invoices\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_nearest_interval() {
+        let friday = parse_date("2024-07-12").ok();
+        assert_eq!(
+            nearest_interval("FREQ=WEEKLY;BYDAY=MO", None),
+            Some(Interval::Monday)
+        );
+        assert_eq!(
+            nearest_interval("RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR", None),
+            Some(Interval::Weekday)
+        );
+        assert_eq!(
+            nearest_interval("FREQ=WEEKLY;INTERVAL=2", friday),
+            Some(Interval::Friday)
+        );
+        assert_eq!(
+            nearest_interval("FREQ=DAILY;INTERVAL=3", None),
+            Some(Interval::Daily)
+        );
+        assert_eq!(nearest_interval("FREQ=YEARLY", None), None);
+        assert_eq!(
+            nearest_interval("every weekday", None),
+            Some(Interval::Weekday)
+        );
+        assert_eq!(nearest_interval("Weekly", None), Some(Interval::Weekly));
+        assert_eq!(nearest_interval("Mondays", None), Some(Interval::Monday));
+        assert_eq!(nearest_interval("yearly", None), None);
+        assert_eq!(tag("Home Office!"), Some("Home-Office".to_string()));
+        assert_eq!(tag("2024"), None);
+    }
+}
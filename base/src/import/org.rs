@@ -0,0 +1,210 @@
+//! Emacs org-mode files: headlines with a TODO keyword are tasks, TODO
+//! headlines below them subtasks.
+use super::{date_prefix, task, weekday_interval, with_due, Import};
+use crate::recurring_task::Interval;
+use crate::task::State as TaskState;
+use lazy_static::lazy_static;
+use regex::Regex;
+use time::Date;
+
+lazy_static! {
+    static ref HEADLINE_REGEX: Regex =
+        Regex::new(r"^(?<stars>\*+)\s+(?<title>.*?)(?:\s+(?<tags>:[\w@#%:]+:))?\s*$").unwrap();
+    static ref PLANNING_REGEX: Regex =
+        Regex::new(r"(?<kind>SCHEDULED|DEADLINE|CLOSED):\s*[<\[](?<stamp>[^>\]]+)[>\]]").unwrap();
+    static ref REPEATER_REGEX: Regex =
+        Regex::new(r"(?:\.\+|\+\+|\+)(?<count>\d+)(?<unit>[hdwmy])").unwrap();
+    static ref COOKIE_REGEX: Regex = Regex::new(r"\s*\[(?:\d*/\d*|\d*%)\]").unwrap();
+}
+
+#[derive(Debug, Default)]
+struct Headline {
+    level: usize,
+    /// `None` for headlines without a TODO keyword, which aren't tasks.
+    state: Option<TaskState>,
+    cancelled: bool,
+    name: String,
+    scheduled: Option<String>,
+    deadline: Option<String>,
+    closed: Option<Date>,
+}
+
+/// Reads the TODO headlines. Completed tasks go on the day they were
+/// closed, open tasks on `today` with the deadline (or scheduled date) as
+/// `@due:`, and tasks with a repeater become recurring tasks.
+pub fn parse(content: &str, today: Date) -> Result<Import, crate::Error> {
+    let mut import = Import::default();
+    // The names of the task headlines above the current one, by level.
+    let mut parents: Vec<(usize, Option<String>)> = Vec::new();
+
+    for headline in headlines(content) {
+        parents.retain(|(level, _)| *level < headline.level);
+        let state = match (&headline.state, headline.cancelled) {
+            (Some(state), false) => state.clone(),
+            (_, true) => {
+                import
+                    .warnings
+                    .push(format!("Skipped cancelled task \"{}\"", headline.name));
+                continue;
+            }
+            (None, false) => continue,
+        };
+
+        let completed = state == TaskState::Completed;
+        let date = match completed {
+            true => headline.closed.unwrap_or(today),
+            false => today,
+        };
+        let stamp = headline.scheduled.as_ref().or(headline.deadline.as_ref());
+        let due = headline
+            .deadline
+            .as_ref()
+            .or(headline.scheduled.as_ref())
+            .and_then(|stamp| date_prefix(stamp));
+
+        // Subtasks go under the first task headline above them.
+        match parents.first() {
+            Some((_, Some(parent))) => {
+                let name = with_due(&headline.name, due.filter(|_| !completed));
+                import.add_subtask(date, parent, task(name, state));
+                continue;
+            }
+            Some((_, None)) => {
+                import.warnings.push(format!(
+                    "Skipped \"{}\", a subtask of a recurring task",
+                    headline.name
+                ));
+                continue;
+            }
+            None => {}
+        }
+
+        let repeater = stamp.and_then(|stamp| Some((stamp, REPEATER_REGEX.captures(stamp)?)));
+        if let (Some((stamp, repeater)), false) = (repeater, completed) {
+            match interval(&repeater["count"], &repeater["unit"], date_prefix(stamp)) {
+                Some(interval) => {
+                    import.add_recurring(headline.name.clone(), interval);
+                    parents.push((headline.level, None));
+                    continue;
+                }
+                None => import.warnings.push(format!(
+                    "Unsupported repeater \"{}\" of \"{}\", imported as a task",
+                    &repeater[0], headline.name
+                )),
+            }
+        }
+
+        let name = match completed {
+            true => headline.name.clone(),
+            false => with_due(&headline.name, due),
+        };
+        import.add(date, task(name.clone(), state));
+        parents.push((headline.level, Some(name)));
+    }
+    Ok(import)
+}
+
+fn headlines(content: &str) -> Vec<Headline> {
+    let mut headlines: Vec<Headline> = Vec::new();
+    for line in content.lines() {
+        if let Some(captures) = HEADLINE_REGEX.captures(line) {
+            let title = captures["title"].trim();
+            let (keyword, rest) = title.split_once(' ').unwrap_or((title, ""));
+            let (state, cancelled, title) = match keyword_state(keyword) {
+                Some((state, cancelled)) => (Some(state), cancelled, rest),
+                None => (None, false, title),
+            };
+            let mut name = COOKIE_REGEX.replace_all(title.trim(), "").to_string();
+            if let Some(tags) = captures.name("tags") {
+                for tag in tags.as_str().split(':').filter(|tag| !tag.is_empty()) {
+                    name.push_str(&format!(" #{}", tag));
+                }
+            }
+            headlines.push(Headline {
+                level: captures["stars"].len(),
+                state,
+                cancelled,
+                name,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(headline) = headlines.last_mut() else {
+            continue;
+        };
+        for captures in PLANNING_REGEX.captures_iter(line) {
+            let stamp = captures["stamp"].to_string();
+            match &captures["kind"] {
+                "SCHEDULED" => headline.scheduled = Some(stamp),
+                "DEADLINE" => headline.deadline = Some(stamp),
+                _ => headline.closed = date_prefix(&stamp),
+            }
+        }
+    }
+    headlines
+}
+
+/// The common TODO keywords, with whether the task was cancelled.
+fn keyword_state(keyword: &str) -> Option<(TaskState, bool)> {
+    match keyword {
+        "TODO" => Some((TaskState::Incomplete, false)),
+        "NEXT" | "STARTED" | "DOING" | "IN-PROGRESS" => Some((TaskState::InProgress, false)),
+        "WAITING" | "WAIT" | "HOLD" | "BLOCKED" => Some((TaskState::Blocked, false)),
+        "DONE" => Some((TaskState::Completed, false)),
+        "CANCELLED" | "CANCELED" | "KILL" => Some((TaskState::Completed, true)),
+        _ => None,
+    }
+}
+
+/// The interval of a repeater like `+1d` or `.+1w`, weekly repeaters on
+/// the weekday of the timestamp.
+fn interval(count: &str, unit: &str, date: Option<Date>) -> Option<Interval> {
+    match (count, unit, date) {
+        ("1", "d", _) => Some(Interval::Daily),
+        ("1", "w", Some(date)) => Some(weekday_interval(date)),
+        ("1", "m", Some(date)) if date.day() == 1 => Some(Interval::Monthly),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+
+    #[test]
+    fn test_parse() {
+        let today = parse_date("2024-07-10").unwrap();
+        let org = "#+TITLE: Work\n\
+                   * Projects\n\
+                   ** TODO Release [1/2] :work:\n\
+                   DEADLINE: <2024-07-12 Fri>\n\
+                   *** DONE Changelog\n\
+                   CLOSED: [2024-07-09 Tue 16:00]\n\
+                   *** NEXT Tag\n\
+                   * TODO Standup\n\
+                   SCHEDULED: <2024-07-08 Mon +1w>\n\
+                   * DONE Taxes\n\
+                   CLOSED: [2024-04-30 Tue 10:00] SCHEDULED: <2024-04-29 Mon>\n\
+                   * CANCELLED Offsite\n\
+                   * WAITING Review\n\
+                   SCHEDULED: <2024-07-10 Wed .+2d>\n";
+        let import = parse(org, today).unwrap();
+
+        let tasks = &import.days[&today];
+        assert_eq!(tasks[0].name, "Release #work @due:2024-07-12");
+        assert_eq!(tasks[0].subtasks[0].name, "Tag");
+        assert_eq!(tasks[0].subtasks[0].state, TaskState::InProgress);
+        assert_eq!(tasks[1].state, TaskState::Blocked);
+        let closed = &import.days[&parse_date("2024-07-09").unwrap()];
+        assert_eq!(closed[0].subtasks[0].name, "Changelog");
+        assert_eq!(
+            import.days[&parse_date("2024-04-30").unwrap()][0].name,
+            "Taxes"
+        );
+        assert_eq!(import.recurring[0].name, "Standup");
+        assert_eq!(import.recurring[0].interval, Interval::Monday);
+        assert_eq!(import.warnings.len(), 2);
+    }
+}
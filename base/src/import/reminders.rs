@@ -0,0 +1,105 @@
+//! Apple Reminders CSV, as exported by Shortcuts or apps like Reminders
+//! Export: a row per reminder with its list, dates and recurrence.
+use super::{date_prefix, nearest_interval, tag, task, with_due, Import};
+use crate::task::State as TaskState;
+use time::Date;
+
+/// Reads the reminders by their column names, `Title` is the only one
+/// that's required. Lists become tags, the due date becomes `@due:`.
+pub fn parse(content: &str, today: Date) -> Result<Import, crate::Error> {
+    let mut import = Import::default();
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_ascii_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.as_str()))
+    };
+    let title = column(&["title", "name"])
+        .ok_or_else(|| crate::Error::Import("no Title column in the Reminders CSV".to_string()))?;
+    let (list, due, completed, completion, recurrence) = (
+        column(&["list"]),
+        column(&["due date", "due"]),
+        column(&["completed", "is completed"]),
+        column(&["completion date", "completed date"]),
+        column(&["recurrence", "repeat"]),
+    );
+
+    for record in reader.records() {
+        let record = record?;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or("").trim();
+        let mut name = field(Some(title)).to_string();
+        if name.is_empty() {
+            continue;
+        }
+        if let Some(list) = tag(field(list)) {
+            name.push_str(&format!(" #{}", list));
+        }
+        let due = date_prefix(field(due));
+        let completion = date_prefix(field(completion));
+        let is_completed = matches!(
+            field(completed).to_ascii_lowercase().as_str(),
+            "true" | "yes" | "1"
+        ) || completion.is_some();
+
+        let rule = field(recurrence);
+        if !rule.is_empty() && !rule.eq_ignore_ascii_case("never") && !is_completed {
+            match nearest_interval(rule, due) {
+                Some(interval) => {
+                    import.add_recurring(name, interval);
+                    continue;
+                }
+                None => import.warnings.push(format!(
+                    "Unsupported recurrence \"{}\" of \"{}\", imported as a reminder",
+                    rule, name
+                )),
+            }
+        }
+
+        match is_completed {
+            true => import.add(
+                completion.unwrap_or(today),
+                task(name, TaskState::Completed),
+            ),
+            false => import.add(today, task(with_due(&name, due), TaskState::Incomplete)),
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+    use crate::recurring_task::Interval;
+
+    #[test]
+    fn test_parse() {
+        let today = parse_date("2024-07-10").unwrap();
+        let csv = "Title,Notes,List,Due Date,Completed,Completion Date,Recurrence\n\
+                   Water plants,,Home Office,2024-07-12 09:00,false,,Weekly\n\
+                   Send invoice,Acme,Work,2024-07-15,false,,\n\
+                   Pay rent,,Home,,true,2024-07-01 08:00,Monthly\n\
+                   Renew passport,,,,false,,Yearly\n";
+        let import = parse(csv, today).unwrap();
+
+        assert_eq!(import.recurring[0].name, "Water plants #Home-Office");
+        assert_eq!(import.recurring[0].interval, Interval::Friday);
+        assert_eq!(
+            import.days[&today][0].name,
+            "Send invoice #Work @due:2024-07-15"
+        );
+        assert_eq!(import.days[&today][1].name, "Renew passport");
+        assert_eq!(
+            import.days[&parse_date("2024-07-01").unwrap()][0].state,
+            TaskState::Completed
+        );
+        assert_eq!(import.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+//! Things 3 JSON, in the format of its `things:///json` URL scheme: to-dos
+//! and projects with their headings, to-dos and checklists.
+use super::{date_prefix, nearest_interval, tag, task, with_due, Import};
+use crate::task::State as TaskState;
+use serde::Deserialize;
+use time::Date;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "attributes", rename_all = "kebab-case")]
+enum Item {
+    ToDo(ToDo),
+    Project(Project),
+    Heading(Heading),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ToDo {
+    title: String,
+    #[serde(default)]
+    when: Option<String>,
+    #[serde(default)]
+    deadline: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    completed: bool,
+    #[serde(default)]
+    canceled: bool,
+    #[serde(default)]
+    completion_date: Option<String>,
+    #[serde(default)]
+    checklist_items: Vec<ChecklistItem>,
+    /// Not part of the URL scheme, but exported by scripts that read the
+    /// repeating rules from the Things database.
+    #[serde(default)]
+    repeat: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Project {
+    title: String,
+    #[serde(default)]
+    area: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Heading {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct ChecklistItem {
+    attributes: ChecklistAttributes,
+}
+
+#[derive(Deserialize)]
+struct ChecklistAttributes {
+    title: String,
+    #[serde(default)]
+    completed: bool,
+}
+
+/// Reads a JSON list of to-dos and projects. Projects and areas become tags
+/// of their to-dos, the deadline (or `when` date) becomes `@due:` and
+/// checklists become subtasks.
+pub fn parse(content: &str, today: Date) -> Result<Import, crate::Error> {
+    let items: Vec<Item> = serde_json::from_str(content)?;
+    let mut import = Import::default();
+    for item in &items {
+        add_item(&mut import, item, &[], today);
+    }
+    Ok(import)
+}
+
+fn add_item(import: &mut Import, item: &Item, tags: &[String], today: Date) {
+    match item {
+        Item::ToDo(todo) => add_todo(import, todo, tags, today),
+        Item::Project(project) => {
+            let mut tags = tags.to_vec();
+            tags.extend(project.area.iter().chain([&project.title]).cloned());
+            tags.extend(project.tags.iter().cloned());
+            for item in &project.items {
+                add_item(import, item, &tags, today);
+            }
+        }
+        Item::Heading(heading) => {
+            for item in &heading.items {
+                add_item(import, item, tags, today);
+            }
+        }
+    }
+}
+
+fn add_todo(import: &mut Import, todo: &ToDo, tags: &[String], today: Date) {
+    if todo.canceled {
+        import
+            .warnings
+            .push(format!("Skipped canceled to-do \"{}\"", todo.title));
+        return;
+    }
+    let mut name = todo.title.trim().to_string();
+    let mut tags: Vec<String> = tags
+        .iter()
+        .chain(&todo.tags)
+        .filter_map(|t| tag(t))
+        .collect();
+    tags.dedup();
+    for tag in tags {
+        name.push_str(&format!(" #{}", tag));
+    }
+    let due = todo
+        .deadline
+        .as_deref()
+        .and_then(date_prefix)
+        .or_else(|| todo.when.as_deref().and_then(date_prefix));
+
+    if let (Some(rule), false) = (&todo.repeat, todo.completed) {
+        match nearest_interval(rule, due) {
+            Some(interval) => return import.add_recurring(name, interval),
+            None => import.warnings.push(format!(
+                "Unsupported repeat \"{}\" of \"{}\", imported as a to-do",
+                rule, name
+            )),
+        }
+    }
+
+    let (date, state, name) = match todo.completed {
+        true => (
+            todo.completion_date
+                .as_deref()
+                .and_then(date_prefix)
+                .unwrap_or(today),
+            TaskState::Completed,
+            name,
+        ),
+        false => (today, TaskState::Incomplete, with_due(&name, due)),
+    };
+    let mut todo_task = task(name, state);
+    for item in &todo.checklist_items {
+        let state = match item.attributes.completed {
+            true => TaskState::Completed,
+            false => TaskState::Incomplete,
+        };
+        todo_task.add_subtask(task(item.attributes.title.clone(), state));
+    }
+    import.add(date, todo_task);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+    use crate::recurring_task::Interval;
+
+    #[test]
+    fn test_parse() {
+        let today = parse_date("2024-07-10").unwrap();
+        let json = r#"[
+            {"type": "project", "attributes": {"title": "Launch", "area": "Work", "items": [
+                {"type": "heading", "attributes": {"title": "Prep", "items": [
+                    {"type": "to-do", "attributes": {"title": "Release", "deadline": "2024-07-12",
+                     "checklist-items": [
+                        {"type": "checklist-item", "attributes": {"title": "Changelog", "completed": true}},
+                        {"type": "checklist-item", "attributes": {"title": "Tag"}}
+                     ]}}
+                ]}},
+                {"type": "to-do", "attributes": {"title": "Kickoff", "completed": true,
+                 "completion-date": "2024-07-01T09:00:00Z"}}
+            ]}},
+            {"type": "to-do", "attributes": {"title": "Standup", "repeat": "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"}},
+            {"type": "to-do", "attributes": {"title": "Offsite", "canceled": true}}
+        ]"#;
+        let import = parse(json, today).unwrap();
+
+        let release = &import.days[&today][0];
+        assert_eq!(release.name, "Release #Work #Launch @due:2024-07-12");
+        assert_eq!(release.subtasks.len(), 2);
+        assert_eq!(release.state, TaskState::Incomplete);
+        assert_eq!(
+            import.days[&parse_date("2024-07-01").unwrap()][0].name,
+            "Kickoff #Work #Launch"
+        );
+        assert_eq!(import.recurring[0].interval, Interval::Weekday);
+        assert_eq!(import.warnings.len(), 1);
+    }
+}
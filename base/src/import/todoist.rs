@@ -0,0 +1,274 @@
+//! Todoist exports: the CSV of a project (a backup is a zip of those) and
+//! the JSON of the sync API, which also has the completed tasks.
+use super::{date_prefix, tag, task, with_due, Import};
+use crate::recurring_task::Interval;
+use crate::task::State as TaskState;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use time::Date;
+
+lazy_static! {
+    static ref LABEL_REGEX: Regex = Regex::new(r"(^|\s)@(?<label>[A-Za-z][\w-]*)").unwrap();
+}
+
+/// Reads a CSV or JSON export. Completed tasks go on the day they were
+/// completed, open tasks on `today`, with the due date as `@due:`.
+pub fn parse(content: &str, today: Date) -> Result<Import, crate::Error> {
+    match content.trim_start().starts_with('{') {
+        true => parse_json(content, today),
+        false => parse_csv(content, today),
+    }
+}
+
+fn parse_csv(content: &str, today: Date) -> Result<Import, crate::Error> {
+    let mut import = Import::default();
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.to_ascii_uppercase())
+        .collect();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (kind, content_column, indent, date) = (
+        column("TYPE"),
+        column("CONTENT"),
+        column("INDENT"),
+        column("DATE"),
+    );
+    if content_column.is_none() {
+        return Err(crate::Error::Import(
+            "no CONTENT column in the Todoist CSV".to_string(),
+        ));
+    }
+
+    let mut parent: Option<String> = None;
+    for record in reader.records() {
+        let record = record?;
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or("");
+        if !field(kind).is_empty() && field(kind) != "task" {
+            continue;
+        }
+        let name = labels_to_tags(field(content_column));
+        if name.is_empty() {
+            continue;
+        }
+
+        let subtask = field(indent).parse::<u8>().unwrap_or(1) > 1;
+        match subtask {
+            true => match &parent {
+                Some(parent) => {
+                    import.add_subtask(today, parent, task(name, TaskState::Incomplete))
+                }
+                None => import.add(today, task(name, TaskState::Incomplete)),
+            },
+            false => {
+                parent = add_task(&mut import, &name, field(date), today)
+                    .then(|| due_name(&name, field(date)));
+            }
+        }
+    }
+    Ok(import)
+}
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default, alias = "task_id")]
+    id: Option<String>,
+    content: String,
+    #[serde(default)]
+    checked: bool,
+    #[serde(default)]
+    completed_at: Option<String>,
+    #[serde(default)]
+    due: Option<Due>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    parent_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Due {
+    #[serde(default)]
+    date: String,
+    #[serde(default)]
+    string: String,
+    #[serde(default)]
+    is_recurring: bool,
+}
+
+fn parse_json(content: &str, today: Date) -> Result<Import, crate::Error> {
+    let export: Export = serde_json::from_str(content)?;
+    let mut import = Import::default();
+    let names: HashMap<&str, String> = export
+        .items
+        .iter()
+        .filter_map(|item| Some((item.id.as_deref()?, item_name(item))))
+        .collect();
+
+    for item in &export.items {
+        let name = item_name(item);
+        let completed = match (&item.completed_at, item.checked) {
+            (Some(completed_at), _) => Some(date_prefix(completed_at).unwrap_or(today)),
+            (None, true) => Some(today),
+            (None, false) => None,
+        };
+        let parent = item
+            .parent_id
+            .as_deref()
+            .and_then(|id| names.get(id))
+            .cloned();
+
+        match (parent, completed) {
+            (Some(parent), date) => import.add_subtask(
+                date.unwrap_or(today),
+                &parent,
+                task(name, state(date.is_some())),
+            ),
+            (None, Some(date)) => import.add(date, task(name, TaskState::Completed)),
+            (None, None) => {
+                let due = match &item.due {
+                    Some(due) if due.is_recurring => due.string.as_str(),
+                    Some(due) => due.date.as_str(),
+                    None => "",
+                };
+                add_task(&mut import, &name, due, today);
+            }
+        }
+    }
+    Ok(import)
+}
+
+fn item_name(item: &Item) -> String {
+    let mut name = labels_to_tags(&item.content);
+    for label in item.labels.iter().filter_map(|label| tag(label)) {
+        name.push_str(&format!(" #{}", label));
+    }
+    name
+}
+
+/// Adds an open task as a recurring task or a task on `today`, returns
+/// whether it became a task of the day.
+fn add_task(import: &mut Import, name: &str, due: &str, today: Date) -> bool {
+    let due = due.trim();
+    if due.to_ascii_lowercase().starts_with("every") {
+        match interval(due) {
+            Some(interval) => {
+                import.add_recurring(name.to_string(), interval);
+                return false;
+            }
+            None => import.warnings.push(format!(
+                "Unsupported recurrence \"{}\" of \"{}\", imported as a task",
+                due, name
+            )),
+        }
+    }
+    import.add(today, task(due_name(name, due), TaskState::Incomplete));
+    true
+}
+
+/// The name with a `@due:` annotation when the date is one.
+fn due_name(name: &str, due: &str) -> String {
+    with_due(name, date_prefix(due))
+}
+
+/// Maps `every day`, `every! week`, `every monday` and the like.
+fn interval(due: &str) -> Option<Interval> {
+    let due = due.to_ascii_lowercase();
+    let rest = due
+        .trim_start_matches("every!")
+        .trim_start_matches("every")
+        .trim();
+    let rest = match rest {
+        "day" => "daily",
+        "week" => "weekly",
+        "month" => "monthly",
+        "workday" | "work day" => "weekday",
+        "weekend day" => "weekend",
+        "mon" => "monday",
+        "tue" => "tuesday",
+        "wed" => "wednesday",
+        "thu" => "thursday",
+        "fri" => "friday",
+        "sat" => "saturday",
+        "sun" => "sunday",
+        rest => rest,
+    };
+    Interval::try_from(rest).ok()
+}
+
+fn state(completed: bool) -> TaskState {
+    match completed {
+        true => TaskState::Completed,
+        false => TaskState::Incomplete,
+    }
+}
+
+/// Todoist puts labels in the content as `@label`, w0rk uses `#tags`.
+fn labels_to_tags(content: &str) -> String {
+    LABEL_REGEX
+        .replace_all(content.trim(), "$1#$label")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+
+    #[test]
+    fn test_parse_csv() {
+        let today = parse_date("2024-07-10").unwrap();
+        let csv =
+            "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE\n\
+                   section,Work,,,,,,,,\n\
+                   task,Standup @work,,4,1,,,every weekday,en,\n\
+                   task,Release,,4,1,,,2024-07-12,en,\n\
+                   task,Changelog,,4,2,,,,en,\n\
+                   task,Water plants,,4,1,,,every 3 days,en,\n";
+        let import = parse(csv, today).unwrap();
+
+        assert_eq!(import.recurring.len(), 1);
+        assert_eq!(import.recurring[0].name, "Standup #work");
+        assert_eq!(import.recurring[0].interval, Interval::Weekday);
+        let tasks = &import.days[&today];
+        assert_eq!(tasks[0].name, "Release @due:2024-07-12");
+        assert_eq!(tasks[0].subtasks[0].name, "Changelog");
+        assert_eq!(tasks[1].name, "Water plants");
+        assert_eq!(import.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let today = parse_date("2024-07-10").unwrap();
+        let json = r#"{"items": [
+            {"id": "1", "content": "Release", "checked": false, "labels": ["infra"]},
+            {"id": "2", "content": "Changelog", "checked": true, "parent_id": "1",
+             "completed_at": "2024-07-09T16:00:00.000000Z"},
+            {"id": "3", "content": "Invoices", "checked": false,
+             "due": {"date": "2024-08-01", "string": "every month", "is_recurring": true}},
+            {"task_id": "4", "content": "Taxes", "completed_at": "2024-04-30T10:00:00Z"}
+        ]}"#;
+        let import = parse(json, today).unwrap();
+
+        assert_eq!(import.days[&today][0].name, "Release #infra");
+        let completed = &import.days[&parse_date("2024-07-09").unwrap()];
+        assert_eq!(completed[0].name, "Release #infra");
+        assert_eq!(completed[0].state, TaskState::Completed);
+        assert_eq!(
+            import.days[&parse_date("2024-04-30").unwrap()][0].name,
+            "Taxes"
+        );
+        assert_eq!(import.recurring[0].interval, Interval::Monthly);
+    }
+}
@@ -0,0 +1,354 @@
+//! An SQLite index of the tasks of all days, kept up to date by reparsing
+//! only the day files that changed since the last update. It's queried with
+//! a `TaskQuery`, like the days themselves.
+use crate::config::DAY_FORMAT;
+use crate::day::parse_date;
+use crate::query::TaskQuery;
+use crate::task::{State as TaskState, Task};
+use crate::workspace::Workspace;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use time::Date;
+
+pub(crate) const INDEX_FILE: &str = "index.sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS days (
+        path TEXT PRIMARY KEY,
+        date TEXT NOT NULL,
+        modified INTEGER NOT NULL,
+        size INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        date TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        parent INTEGER REFERENCES tasks(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        state TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        task INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tasks_date ON tasks(date);
+    CREATE INDEX IF NOT EXISTS tasks_path ON tasks(path);
+    CREATE INDEX IF NOT EXISTS tags_tag ON tags(tag);
+";
+
+pub struct TaskIndex {
+    connection: Connection,
+}
+
+/// What an update of the index changed.
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexUpdate {
+    pub days_indexed: usize,
+    pub days_removed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedTask {
+    /// The id of the task in the index, it changes when its day changes.
+    pub id: i64,
+    pub date: Date,
+    pub name: String,
+    pub state: TaskState,
+    pub tags: Vec<String>,
+    /// The id of the parent task for subtasks.
+    pub parent: Option<i64>,
+}
+
+impl TaskIndex {
+    /// Opens the index in the state dir, creating it when needed.
+    pub fn open(state_dir: &Path) -> Result<Self, crate::Error> {
+        std::fs::create_dir_all(state_dir)?;
+        Self::with_connection(Connection::open(state_dir.join(INDEX_FILE))?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, crate::Error> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Indexes the days that were added or changed since the last update,
+    /// by their modified time and size, and drops the deleted ones. The
+    /// index is in plain text, so encrypted workspaces aren't indexed.
+    pub fn update(&mut self, workspace: &Workspace) -> Result<IndexUpdate, crate::Error> {
+        if workspace.is_encrypted() {
+            return Err(crate::Error::NotEncrypted("The task index".to_string()));
+        }
+        // The days are parsed with the checkboxes of the workspace, with
+        // other ones than the last time everything is indexed again.
+        let checkboxes = serde_json::to_string(&workspace.checkboxes)?;
+        let indexed_with: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'checkboxes'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if indexed_with.as_ref() != Some(&checkboxes) {
+            self.connection
+                .execute_batch("DELETE FROM tasks; DELETE FROM days;")?;
+            self.connection.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('checkboxes', ?1)",
+                [&checkboxes],
+            )?;
+        }
+        let indexed: HashMap<String, (i64, i64)> = self
+            .connection
+            .prepare("SELECT path, modified, size FROM days")?
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<_, _>>()?;
+
+        let mut changed = Vec::new();
+        let mut present = HashSet::new();
+        for (_, path) in workspace.day_list.iter() {
+            let key = path.to_string_lossy().to_string();
+            let metadata = std::fs::metadata(path)?;
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as i64)
+                .unwrap_or_default();
+            let stat = (modified, metadata.len() as i64);
+            if indexed.get(&key) != Some(&stat) {
+                changed.push((path, key.clone(), stat));
+            }
+            present.insert(key);
+        }
+
+        let transaction = self.connection.transaction()?;
+        let mut update = IndexUpdate::default();
+        for path in indexed.keys().filter(|path| !present.contains(*path)) {
+            transaction.execute("DELETE FROM tasks WHERE path = ?1", [path])?;
+            transaction.execute("DELETE FROM days WHERE path = ?1", [path])?;
+            update.days_removed += 1;
+        }
+        for (day_path, path, (modified, size)) in &changed {
+            let day = workspace.read_day(day_path)?;
+            transaction.execute("DELETE FROM tasks WHERE path = ?1", [path])?;
+            for (position, task) in day.tasks.iter().enumerate() {
+                let parent = insert_task(&transaction, path, &day.date, position, None, task)?;
+                for (position, subtask) in task.subtasks.iter().enumerate() {
+                    insert_task(
+                        &transaction,
+                        path,
+                        &day.date,
+                        position,
+                        Some(parent),
+                        subtask,
+                    )?;
+                }
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO days (path, date, modified, size) VALUES (?1, ?2, ?3, ?4)",
+                params![path, day.date.format(&DAY_FORMAT)?, modified, size],
+            )?;
+            update.days_indexed += 1;
+        }
+        transaction.commit()?;
+        Ok(update)
+    }
+
+    /// The tasks matching the query, by date and their order in the day.
+    /// The states and dates are looked up in the index, the other conditions
+    /// are matched like on the days.
+    pub fn query(&self, query: &TaskQuery) -> Result<Vec<IndexedTask>, crate::Error> {
+        let mut sql = "SELECT id, date, name, state, parent FROM tasks WHERE 1 = 1".to_string();
+        let mut values: Vec<String> = Vec::new();
+        if !query.states().is_empty() {
+            let states: Vec<String> = query
+                .states()
+                .iter()
+                .map(|state| {
+                    values.push(state_name(state).to_string());
+                    format!("?{}", values.len())
+                })
+                .collect();
+            sql.push_str(&format!(" AND state IN ({})", states.join(", ")));
+        }
+        // Adds `AND <clause> ?<n>` with the value as parameter n.
+        let mut filter = |clause: &str, value: String| {
+            values.push(value);
+            sql.push_str(&format!(" AND {} ?{}", clause, values.len()));
+        };
+        if let Some(from) = &query.range().from {
+            filter("date >=", from.format(&DAY_FORMAT)?);
+        }
+        if let Some(to) = &query.range().to {
+            filter("date <=", to.format(&DAY_FORMAT)?);
+        }
+        sql.push_str(" ORDER BY date, coalesce(parent, id), parent IS NOT NULL, position");
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(&values), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tasks = Vec::new();
+        for (id, date, name, state, parent) in rows {
+            let task = Task {
+                name,
+                state: parse_state(&state)?,
+                subtasks: Vec::new(),
+            };
+            if !query.matches(&task) {
+                continue;
+            }
+            tasks.push(IndexedTask {
+                id,
+                date: parse_date(&date)?,
+                tags: self.tags(id)?,
+                name: task.name,
+                state: task.state,
+                parent,
+            });
+        }
+        Ok(tasks)
+    }
+
+    fn tags(&self, id: i64) -> Result<Vec<String>, crate::Error> {
+        Ok(self
+            .connection
+            .prepare_cached("SELECT tag FROM tags WHERE task = ?1 ORDER BY rowid")?
+            .query_map([id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// The number of tasks and subtasks in the index.
+    pub fn task_count(&self) -> Result<usize, crate::Error> {
+        let count: i64 = self
+            .connection
+            .query_row("SELECT count(*) FROM tasks", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+}
+
+fn insert_task(
+    transaction: &Transaction,
+    path: &str,
+    date: &Date,
+    position: usize,
+    parent: Option<i64>,
+    task: &Task,
+) -> Result<i64, crate::Error> {
+    transaction.execute(
+        "INSERT INTO tasks (path, date, position, parent, name, state) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            path,
+            date.format(&DAY_FORMAT)?,
+            position as i64,
+            parent,
+            task.name,
+            state_name(&task.state)
+        ],
+    )?;
+    let id = transaction.last_insert_rowid();
+    for tag in task.tags() {
+        transaction.execute(
+            "INSERT INTO tags (task, tag) VALUES (?1, ?2)",
+            params![id, tag.to_lowercase()],
+        )?;
+    }
+    Ok(id)
+}
+
+fn state_name(state: &TaskState) -> &'static str {
+    match state {
+        TaskState::Completed => "completed",
+        TaskState::Incomplete => "incomplete",
+        TaskState::InProgress => "in_progress",
+        TaskState::Blocked => "blocked",
+        TaskState::Waiting => "waiting",
+    }
+}
+
+fn parse_state(name: &str) -> Result<TaskState, crate::Error> {
+    match name {
+        "completed" => Ok(TaskState::Completed),
+        "incomplete" => Ok(TaskState::Incomplete),
+        "in_progress" => Ok(TaskState::InProgress),
+        "blocked" => Ok(TaskState::Blocked),
+        "waiting" => Ok(TaskState::Waiting),
+        _ => Err(crate::Error::InvalidTaskSyntax(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_and_query() {
+        let dir = std::env::temp_dir().join("w0rk-test-index");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("2024-06-03.md"),
+            "* [#] Upgrade the database #infra\n  * [x] Backup #infra\n* [ ] Write docs\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [#] Rotate keys #Infra\n").unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+        let mut index = TaskIndex::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        let update = index.update(&workspace).unwrap();
+        assert_eq!(update.days_indexed, 2);
+        assert_eq!(index.task_count().unwrap(), 4);
+        assert_eq!(index.update(&workspace).unwrap(), IndexUpdate::default());
+
+        // Read with other checkboxes, the days are indexed again.
+        let workspace = workspace.with_checkboxes(std::collections::BTreeMap::from([(
+            "?".to_string(),
+            TaskState::Waiting,
+        )]));
+        assert_eq!(index.update(&workspace).unwrap().days_indexed, 2);
+        assert_eq!(index.update(&workspace).unwrap(), IndexUpdate::default());
+        assert_eq!(index.task_count().unwrap(), 4);
+
+        let june = TaskQuery::new()
+            .state(TaskState::Blocked)
+            .tag("#infra")
+            .in_range(parse_date("2024-06-01").unwrap()..=parse_date("2024-06-30").unwrap());
+        let tasks = index.query(&june).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "Upgrade the database #infra");
+        assert_eq!(tasks[0].tags, vec!["infra"]);
+
+        let backup = TaskQuery::new().text("BACKUP");
+        assert_eq!(index.query(&backup).unwrap()[0].parent, Some(tasks[0].id));
+
+        std::fs::write(
+            dir.join("2024-06-03.md"),
+            "* [x] Upgrade the database #infra\n",
+        )
+        .unwrap();
+        std::fs::remove_file(dir.join("2024-07-01.md")).unwrap();
+        let workspace = Workspace::from_path(&dir)
+            .unwrap()
+            .with_checkboxes(workspace.checkboxes.clone());
+        let update = index.update(&workspace).unwrap();
+        assert_eq!(update.days_indexed, 1);
+        assert_eq!(update.days_removed, 1);
+        assert!(index.query(&june).unwrap().is_empty());
+        assert_eq!(index.task_count().unwrap(), 1);
+    }
+}
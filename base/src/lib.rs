@@ -1,16 +1,79 @@
-pub use config::{Config, Rewrite};
-pub use day::Day;
+#[cfg(feature = "fs")]
+pub use config::CONFIG_FILES;
+pub use config::{
+    BackupConfig, Bullet, CaldavConfig, CheckboxConfig, Config, DaemonConfig, Dialect,
+    DigestPeriod, DiscordConfig, EmailConfig, EmojiConfig, EncryptionConfig, GitConfig, Layout,
+    MatrixConfig, MattermostConfig, NotificationConfig, NotionConfig, ObsidianConfig,
+    PomodoroConfig, ReminderConfig, ReminderKind, Rewrite, ServerConfig, SlackConfig, SlackMode,
+    StyleConfig, TeamsConfig, TelegramConfig, WebhookConfig,
+};
+pub use dates::parse_natural_date;
+pub use day::{is_locked, parse_date, Day, Diagnostic, TaskEntry};
+pub use recurring_task::{Deferred, Interval, RecurringState, RecurringTask, RecurringTasks};
 pub use task::{State as TaskState, Task};
 use thiserror::Error;
-pub use workspace::Workspace;
+#[cfg(feature = "fs")]
+pub use workspace::{AllTasks, Workspace};
 
+#[cfg(feature = "encryption")]
+pub use encryption::Key;
+/// Without the `encryption` feature there are no keys, the day files are
+/// always read and written in plain text.
+#[cfg(not(feature = "encryption"))]
+#[derive(Clone)]
+pub enum Key {}
+
+pub mod analytics;
+#[cfg(feature = "fs")]
+pub mod backup;
+pub mod board;
+#[cfg(feature = "fs")]
+mod cache;
+#[cfg(feature = "fs")]
+pub mod collect;
 mod config;
+mod dates;
 mod day;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod export;
+#[cfg(feature = "fs")]
+pub mod format;
+#[cfg(feature = "fs")]
+pub mod git;
+pub mod grep;
+#[cfg(feature = "fs")]
+pub mod html;
+pub mod ics;
+pub mod import;
+#[cfg(feature = "index")]
+pub mod index;
+#[cfg(feature = "fs")]
+pub mod lint;
+mod logseq;
+#[cfg(feature = "fs")]
+pub mod lsp;
+pub mod meeting;
+#[cfg(feature = "fs")]
+pub mod migrate;
+pub mod planner;
+pub mod pomodoro;
+pub mod query;
 mod recurring_task;
+pub mod report;
+pub mod review;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(feature = "fs")]
+pub mod secrets;
 mod task;
+mod taskpaper;
+pub mod triage;
+#[cfg(feature = "fs")]
 mod workspace;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Time parse error: {0}")]
     TimeParse(#[from] time::error::Parse),
@@ -20,6 +83,15 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
+    #[error("Invalid config: {0}")]
+    Config(String),
+    #[error("git failed in {0}")]
+    Git(String),
+    #[cfg(feature = "fs")]
+    #[error("Keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+    #[error("No token \"{0}\" in the keyring, add it with `w0rk auth set {0}`")]
+    MissingSecret(String),
     #[error("Error while parsing: \"{0}\". Expected format: \"* [] @<interval> <name>\"")]
     InvalidRecurringTaskSyntax(String),
     #[error("Error while parsing: \"{0}\". Expected format: \"* [] <name>\"")]
@@ -34,14 +106,104 @@ pub enum Error {
     InvalidDayPath(String),
     #[error("Day already exists: {0}")]
     DayAlreadyExists(String),
+    #[error("The day of {0} is locked by `lock_after_days`, change it with --force")]
+    DayIsLocked(time::Date),
+    #[error("There's no day for today yet, create it with `w0rk new`")]
+    NoToday,
+    #[error("There's no day for {0}")]
+    NoDay(time::Date),
+    #[error("There's no day for {0} in the trash")]
+    NotInTrash(time::Date),
+    #[error("There's no meeting \"{0}\" in today's notes")]
+    NoMeeting(String),
+    #[error("There's no task {0} in today's file")]
+    NoTaskAt(usize),
+    #[error("\"{0}\" is not a recurring task")]
+    NotRecurring(String),
+    #[cfg(feature = "fs")]
+    #[error("Invalid glob pattern: {0}")]
+    Glob(#[from] glob::PatternError),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Could not import: {0}")]
+    Import(String),
+    #[error("{}: {source}", path.display())]
+    File {
+        path: std::path::PathBuf,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{}:{line}: {source}", path.display())]
+    Line {
+        path: std::path::PathBuf,
+        /// The line number, starting at 1.
+        line: usize,
+        content: String,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("Invalid date range: \"{0}\". Expected format: \"<from>..<to>\", e.g. \"2024-07-01..2024-07-31\"")]
+    InvalidDateRange(String),
+    #[error("Invalid date: \"{0}\". Expected a date like \"2024-07-01\", \"yesterday\", \"last friday\", \"next mon\" or \"3 days ago\"")]
+    InvalidDate(String),
+    #[error("Invalid filter: \"{0}\". Expected terms like \"state:blocked\", \"tag:infra\", \"due-before:2024-07-01\" or \"range:2024-07-01..2024-07-31\"")]
+    InvalidFilter(String),
+    #[error("The day file is encrypted, set `encryption` in the config to read it")]
+    Encrypted,
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    #[error("{0} would keep the tasks in plain text, it's not available with `encryption`")]
+    NotEncrypted(String),
+    #[error("{} already exists, use --force to overwrite it", .0.display())]
+    AlreadyExists(std::path::PathBuf),
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+    #[cfg(feature = "index")]
+    #[error("Index error: {0}")]
+    Index(#[from] rusqlite::Error),
+    #[cfg(feature = "search")]
+    #[error("Search index error: {0}")]
+    Search(#[from] tantivy::TantivyError),
+    #[cfg(feature = "search")]
+    #[error("Invalid search query: {0}")]
+    SearchQuery(#[from] tantivy::query::QueryParserError),
+}
+
+impl Error {
+    /// Wraps an error with the file it happened in, for `map_err`.
+    pub(crate) fn in_file<E: Into<Error>>(path: &std::path::Path) -> impl FnOnce(E) -> Error + '_ {
+        move |err| Error::File {
+            path: path.to_owned(),
+            source: Box::new(err.into()),
+        }
+    }
+
+    /// Wraps an error with the line of the file it happened on.
+    pub(crate) fn on_line(self, path: &std::path::Path, index: usize, content: &str) -> Error {
+        Error::Line {
+            path: path.to_owned(),
+            line: index + 1,
+            content: content.to_string(),
+            source: Box::new(self),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
+#[cfg(any(test, feature = "test-helpers"))]
+#[doc(hidden)]
+pub mod tests {
 
     pub mod helpers {
+        use crate::Day;
         use std::env::current_dir;
-        use std::path::PathBuf;
+        use std::path::{Path, PathBuf};
+
+        /// The day of the date with the content, without a file.
+        pub fn day(date: &str, content: &str) -> Day {
+            Day::parse(Path::new(&format!("{}.md", date)), content)
+                .unwrap()
+                .0
+        }
 
         pub fn test_fixtures_path() -> PathBuf {
             let current_dir = current_dir().expect("Could not get current dir");
@@ -0,0 +1,445 @@
+//! Checks of the day files and `.recurring.md` for `w0rk lint`, with fixes
+//! for the mechanical problems like a `[X]` checkbox or a `2024-7-1.md`
+//! file name.
+//!
+//! The work dir is read file by file instead of as a `Workspace`, which
+//! stops at the first invalid line of `.recurring.md`.
+use crate::config::{DAY_EXTENTION, RECURRING_FILE, TASKPAPER_EXTENSION};
+use crate::day::{
+    date_from_path, is_locked, list_files, read_day_file, write_day_content, Day, CHECKBOX_REGEX,
+};
+use crate::recurring_task::RecurringTask;
+use crate::task::{State as TaskState, Task, ANNOTATION_REGEX};
+use crate::Key;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use time::Date;
+
+lazy_static! {
+    // A date without the leading zeros, like `2024-7-1`.
+    static ref LOOSE_DATE_REGEX: Regex =
+        Regex::new(r"(?<year>\d{4})(?<separator>[-_])(?<month>\d{1,2})[-_](?<day>\d{1,2})").unwrap();
+    static ref SPENT_VALUE_REGEX: Regex = Regex::new(r"^(?:\d+h)?(?:\d+m)?$").unwrap();
+}
+
+/// A problem in a file of the work dir.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub path: PathBuf,
+    /// The line number, starting at 1, for problems in the content.
+    pub line: Option<usize>,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+/// How `fix` solves a problem.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Replace the line with this one.
+    Line(String),
+    /// Rename the file to this path.
+    Rename(PathBuf),
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// The problems in the day files and `.recurring.md` of the work dir, and
+/// the year and month folders in it, by file. The checkboxes in the table
+/// are valid besides the usual ones, see `CheckboxConfig`.
+pub fn lint(
+    dir: &Path,
+    key: Option<&Key>,
+    checkboxes: &BTreeMap<String, TaskState>,
+) -> Result<Vec<Problem>, crate::Error> {
+    let mut files = list_files(dir)?;
+    files.sort();
+
+    let mut problems = Vec::new();
+    let mut dates: BTreeMap<Date, PathBuf> = BTreeMap::new();
+    for path in files {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if file_name == RECURRING_FILE {
+            problems.extend(lint_recurring(&path)?);
+            continue;
+        }
+        let is_day_file = [DAY_EXTENTION, TASKPAPER_EXTENSION]
+            .iter()
+            .any(|extension| path.extension().is_some_and(|ext| ext == *extension));
+        if !is_day_file || file_name.starts_with('.') {
+            continue;
+        }
+
+        match date_from_path(&path) {
+            Ok(date) => match dates.get(&date) {
+                Some(first) => problems.push(Problem {
+                    path: path.clone(),
+                    line: None,
+                    message: format!("another day file for {}, {}", date, first.display()),
+                    fix: None,
+                }),
+                None => {
+                    dates.insert(date, path.clone());
+                }
+            },
+            Err(_) => {
+                problems.push(lint_file_name(&path));
+                continue;
+            }
+        }
+        problems.extend(lint_day(&path, key, checkboxes));
+    }
+    Ok(problems)
+}
+
+/// Applies the fixes of the problems, returning how many were fixed. The
+/// lines are fixed before a file is renamed. The days locked by
+/// `lock_after` are left as they are, see `is_locked`.
+pub fn fix(
+    problems: &[Problem],
+    key: Option<&Key>,
+    lock_after: Option<u32>,
+) -> Result<usize, crate::Error> {
+    let locked = |path: &Path| date_from_path(path).is_ok_and(|date| is_locked(&date, lock_after));
+    let mut lines: BTreeMap<&Path, Vec<(usize, &str)>> = BTreeMap::new();
+    let mut renames = Vec::new();
+    for problem in problems.iter().filter(|problem| !locked(&problem.path)) {
+        match (&problem.fix, problem.line) {
+            (Some(Fix::Line(line)), Some(number)) => lines
+                .entry(problem.path.as_path())
+                .or_default()
+                .push((number, line)),
+            (Some(Fix::Rename(to)), _) if !locked(to) => renames.push((problem.path.as_path(), to)),
+            _ => {}
+        }
+    }
+
+    let mut fixed = 0;
+    for (path, fixes) in lines {
+        let content = read_day_file(path, key)?;
+        let mut content_lines: Vec<&str> = content.lines().collect();
+        for (number, line) in &fixes {
+            if let Some(existing) = content_lines.get_mut(number - 1) {
+                *existing = line;
+                fixed += 1;
+            }
+        }
+        let mut new_content = content_lines.join("\n");
+        if content.ends_with('\n') {
+            new_content.push('\n');
+        }
+        write_day_content(path, new_content, key)?;
+    }
+    for (from, to) in renames {
+        if to.exists() {
+            continue;
+        }
+        std::fs::rename(from, to).map_err(crate::Error::in_file(from))?;
+        fixed += 1;
+    }
+    Ok(fixed)
+}
+
+/// A day file whose name has no `YYYY-MM-DD` date, renamed when it has one
+/// without the leading zeros.
+fn lint_file_name(path: &Path) -> Problem {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let padded = pad_date(&file_name).map(|name| path.with_file_name(name));
+    let fix = padded.filter(|padded| !padded.exists()).map(Fix::Rename);
+    Problem {
+        path: path.to_path_buf(),
+        line: None,
+        message: "the file name has no `YYYY-MM-DD` date".to_string(),
+        fix,
+    }
+}
+
+/// The text with the first date without leading zeros padded, like
+/// `2024-07-01` for `2024-7-1`.
+fn pad_date(text: &str) -> Option<String> {
+    let captures = LOOSE_DATE_REGEX.captures(text)?;
+    let number = |name: &str| captures[name].parse::<u8>().ok();
+    let month = time::Month::try_from(number("month")?).ok()?;
+    let date = Date::from_calendar_date(captures["year"].parse().ok()?, month, number("day")?);
+    let date = date.ok()?;
+    let separator = &captures["separator"];
+    let padded = format!(
+        "{:04}{separator}{:02}{separator}{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    );
+    let found = captures.get(0)?;
+    Some(format!(
+        "{}{}{}",
+        &text[..found.start()],
+        padded,
+        &text[found.end()..]
+    ))
+}
+
+fn lint_day(
+    path: &Path,
+    key: Option<&Key>,
+    checkboxes: &BTreeMap<String, TaskState>,
+) -> Vec<Problem> {
+    match read_day_file(path, key) {
+        Ok(content) => lint_day_content(path, &content, checkboxes),
+        Err(err) => vec![Problem {
+            path: path.to_path_buf(),
+            line: None,
+            message: format!("can't read the file: {}", err),
+            fix: None,
+        }],
+    }
+}
+
+/// The problems in the content of a day file or `.recurring.md` at the
+/// path, like the one in an editor, see `lsp`.
+pub(crate) fn lint_content(
+    path: &Path,
+    content: &str,
+    checkboxes: &BTreeMap<String, TaskState>,
+) -> Vec<Problem> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    if file_name == RECURRING_FILE {
+        return lint_recurring_content(path, content);
+    }
+    if date_from_path(path).is_err() {
+        return vec![lint_file_name(path)];
+    }
+    lint_day_content(path, content, checkboxes)
+}
+
+fn lint_day_content(
+    path: &Path,
+    content: &str,
+    checkboxes: &BTreeMap<String, TaskState>,
+) -> Vec<Problem> {
+    let problem = |line: Option<usize>, message: String| Problem {
+        path: path.to_path_buf(),
+        line,
+        message,
+        fix: None,
+    };
+    let (day, diagnostics) = match Day::parse_with_checkboxes(path, content, checkboxes) {
+        Ok(parsed) => parsed,
+        Err(err) => return vec![problem(None, err.to_string())],
+    };
+
+    let mut problems: Vec<Problem> = diagnostics
+        .into_iter()
+        .map(|diagnostic| Problem {
+            fix: fix_checkbox(&diagnostic.content).map(Fix::Line),
+            ..problem(Some(diagnostic.line), diagnostic.reason)
+        })
+        .collect();
+    let mut ids = HashSet::new();
+    for (index, line) in content.lines().enumerate() {
+        let Ok(task) = Task::try_from(line.trim_start()) else {
+            continue;
+        };
+        for dependency in task.blocked_by() {
+            if !day
+                .task_entries()
+                .any(|entry| entry.task.is_dependency(&dependency))
+            {
+                problems.push(problem(
+                    Some(index + 1),
+                    format!(
+                        "no task `{}` in the day for `@blocked-by`, it stays blocked",
+                        dependency
+                    ),
+                ));
+            }
+        }
+        for (message, fix) in lint_annotations(line, &mut ids) {
+            problems.push(Problem {
+                fix: fix.map(Fix::Line),
+                ..problem(Some(index + 1), message)
+            });
+        }
+    }
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+/// The line with a `+` bullet, `[X]` or `[]` written the usual way, when
+/// that makes it a task.
+pub(crate) fn fix_checkbox(line: &str) -> Option<String> {
+    let captures = CHECKBOX_REGEX.captures(line)?;
+    let state = match &captures["state"] {
+        "X" => "x",
+        "" => " ",
+        state => state,
+    };
+    let bullet = match &captures["bullet"] {
+        "+" => "*",
+        bullet => bullet,
+    };
+    let fixed = format!(
+        "{}{} [{}]{}",
+        &captures["indent"],
+        bullet,
+        state,
+        &line[captures.get(0)?.end()..]
+    );
+    match Task::try_from(fixed.trim_start()) {
+        Ok(_) if fixed != line => Some(fixed),
+        _ => None,
+    }
+}
+
+/// The invalid `@due`, `@spent`, `@id`, `@blocked-by` and `@every`
+/// annotations in a task line, with the fixed line if there's a mechanical
+/// fix. `ids` are the ids seen in the day so far.
+fn lint_annotations(line: &str, ids: &mut HashSet<String>) -> Vec<(String, Option<String>)> {
+    let mut problems = Vec::new();
+    for captures in ANNOTATION_REGEX.captures_iter(line) {
+        let (key, value) = (&captures["key"], &captures["value"]);
+        let annotation = format!("@{}:{}", key, value);
+        match key {
+            "due" if crate::parse_date(value).is_err() => {
+                let fix = pad_date(value)
+                    .filter(|padded| crate::parse_date(padded).is_ok())
+                    .map(|padded| line.replacen(&annotation, &format!("@due:{}", padded), 1));
+                problems.push((
+                    format!(
+                        "invalid due date `{}`, expected `@due:YYYY-MM-DD`",
+                        annotation
+                    ),
+                    fix,
+                ));
+            }
+            "spent" if value.is_empty() || !SPENT_VALUE_REGEX.is_match(value) => problems.push((
+                format!(
+                    "invalid time spent `{}`, expected like `@spent:1h30m`",
+                    annotation
+                ),
+                None,
+            )),
+            "every" if crate::Interval::try_from(value).is_err() => problems.push((
+                format!(
+                    "invalid interval `{}`, expected like `@every:monday`",
+                    annotation
+                ),
+                None,
+            )),
+            "id" | "blocked-by" if value.is_empty() => {
+                problems.push((format!("`@{}:` without a value", key), None))
+            }
+            "id" if !ids.insert(value.to_string()) => {
+                problems.push((format!("the id `{}` is used by another task", value), None))
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+fn lint_recurring(path: &Path) -> Result<Vec<Problem>, crate::Error> {
+    let content = std::fs::read_to_string(path).map_err(crate::Error::in_file(path))?;
+    Ok(lint_recurring_content(path, &content))
+}
+
+fn lint_recurring_content(path: &Path, content: &str) -> Vec<Problem> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| {
+            let err = RecurringTask::try_from(line).err()?;
+            Some(Problem {
+                path: path.to_path_buf(),
+                line: Some(index + 1),
+                message: err.to_string(),
+                fix: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_and_fix() {
+        let dir = std::env::temp_dir().join("w0rk-test-lint");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("2024-07-01.md"),
+            "* [X] Deploy\n+ [ ] Review\n* [?] Ask\n\
+             * [ ] Call @due:2024-7-5 @spent:1.5h @id:call\n* [ ] Mail @id:call\n\
+             * [#] Announce @blocked-by:call @blocked-by:launch\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("2024-7-2.md"), "* [ ] Rotate keys\n").unwrap();
+        std::fs::write(dir.join("2024-07-03.md"), "* [ ] Plan\n").unwrap();
+        std::fs::write(dir.join("2024-07-03.taskpaper"), "- Plan\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "Scratch\n").unwrap();
+        std::fs::write(
+            dir.join(RECURRING_FILE),
+            "* [ ] @daily Standup\n* [ ] Oops\n",
+        )
+        .unwrap();
+
+        let problems = lint(&dir, None, &BTreeMap::new()).unwrap();
+        let found: Vec<(String, Option<usize>, bool)> = problems
+            .iter()
+            .map(|problem| {
+                (
+                    problem
+                        .path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    problem.line,
+                    problem.fix.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (RECURRING_FILE.to_string(), Some(2), false),
+                ("2024-07-01.md".to_string(), Some(1), true),
+                ("2024-07-01.md".to_string(), Some(2), true),
+                ("2024-07-01.md".to_string(), Some(3), false),
+                ("2024-07-01.md".to_string(), Some(4), true),
+                ("2024-07-01.md".to_string(), Some(4), false),
+                ("2024-07-01.md".to_string(), Some(5), false),
+                ("2024-07-01.md".to_string(), Some(6), false),
+                ("2024-07-03.taskpaper".to_string(), None, false),
+                ("2024-7-2.md".to_string(), None, true),
+                ("notes.md".to_string(), None, false),
+            ]
+        );
+
+        // Days locked by `lock_after_days` aren't fixed, or renamed to a
+        // locked date.
+        assert_eq!(fix(&problems, None, Some(0)).unwrap(), 0);
+        assert!(dir.join("2024-7-2.md").exists());
+        assert_eq!(fix(&problems, None, None).unwrap(), 4);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("2024-07-01.md")).unwrap(),
+            "* [x] Deploy\n* [ ] Review\n* [?] Ask\n\
+             * [ ] Call @due:2024-07-05 @spent:1.5h @id:call\n* [ ] Mail @id:call\n\
+             * [#] Announce @blocked-by:call @blocked-by:launch\n"
+        );
+        assert!(dir.join("2024-07-02.md").exists());
+        assert_eq!(lint(&dir, None, &BTreeMap::new()).unwrap().len(), 7);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
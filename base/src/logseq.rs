@@ -0,0 +1,280 @@
+//! The Logseq dialect of the day files, its journal pages: outliner blocks
+//! like `- TODO Deploy #infra`, with the subtasks as indented blocks.
+use crate::day::Source;
+use crate::task::{State as TaskState, Task};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::path::Path;
+use time::format_description::{parse_owned, OwnedFormatItem};
+
+lazy_static! {
+    static ref BLOCK_REGEX: Regex = Regex::new(
+        r"^(?<indent>\s*)- (?<keyword>TODO|LATER|DOING|NOW|WAITING|WAIT|DONE) (?<name>.+?)\s*$"
+    )
+    .unwrap();
+    static ref BLOCK_START_REGEX: Regex = Regex::new(r"^(?<indent>\s*)- ").unwrap();
+    static ref JOURNAL_REGEX: Regex = Regex::new(r"^\d{4}_\d{2}_\d{2}$").unwrap();
+    static ref DEADLINE_REGEX: Regex =
+        Regex::new(r"^\s*(?:DEADLINE|SCHEDULED): <(?<date>\d{4}-\d{2}-\d{2})[^>]*>\s*$").unwrap();
+    static ref DUE_REGEX: Regex = Regex::new(r"\s*@due:(?<date>\d{4}-\d{2}-\d{2})").unwrap();
+    static ref DEADLINE_FORMAT: OwnedFormatItem =
+        parse_owned::<2>("[year]-[month]-[day] [weekday repr:short]").unwrap();
+    /// The file names of the journal pages, `2024_07_01.md`.
+    pub(crate) static ref JOURNAL_FORMAT: OwnedFormatItem =
+        parse_owned::<2>("[year]_[month]_[day]").unwrap();
+}
+
+/// Whether the file is a journal page, like `journals/2024_07_01.md`.
+pub(crate) fn is_journal(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| JOURNAL_REGEX.is_match(stem))
+}
+
+/// The page properties (`title:: …`) and the blocks above the first task
+/// are the preamble. Top-level blocks with a task keyword are tasks, the
+/// ones directly below them subtasks. A `DEADLINE` or `SCHEDULED` line
+/// below a task becomes `@due:`. The other blocks below a task stay with it
+/// in its source, every other block is kept as notes, so the tree of
+/// blocks is written back as it was.
+pub(crate) fn parse(content: &str, sources: &mut Vec<Source>) -> (String, Vec<Task>, String) {
+    let mut preamble = String::new();
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut notes = String::new();
+    // Whether the lines belong to the last task, below its top-level block.
+    let mut in_task = false;
+    // Whether the last line was a task, for the deadline below it.
+    let mut last_task = false;
+
+    for line in content.lines() {
+        if let (true, Some(captures)) = (last_task, DEADLINE_REGEX.captures(line)) {
+            let task = match tasks.last_mut() {
+                Some(task) if !task.subtasks.is_empty() => task.subtasks.last_mut(),
+                task => task,
+            };
+            if let (Some(task), Some(source)) = (task, sources.last_mut()) {
+                if task.due().is_none() {
+                    task.name.push_str(&format!(" @due:{}", &captures["date"]));
+                    source.name = task.name.clone();
+                }
+                source.children.push_str(line);
+                source.children.push('\n');
+                continue;
+            }
+        }
+
+        let depth = BLOCK_START_REGEX
+            .captures(line)
+            .map(|captures| depth(&captures["indent"]));
+        last_task = false;
+        match (depth, BLOCK_REGEX.captures(line)) {
+            (Some(0), Some(captures)) => {
+                let task = task(&captures);
+                sources.push(source(&task, false, line));
+                tasks.push(task);
+                in_task = true;
+                last_task = true;
+            }
+            (Some(1), Some(captures)) if in_task => {
+                let task = task(&captures);
+                sources.push(source(&task, true, line));
+                if let Some(parent) = tasks.last_mut() {
+                    parent.subtasks.push(task);
+                }
+                last_task = true;
+            }
+            (Some(0), None) if in_task => {
+                in_task = false;
+                notes.push_str(line);
+                notes.push('\n');
+            }
+            _ if in_task => {
+                if let Some(source) = sources.last_mut() {
+                    source.children.push_str(line);
+                    source.children.push('\n');
+                }
+            }
+            _ => {
+                let text = match tasks.is_empty() {
+                    true => &mut preamble,
+                    false => &mut notes,
+                };
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+
+    (preamble, tasks, notes)
+}
+
+fn task(captures: &Captures) -> Task {
+    Task {
+        name: captures["name"].to_string(),
+        state: match &captures["keyword"] {
+            "DOING" | "NOW" => TaskState::InProgress,
+            "WAITING" | "WAIT" => TaskState::Blocked,
+            "DONE" => TaskState::Completed,
+            _ => TaskState::Incomplete,
+        },
+        subtasks: Vec::new(),
+    }
+}
+
+fn source(task: &Task, subtask: bool, line: &str) -> Source {
+    Source {
+        subtask,
+        state: task.state.clone(),
+        name: task.name.clone(),
+        line: line.to_string(),
+        children: String::new(),
+    }
+}
+
+/// The level of a block, by tabs or two spaces.
+fn depth(indent: &str) -> usize {
+    indent
+        .chars()
+        .map(|c| if c == '\t' { 2 } else { 1 })
+        .sum::<usize>()
+        / 2
+}
+
+/// The tasks as blocks with `TODO`, `DOING`, `WAITING` or `DONE`, and the
+/// notes below them. The blocks of tasks that are still there are written
+/// like they were read with the blocks below them, with the new keyword.
+pub(crate) fn to_string(tasks: &[Task], notes: &str, sources: &[Source]) -> String {
+    let mut sources: Vec<&Source> = sources.iter().collect();
+    let mut content = String::new();
+    for task in tasks {
+        content.push_str(&task_block(task, false, &mut sources));
+        for subtask in &task.subtasks {
+            content.push_str(&task_block(subtask, true, &mut sources));
+        }
+    }
+    format!("{}{}", content, notes.trim_start_matches('\n'))
+}
+
+fn task_block(task: &Task, subtask: bool, sources: &mut Vec<&Source>) -> String {
+    let same = |source: &&Source| source.subtask == subtask && source.name == task.name;
+    let index = sources
+        .iter()
+        .position(|source| same(source) && source.state == task.state)
+        .or_else(|| sources.iter().position(same));
+    let Some(index) = index else {
+        return block(task, if subtask { "\t" } else { "" });
+    };
+    let source = sources.remove(index);
+    let line = match source.state == task.state {
+        true => source.line.clone(),
+        false => BLOCK_REGEX
+            .replace(
+                &source.line,
+                format!("${{indent}}- {} ${{name}}", keyword(&task.state)).as_str(),
+            )
+            .to_string(),
+    };
+    format!("{}\n{}", line, source.children)
+}
+
+fn keyword(state: &TaskState) -> &'static str {
+    match state {
+        TaskState::Incomplete => "TODO",
+        TaskState::InProgress => "DOING",
+        // Logseq has no blocked keyword, both read back as blocked.
+        TaskState::Blocked | TaskState::Waiting => "WAITING",
+        TaskState::Completed => "DONE",
+    }
+}
+
+fn block(task: &Task, indent: &str) -> String {
+    let name = DUE_REGEX.replace_all(&task.name, "");
+    let mut block = format!("{}- {} {}\n", indent, keyword(&task.state), name);
+    if let Some(due) = task.due() {
+        let date = due
+            .format(&DEADLINE_FORMAT)
+            .unwrap_or_else(|_| due.to_string());
+        block.push_str(&format!("{}  DEADLINE: <{}>\n", indent, date));
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let content = "title:: Jul 1st, 2024\n\
+                       - TODO Deploy #infra\n\
+                       \x20\x20DEADLINE: <2024-07-12 Fri>\n\
+                       \t- DONE Build\n\
+                       \t- DOING Tag [[Release]]\n\
+                       - WAITING Review\n\
+                       - Meeting with [[Alice]]\n\
+                       \t- Agreed on the scope\n";
+        let mut sources = Vec::new();
+        let (preamble, tasks, notes) = parse(content, &mut sources);
+
+        assert_eq!(preamble, "title:: Jul 1st, 2024\n");
+        assert_eq!(tasks[0].name, "Deploy #infra @due:2024-07-12");
+        assert_eq!(tasks[0].subtasks[1].state, TaskState::InProgress);
+        assert_eq!(tasks[1].state, TaskState::Blocked);
+        assert_eq!(notes, "- Meeting with [[Alice]]\n\t- Agreed on the scope\n");
+        assert_eq!(
+            format!("{}{}", preamble, to_string(&tasks, &notes, &sources)),
+            content
+        );
+    }
+
+    #[test]
+    fn test_round_trip_nested() {
+        let content = "- Plan for today\n\
+                       - TODO Deploy\n\
+                       \t- Needs the new keys\n\
+                       \t\t- From [[Bob]]\n\
+                       \t- TODO Build\n\
+                       \t\t- TODO Lint\n\
+                       - Meeting with [[Alice]]\n\
+                       \t- TODO Send the notes\n";
+        let mut sources = Vec::new();
+        let (preamble, mut tasks, notes) = parse(content, &mut sources);
+
+        assert_eq!(preamble, "- Plan for today\n");
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].subtasks.len(), 1);
+        assert_eq!(notes, "- Meeting with [[Alice]]\n\t- TODO Send the notes\n");
+        assert_eq!(
+            format!("{}{}", preamble, to_string(&tasks, &notes, &sources)),
+            content
+        );
+
+        tasks[0].subtasks[0].state = TaskState::Completed;
+        assert_eq!(
+            to_string(&tasks, &notes, &sources),
+            "- TODO Deploy\n\
+             \t- Needs the new keys\n\
+             \t\t- From [[Bob]]\n\
+             \t- DONE Build\n\
+             \t\t- TODO Lint\n\
+             - Meeting with [[Alice]]\n\
+             \t- TODO Send the notes\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_later_now() {
+        let (_, tasks, _) = parse(
+            "- LATER Taxes\n- NOW Standup\n- CANCELED Offsite\n",
+            &mut Vec::new(),
+        );
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].state, TaskState::InProgress);
+    }
+
+    #[test]
+    fn test_is_journal() {
+        assert!(is_journal(Path::new("journals/2024_07_01.md")));
+        assert!(!is_journal(Path::new("2024-07-01.md")));
+    }
+}
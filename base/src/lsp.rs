@@ -0,0 +1,594 @@
+//! A minimal language server for the day files and `.recurring.md`, for
+//! `w0rk lsp`. It speaks JSON-RPC over stdin and stdout and has:
+//!
+//! - the problems of `w0rk lint` as diagnostics, with their fixes as quick
+//!   fixes
+//! - completion of the intervals after `@` in `.recurring.md`, and in day
+//!   files of the annotations after `@`, dates after `@due:`, the ids of the
+//!   day after `@blocked-by:` and the tags of the workspace after `#`
+//! - code actions to complete or reopen the task on a line, or to mark it in
+//!   progress, blocked or waiting
+//!
+//! Editors send the full document on every change.
+use crate::config::RECURRING_FILE;
+use crate::day::CHECKBOX_REGEX;
+use crate::lint::{lint_content, Fix};
+use crate::recurring_task::Interval;
+use crate::task::{State as TaskState, Task, TAG_REGEX};
+use crate::workspace::Workspace;
+use crate::Key;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use time::{Date, Duration, OffsetDateTime};
+
+lazy_static! {
+    // What's being typed at the cursor, after what it completes.
+    static ref TRIGGER_REGEX: Regex =
+        Regex::new(r"(?:^|\s)(?<trigger>@due:|@blocked-by:|@|#)(?<value>[\w-]*)$").unwrap();
+    static ref ID_REGEX: Regex = Regex::new(r"(?:^|\s)@id:(?<id>\S+)").unwrap();
+}
+
+const ANNOTATIONS: [&str; 5] = ["due:", "spent:", "id:", "blocked-by:", "every:"];
+
+const INTERVALS: [Interval; 12] = [
+    Interval::Daily,
+    Interval::Weekly,
+    Interval::Monthly,
+    Interval::Weekday,
+    Interval::Weekend,
+    Interval::Monday,
+    Interval::Tuesday,
+    Interval::Wednesday,
+    Interval::Thursday,
+    Interval::Friday,
+    Interval::Saturday,
+    Interval::Sunday,
+];
+
+// See `CompletionItemKind` in the spec.
+const KIND_VALUE: u8 = 12;
+const KIND_KEYWORD: u8 = 14;
+
+pub struct Server {
+    /// The text of the open documents by URI.
+    documents: HashMap<String, String>,
+    /// The tags of the workspace when the server started.
+    tags: BTreeSet<String>,
+    /// The checkboxes of the config, valid besides the usual ones.
+    checkboxes: BTreeMap<String, TaskState>,
+    today: Date,
+}
+
+impl Server {
+    pub fn new(work_dir: &Path, key: Option<Key>, checkboxes: BTreeMap<String, TaskState>) -> Self {
+        let tags = Workspace::open(work_dir, key)
+            .map(|workspace| workspace.with_checkboxes(checkboxes.clone()))
+            .and_then(|workspace| workspace.all_tasks())
+            .map(|all| all.iter().flat_map(|entry| entry.task.tags()).collect())
+            .unwrap_or_default();
+        Self {
+            documents: HashMap::new(),
+            tags,
+            checkboxes,
+            today: OffsetDateTime::now_utc().date(),
+        }
+    }
+
+    /// Handles a message from the editor, returning the messages to send
+    /// back: the response to a request and the diagnostics of changed
+    /// documents.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // A response, we don't send requests.
+            None => return Vec::new(),
+        };
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": ["@", "#", ":"] },
+                    "codeActionProvider": true,
+                },
+                "serverInfo": { "name": "w0rk" },
+            })),
+            "shutdown" => Some(Value::Null),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), text.to_string());
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|changes| changes.last()?["text"].as_str()) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )];
+            }
+            "textDocument/completion" => Some(Value::Array(self.completion(uri, params))),
+            "textDocument/codeAction" => Some(Value::Array(self.code_actions(uri, params))),
+            _ => None,
+        };
+
+        match (message.get("id"), result) {
+            (Some(id), Some(result)) => {
+                vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+            }
+            (Some(id), None) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method {}", method) },
+            })],
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn text(&self, uri: &str) -> &str {
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    fn diagnostics(&self, uri: &str) -> Value {
+        let text = self.text(uri);
+        let lines: Vec<&str> = text.lines().collect();
+        let diagnostics: Vec<Value> = lint_content(&uri_path(uri), text, &self.checkboxes)
+            .into_iter()
+            .map(|problem| {
+                let line = problem.line.map(|line| line - 1).unwrap_or_default();
+                json!({
+                    "range": line_range(line, lines.get(line).unwrap_or(&"")),
+                    "severity": 1,
+                    "source": "w0rk",
+                    "message": problem.message,
+                })
+            })
+            .collect();
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn completion(&self, uri: &str, params: &Value) -> Vec<Value> {
+        let text = self.text(uri);
+        let line = params["position"]["line"].as_u64().unwrap_or_default() as usize;
+        let character = params["position"]["character"].as_u64().unwrap_or_default() as usize;
+        let line_text = text.lines().nth(line).unwrap_or_default();
+        let before = &line_text[..byte_index(line_text, character)];
+        let captures = match TRIGGER_REGEX.captures(before) {
+            Some(captures) => captures,
+            None => return Vec::new(),
+        };
+        // The typed part is replaced by the completion.
+        let start = character - utf16_len(&captures["value"]);
+        let item = |label: String, kind: u8, detail: Option<String>| {
+            json!({
+                "label": label,
+                "kind": kind,
+                "detail": detail,
+                "textEdit": {
+                    "range": {
+                        "start": { "line": line, "character": start },
+                        "end": { "line": line, "character": character },
+                    },
+                    "newText": label,
+                },
+            })
+        };
+
+        let is_recurring = uri_path(uri)
+            .file_name()
+            .is_some_and(|name| name == RECURRING_FILE);
+        match (&captures["trigger"], is_recurring) {
+            ("@", true) => intervals(&captures["value"])
+                .iter()
+                .map(|interval| item(interval.to_string(), KIND_KEYWORD, None))
+                .collect(),
+            (_, true) => Vec::new(),
+            ("@", false) => ANNOTATIONS
+                .iter()
+                .map(|annotation| item(annotation.to_string(), KIND_KEYWORD, None))
+                .collect(),
+            ("@due:", false) => (0..8)
+                .filter_map(|days| {
+                    let date = self.today.checked_add(Duration::days(days))?;
+                    let detail = match days {
+                        0 => "today".to_string(),
+                        1 => "tomorrow".to_string(),
+                        _ => date.weekday().to_string(),
+                    };
+                    Some(item(date.to_string(), KIND_VALUE, Some(detail)))
+                })
+                .collect(),
+            ("@blocked-by:", false) => ID_REGEX
+                .captures_iter(text)
+                .map(|captures| captures["id"].to_string())
+                .collect::<BTreeSet<String>>()
+                .into_iter()
+                .map(|id| item(id, KIND_VALUE, None))
+                .collect(),
+            _ => {
+                let mut tags = self.tags.clone();
+                tags.extend(
+                    TAG_REGEX
+                        .captures_iter(text)
+                        .map(|captures| captures["tag"].to_string()),
+                );
+                tags.into_iter()
+                    .map(|tag| item(tag, KIND_VALUE, None))
+                    .collect()
+            }
+        }
+    }
+
+    fn code_actions(&self, uri: &str, params: &Value) -> Vec<Value> {
+        let text = self.text(uri);
+        let lines: Vec<&str> = text.lines().collect();
+        let first = params["range"]["start"]["line"]
+            .as_u64()
+            .unwrap_or_default() as usize;
+        let last = params["range"]["end"]["line"].as_u64().unwrap_or_default() as usize;
+        let path = uri_path(uri);
+        let action = |title: String, kind: &str, line: usize, new_text: &str| {
+            json!({
+                "title": title,
+                "kind": kind,
+                "edit": { "changes": { uri: [{
+                    "range": line_range(line, lines.get(line).unwrap_or(&"")),
+                    "newText": new_text,
+                }] } },
+            })
+        };
+
+        let mut actions = Vec::new();
+        for problem in lint_content(&path, text, &self.checkboxes) {
+            if let (Some(Fix::Line(fixed)), Some(line)) = (&problem.fix, problem.line) {
+                if (first..=last).contains(&(line - 1)) {
+                    let title = format!("Fix: {}", problem.message);
+                    actions.push(action(title, "quickfix", line - 1, fixed));
+                }
+            }
+        }
+
+        // The checkboxes of recurring tasks are a template.
+        let line_text = lines.get(first).copied().unwrap_or_default();
+        if path.file_name().is_some_and(|name| name == RECURRING_FILE) {
+            return actions;
+        }
+        let task = match Task::try_from(line_text.trim_start()) {
+            Ok(task) => task,
+            Err(_) => return actions,
+        };
+        // Toggling between complete and open comes first.
+        let toggle = match task.state {
+            TaskState::Completed => (TaskState::Incomplete, "Reopen the task"),
+            _ => (TaskState::Completed, "Complete the task"),
+        };
+        let others = [
+            (TaskState::InProgress, "Mark the task in progress"),
+            (TaskState::Blocked, "Mark the task blocked"),
+            (TaskState::Waiting, "Mark the task waiting"),
+        ];
+        let states = others.into_iter().filter(|(state, _)| *state != task.state);
+        for (state, title) in std::iter::once(toggle).chain(states) {
+            if let Some(new_text) = with_state(line_text, &state) {
+                actions.push(action(
+                    title.to_string(),
+                    "refactor.rewrite",
+                    first,
+                    &new_text,
+                ));
+            }
+        }
+        actions
+    }
+}
+
+/// The intervals to complete in `.recurring.md`, with the frequencies for
+/// the number being typed, like `3x-week` after `@3`, or for 2 times.
+fn intervals(value: &str) -> Vec<Interval> {
+    let times = value
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|times| times.parse().ok())
+        .filter(|times| *times > 0)
+        .unwrap_or(2);
+    INTERVALS
+        .iter()
+        .cloned()
+        .chain([
+            Interval::TimesPerWeek(times),
+            Interval::TimesPerMonth(times),
+        ])
+        .collect()
+}
+
+/// Runs the server on stdin and stdout until the editor exits it.
+pub fn serve(
+    work_dir: &Path,
+    key: Option<Key>,
+    checkboxes: BTreeMap<String, TaskState>,
+) -> Result<(), crate::Error> {
+    let mut server = Server::new(work_dir, key, checkboxes);
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    while let Some(content) = read_message(&mut input)? {
+        let message: Value = match serde_json::from_slice(&content) {
+            Ok(message) => message,
+            // The message is skipped, the server keeps running.
+            Err(err) => {
+                write_message(&mut output, &parse_error(&err))?;
+                continue;
+            }
+        };
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the content of a message with its `Content-Length` header, `None`
+/// at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>, crate::Error> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut content = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+/// The reply to a message that isn't JSON, without an id to reply to.
+fn parse_error(err: &serde_json::Error) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": Value::Null,
+        "error": { "code": -32700, "message": format!("Parse error: {}", err) },
+    })
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<(), crate::Error> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()?;
+    Ok(())
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// The task line with its checkbox set to the state.
+fn with_state(line: &str, state: &TaskState) -> Option<String> {
+    let captures = CHECKBOX_REGEX.captures(line)?;
+    let end = captures.get(0)?.end();
+    Some(format!("{}{}]{}", &captures["prefix"], state, &line[end..]))
+}
+
+/// The path of a `file://` URI, with the `%20` like escapes decoded.
+fn uri_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri).as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut index = 0;
+    while index < path.len() {
+        let escaped = path
+            .get(index + 1..index + 3)
+            .filter(|_| path[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(path[index]);
+                index += 1;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+/// The range of the whole line, positions count UTF-16 code units.
+fn line_range(line: usize, text: &str) -> Value {
+    json!({
+        "start": { "line": line, "character": 0 },
+        "end": { "line": line, "character": utf16_len(text) },
+    })
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// The byte index in the line of a position in UTF-16 code units.
+fn byte_index(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= character {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///work/2024-07-01.md";
+
+    fn server(text: &str) -> Server {
+        let mut server = Server {
+            documents: HashMap::new(),
+            tags: BTreeSet::from(["infra".to_string()]),
+            checkboxes: BTreeMap::new(),
+            today: crate::parse_date("2024-07-01").unwrap(),
+        };
+        server.handle(&json!({
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "text": text } },
+        }));
+        server
+    }
+
+    fn labels(server: &mut Server, uri: &str, line: usize, character: usize) -> Vec<String> {
+        let reply = server.handle(&json!({
+            "id": 1,
+            "method": "textDocument/completion",
+            "params": {
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            },
+        }));
+        reply[0]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = server("* [ ] Deploy\n");
+        let reply = server.handle(&json!({
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": URI },
+                "contentChanges": [{ "text": "* [ ] Deploy @due:2024-7-2\n* [X] Review\n" }],
+            },
+        }));
+        let diagnostics = reply[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1]["range"]["start"]["line"], 1);
+        assert_eq!(diagnostics[1]["range"]["end"]["character"], 12);
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut server = server("* [ ] Deploy @id:deploy #release\n* [ ] Announce @\n");
+        assert_eq!(labels(&mut server, URI, 1, 16), ANNOTATIONS);
+
+        server.documents.insert(
+            URI.to_string(),
+            "* [ ] Deploy @id:deploy #release\n* [ ] Announce @blocked-by:de #\n".to_string(),
+        );
+        assert_eq!(labels(&mut server, URI, 1, 29), vec!["deploy"]);
+        assert_eq!(labels(&mut server, URI, 1, 31), vec!["infra", "release"]);
+        assert!(labels(&mut server, URI, 0, 5).is_empty());
+
+        server
+            .documents
+            .insert(URI.to_string(), "* [ ] Taxes @due:".to_string());
+        let dates = labels(&mut server, URI, 0, 17);
+        assert_eq!(dates.len(), 8);
+        assert_eq!(dates[1], "2024-07-02");
+
+        let recurring = "file:///work/.recurring.md";
+        server
+            .documents
+            .insert(recurring.to_string(), "* [] @we".to_string());
+        assert_eq!(
+            labels(&mut server, recurring, 0, 8).len(),
+            INTERVALS.len() + 2
+        );
+        server
+            .documents
+            .insert(recurring.to_string(), "* [] @3".to_string());
+        let frequencies = labels(&mut server, recurring, 0, 7);
+        assert!(frequencies.contains(&"3x-week".to_string()));
+        assert!(frequencies.contains(&"3x-month".to_string()));
+    }
+
+    #[test]
+    fn test_code_actions() {
+        let mut server = server("* [~] Deploy 🚀\n* [X] Review\n");
+        let reply = server.handle(&json!({
+            "id": 2,
+            "method": "textDocument/codeAction",
+            "params": {
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 1, "character": 0 },
+                },
+            },
+        }));
+        let actions = reply[0]["result"].as_array().unwrap();
+        let titles: Vec<&str> = actions
+            .iter()
+            .map(|action| action["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Fix: unknown state `[X]`, expected `[ ]`, `[x]`, `[~]`, `[#]` or `[>]`",
+                "Complete the task",
+                "Mark the task blocked",
+                "Mark the task waiting"
+            ]
+        );
+        let edit = &actions[1]["edit"]["changes"][URI][0];
+        assert_eq!(edit["newText"], "* [x] Deploy 🚀");
+        // The emoji is two UTF-16 code units.
+        assert_eq!(edit["range"]["end"]["character"], 15);
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut input = "Content-Length: 17\r\n\r\n{\"method\":\"exit\"}".as_bytes();
+        let content = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(content, b"{\"method\":\"exit\"}");
+        assert!(read_message(&mut input).unwrap().is_none());
+        let err = serde_json::from_slice::<Value>(b"{\"method\"").unwrap_err();
+        assert_eq!(parse_error(&err)["error"]["code"], -32700);
+
+        let mut output = Vec::new();
+        write_message(&mut output, &json!({ "id": 1 })).unwrap();
+        assert_eq!(output, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+
+        assert_eq!(
+            uri_path("file:///work/My%20Notes/2024-07-01.md"),
+            PathBuf::from("/work/My Notes/2024-07-01.md")
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Meeting blocks in the notes of a day, for `w0rk meeting`:
+//!
+//! ```markdown
+//! ## Meeting: Weekly sync
+//! Time: 10:00
+//! Attendees: @alice, @bob
+//!
+//! Went over the budget.
+//! - [ ] Send the budget to @alice
+//! ```
+//!
+//! A block runs to the next heading of the same or a higher level. The
+//! action items in it stay in the block instead of becoming tasks of the
+//! day, `extract_actions` copies the open ones to the tasks.
+use crate::config::StyleConfig;
+use crate::day::Day;
+use crate::task::{State as TaskState, Task};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref HEADING_REGEX: Regex =
+        Regex::new(r"^(?<level>#{1,6})\s+(?<meeting>(?i:meeting):)?\s*(?<title>.*?)\s*$").unwrap();
+    static ref FIELD_REGEX: Regex =
+        Regex::new(r"^(?<key>(?i:time|attendees)):\s*(?<value>.*?)\s*$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meeting {
+    pub title: String,
+    pub time: Option<String>,
+    /// The attendees without the `@`.
+    pub attendees: Vec<String>,
+    /// The lines of the block that aren't a field or an action item.
+    pub notes: String,
+    pub actions: Vec<Task>,
+}
+
+impl Meeting {
+    pub fn new(title: &str, time: Option<&str>, attendees: &[String]) -> Self {
+        Self {
+            title: title.to_string(),
+            time: time.map(ToString::to_string),
+            attendees: attendees
+                .iter()
+                .map(|attendee| attendee.trim_start_matches('@').to_string())
+                .collect(),
+            notes: String::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// The tag of the action items, the title in lowercase with dashes like
+    /// `weekly-sync`.
+    pub fn tag(&self) -> String {
+        let slug = self
+            .title
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>()
+            .join("-");
+        // Tags start with a letter, see `Task::tags`.
+        match slug.chars().next() {
+            Some(c) if c.is_alphabetic() => slug,
+            Some(_) => format!("meeting-{}", slug),
+            None => "meeting".to_string(),
+        }
+    }
+
+    /// The open action items as tasks of the day, tagged with the meeting.
+    pub fn open_actions(&self) -> Vec<Task> {
+        let tag = self.tag();
+        self.actions
+            .iter()
+            .filter(|action| action.state == TaskState::Incomplete)
+            .map(|action| Task {
+                name: match action.has_tag(&tag) {
+                    true => action.name.clone(),
+                    false => format!("{} #{}", action.name, tag),
+                },
+                state: TaskState::Incomplete,
+                subtasks: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// The block for the notes of a day.
+    pub fn to_markdown(&self, style: &StyleConfig) -> String {
+        let mut text = format!("## Meeting: {}\n", self.title);
+        if let Some(time) = &self.time {
+            text.push_str(&format!("Time: {}\n", time));
+        }
+        let attendees: Vec<String> = self
+            .attendees
+            .iter()
+            .map(|attendee| format!("@{}", attendee))
+            .collect();
+        text.push_str(&format!("Attendees: {}\n\n", attendees.join(", ")));
+        if !self.notes.is_empty() {
+            text.push_str(&self.notes);
+            text.push('\n');
+        }
+        for action in &self.actions {
+            text.push_str(&format!("{} {}\n", action.state.marker(style), action.name));
+        }
+        text
+    }
+}
+
+/// The level of a heading line, with whether it starts a meeting block.
+pub(crate) fn heading(line: &str) -> Option<(usize, bool)> {
+    let captures = HEADING_REGEX.captures(line)?;
+    Some((captures["level"].len(), captures.name("meeting").is_some()))
+}
+
+/// The meeting blocks in the text.
+pub fn parse(text: &str) -> Vec<Meeting> {
+    let mut meetings = Vec::new();
+    let mut current: Option<(usize, Meeting)> = None;
+    for line in text.lines() {
+        if let Some(captures) = HEADING_REGEX.captures(line) {
+            let level = captures["level"].len();
+            if captures.name("meeting").is_some() {
+                meetings.extend(current.take().map(|(_, meeting)| meeting));
+                current = Some((level, Meeting::new(&captures["title"], None, &[])));
+                continue;
+            }
+            if current.as_ref().is_some_and(|(open, _)| level <= *open) {
+                meetings.extend(current.take().map(|(_, meeting)| meeting));
+            }
+        }
+        let meeting = match current.as_mut() {
+            Some((_, meeting)) => meeting,
+            None => continue,
+        };
+
+        if let Some(captures) = FIELD_REGEX.captures(line) {
+            let value = &captures["value"];
+            match captures["key"].to_lowercase().as_str() {
+                "time" => meeting.time = Some(value.to_string()),
+                _ => meeting.attendees.extend(
+                    value
+                        .split(',')
+                        .map(|attendee| attendee.trim().trim_start_matches('@'))
+                        .filter(|attendee| !attendee.is_empty())
+                        .map(ToString::to_string),
+                ),
+            }
+        } else if let Ok(action) = Task::try_from(line.trim_start()) {
+            meeting.actions.push(action);
+        } else if !line.trim().is_empty() || !meeting.notes.is_empty() {
+            meeting.notes.push_str(line);
+            meeting.notes.push('\n');
+        }
+    }
+    meetings.extend(current.map(|(_, meeting)| meeting));
+    for meeting in meetings.iter_mut() {
+        meeting.notes = meeting.notes.trim_end().to_string();
+    }
+    meetings
+}
+
+/// The meeting blocks of the day, above and below its tasks.
+pub fn meetings(day: &Day) -> Vec<Meeting> {
+    let mut meetings = parse(&day.preamble);
+    meetings.extend(parse(&day.notes));
+    meetings
+}
+
+/// Adds the meeting block to the end of the notes of the day.
+pub fn add(day: &mut Day, meeting: &Meeting) {
+    if !day.notes.trim().is_empty() {
+        day.notes = format!("{}\n\n", day.notes.trim_end());
+    } else {
+        day.notes.clear();
+    }
+    day.notes.push_str(&meeting.to_markdown(&day.style));
+}
+
+/// Adds the open action items of the meetings of the day to its tasks, or
+/// of only the meeting with the title (ignoring case) when given. Items that
+/// were extracted before are skipped. Returns the new tasks.
+pub fn extract_actions(day: &mut Day, title: Option<&str>) -> Result<Vec<Task>, crate::Error> {
+    let meetings: Vec<Meeting> = meetings(day)
+        .into_iter()
+        .filter(|meeting| title.is_none_or(|title| meeting.title.eq_ignore_ascii_case(title)))
+        .collect();
+    if let (Some(title), true) = (title, meetings.is_empty()) {
+        return Err(crate::Error::NoMeeting(title.to_string()));
+    }
+
+    let mut added = Vec::new();
+    for task in meetings.iter().flat_map(Meeting::open_actions) {
+        if day.tasks.iter().any(|existing| existing.name == task.name) {
+            continue;
+        }
+        day.tasks.push(task.clone());
+        added.push(task);
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const CONTENT: &str = "* [ ] Deploy\n\n\
+                           ## Meeting: Weekly sync\n\
+                           Time: 10:00\n\
+                           Attendees: @alice, bob\n\
+                           \n\
+                           Went over the budget.\n\
+                           - [ ] Send the budget to @alice\n\
+                           - [x] Book the venue\n\
+                           ### Follow up\n\
+                           - [ ] Ask legal #legal #weekly-sync\n\
+                           \n\
+                           ## Lunch\n\
+                           * [ ] Not a meeting task\n";
+
+    fn day() -> Day {
+        Day::parse(Path::new("2024-07-01.md"), CONTENT).unwrap().0
+    }
+
+    #[test]
+    fn test_parse() {
+        let day = day();
+        // The action items aren't tasks of the day, the task after the block is.
+        let names: Vec<&str> = day.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["Deploy", "Not a meeting task"]);
+
+        let meetings = meetings(&day);
+        assert_eq!(meetings.len(), 1);
+        let meeting = &meetings[0];
+        assert_eq!(meeting.title, "Weekly sync");
+        assert_eq!(meeting.tag(), "weekly-sync");
+        assert_eq!(meeting.time.as_deref(), Some("10:00"));
+        assert_eq!(meeting.attendees, vec!["alice", "bob"]);
+        assert_eq!(meeting.notes, "Went over the budget.\n### Follow up");
+        assert_eq!(meeting.actions.len(), 3);
+        assert_eq!(
+            Day::parse(day.path.as_path(), &day.to_content())
+                .unwrap()
+                .0
+                .tasks
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_extract_actions() {
+        let mut day = day();
+        let added = extract_actions(&mut day, None).unwrap();
+        let names: Vec<&str> = added.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Send the budget to @alice #weekly-sync",
+                "Ask legal #legal #weekly-sync"
+            ]
+        );
+        assert_eq!(day.tasks.len(), 4);
+        assert!(extract_actions(&mut day, Some("WEEKLY SYNC"))
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            extract_actions(&mut day, Some("Standup")),
+            Err(crate::Error::NoMeeting(_))
+        ));
+    }
+
+    #[test]
+    fn test_add() {
+        let mut day = Day::parse(Path::new("2024-07-01.md"), "* [ ] Deploy\n\nNotes\n")
+            .unwrap()
+            .0;
+        let meeting = Meeting::new("1:1 Alice", Some("14:00"), &["@alice".to_string()]);
+        assert_eq!(meeting.tag(), "meeting-1-1-alice");
+        add(&mut day, &meeting);
+        assert_eq!(
+            day.to_content(),
+            "* [ ] Deploy\n\nNotes\n\n## Meeting: 1:1 Alice\nTime: 14:00\nAttendees: @alice\n\n"
+        );
+        assert_eq!(meetings(&day), vec![meeting]);
+    }
+}
@@ -0,0 +1,158 @@
+//! Moves of the day files for `w0rk migrate`, to the file names and the
+//! folders of the config after changing them.
+//!
+//! The caches and indexes in the state dir are keyed by path and drop the
+//! old paths on their next update, the sync state is keyed by date. Locked
+//! days stay where they are, see `Workspace::is_locked`.
+use crate::day::DaysList;
+use crate::workspace::{move_day_file, Workspace};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use time::format_description::OwnedFormatItem;
+
+/// A day file to move, from the first path to the second.
+pub type Move = (PathBuf, PathBuf);
+
+/// The day files to rename to the file format of the workspace, staying
+/// in their folder and keeping their extension. With a format, the files
+/// named in that one instead of the days of the workspace, e.g. the
+/// previous date format of Obsidian.
+pub fn rename_moves(
+    workspace: &Workspace,
+    from: Option<&OwnedFormatItem>,
+) -> Result<Vec<Move>, crate::Error> {
+    let days = match from {
+        Some(from) => DaysList::from_path(&workspace.path)?.named(&[from]),
+        None => workspace.list_days(&workspace.path)?,
+    };
+    let mut moves = Vec::new();
+    for (date, path) in days.iter().filter(|(date, _)| !workspace.is_locked(date)) {
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        let to = path.with_file_name(workspace.file_name(date, &extension)?);
+        if &to != path {
+            moves.push((path.clone(), to));
+        }
+    }
+    Ok(moves)
+}
+
+/// The day files to move to the folders of the layout of the workspace,
+/// keeping their file name.
+pub fn layout_moves(workspace: &Workspace) -> Vec<Move> {
+    workspace
+        .day_list
+        .iter()
+        .filter(|(date, _)| !workspace.is_locked(date))
+        .filter_map(|(date, path)| {
+            let to = workspace
+                .path
+                .join(workspace.layout.folder(date))
+                .join(path.file_name()?);
+            (&to != path).then(|| (path.clone(), to))
+        })
+        .collect()
+}
+
+/// Moves the day files and lists the days of the workspace again. Nothing
+/// is moved when one of the new paths is taken, and year and month folders
+/// left empty are removed.
+pub fn apply(workspace: &mut Workspace, moves: &[Move]) -> Result<(), crate::Error> {
+    let mut targets = HashSet::new();
+    for (_, to) in moves {
+        if to.exists() || !targets.insert(to) {
+            return Err(crate::Error::DayAlreadyExists(
+                to.to_string_lossy().to_string(),
+            ));
+        }
+    }
+
+    for (from, to) in moves {
+        move_day_file(from, to)?;
+        // `remove_dir` only removes empty folders, the month and then the year.
+        for dir in from.ancestors().skip(1).take(2) {
+            if dir == workspace.path || std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    workspace.day_list = workspace.list_days(&workspace.path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Layout, ObsidianConfig};
+
+    #[test]
+    fn test_migrate() {
+        let dir = std::env::temp_dir().join("w0rk-test-migrate");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [ ] Deploy\n").unwrap();
+        std::fs::write(dir.join("2024-07-02 Tuesday.md"), "* [ ] Review\n").unwrap();
+
+        let mut workspace = Workspace::from_path(&dir)
+            .unwrap()
+            .with_layout(Layout::Monthly);
+        // Locked days stay where they are.
+        workspace.lock_after = Some(0);
+        assert!(layout_moves(&workspace).is_empty());
+        workspace.lock_after = None;
+        let moves = layout_moves(&workspace);
+        assert_eq!(
+            moves[0],
+            (dir.join("2024-07-01.md"), dir.join("2024/07/2024-07-01.md"))
+        );
+        apply(&mut workspace, &moves).unwrap();
+        assert!(layout_moves(&workspace).is_empty());
+        assert_eq!(workspace.day_list.iter().count(), 2);
+
+        let moves = rename_moves(&workspace, None).unwrap();
+        assert_eq!(
+            moves,
+            vec![(
+                dir.join("2024/07/2024-07-02 Tuesday.md"),
+                dir.join("2024/07/2024-07-02.md")
+            )]
+        );
+        apply(&mut workspace, &moves).unwrap();
+
+        // Back to a flat work dir, without the empty folders.
+        workspace = workspace.with_layout(Layout::Flat);
+        let moves = layout_moves(&workspace);
+        apply(&mut workspace, &moves).unwrap();
+        assert!(dir.join("2024-07-02.md").exists());
+        assert!(!dir.join("2024").exists());
+
+        let taken = vec![(dir.join("2024-07-01.md"), dir.join("2024-07-02.md"))];
+        assert!(matches!(
+            apply(&mut workspace, &taken),
+            Err(crate::Error::DayAlreadyExists(_))
+        ));
+
+        // Obsidian's daily notes, other notes with a date aren't days.
+        std::fs::write(dir.join("Meeting 2024-07-03.md"), "* [ ] Agenda\n").unwrap();
+        let daily_notes = ObsidianConfig {
+            date_format: "YYYY-MM-DD dddd".to_string(),
+        }
+        .file_format()
+        .unwrap();
+        let mut workspace = Workspace::from_path(&dir)
+            .unwrap()
+            .with_file_format(daily_notes.clone());
+        assert_eq!(workspace.day_list.iter().count(), 2);
+        let moves = rename_moves(&workspace, None).unwrap();
+        assert_eq!(moves[0].1, dir.join("2024-07-01 Monday.md"));
+        apply(&mut workspace, &moves).unwrap();
+        assert_eq!(workspace.day_list.iter().count(), 2);
+
+        // And back from the format of the daily notes.
+        let workspace = Workspace::from_path(&dir).unwrap();
+        let moves = rename_moves(&workspace, Some(&daily_notes)).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[1].1, dir.join("2024-07-02.md"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
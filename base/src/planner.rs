@@ -0,0 +1,219 @@
+//! The tasks of a new day: the open tasks carried over from the days before
+//! it, the tasks deferred until its date and the recurring tasks of the date.
+//! `Workspace::new_day_with` writes a day with them, the policy decides which
+//! of them go in, like the flags of `w0rk new` and `w0rk plan`.
+use crate::day::Day;
+use crate::recurring_task::{Interval, RecurringState, RecurringTasks};
+use crate::task::{State as TaskState, Task};
+use time::Date;
+
+/// Which tasks go into a new day, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Policy {
+    /// The tasks of the last previous day that aren't completed.
+    pub carry_over: bool,
+    /// The tasks deferred until the date or before it.
+    pub deferred: bool,
+    /// The recurring tasks of the date that aren't snoozed.
+    pub recurring: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            carry_over: true,
+            deferred: true,
+            recurring: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Planner {
+    pub policy: Policy,
+}
+
+impl Planner {
+    pub fn new(policy: Policy) -> Self {
+        Self { policy }
+    }
+
+    /// The tasks of a new day of the date, after the previous days (oldest
+    /// first). The open tasks of the last one are carried over, the days
+    /// of the week or month before it count the completions of recurring
+    /// tasks like `@3x-week`. Pinned tasks come first, recurring ones too,
+    /// and a task is only added once by name.
+    /// The deferred tasks are taken from the state, so the caller writes it
+    /// to add them to one day only.
+    pub fn plan(
+        &self,
+        previous_days: &[Day],
+        recurring: &RecurringTasks,
+        state: &mut RecurringState,
+        date: &Date,
+    ) -> Vec<Task> {
+        let mut tasks: Vec<Task> = Vec::new();
+
+        if self.policy.carry_over {
+            if let Some(last_day) = previous_days.iter().rev().find(|day| &day.date < date) {
+                tasks = last_day
+                    .tasks
+                    .iter()
+                    .filter(|task| task.state != TaskState::Completed)
+                    // A recurring task comes back on the next date it's due
+                    // instead.
+                    .filter(|task| {
+                        task.recurrence().is_none_or(|interval| {
+                            is_due(&interval, &task.recurring_name(), previous_days, date)
+                        })
+                    })
+                    .cloned()
+                    .map(|mut task| {
+                        last_day.remove_completed_dependencies(&mut task);
+                        task
+                    })
+                    .collect();
+            }
+        }
+
+        if self.policy.deferred {
+            for task in state.take_deferred(date) {
+                if !tasks.iter().any(|existing| existing.name == task.name) {
+                    tasks.push(task);
+                }
+            }
+        }
+
+        if self.policy.recurring {
+            for rt in recurring.for_date(date).iter() {
+                if tasks.iter().any(|task| rt.matches(task))
+                    || state.is_snoozed(&rt.name, date)
+                    || !is_due(&rt.interval, &rt.name, previous_days, date)
+                {
+                    continue;
+                }
+                tasks.push(rt.into());
+            }
+        }
+        tasks.sort_by_key(|task| !task.is_pinned());
+        tasks
+    }
+}
+
+/// Whether the recurring task of the name is due on the date. One with a
+/// frequency interval is until it's completed that many times on the
+/// previous days of the week or month.
+pub(crate) fn is_due(interval: &Interval, name: &str, previous_days: &[Day], date: &Date) -> bool {
+    let Some((times, since)) = interval.frequency(date) else {
+        return interval.is_due(date);
+    };
+    let completed = previous_days
+        .iter()
+        .filter(|day| day.date >= since && &day.date < date)
+        .filter(|day| {
+            day.tasks
+                .iter()
+                .any(|task| task.state == TaskState::Completed && task.recurring_name() == name)
+        })
+        .count();
+    completed < times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_date;
+    use crate::recurring_task::RecurringTask;
+    use std::path::Path;
+
+    #[test]
+    fn test_plan() {
+        let monday = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [x] Review\n* [ ] Deploy\n* [~] Announce !pin\n* [ ] Weekly report @every:monday\n",
+        )
+        .unwrap()
+        .0;
+        let recurring: RecurringTasks = vec![
+            RecurringTask::try_from("* [ ] @daily Standup").unwrap(),
+            RecurringTask::try_from("* [ ] @daily Deploy").unwrap(),
+            RecurringTask::try_from("* [ ] @daily Inbox zero").unwrap(),
+            RecurringTask::try_from("* [ ] @daily Stretch !pin").unwrap(),
+        ]
+        .into();
+        let date = parse_date("2024-07-02").unwrap();
+        let mut state = RecurringState::default();
+        state.snooze("Inbox zero", parse_date("2024-07-05").unwrap(), date);
+        state.defer("* [ ] Taxes".try_into().unwrap(), date);
+        let names =
+            |tasks: Vec<Task>| -> Vec<String> { tasks.into_iter().map(|task| task.name).collect() };
+
+        let tasks = Planner::default().plan(
+            std::slice::from_ref(&monday),
+            &recurring,
+            &mut state.clone(),
+            &date,
+        );
+        assert_eq!(
+            names(tasks),
+            vec![
+                "Announce !pin",
+                "Stretch !pin @every:daily",
+                "Deploy",
+                "Taxes",
+                "Standup @every:daily"
+            ]
+        );
+
+        let planner = Planner::new(Policy {
+            carry_over: false,
+            ..Policy::default()
+        });
+        let tasks = planner.plan(&[monday], &recurring, &mut state, &date);
+        assert_eq!(
+            names(tasks),
+            vec![
+                "Stretch !pin @every:daily",
+                "Taxes",
+                "Standup @every:daily",
+                "Deploy @every:daily"
+            ]
+        );
+        assert!(state.deferred.is_empty());
+    }
+
+    #[test]
+    fn test_plan_frequency() {
+        let day = |content: &str, date: &str| {
+            Day::parse(Path::new(&format!("{}.md", date)), content)
+                .unwrap()
+                .0
+        };
+        // Completed on Monday the 1st and Tuesday, enough for that week.
+        let days = vec![
+            day("* [x] Run @every:2x-week\n", "2024-07-01"),
+            day("* [x] Run @every:2x-week\n", "2024-07-02"),
+        ];
+        let recurring: RecurringTasks =
+            vec![RecurringTask::try_from("* [ ] @2x-week Run").unwrap()].into();
+        let plan = |days: &[Day], date: &str| {
+            Planner::default().plan(
+                days,
+                &recurring,
+                &mut RecurringState::default(),
+                &parse_date(date).unwrap(),
+            )
+        };
+
+        assert_eq!(plan(&days[..1], "2024-07-02")[0].name, "Run @every:2x-week");
+        assert!(plan(&days, "2024-07-03").is_empty());
+        // An open one isn't carried over either once it's done enough.
+        let open = [
+            days.clone(),
+            vec![day("* [ ] Run @every:2x-week\n", "2024-07-03")],
+        ]
+        .concat();
+        assert!(plan(&open, "2024-07-04").is_empty());
+        assert_eq!(plan(&days, "2024-07-08").len(), 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! The phases of `w0rk pomo`: work, then a short break, with a long break
+//! after every few pomodoros.
+use crate::config::PomodoroConfig;
+use crate::Day;
+use time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+pub struct Pomodoro {
+    config: PomodoroConfig,
+    pub phase: Phase,
+    /// The number of finished work phases.
+    pub completed: u32,
+}
+
+impl Pomodoro {
+    /// Starts with a work phase.
+    pub fn new(config: PomodoroConfig) -> Self {
+        Self {
+            config,
+            phase: Phase::Work,
+            completed: 0,
+        }
+    }
+
+    /// The length of the current phase.
+    pub fn duration(&self) -> Duration {
+        let minutes = match self.phase {
+            Phase::Work => self.config.work,
+            Phase::ShortBreak => self.config.short_break,
+            Phase::LongBreak => self.config.long_break,
+        };
+        Duration::minutes(minutes.into())
+    }
+
+    /// Moves on to the next phase, counting a finished work phase.
+    pub fn next(&mut self) {
+        self.phase = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                let every = self.config.long_break_after.max(1);
+                match self.completed % every {
+                    0 => Phase::LongBreak,
+                    _ => Phase::ShortBreak,
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+    }
+
+    /// Moves on to the next phase before the current one is finished, a
+    /// skipped work phase isn't counted.
+    pub fn skip(&mut self) {
+        self.phase = match self.phase {
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+    }
+}
+
+/// Adds the duration to the `@spent` of the task (or subtask) with the name,
+/// leaving out its `@spent`. Returns whether the task was found.
+pub fn log(day: &mut Day, name: &str, duration: Duration) -> bool {
+    for task in day.tasks.iter_mut() {
+        if task.name_without_spent() == name {
+            task.add_spent(duration);
+            return true;
+        }
+        if let Some(subtask) = task
+            .subtasks
+            .iter_mut()
+            .find(|subtask| subtask.name_without_spent() == name)
+        {
+            subtask.add_spent(duration);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_phases() {
+        let mut pomodoro = Pomodoro::new(PomodoroConfig {
+            long_break_after: 2,
+            ..PomodoroConfig::default()
+        });
+        let mut phases = Vec::new();
+        for _ in 0..5 {
+            phases.push((pomodoro.phase, pomodoro.duration().whole_minutes()));
+            pomodoro.next();
+        }
+        assert_eq!(
+            phases,
+            vec![
+                (Phase::Work, 25),
+                (Phase::ShortBreak, 5),
+                (Phase::Work, 25),
+                (Phase::LongBreak, 15),
+                (Phase::Work, 25),
+            ]
+        );
+        assert_eq!(pomodoro.completed, 3);
+
+        pomodoro.skip();
+        assert_eq!(pomodoro.phase, Phase::Work);
+        pomodoro.skip();
+        assert_eq!(pomodoro.phase, Phase::ShortBreak);
+        assert_eq!(pomodoro.completed, 3);
+    }
+
+    #[test]
+    fn test_log() {
+        let (mut day, _) = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [~] Deploy @spent:25m\n* [ ] Review\n  * [ ] Tests\n",
+        )
+        .unwrap();
+
+        assert!(log(&mut day, "Deploy", Duration::minutes(25)));
+        assert!(log(&mut day, "Tests", Duration::minutes(25)));
+        assert!(!log(&mut day, "Lunch", Duration::minutes(25)));
+        assert_eq!(
+            day.to_content(),
+            "* [~] Deploy @spent:50m\n* [ ] Review\n  * [ ] Tests @spent:25m\n\n"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Filtering tasks by state, tag, due date and the date of their day, for
+//! `w0rk list --filter`, reports and the sinks.
+use crate::day::{parse_date, Day};
+use crate::export::{parse_range, DateRange};
+use crate::task::{State as TaskState, Task};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+use time::Date;
+
+/// The tasks matching all of the conditions, built up like
+/// `TaskQuery::new().state(TaskState::Blocked).tag("infra").due_before(date)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskQuery {
+    states: Vec<TaskState>,
+    tags: Vec<String>,
+    any_tags: Vec<String>,
+    people: Vec<String>,
+    due_before: Option<Date>,
+    range: DateRange,
+    text: Option<String>,
+}
+
+impl TaskQuery {
+    /// A query matching every task.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tasks in this state, or in one of the states when called again.
+    pub fn state(mut self, state: TaskState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    /// Tasks with the tag, and with every other tag when called again.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.trim_start_matches('#').to_string());
+        self
+    }
+
+    /// Tasks with at least one of the tags, any task when there are none.
+    pub fn any_tag(mut self, tags: &[String]) -> Self {
+        self.any_tags.extend(tags.iter().cloned());
+        self
+    }
+
+    /// Tasks mentioning the person with `@name`, and every other person when
+    /// called again.
+    pub fn person(mut self, person: &str) -> Self {
+        self.people.push(person.trim_start_matches('@').to_string());
+        self
+    }
+
+    /// Tasks due before the date, leaving out tasks without a due date.
+    pub fn due_before(mut self, date: Date) -> Self {
+        self.due_before = Some(date);
+        self
+    }
+
+    /// Tasks of the days in the range, like `from..to` or `from..=to`.
+    pub fn in_range(mut self, range: impl RangeBounds<Date>) -> Self {
+        self.range = DateRange {
+            from: match range.start_bound() {
+                Bound::Included(date) => Some(*date),
+                Bound::Excluded(date) => date.next_day(),
+                Bound::Unbounded => None,
+            },
+            to: match range.end_bound() {
+                Bound::Included(date) => Some(*date),
+                Bound::Excluded(date) => date.previous_day(),
+                Bound::Unbounded => None,
+            },
+        };
+        self
+    }
+
+    /// Tasks with the text in their name, ignoring case.
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_lowercase());
+        self
+    }
+
+    /// The states of the tasks, any state when empty.
+    #[cfg(feature = "index")]
+    pub(crate) fn states(&self) -> &[TaskState] {
+        &self.states
+    }
+
+    /// The dates of the days of the tasks.
+    #[cfg(feature = "index")]
+    pub(crate) fn range(&self) -> &DateRange {
+        &self.range
+    }
+
+    /// Whether the tasks of the day with this date can match.
+    pub fn contains_date(&self, date: &Date) -> bool {
+        self.range.contains(date)
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        (self.states.is_empty() || self.states.contains(&task.state))
+            && self.tags.iter().all(|tag| task.has_tag(tag))
+            && (self.any_tags.is_empty() || self.any_tags.iter().any(|tag| task.has_tag(tag)))
+            && self.people.iter().all(|person| task.mentions(person))
+            && self
+                .due_before
+                .is_none_or(|before| task.due().is_some_and(|due| due < before))
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| task.name.to_lowercase().contains(text))
+    }
+
+    /// A copy of the day with only the matching tasks, see `Day::filtered`.
+    pub fn filter_day(&self, day: &Day) -> Day {
+        match self.contains_date(&day.date) {
+            true => day.filtered(|task| self.matches(task)),
+            false => day.filtered(|_| false),
+        }
+    }
+
+    /// The days with matching tasks, with only those tasks, in order.
+    pub fn filter_days(&self, days: &[Day]) -> Vec<Day> {
+        days.iter()
+            .map(|day| self.filter_day(day))
+            .filter(|day| !day.tasks.is_empty())
+            .collect()
+    }
+
+    #[cfg(feature = "fs")]
+    /// The days of the workspace with matching tasks, see `filter_days`.
+    pub fn days(&self, workspace: &Workspace) -> Result<Vec<Day>, crate::Error> {
+        let days = workspace.read_days(|date| self.contains_date(date))?;
+        Ok(self.filter_days(&days))
+    }
+}
+
+/// Parses a filter like `state:blocked tag:infra due-before:2024-07-01`.
+/// The terms are `state:<open|in-progress|blocked|waiting|completed>`, `tag:<tag>`
+/// (or `#<tag>`), `person:<name>` (or `@<name>`), `due-before:<date>` and
+/// `range:<from>..<to>`, any other words are text in the name.
+impl FromStr for TaskQuery {
+    type Err = crate::Error;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let invalid = |term: &str| crate::Error::InvalidFilter(term.to_string());
+        let mut query = TaskQuery::new();
+        let mut words = Vec::new();
+        for term in filter.split_whitespace() {
+            query = match term.split_once(':') {
+                Some(("state", state)) => query.state(match state {
+                    "open" => TaskState::Incomplete,
+                    "in-progress" => TaskState::InProgress,
+                    "blocked" => TaskState::Blocked,
+                    "waiting" => TaskState::Waiting,
+                    "completed" => TaskState::Completed,
+                    _ => return Err(invalid(term)),
+                }),
+                Some(("tag", tag)) if !tag.is_empty() => query.tag(tag),
+                Some(("due-before", date)) => {
+                    query.due_before(parse_date(date).map_err(|_| invalid(term))?)
+                }
+                Some(("range", range)) => {
+                    query.range = parse_range(range).map_err(|_| invalid(term))?;
+                    query
+                }
+                Some(("person", person)) if !person.is_empty() => query.person(person),
+                Some(("tag" | "person", _)) => return Err(invalid(term)),
+                _ if term.len() > 1 && term.starts_with('#') => query.tag(term),
+                None if term.len() > 1 && term.starts_with('@') => query.person(term),
+                _ => {
+                    words.push(term);
+                    query
+                }
+            };
+        }
+        if !words.is_empty() {
+            query = query.text(&words.join(" "));
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::helpers::day;
+
+    fn names(days: &[Day]) -> Vec<String> {
+        days.iter()
+            .flat_map(|day| &day.tasks)
+            .flat_map(|task| [task].into_iter().chain(&task.subtasks))
+            .map(|task| task.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_matches() {
+        let days = vec![
+            day(
+                "2024-06-03",
+                "* [#] Upgrade the database #infra @due:2024-06-10\n\
+                 * [#] Review the budget @due:2024-06-20\n\
+                 * [ ] Plan the offsite #team\n  * [#] Book the venue #infra\n",
+            ),
+            day("2024-06-10", "* [#] Rotate keys #infra @due:2024-06-12\n"),
+        ];
+        let june = |day| parse_date(&format!("2024-06-{:02}", day)).unwrap();
+
+        let query = TaskQuery::new()
+            .state(TaskState::Blocked)
+            .tag("infra")
+            .due_before(june(15));
+        assert_eq!(
+            names(&query.filter_days(&days)),
+            vec![
+                "Upgrade the database #infra @due:2024-06-10",
+                "Rotate keys #infra @due:2024-06-12"
+            ]
+        );
+
+        let query = TaskQuery::new().tag("#infra").in_range(june(1)..june(10));
+        assert_eq!(
+            names(&query.filter_days(&days)),
+            vec![
+                "Upgrade the database #infra @due:2024-06-10",
+                "Plan the offsite #team",
+                "Book the venue #infra"
+            ]
+        );
+        assert_eq!(query.filter_days(&days)[0].tasks[1].subtasks.len(), 1);
+        assert!(!query.contains_date(&june(10)));
+        assert!(TaskQuery::new()
+            .in_range(june(1)..=june(10))
+            .contains_date(&june(10)));
+
+        let query = TaskQuery::new().any_tag(&["team".to_string(), "budget".to_string()]);
+        // The subtasks of a matching task are kept.
+        assert_eq!(
+            names(&query.filter_days(&days)),
+            vec!["Plan the offsite #team", "Book the venue #infra"]
+        );
+        assert_eq!(names(&TaskQuery::new().filter_days(&days)), names(&days));
+    }
+
+    #[test]
+    fn test_from_str() {
+        let query: TaskQuery = "state:blocked #infra due-before:2024-07-01 the database"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query,
+            TaskQuery::new()
+                .state(TaskState::Blocked)
+                .tag("infra")
+                .due_before(parse_date("2024-07-01").unwrap())
+                .text("the database")
+        );
+
+        let query: TaskQuery = "state:open state:in-progress range:2024-07-01.."
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query.states,
+            vec![TaskState::Incomplete, TaskState::InProgress]
+        );
+        assert!(query.contains_date(&parse_date("2024-07-01").unwrap()));
+        assert!(!query.contains_date(&parse_date("2024-06-30").unwrap()));
+
+        assert!("state:done".parse::<TaskQuery>().is_err());
+        assert!("due-before:tomorrow".parse::<TaskQuery>().is_err());
+        assert!("tag:".parse::<TaskQuery>().is_err());
+
+        let query: TaskQuery = "@alice person:bob budget".parse().unwrap();
+        assert_eq!(
+            query,
+            TaskQuery::new()
+                .person("alice")
+                .person("bob")
+                .text("budget")
+        );
+        let task = Task::try_from("* [#] Budget with @Alice and @bob").unwrap();
+        assert!(query.matches(&task));
+        assert!(!TaskQuery::new().person("carol").matches(&task));
+        assert!("person:".parse::<TaskQuery>().is_err());
+    }
+}
@@ -1,31 +1,70 @@
 use crate::task::{State as TaskState, Task};
 use std::convert::TryFrom;
 use std::fmt::Display;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 
+use crate::config::DAY_FORMAT;
+use crate::day::{parse_date, Diagnostic};
 use crate::Error;
+#[cfg(feature = "fs")]
+use crate::Key;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use time::Date;
 
 #[derive(Default, Debug)]
 pub struct RecurringTasks(Vec<RecurringTask>);
 
 impl RecurringTasks {
+    #[cfg(feature = "fs")]
     pub fn from_path(path: &std::path::Path) -> Result<Self, crate::Error> {
-        let mut tasks = Vec::new();
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        let content = std::fs::read_to_string(path).map_err(crate::Error::in_file(path))?;
+        Self::parse(path, &content)
+    }
 
-        for line in reader.lines() {
-            let line = line?;
-            tasks.push(line.as_str().try_into()?);
+    /// Parses the recurring tasks, one per line. Blank lines are skipped,
+    /// an invalid line is an error with its line number in the file.
+    pub fn parse(path: &std::path::Path, content: &str) -> Result<Self, crate::Error> {
+        let mut tasks = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let task =
+                RecurringTask::try_from(line).map_err(|err| err.on_line(path, index, line))?;
+            tasks.push(task);
         }
 
         Ok(Self(tasks))
     }
 
+    /// Parses the recurring tasks like `parse`, leaving out the invalid lines
+    /// with a diagnostic for each of them, so one typo doesn't stop every
+    /// command.
+    pub fn parse_with_diagnostics(content: &str) -> (Self, Vec<Diagnostic>) {
+        let mut tasks = Vec::new();
+        let mut diagnostics = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match RecurringTask::try_from(line) {
+                Ok(task) => tasks.push(task),
+                Err(err) => diagnostics.push(Diagnostic {
+                    line: index + 1,
+                    content: line.to_string(),
+                    reason: err.to_string(),
+                }),
+            }
+        }
+        (Self(tasks), diagnostics)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<RecurringTask> {
+        self.0.iter()
+    }
+
     pub fn for_date(&self, date: &Date) -> Vec<RecurringTask> {
         self.0
             .iter()
@@ -35,10 +74,142 @@ impl RecurringTasks {
     }
 }
 
+/// What happened to the recurring tasks, kept next to them in
+/// `.recurring.json`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecurringState {
+    /// The names of the snoozed tasks, left out of new days before the date.
+    #[serde(default, serialize_with = "serialize_dates")]
+    #[serde(deserialize_with = "deserialize_dates")]
+    pub snoozed: BTreeMap<String, Date>,
+    /// Tasks put off until a date with `w0rk review`, added to the first
+    /// new day from then on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deferred: Vec<Deferred>,
+}
+
+/// A task put off until a date, see `RecurringState::defer`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deferred {
+    #[serde(
+        serialize_with = "serialize_date",
+        deserialize_with = "deserialize_date"
+    )]
+    pub until: Date,
+    pub task: Task,
+}
+
+fn serialize_date<S>(date: &Date, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    date.format(&DAY_FORMAT)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Date, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    parse_date(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// The dates as `YYYY-MM-DD`, like the day files, for people reading along.
+fn serialize_dates<S>(dates: &BTreeMap<String, Date>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut formatted = BTreeMap::new();
+    for (name, date) in dates {
+        let date = date
+            .format(&DAY_FORMAT)
+            .map_err(serde::ser::Error::custom)?;
+        formatted.insert(name, date);
+    }
+    formatted.serialize(serializer)
+}
+
+fn deserialize_dates<'de, D>(deserializer: D) -> Result<BTreeMap<String, Date>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, date)| Ok((name, parse_date(&date).map_err(serde::de::Error::custom)?)))
+        .collect()
+}
+
+impl RecurringState {
+    /// Reads the state, an empty one when there's no file yet. It's
+    /// encrypted like the day files, for the deferred tasks.
+    #[cfg(feature = "fs")]
+    pub fn from_path(path: &std::path::Path, key: Option<&Key>) -> Result<Self, crate::Error> {
+        match std::fs::read(path) {
+            Ok(content) => Self::parse(path, content, key),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(crate::Error::in_file(path)(err)),
+        }
+    }
+
+    /// Parses the content of the state file at the path, see `from_path`.
+    #[cfg(feature = "fs")]
+    pub(crate) fn parse(
+        path: &std::path::Path,
+        content: Vec<u8>,
+        key: Option<&Key>,
+    ) -> Result<Self, crate::Error> {
+        let content = crate::day::decode(content, key).map_err(crate::Error::in_file(path))?;
+        serde_json::from_str(&content).map_err(crate::Error::in_file(path))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn write(&self, path: &std::path::Path, key: Option<&Key>) -> Result<(), crate::Error> {
+        let content = serde_json::to_string_pretty(self)?;
+        crate::day::write_day_content(path, content, key)
+    }
+
+    /// Snoozes the task until the date, forgetting the snoozes that ended
+    /// before `today`.
+    pub fn snooze(&mut self, name: &str, until: Date, today: Date) {
+        self.snoozed.retain(|_, until| *until > today);
+        self.snoozed.insert(name.to_string(), until);
+    }
+
+    pub fn is_snoozed(&self, name: &str, date: &Date) -> bool {
+        self.snoozed.get(name).is_some_and(|until| date < until)
+    }
+
+    /// Puts the task off until the date.
+    pub fn defer(&mut self, task: Task, until: Date) {
+        self.deferred.push(Deferred { until, task });
+    }
+
+    /// Removes the tasks deferred until the date or before it and returns
+    /// them, for a new day of the date.
+    pub fn take_deferred(&mut self, date: &Date) -> Vec<Task> {
+        let (due, later) = std::mem::take(&mut self.deferred)
+            .into_iter()
+            .partition(|deferred| &deferred.until <= date);
+        self.deferred = later;
+        due.into_iter()
+            .map(|deferred: Deferred| deferred.task)
+            .collect()
+    }
+}
+
+impl From<Vec<RecurringTask>> for RecurringTasks {
+    fn from(tasks: Vec<RecurringTask>) -> Self {
+        Self(tasks)
+    }
+}
+
+/// The task of a day for the recurring task, with an `@every:` annotation so
+/// it isn't carried over to days it isn't due on.
 impl From<&RecurringTask> for Task {
     fn from(val: &RecurringTask) -> Self {
         Task {
-            name: val.name.to_string(),
+            name: format!("{} @every:{}", val.name, val.interval),
             state: TaskState::Incomplete,
             subtasks: Vec::new(),
         }
@@ -47,7 +218,7 @@ impl From<&RecurringTask> for Task {
 
 lazy_static! {
     static ref RECURRING_TASK_REGEX: Regex =
-        Regex::new(r"^[\*|-]\s?\[\s?\]\s?@(?<interval>\w+)\s(?<name>.+)$").unwrap();
+        Regex::new(r"^[\*|-]\s?\[\s?\]\s?@(?<interval>[\w-]+)\s(?<name>.+)$").unwrap();
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -57,21 +228,14 @@ pub struct RecurringTask {
 }
 
 impl RecurringTask {
+    /// Whether the task of a day was written from this recurring task, or
+    /// has its name.
+    pub fn matches(&self, task: &Task) -> bool {
+        task.recurring_name() == self.name
+    }
+
     pub fn is_due(&self, date: &Date) -> bool {
-        match self.interval {
-            Interval::Daily => true,
-            Interval::Weekly => date.weekday().number_from_monday() == 1,
-            Interval::Monthly => date.day() == 1,
-            Interval::Weekday => date.weekday().number_from_monday() <= 5,
-            Interval::Weekend => date.weekday().number_from_monday() > 5,
-            Interval::Monday => date.weekday().number_from_monday() == 1,
-            Interval::Tuesday => date.weekday().number_from_monday() == 2,
-            Interval::Wednesday => date.weekday().number_from_monday() == 3,
-            Interval::Thursday => date.weekday().number_from_monday() == 4,
-            Interval::Friday => date.weekday().number_from_monday() == 5,
-            Interval::Saturday => date.weekday().number_from_monday() == 6,
-            Interval::Sunday => date.weekday().number_from_monday() == 7,
-        }
+        self.interval.is_due(date)
     }
 }
 
@@ -89,6 +253,47 @@ pub enum Interval {
     Friday,
     Saturday,
     Sunday,
+    /// Every day until it's completed this many times in the week, from
+    /// Monday, like `@3x-week`.
+    TimesPerWeek(u8),
+    /// Every day until it's completed this many times in the month.
+    TimesPerMonth(u8),
+}
+
+impl Interval {
+    /// Whether a task of the interval is due on the date.
+    pub fn is_due(&self, date: &Date) -> bool {
+        match self {
+            Interval::Daily => true,
+            Interval::Weekly => date.weekday().number_from_monday() == 1,
+            Interval::Monthly => date.day() == 1,
+            Interval::Weekday => date.weekday().number_from_monday() <= 5,
+            Interval::Weekend => date.weekday().number_from_monday() > 5,
+            Interval::Monday => date.weekday().number_from_monday() == 1,
+            Interval::Tuesday => date.weekday().number_from_monday() == 2,
+            Interval::Wednesday => date.weekday().number_from_monday() == 3,
+            Interval::Thursday => date.weekday().number_from_monday() == 4,
+            Interval::Friday => date.weekday().number_from_monday() == 5,
+            Interval::Saturday => date.weekday().number_from_monday() == 6,
+            Interval::Sunday => date.weekday().number_from_monday() == 7,
+            Interval::TimesPerWeek(_) | Interval::TimesPerMonth(_) => true,
+        }
+    }
+
+    /// How often and since when a task of a frequency interval is to be
+    /// completed by the date, the start of the week or the month.
+    pub fn frequency(&self, date: &Date) -> Option<(usize, Date)> {
+        match self {
+            Interval::TimesPerWeek(times) => Some((
+                usize::from(*times),
+                *date - time::Duration::days(i64::from(date.weekday().number_days_from_monday())),
+            )),
+            Interval::TimesPerMonth(times) => {
+                Some((usize::from(*times), date.replace_day(1).ok()?))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Display for Interval {
@@ -106,6 +311,8 @@ impl Display for Interval {
             Interval::Friday => write!(f, "friday"),
             Interval::Saturday => write!(f, "saturday"),
             Interval::Sunday => write!(f, "sunday"),
+            Interval::TimesPerWeek(times) => write!(f, "{}x-week", times),
+            Interval::TimesPerMonth(times) => write!(f, "{}x-month", times),
         }
     }
 }
@@ -127,7 +334,19 @@ impl TryFrom<&str> for Interval {
             "friday" => Ok(Interval::Friday),
             "saturday" => Ok(Interval::Saturday),
             "sunday" => Ok(Interval::Sunday),
-            _ => Err(Error::InvalidIntervalSyntax(value.to_string())),
+            other => {
+                let frequency = |period: &str| {
+                    other
+                        .strip_suffix(period)
+                        .and_then(|times| times.parse::<u8>().ok())
+                        .filter(|times| *times > 0)
+                };
+                match (frequency("x-week"), frequency("x-month")) {
+                    (Some(times), _) => Ok(Interval::TimesPerWeek(times)),
+                    (_, Some(times)) => Ok(Interval::TimesPerMonth(times)),
+                    _ => Err(Error::InvalidIntervalSyntax(value.to_string())),
+                }
+            }
         }
     }
 }
@@ -163,9 +382,11 @@ mod tests {
     use time::Month;
 
     use super::*;
+    #[cfg(feature = "fs")]
     use crate::tests::helpers::test_fixtures_path;
 
     #[test]
+    #[cfg(feature = "fs")]
     fn test_recurring_tasks_from_path() {
         let path = test_fixtures_path().join("work");
         let recurring_tasks = RecurringTasks::from_path(&path.join(".recurring.md"))
@@ -174,6 +395,51 @@ mod tests {
         assert_eq!(recurring_tasks.0.len(), 4);
     }
 
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_recurring_tasks_error_line() {
+        let path = std::env::temp_dir().join("w0rk-test-recurring-error.md");
+        std::fs::write(&path, "* [] @daily Standup\n\n* [] @fortnightly Review\n").unwrap();
+        let err = RecurringTasks::from_path(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, Error::Line { line: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}:3: Error while parsing interval: \"fortnightly\". Expected one of: [daily, weekly, monthly, weekday, weekend]",
+                path.display()
+            )
+        );
+
+        let (tasks, diagnostics) = RecurringTasks::parse_with_diagnostics(
+            "* [] @daily Standup\n\n* [] @fortnightly Review\n",
+        );
+        assert_eq!(tasks.0.len(), 1);
+        assert_eq!(diagnostics[0].line, 3);
+        assert_eq!(diagnostics[0].content, "* [] @fortnightly Review");
+    }
+
+    #[test]
+    fn test_recurring_state_snooze() {
+        let date = |day| Date::from_calendar_date(2024, Month::July, day).unwrap();
+        let mut state = RecurringState::default();
+        state.snooze("Water the plants", date(3), date(1));
+        assert!(state.is_snoozed("Water the plants", &date(2)));
+        assert!(!state.is_snoozed("Water the plants", &date(3)));
+        assert!(!state.is_snoozed("Standup", &date(2)));
+
+        state.snooze("Standup", date(10), date(5));
+        assert_eq!(state.snoozed.keys().collect::<Vec<_>>(), vec!["Standup"]);
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, r#"{"snoozed":{"Standup":"2024-07-10"}}"#);
+        assert_eq!(
+            serde_json::from_str::<RecurringState>(&json).unwrap(),
+            state
+        );
+    }
+
     #[test]
     fn test_recurring_task_to_string() {
         let recurring_task = RecurringTask {
@@ -192,6 +458,28 @@ mod tests {
         let recurring_task = RecurringTask::try_from("-[]@weekly test").unwrap();
         assert_eq!(recurring_task.name, "test");
         assert_eq!(recurring_task.interval, Interval::Weekly);
+
+        let recurring_task = RecurringTask::try_from("* [ ] @3x-week Run").unwrap();
+        assert_eq!(recurring_task.interval, Interval::TimesPerWeek(3));
+        assert_eq!(recurring_task.to_string(), "* [] @3x-week Run");
+        assert!(RecurringTask::try_from("* [ ] @0x-week Run").is_err());
+    }
+
+    #[test]
+    fn test_frequency() {
+        // July 17th, a Wednesday
+        let date = Date::from_calendar_date(2024, Month::July, 17).unwrap();
+        let monday = Date::from_calendar_date(2024, Month::July, 15).unwrap();
+        let first = Date::from_calendar_date(2024, Month::July, 1).unwrap();
+        assert_eq!(
+            Interval::TimesPerWeek(3).frequency(&date),
+            Some((3, monday))
+        );
+        assert_eq!(
+            Interval::TimesPerMonth(2).frequency(&date),
+            Some((2, first))
+        );
+        assert_eq!(Interval::Daily.frequency(&date), None);
     }
 
     #[test]
@@ -0,0 +1,1186 @@
+use crate::config::{rewrite, Rewrite, StyleConfig};
+use crate::day::Day;
+use crate::task::{State as TaskState, Task, TAG_REGEX};
+#[cfg(feature = "fs")]
+use crate::workspace::Workspace;
+use std::collections::HashMap;
+use std::fmt::Write;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use time::{Date, Duration, Month, Weekday};
+
+/// The days a report covers, an ISO week or a calendar month.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Period {
+    /// `2024-W27` for a week, `2024-07` for a month, also the report's file name.
+    pub name: String,
+    pub start: Date,
+    /// The last day of the period, inclusive.
+    pub end: Date,
+}
+
+impl Period {
+    /// The ISO week (Monday to Sunday) the date is in.
+    pub fn week(date: Date) -> Self {
+        let (year, week, _) = date.to_iso_week_date();
+        let start = date - Duration::days(date.weekday().number_days_from_monday() as i64);
+        Self {
+            name: format!("{}-W{:02}", year, week),
+            start,
+            end: start + Duration::days(6),
+        }
+    }
+
+    /// The calendar month the date is in.
+    pub fn month(date: Date) -> Self {
+        let start = date.replace_day(1).expect("every month has a first day");
+        let end = start + Duration::days(date.month().length(date.year()) as i64 - 1);
+        Self {
+            name: format!("{}-{:02}", date.year(), date.month() as u8),
+            start,
+            end,
+        }
+    }
+
+    pub fn contains(&self, date: &Date) -> bool {
+        date >= &self.start && date <= &self.end
+    }
+
+    #[cfg(feature = "fs")]
+    /// The days of the workspace in this period, in order.
+    /// The days of the period until today, a day planned ahead isn't part
+    /// of it yet.
+    pub fn days(&self, workspace: &Workspace) -> Result<Vec<Day>, crate::Error> {
+        let today = time::OffsetDateTime::now_utc().date();
+        workspace.read_days(|date| self.contains(date) && date <= &today)
+    }
+
+    fn title(&self) -> String {
+        match self.start.weekday() == Weekday::Monday && self.end - self.start == Duration::days(6)
+        {
+            true => format!("Week {}", self.name),
+            false => format!("{} {}", month_name(self.start.month()), self.start.year()),
+        }
+    }
+}
+
+/// A summary of a period: what got done, what's still open at the end of
+/// it and the notes that were written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub period: Period,
+    /// The names of the completed tasks with how often they were completed,
+    /// subtasks as `<task>: <subtask>`.
+    pub completed: Vec<(String, usize)>,
+    /// Open tasks on the last day of the period, carried to the next one.
+    pub carried: Vec<String>,
+    /// Blocked tasks and subtasks on the last day of the period.
+    pub blocked: Vec<String>,
+    /// Waiting tasks and subtasks on the last day of the period by who
+    /// they're handed off to, see `Task::delegate`. Tasks that aren't
+    /// handed off to anyone in particular come last, under `None`.
+    pub waiting: Vec<(Option<String>, Vec<String>)>,
+    pub notes: Vec<(Date, String)>,
+}
+
+impl Report {
+    pub fn new(period: Period, days: &[Day]) -> Self {
+        let mut completed: Vec<(String, usize)> = Vec::new();
+        let mut complete = |name: String| match completed.iter_mut().find(|(n, _)| n == &name) {
+            Some((_, count)) => *count += 1,
+            None => completed.push((name, 1)),
+        };
+
+        for day in days {
+            for task in &day.tasks {
+                if task.state == TaskState::Completed {
+                    complete(day.rewrite(&task.name));
+                    continue;
+                }
+                for subtask in &task.subtasks {
+                    if subtask.state == TaskState::Completed {
+                        complete(day.rewrite(&format!("{}: {}", task.name, subtask.name)));
+                    }
+                }
+            }
+        }
+
+        let last_tasks: &[Task] = days.last().map(|day| &day.tasks[..]).unwrap_or_default();
+        let rewrite = |name: &str| match days.last() {
+            Some(last) => last.rewrite(name),
+            None => name.to_string(),
+        };
+        let carried = last_tasks
+            .iter()
+            .filter(|task| {
+                !matches!(
+                    task.state,
+                    TaskState::Completed | TaskState::Blocked | TaskState::Waiting
+                )
+            })
+            .map(|task| rewrite(&task.name))
+            .collect();
+        let mut blocked = Vec::new();
+        let mut waiting: Vec<(Option<String>, Vec<String>)> = Vec::new();
+        for task in last_tasks {
+            let subtasks = task.subtasks.iter().map(|subtask| {
+                (
+                    subtask,
+                    rewrite(&format!("{}: {}", task.name, subtask.name)),
+                )
+            });
+            for (task, name) in std::iter::once((task, rewrite(&task.name))).chain(subtasks) {
+                match task.state {
+                    TaskState::Blocked => blocked.push(name),
+                    TaskState::Waiting => {
+                        let delegate = task.delegate();
+                        let key = delegate.as_ref().map(|delegate| delegate.to_lowercase());
+                        match waiting.iter_mut().find(|(other, _)| {
+                            other.as_ref().map(|other| other.to_lowercase()) == key
+                        }) {
+                            Some((_, names)) => names.push(name),
+                            None => waiting.push((delegate, vec![name])),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        waiting.sort_by_key(|(delegate, _)| {
+            (
+                delegate.is_none(),
+                delegate.as_ref().map(|delegate| delegate.to_lowercase()),
+            )
+        });
+
+        let notes = days
+            .iter()
+            .filter(|day| !day.notes.trim().is_empty())
+            .map(|day| (day.date, day.rewrite(day.notes.trim())))
+            .collect();
+
+        Self {
+            period,
+            completed,
+            carried,
+            blocked,
+            waiting,
+            notes,
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_workspace(workspace: &Workspace, period: Period) -> Result<Self, crate::Error> {
+        let days = period.days(workspace)?;
+        Ok(Self::new(period, &days))
+    }
+
+    #[cfg(feature = "fs")]
+    /// Where `w0rk report --write` puts the report, `reports/2024-W27.md`.
+    pub fn path(&self, workspace: &Workspace) -> PathBuf {
+        workspace
+            .path
+            .join("reports")
+            .join(format!("{}.md", self.period.name))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!(
+            "# {} ({} – {})\n",
+            self.period.title(),
+            self.period.start,
+            self.period.end
+        );
+
+        text.push_str("\n## Completed\n\n");
+        if self.completed.is_empty() {
+            text.push_str("Nothing completed.\n");
+        }
+        for (name, count) in &self.completed {
+            match count {
+                1 => writeln!(text, "- {}", name),
+                count => writeln!(text, "- {} ({}×)", name, count),
+            }
+            .unwrap();
+        }
+
+        for (heading, names) in [("Carried over", &self.carried), ("Blocked", &self.blocked)] {
+            if names.is_empty() {
+                continue;
+            }
+            writeln!(text, "\n## {}\n", heading).unwrap();
+            for name in names {
+                writeln!(text, "- {}", name).unwrap();
+            }
+        }
+
+        if !self.waiting.is_empty() {
+            text.push_str("\n## Waiting on\n");
+            for (delegate, names) in &self.waiting {
+                match delegate {
+                    Some(delegate) => writeln!(text, "\n### @{}\n", delegate),
+                    None => writeln!(text, "\n### Someone else\n"),
+                }
+                .unwrap();
+                for name in names {
+                    writeln!(text, "- {}", name).unwrap();
+                }
+            }
+        }
+
+        if !self.notes.is_empty() {
+            text.push_str("\n## Notes\n");
+            for (date, notes) in &self.notes {
+                write!(text, "\n### {}\n\n{}\n", date, notes).unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// Where the time of `@spent` annotations went in a period. The annotations
+/// are totals carried along with the task, so a day gets the time that was
+/// added since the task showed up before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeReport {
+    pub period: Period,
+    pub total: Duration,
+    /// Most time first, time of tasks without tags isn't in here.
+    pub by_tag: Vec<(String, Duration)>,
+    /// Most time first, subtasks as `<task>: <subtask>`.
+    pub by_task: Vec<(String, Duration)>,
+    pub by_day: Vec<(Date, Duration)>,
+}
+
+impl TimeReport {
+    /// The days are expected in order and may start before the period, to
+    /// know how much time was spent on carried over tasks already.
+    pub fn new(period: Period, days: &[Day]) -> Self {
+        let mut report = Self {
+            period,
+            total: Duration::ZERO,
+            by_tag: Vec::new(),
+            by_task: Vec::new(),
+            by_day: Vec::new(),
+        };
+        let mut previous: HashMap<String, Duration> = HashMap::new();
+
+        for day in days.iter().filter(|day| day.date <= report.period.end) {
+            for entry in day.task_entries() {
+                let spent = match entry.task.spent() {
+                    Some(spent) => spent,
+                    None => continue,
+                };
+                let (name, mut tags) = match entry.parent {
+                    Some(parent) => {
+                        let mut tags = parent.tags();
+                        tags.extend(entry.task.tags());
+                        let name = format!(
+                            "{}: {}",
+                            parent.recurring_name(),
+                            entry.task.recurring_name()
+                        );
+                        (name, tags)
+                    }
+                    None => (entry.task.recurring_name(), entry.task.tags()),
+                };
+                let added = spent - previous.insert(name.clone(), spent).unwrap_or_default();
+                if !report.period.contains(&day.date) || added <= Duration::ZERO {
+                    continue;
+                }
+
+                report.total += added;
+                add(&mut report.by_task, day.rewrite(&name), added);
+                add(&mut report.by_day, day.date, added);
+                tags.sort();
+                tags.dedup();
+                for tag in tags {
+                    add(&mut report.by_tag, tag, added);
+                }
+            }
+        }
+
+        report.by_tag.sort_by(|a, b| b.1.cmp(&a.1));
+        report.by_task.sort_by(|a, b| b.1.cmp(&a.1));
+        report
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_workspace(workspace: &Workspace, period: Period) -> Result<Self, crate::Error> {
+        let days = workspace.read_days(|date| date <= &period.end)?;
+        Ok(Self::new(period, &days))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!(
+            "# Time {} ({} – {})\n\nTotal: {}\n",
+            self.period.title(),
+            self.period.start,
+            self.period.end,
+            format_duration(self.total)
+        );
+        let by_day: Vec<(String, Duration)> = self
+            .by_day
+            .iter()
+            .map(|(date, spent)| (date.to_string(), *spent))
+            .collect();
+        let by_tag: Vec<(String, Duration)> = self
+            .by_tag
+            .iter()
+            .map(|(tag, spent)| (format!("#{}", tag), *spent))
+            .collect();
+
+        for (heading, column, rows) in [
+            ("By tag", "Tag", &by_tag),
+            ("By task", "Task", &self.by_task),
+            ("By day", "Day", &by_day),
+        ] {
+            if rows.is_empty() {
+                continue;
+            }
+            write!(
+                text,
+                "\n## {}\n\n| {} | Time |\n| --- | --- |\n",
+                heading, column
+            )
+            .unwrap();
+            for (name, spent) in rows {
+                writeln!(text, "| {} | {} |", name, format_duration(*spent)).unwrap();
+            }
+        }
+        text
+    }
+
+    /// Times are in minutes.
+    pub fn to_json(&self) -> serde_json::Value {
+        let rows = |key: &str, rows: Vec<(String, Duration)>| -> Vec<serde_json::Value> {
+            rows.into_iter()
+                .map(|(name, spent)| serde_json::json!({ key: name, "minutes": spent.whole_minutes() }))
+                .collect()
+        };
+        serde_json::json!({
+            "period": self.period.name,
+            "start": self.period.start.to_string(),
+            "end": self.period.end.to_string(),
+            "minutes": self.total.whole_minutes(),
+            "by_tag": rows("tag", self.by_tag.clone()),
+            "by_task": rows("task", self.by_task.clone()),
+            "by_day": rows(
+                "date",
+                self.by_day.iter().map(|(date, spent)| (date.to_string(), *spent)).collect()
+            ),
+        })
+    }
+}
+
+/// The completed tasks and spent time of a period per tag, e.g. per client
+/// or project. A task with several tags is in each of their groups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagReport {
+    pub period: Period,
+    pub total: Duration,
+    /// Most time first, then most completed tasks. The tasks without tags
+    /// are in a last group with no tag.
+    pub groups: Vec<TagGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagGroup {
+    pub tag: Option<String>,
+    pub spent: Duration,
+    /// Subtasks as `<task>: <subtask>`, with how often they were completed.
+    pub completed: Vec<(String, usize)>,
+}
+
+impl TagReport {
+    /// Like [`TimeReport::new`], the days may start before the period.
+    pub fn new(period: Period, days: &[Day]) -> Self {
+        let time = TimeReport::new(period.clone(), days);
+        let mut groups: Vec<TagGroup> = Vec::new();
+        for (tag, spent) in &time.by_tag {
+            group(&mut groups, Some(tag.clone())).spent = *spent;
+        }
+        for (name, spent) in &time.by_task {
+            if !TAG_REGEX.is_match(name) {
+                group(&mut groups, None).spent += *spent;
+            }
+        }
+
+        for day in days.iter().filter(|day| period.contains(&day.date)) {
+            for task in &day.tasks {
+                let name = task.recurring_name();
+                let mut completed = Vec::new();
+                if task.state == TaskState::Completed {
+                    completed.push((name.clone(), task.tags()));
+                }
+                for subtask in &task.subtasks {
+                    if subtask.state == TaskState::Completed {
+                        let mut tags = task.tags();
+                        tags.extend(subtask.tags());
+                        let name = format!("{}: {}", name, subtask.recurring_name());
+                        completed.push((name, tags));
+                    }
+                }
+
+                for (name, mut tags) in completed {
+                    let name = day.rewrite(&name);
+                    tags.sort();
+                    tags.dedup();
+                    let tags = match tags.is_empty() {
+                        true => vec![None],
+                        false => tags.into_iter().map(Some).collect(),
+                    };
+                    for tag in tags {
+                        let completed = &mut group(&mut groups, tag).completed;
+                        match completed.iter_mut().find(|(n, _)| n == &name) {
+                            Some((_, count)) => *count += 1,
+                            None => completed.push((name.clone(), 1)),
+                        }
+                    }
+                }
+            }
+        }
+
+        groups.sort_by(|a, b| {
+            (a.tag.is_none(), b.spent, b.completed.len()).cmp(&(
+                b.tag.is_none(),
+                a.spent,
+                a.completed.len(),
+            ))
+        });
+        Self {
+            period,
+            total: time.total,
+            groups,
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_workspace(workspace: &Workspace, period: Period) -> Result<Self, crate::Error> {
+        let days = workspace.read_days(|date| date <= &period.end)?;
+        Ok(Self::new(period, &days))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!(
+            "# Tags {} ({} – {})\n\nTotal: {}\n",
+            self.period.title(),
+            self.period.start,
+            self.period.end,
+            format_duration(self.total)
+        );
+        for group in &self.groups {
+            let heading = match &group.tag {
+                Some(tag) => format!("#{}", tag),
+                None => "Untagged".to_string(),
+            };
+            write!(
+                text,
+                "\n## {} ({})\n\n",
+                heading,
+                format_duration(group.spent)
+            )
+            .unwrap();
+            if group.completed.is_empty() {
+                text.push_str("Nothing completed.\n");
+            }
+            for (name, count) in &group.completed {
+                match count {
+                    1 => writeln!(text, "- {}", name),
+                    count => writeln!(text, "- {} ({}×)", name, count),
+                }
+                .unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// A task that's still open, with how long it has been carried over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarriedTask {
+    /// The name the task is followed by, see `Task::recurring_name`.
+    pub name: String,
+    /// The name as it's shown, with the rewrites of the config.
+    pub display_name: String,
+    pub first_seen: Date,
+    /// The number of days the task was carried over to the next one.
+    pub carried: usize,
+    /// Days from the first day the task showed up to the last day.
+    pub age: i64,
+}
+
+/// The open tasks of the last day, oldest first, to make the tasks that
+/// keep getting pushed to tomorrow visible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebtReport {
+    pub date: Option<Date>,
+    pub tasks: Vec<CarriedTask>,
+}
+
+impl DebtReport {
+    /// The days are expected in order, tasks are followed from day to day by
+    /// their name. A completed task starts over when it's added again.
+    pub fn new(days: &[Day]) -> Self {
+        let mut seen: HashMap<String, (Date, usize)> = HashMap::new();
+        for day in days {
+            for task in &day.tasks {
+                if task.state == TaskState::Completed {
+                    seen.remove(&task.recurring_name());
+                    continue;
+                }
+                let (_, count) = seen.entry(task.recurring_name()).or_insert((day.date, 0));
+                *count += 1;
+            }
+        }
+
+        let last = match days.last() {
+            Some(last) => last,
+            None => {
+                return Self {
+                    date: None,
+                    tasks: Vec::new(),
+                }
+            }
+        };
+        let mut tasks: Vec<CarriedTask> = last
+            .tasks
+            .iter()
+            .filter(|task| !matches!(task.state, TaskState::Completed | TaskState::Waiting))
+            .map(|task| {
+                let name = task.recurring_name();
+                let (first_seen, count) = seen[&name];
+                CarriedTask {
+                    display_name: last.rewrite(&name),
+                    name,
+                    first_seen,
+                    carried: count - 1,
+                    age: (last.date - first_seen).whole_days(),
+                }
+            })
+            .collect();
+        tasks.sort_by_key(|task| task.first_seen);
+
+        Self {
+            date: Some(last.date),
+            tasks,
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    /// The report as of the given date, today when there's none.
+    pub fn from_workspace(workspace: &Workspace, date: Option<Date>) -> Result<Self, crate::Error> {
+        let date = date.unwrap_or_else(|| time::OffsetDateTime::now_utc().date());
+        let days = workspace.read_days(|day| day <= &date)?;
+        Ok(Self::new(&days))
+    }
+
+    /// The tasks that have been open for at least this many days.
+    pub fn stale(&self, days: i64) -> Vec<&CarriedTask> {
+        self.tasks.iter().filter(|task| task.age >= days).collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = match self.date {
+            Some(date) => format!("# Oldest debt ({})\n\n", date),
+            None => "# Oldest debt\n\n".to_string(),
+        };
+        if self.tasks.is_empty() {
+            text.push_str("Nothing open.\n");
+            return text;
+        }
+        text.push_str("| Task | First seen | Age | Carried over |\n| --- | --- | --- | --- |\n");
+        for task in &self.tasks {
+            writeln!(
+                text,
+                "| {} | {} | {} | {} |",
+                task.display_name,
+                task.first_seen,
+                plural(task.age as usize, "day"),
+                plural(task.carried, "time")
+            )
+            .unwrap();
+        }
+        text
+    }
+}
+
+/// A person mentioned with `@name` in the tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Person {
+    /// The name as in the last mention, mentions are grouped ignoring case.
+    pub name: String,
+    pub last_mentioned: Date,
+    /// The open tasks of the last day mentioning the person, with the day
+    /// each task first showed up.
+    pub open: Vec<(Task, Date)>,
+}
+
+/// Everyone mentioned in the tasks with what's still open with them, what
+/// you're waiting on or working on together, to prepare for 1:1s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeopleReport {
+    pub date: Option<Date>,
+    pub people: Vec<Person>,
+    /// The style of the tasks, that of the days.
+    pub style: StyleConfig,
+    /// The rewrites of the task names, those of the days.
+    pub rewrites: Vec<Rewrite>,
+}
+
+impl PeopleReport {
+    /// The days are expected in order, tasks are followed from day to day by
+    /// their name like in `DebtReport`, a completed task starts over.
+    pub fn new(days: &[Day]) -> Self {
+        let mut first_seen: HashMap<String, Date> = HashMap::new();
+        let mut people: Vec<Person> = Vec::new();
+        for day in days {
+            for entry in day.task_entries() {
+                match entry.task.state {
+                    TaskState::Completed => {
+                        first_seen.remove(&entry.task.recurring_name());
+                    }
+                    _ => {
+                        first_seen
+                            .entry(entry.task.recurring_name())
+                            .or_insert(day.date);
+                    }
+                }
+                for name in entry.task.people() {
+                    match people
+                        .iter_mut()
+                        .find(|person| person.name.eq_ignore_ascii_case(&name))
+                    {
+                        Some(person) => {
+                            person.name = name;
+                            person.last_mentioned = day.date;
+                        }
+                        None => people.push(Person {
+                            name,
+                            last_mentioned: day.date,
+                            open: Vec::new(),
+                        }),
+                    }
+                }
+            }
+        }
+
+        if let Some(last) = days.last() {
+            let open = last
+                .task_entries()
+                .filter(|entry| entry.task.state != TaskState::Completed);
+            for entry in open {
+                let since = first_seen[&entry.task.recurring_name()];
+                for person in people
+                    .iter_mut()
+                    .filter(|person| entry.task.mentions(&person.name))
+                {
+                    person.open.push((entry.task.clone(), since));
+                }
+            }
+        }
+        people.sort_by_key(|person| person.name.to_lowercase());
+
+        Self {
+            date: days.last().map(|day| day.date),
+            people,
+            style: days.last().map(|day| day.style).unwrap_or_default(),
+            rewrites: days
+                .last()
+                .map(|day| day.rewrites.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    /// The report as of today.
+    pub fn from_workspace(workspace: &Workspace) -> Result<Self, crate::Error> {
+        let today = time::OffsetDateTime::now_utc().date();
+        Ok(Self::new(&workspace.read_days(|date| date <= &today)?))
+    }
+
+    /// Only the person with the name, ignoring case and the `@`.
+    pub fn only(mut self, name: &str) -> Self {
+        let name = name.trim_start_matches('@');
+        self.people
+            .retain(|person| person.name.eq_ignore_ascii_case(name));
+        self
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = match self.date {
+            Some(date) => format!("# People ({})\n", date),
+            None => "# People\n".to_string(),
+        };
+        if self.people.is_empty() {
+            text.push_str("\nNobody mentioned.\n");
+            return text;
+        }
+        for person in &self.people {
+            write!(
+                text,
+                "\n## @{}\n\nLast mentioned on {}.\n\n",
+                person.name, person.last_mentioned
+            )
+            .unwrap();
+            if person.open.is_empty() {
+                text.push_str("Nothing open.\n");
+            }
+            for (task, since) in &person.open {
+                writeln!(
+                    text,
+                    "{} {}, since {}",
+                    task.state.marker(&self.style),
+                    rewrite(&task.name, &self.rewrites),
+                    since
+                )
+                .unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// For a standup: what got done on the day before, what's open today and
+/// what's in the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandupReport {
+    pub date: Date,
+    /// The last day before, `None` when there's none.
+    pub previous: Option<Date>,
+    /// Completed on the day before, subtasks as `<task>: <subtask>`.
+    pub done: Vec<String>,
+    /// The open tasks of the day, pinned ones first.
+    pub planned: Vec<String>,
+    /// Blocked and waiting tasks and subtasks of the day.
+    pub blocked: Vec<String>,
+}
+
+impl StandupReport {
+    /// The days are expected in order, up to and including the date.
+    pub fn new(date: Date, days: &[Day]) -> Self {
+        let mut report = Self {
+            date,
+            previous: None,
+            done: Vec::new(),
+            planned: Vec::new(),
+            blocked: Vec::new(),
+        };
+
+        if let Some(previous) = days.iter().rev().find(|day| day.date < date) {
+            report.previous = Some(previous.date);
+            for task in &previous.tasks {
+                if task.state == TaskState::Completed {
+                    report.done.push(previous.display_name(task));
+                    continue;
+                }
+                for subtask in &task.subtasks {
+                    if subtask.state == TaskState::Completed {
+                        report.done.push(format!(
+                            "{}: {}",
+                            previous.display_name(task),
+                            previous.display_name(subtask)
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(today) = days.iter().find(|day| day.date == date) {
+            let mut tasks: Vec<&Task> = today.tasks.iter().collect();
+            tasks.sort_by_key(|task| !task.is_pinned());
+            for task in tasks {
+                match task.state {
+                    TaskState::Completed => {}
+                    TaskState::Blocked | TaskState::Waiting => {
+                        report.blocked.push(today.display_name(task))
+                    }
+                    _ => report.planned.push(today.display_name(task)),
+                }
+                let blocked = task.subtasks.iter().filter(|subtask| {
+                    matches!(subtask.state, TaskState::Blocked | TaskState::Waiting)
+                });
+                for subtask in blocked {
+                    report.blocked.push(format!(
+                        "{}: {}",
+                        today.display_name(task),
+                        today.display_name(subtask)
+                    ));
+                }
+            }
+        }
+        report
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_workspace(workspace: &Workspace, date: Date) -> Result<Self, crate::Error> {
+        let days = workspace.read_days(|day| day <= &date)?;
+        Ok(Self::new(date, &days))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# Standup ({})\n", self.date);
+
+        match self.previous {
+            Some(previous) => writeln!(text, "\n## Done ({})\n", previous),
+            None => writeln!(text, "\n## Done\n"),
+        }
+        .unwrap();
+        if self.done.is_empty() {
+            text.push_str("Nothing completed.\n");
+        }
+        for name in &self.done {
+            writeln!(text, "- {}", name).unwrap();
+        }
+
+        text.push_str("\n## Today\n\n");
+        if self.planned.is_empty() {
+            text.push_str("Nothing planned.\n");
+        }
+        for name in &self.planned {
+            writeln!(text, "- {}", name).unwrap();
+        }
+
+        if !self.blocked.is_empty() {
+            text.push_str("\n## Blocked\n\n");
+            for name in &self.blocked {
+                writeln!(text, "- {}", name).unwrap();
+            }
+        }
+        text
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    match count {
+        1 => format!("1 {}", word),
+        count => format!("{} {}s", count, word),
+    }
+}
+
+fn group(groups: &mut Vec<TagGroup>, tag: Option<String>) -> &mut TagGroup {
+    let index = match groups.iter().position(|group| group.tag == tag) {
+        Some(index) => index,
+        None => {
+            groups.push(TagGroup {
+                tag,
+                spent: Duration::ZERO,
+                completed: Vec::new(),
+            });
+            groups.len() - 1
+        }
+    };
+    &mut groups[index]
+}
+
+fn add<K: PartialEq>(totals: &mut Vec<(K, Duration)>, key: K, spent: Duration) {
+    match totals.iter_mut().find(|(k, _)| k == &key) {
+        Some((_, total)) => *total += spent,
+        None => totals.push((key, spent)),
+    }
+}
+
+/// Formats as `1h 30m`.
+pub fn format_duration(duration: Duration) -> String {
+    match (duration.whole_hours(), duration.whole_minutes() % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+fn month_name(month: Month) -> &'static str {
+    match month {
+        Month::January => "January",
+        Month::February => "February",
+        Month::March => "March",
+        Month::April => "April",
+        Month::May => "May",
+        Month::June => "June",
+        Month::July => "July",
+        Month::August => "August",
+        Month::September => "September",
+        Month::October => "October",
+        Month::November => "November",
+        Month::December => "December",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+    use crate::tests::helpers::day;
+
+    #[test]
+    fn test_periods() {
+        let date = parse_date("2024-07-03").unwrap();
+        let week = Period::week(date);
+        assert_eq!(week.name, "2024-W27");
+        assert_eq!(week.start, parse_date("2024-07-01").unwrap());
+        assert_eq!(week.end, parse_date("2024-07-07").unwrap());
+        assert_eq!(week.title(), "Week 2024-W27");
+
+        let month = Period::month(date);
+        assert_eq!(month.name, "2024-07");
+        assert_eq!(month.end, parse_date("2024-07-31").unwrap());
+        assert_eq!(month.title(), "July 2024");
+
+        // The first days of January can be in the last week of the year before.
+        assert_eq!(
+            Period::week(parse_date("2021-01-01").unwrap()).name,
+            "2020-W53"
+        );
+    }
+
+    #[test]
+    fn test_time_report() {
+        let days = vec![
+            day("2024-06-28", "* [ ] #infra Upgrade database @spent:1h\n"),
+            day(
+                "2024-07-01",
+                "* [ ] #infra Upgrade database @spent:2h30m\n* [x] Review @spent:15m\n",
+            ),
+            day(
+                "2024-07-02",
+                "* [x] #infra Upgrade database @spent:3h\n* [ ] Release\n  * [x] #docs Changelog @spent:45m\n",
+            ),
+        ];
+        let report = TimeReport::new(Period::week(days[1].date), &days);
+
+        assert_eq!(report.total, Duration::minutes(150 + 15 + 30 + 45 - 60));
+        assert_eq!(
+            report.by_task,
+            vec![
+                ("#infra Upgrade database".to_string(), Duration::hours(2)),
+                (
+                    "Release: #docs Changelog".to_string(),
+                    Duration::minutes(45)
+                ),
+                ("Review".to_string(), Duration::minutes(15)),
+            ]
+        );
+        assert_eq!(report.by_tag[0], ("infra".to_string(), Duration::hours(2)));
+        assert_eq!(report.by_day[1].1, Duration::minutes(75));
+        assert_eq!(report.to_json()["by_tag"][1]["minutes"], 45);
+        assert!(report.to_markdown().contains("| #infra | 2h |\n"));
+        assert_eq!(format_duration(Duration::minutes(90)), "1h 30m");
+
+        // A tag of both the parent and the subtask counts once.
+        let days = vec![day(
+            "2024-07-01",
+            "* [ ] #docs Release\n  * [x] #infra #docs Changelog @spent:1h\n",
+        )];
+        let report = TimeReport::new(Period::week(days[0].date), &days);
+        assert_eq!(
+            report.by_tag,
+            vec![
+                ("docs".to_string(), Duration::hours(1)),
+                ("infra".to_string(), Duration::hours(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_report() {
+        let days = vec![
+            day("2024-06-28", "* [ ] #acme Migration @spent:1h\n"),
+            day(
+                "2024-07-01",
+                "* [x] #acme Migration @spent:3h\n* [x] Standup\n* [ ] Invoices\n  * [x] #globex Send @spent:30m\n",
+            ),
+            day("2024-07-02", "* [x] Standup\n* [x] #acme #globex Call @spent:1h\n"),
+        ];
+        let report = TagReport::new(Period::week(days[1].date), &days);
+
+        assert_eq!(report.total, Duration::minutes(210));
+        let tags: Vec<Option<&str>> = report.groups.iter().map(|g| g.tag.as_deref()).collect();
+        assert_eq!(tags, vec![Some("acme"), Some("globex"), None]);
+        assert_eq!(report.groups[0].spent, Duration::hours(3));
+        assert_eq!(
+            report.groups[1].completed,
+            vec![
+                ("Invoices: #globex Send".to_string(), 1),
+                ("#acme #globex Call".to_string(), 1)
+            ]
+        );
+        assert!(report
+            .to_markdown()
+            .ends_with("\n## Untagged (0m)\n\n- Standup (2×)\n"));
+
+        // A tag of both the parent and the subtask counts once.
+        let days = vec![day(
+            "2024-07-01",
+            "* [ ] #docs Release\n  * [x] #infra #docs Notes\n",
+        )];
+        let report = TagReport::new(Period::week(days[0].date), &days);
+        assert_eq!(
+            report.groups[0].completed,
+            vec![("#docs Release: #infra #docs Notes".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_debt_report() {
+        let days = vec![
+            day("2024-07-01", "* [ ] Taxes\n* [x] Deploy\n"),
+            day("2024-07-02", "* [ ] Taxes\n* [ ] Review @spent:1h\n"),
+            day(
+                "2024-07-04",
+                "* [ ] Taxes @every:monthly\n* [~] Review @spent:2h\n* [x] Release\n* [ ] Deploy\n",
+            ),
+        ];
+        let report = DebtReport::new(&days);
+
+        assert_eq!(
+            report.to_markdown(),
+            "# Oldest debt (2024-07-04)\n\n\
+             | Task | First seen | Age | Carried over |\n\
+             | --- | --- | --- | --- |\n\
+             | Taxes | 2024-07-01 | 3 days | 2 times |\n\
+             | Review | 2024-07-02 | 2 days | 1 time |\n\
+             | Deploy | 2024-07-04 | 0 days | 0 times |\n"
+        );
+        assert_eq!(report.stale(3).len(), 1);
+    }
+
+    #[test]
+    fn test_people_report() {
+        let days = vec![
+            day(
+                "2024-07-01",
+                "* [#] Budget from @Alice\n* [ ] Lunch with @bob\n",
+            ),
+            day(
+                "2024-07-02",
+                "* [#] Budget from @Alice\n* [x] Lunch with @bob\n\
+                 * [~] Offsite\n  * [ ] Venue with @alice\n",
+            ),
+        ];
+        let report = PeopleReport::new(&days);
+
+        assert_eq!(
+            report.to_markdown(),
+            "# People (2024-07-02)\n\
+             \n## @alice\n\nLast mentioned on 2024-07-02.\n\n\
+             * [#] Budget from @Alice, since 2024-07-01\n\
+             * [ ] Venue with @alice, since 2024-07-02\n\
+             \n## @bob\n\nLast mentioned on 2024-07-02.\n\n\
+             Nothing open.\n"
+        );
+        assert_eq!(report.only("@BOB").people.len(), 1);
+
+        // Lunch starts over after it was completed.
+        let mut days = days;
+        days.push(day("2024-07-03", "* [ ] Lunch with @bob\n"));
+        let report = PeopleReport::new(&days).only("bob");
+        assert_eq!(
+            report.people[0].open[0].1,
+            crate::parse_date("2024-07-03").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_standup_report() {
+        let mut days = vec![
+            day(
+                "2024-06-28",
+                "* [x] Deploy\n* [ ] Release\n  * [x] Changelog\n",
+            ),
+            day(
+                "2024-07-01",
+                "* [ ] Release\n* [ ] Review #12 !pin\n* [>] Budget >@alice\n\
+                 * [ ] Offsite\n  * [#] Venue\n",
+            ),
+        ];
+        days[1].rewrites =
+            serde_json::from_str(r##"[{"from": "#(\\d+)", "to": "PR $1"}]"##).unwrap();
+        let report = StandupReport::new(crate::parse_date("2024-07-01").unwrap(), &days);
+
+        assert_eq!(
+            report.to_markdown(),
+            "# Standup (2024-07-01)\n\
+             \n## Done (2024-06-28)\n\n\
+             - Deploy\n\
+             - Release: Changelog\n\
+             \n## Today\n\n\
+             - Review PR 12\n\
+             - Release\n\
+             - Offsite\n\
+             \n## Blocked\n\n\
+             - Budget >@alice\n\
+             - Offsite: Venue\n"
+        );
+
+        let report = StandupReport::new(crate::parse_date("2024-06-28").unwrap(), &days[..1]);
+        assert!(report.to_markdown().starts_with(
+            "# Standup (2024-06-28)\n\n## Done\n\nNothing completed.\n\n## Today\n\n- Release\n"
+        ));
+    }
+
+    #[test]
+    fn test_report() {
+        let days = vec![
+            day(
+                "2024-07-01",
+                "* [x] Standup\n* [ ] Deploy\n  * [x] Build\n  * [ ] Ship\nShipped the build.\n",
+            ),
+            day(
+                "2024-07-02",
+                "* [x] Standup\n* [x] Deploy\n* [#] Release\n* [~] Review\n\
+                 * [>] Budget >@Bob\n* [>] Legal review\n* [~] Offsite\n  * [>] Venue >@bob\n",
+            ),
+        ];
+        let report = Report::new(Period::week(days[0].date), &days);
+
+        assert_eq!(
+            report.to_markdown(),
+            "# Week 2024-W27 (2024-07-01 – 2024-07-07)\n\
+             \n## Completed\n\n\
+             - Standup (2×)\n\
+             - Deploy: Build\n\
+             - Deploy\n\
+             \n## Carried over\n\n\
+             - Review\n\
+             - Offsite\n\
+             \n## Blocked\n\n\
+             - Release\n\
+             \n## Waiting on\n\
+             \n### @Bob\n\n\
+             - Budget >@Bob\n\
+             - Offsite: Venue >@bob\n\
+             \n### Someone else\n\n\
+             - Legal review\n\
+             \n## Notes\n\
+             \n### 2024-07-01\n\nShipped the build.\n"
+        );
+    }
+
+    #[test]
+    fn test_reports_rewrites() {
+        let rewrites: Vec<crate::Rewrite> =
+            serde_json::from_str(r##"[{"from": "#(\\d+)", "to": "PR $1"}]"##).unwrap();
+        let mut days = vec![
+            day(
+                "2024-07-01",
+                "* [x] Review #12\n* [ ] Merge #13 with @bob\n",
+            ),
+            day("2024-07-02", "* [ ] Merge #13 with @bob\nAbout #13.\n"),
+        ];
+        days.iter_mut()
+            .for_each(|day| day.rewrites = rewrites.clone());
+
+        let report = Report::new(Period::week(days[0].date), &days);
+        assert_eq!(report.completed, vec![("Review PR 12".to_string(), 1)]);
+        assert_eq!(report.carried, vec!["Merge PR 13 with @bob"]);
+        assert_eq!(report.notes[0].1, "About PR 13.");
+
+        let report = DebtReport::new(&days);
+        assert_eq!(report.tasks[0].name, "Merge #13 with @bob");
+        assert_eq!(report.tasks[0].display_name, "Merge PR 13 with @bob");
+
+        assert!(PeopleReport::new(&days)
+            .to_markdown()
+            .contains("* [ ] Merge PR 13 with @bob, since 2024-07-01"));
+    }
+}
@@ -0,0 +1,117 @@
+//! The end of day review of `w0rk review`, a decision for every open task
+//! of today.
+use crate::day::Day;
+use crate::recurring_task::RecurringState;
+use crate::task::State as TaskState;
+use time::Date;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Complete,
+    /// Leave it open, to carry it over to the next day.
+    Carry,
+    /// Take it out of the day until the date, see `RecurringState::defer`.
+    Defer(Date),
+    /// Take it out of the day with a line in the notes, as it won't be done.
+    Cancel,
+    /// Take it out of the day without a trace.
+    Drop,
+}
+
+/// The positions (from 1) of the tasks of the day to review, the ones that
+/// aren't completed.
+pub fn open_tasks(day: &Day) -> Vec<usize> {
+    day.tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| task.state != TaskState::Completed)
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Applies the decisions for the tasks at the positions (from 1) to the
+/// day, deferred tasks go to the state. Returns the names of the tasks that
+/// were unblocked by the completed ones.
+pub fn apply(
+    day: &mut Day,
+    state: &mut RecurringState,
+    decisions: &[(usize, Decision)],
+) -> Result<Vec<String>, crate::Error> {
+    let mut removed = Vec::new();
+    for (position, decision) in decisions {
+        let index = day.task_index(*position)?;
+        match decision {
+            Decision::Complete => {
+                day.set_task_state(index, None, TaskState::Completed);
+            }
+            Decision::Carry => {}
+            Decision::Defer(until) => {
+                state.defer(day.tasks[index].clone(), *until);
+                removed.push(index);
+            }
+            Decision::Cancel => {
+                let notes = day.notes.trim_end();
+                let separator = if notes.is_empty() { "" } else { "\n" };
+                day.notes = format!(
+                    "{}{}Cancelled: {}\n",
+                    notes, separator, day.tasks[index].name
+                );
+                removed.push(index);
+            }
+            Decision::Drop => removed.push(index),
+        }
+    }
+
+    removed.sort_unstable();
+    removed.dedup();
+    for index in removed.into_iter().rev() {
+        day.tasks.remove(index);
+    }
+    Ok(day.update_dependencies())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_apply() {
+        let mut day = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [ ] Deploy @id:deploy\n* [x] Review\n* [#] Announce @blocked-by:deploy\n\
+             * [ ] Offsite\n* [ ] Taxes\n* [ ] Lunch\n\nNotes\n",
+        )
+        .unwrap()
+        .0;
+        assert_eq!(open_tasks(&day), vec![1, 3, 4, 5, 6]);
+
+        let mut state = RecurringState::default();
+        let until = crate::parse_date("2024-07-08").unwrap();
+        let unblocked = apply(
+            &mut day,
+            &mut state,
+            &[
+                (1, Decision::Complete),
+                (3, Decision::Carry),
+                (4, Decision::Cancel),
+                (5, Decision::Defer(until)),
+                (6, Decision::Drop),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(unblocked, vec!["Announce @blocked-by:deploy"]);
+        assert_eq!(
+            day.to_content(),
+            "* [x] Deploy @id:deploy\n* [x] Review\n* [ ] Announce @blocked-by:deploy\n\n\
+             Notes\nCancelled: Offsite\n"
+        );
+        assert!(state
+            .take_deferred(&crate::parse_date("2024-07-07").unwrap())
+            .is_empty());
+        let deferred = state.take_deferred(&until);
+        assert_eq!(deferred[0].name, "Taxes");
+        assert!(state.deferred.is_empty());
+    }
+}
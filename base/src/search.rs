@@ -0,0 +1,310 @@
+//! A full-text index of the tasks and notes of all days, for ranked search
+//! with the matching words highlighted. Only the day files that changed
+//! since the last update are indexed again.
+use crate::config::DAY_FORMAT;
+use crate::day::{parse_date, Day};
+use crate::task::State as TaskState;
+use crate::workspace::Workspace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{doc, Index, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+use time::Date;
+
+pub(crate) const SEARCH_DIR: &str = "search";
+/// The modified times and sizes of the indexed day files.
+const STAMPS_FILE: &str = "days.json";
+const WRITER_MEMORY: usize = 20_000_000;
+
+pub struct SearchIndex {
+    dir: PathBuf,
+    index: Index,
+    fields: Fields,
+    stamps: HashMap<PathBuf, (SystemTime, u64)>,
+    /// The checkboxes the days in the index were read with.
+    checkboxes: BTreeMap<String, TaskState>,
+}
+
+/// The stamps of the indexed day files, with the checkboxes they were read
+/// with like the day cache, other ones index everything again.
+#[derive(Serialize, Deserialize)]
+struct StampsFile {
+    checkboxes: BTreeMap<String, TaskState>,
+    days: HashMap<PathBuf, (SystemTime, u64)>,
+}
+
+#[derive(Clone, Copy)]
+struct Fields {
+    path: Field,
+    date: Field,
+    kind: Field,
+    text: Field,
+}
+
+/// Whether a hit is a task (or subtask) or the notes of a day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HitKind {
+    Task,
+    Notes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub date: Date,
+    pub kind: HitKind,
+    pub score: f32,
+    /// The matching part of the text with the matched words in `**`.
+    pub snippet: String,
+}
+
+impl SearchIndex {
+    /// Opens the index in the state dir. An index that can't be opened,
+    /// like one from an older schema, is built again from scratch.
+    pub fn open(state_dir: &Path) -> Result<Self, crate::Error> {
+        let dir = state_dir.join(SEARCH_DIR);
+        let (schema, fields) = schema();
+        let index = match open_index(&dir, &schema) {
+            Ok(index) => index,
+            Err(_) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                open_index(&dir, &schema)?
+            }
+        };
+        let stamps = std::fs::read_to_string(dir.join(STAMPS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<StampsFile>(&content).ok())
+            .unwrap_or(StampsFile {
+                checkboxes: BTreeMap::new(),
+                days: HashMap::new(),
+            });
+        Ok(Self {
+            dir,
+            index,
+            fields,
+            stamps: stamps.days,
+            checkboxes: stamps.checkboxes,
+        })
+    }
+
+    /// Indexes the days that were added or changed since the last update
+    /// and removes the deleted ones. Returns the number of changed days.
+    /// Like the task index, encrypted workspaces aren't indexed.
+    pub fn update(&mut self, workspace: &Workspace) -> Result<usize, crate::Error> {
+        if workspace.is_encrypted() {
+            return Err(crate::Error::NotEncrypted("The search index".to_string()));
+        }
+        if self.checkboxes != workspace.checkboxes {
+            self.stamps.clear();
+        }
+        let mut stamps = HashMap::new();
+        let mut changed = Vec::new();
+        for (date, path) in workspace.day_list.iter() {
+            let metadata = std::fs::metadata(path)?;
+            let stamp = (metadata.modified()?, metadata.len());
+            if self.stamps.get(path) != Some(&stamp) {
+                changed.push((*date, path));
+            }
+            stamps.insert(path.clone(), stamp);
+        }
+        let removed: Vec<&PathBuf> = self
+            .stamps
+            .keys()
+            .filter(|path| !stamps.contains_key(*path))
+            .collect();
+        if changed.is_empty() && removed.is_empty() {
+            return Ok(0);
+        }
+
+        let dates: HashSet<Date> = changed.iter().map(|(date, _)| *date).collect();
+        let days = workspace.read_days(|date| dates.contains(date))?;
+        let mut writer: IndexWriter = self.index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        // Without stamps every day is indexed again, including the ones
+        // deleted since.
+        if self.stamps.is_empty() {
+            writer.delete_all_documents()?;
+        }
+        for path in changed.iter().map(|(_, path)| path).chain(&removed) {
+            writer.delete_term(Term::from_field_text(
+                self.fields.path,
+                &path.to_string_lossy(),
+            ));
+        }
+        for day in &days {
+            self.add_day(&writer, day)?;
+        }
+        writer.commit()?;
+
+        let count = changed.len() + removed.len();
+        let file = StampsFile {
+            checkboxes: workspace.checkboxes.clone(),
+            days: stamps,
+        };
+        std::fs::write(self.dir.join(STAMPS_FILE), serde_json::to_string(&file)?)?;
+        self.stamps = file.days;
+        self.checkboxes = file.checkboxes;
+        Ok(count)
+    }
+
+    fn add_day(&self, writer: &IndexWriter, day: &Day) -> Result<(), crate::Error> {
+        let path = day.path.to_string_lossy().to_string();
+        let date = day.date.format(&DAY_FORMAT)?;
+        let Fields {
+            path: path_field,
+            date: date_field,
+            kind,
+            text,
+        } = self.fields;
+        for entry in day.task_entries() {
+            writer.add_document(doc!(
+                path_field => path.clone(),
+                date_field => date.clone(),
+                kind => "task",
+                text => entry.task.name.clone(),
+            ))?;
+        }
+        let notes = format!("{}{}", day.preamble, day.notes);
+        if !notes.trim().is_empty() {
+            writer.add_document(doc!(
+                path_field => path,
+                date_field => date,
+                kind => "notes",
+                text => notes.trim().to_string(),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// The best matches for the query, in tantivy's query syntax: words,
+    /// `"phrases"`, `+required` and `-excluded` words.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, crate::Error> {
+        let reader = self
+            .index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let parser = QueryParser::for_index(&self.index, vec![self.fields.text]);
+        let query = parser.parse_query(query)?;
+        let mut snippets = SnippetGenerator::create(&searcher, &*query, self.fields.text)?;
+        snippets.set_max_num_chars(160);
+
+        let mut hits = Vec::new();
+        for (score, address) in searcher.search(&query, &TopDocs::with_limit(limit))? {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let field = |field: Field| {
+                doc.get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let snippet = snippets.snippet_from_doc(&doc);
+            hits.push(SearchHit {
+                date: parse_date(&field(self.fields.date))?,
+                kind: match field(self.fields.kind).as_str() {
+                    "notes" => HitKind::Notes,
+                    _ => HitKind::Task,
+                },
+                score,
+                snippet: match snippet.fragment().is_empty() {
+                    true => field(self.fields.text),
+                    false => highlight(snippet.fragment(), snippet.highlighted()),
+                },
+            });
+        }
+        Ok(hits)
+    }
+}
+
+fn schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        path: builder.add_text_field("path", STRING | STORED),
+        date: builder.add_text_field("date", STRING | STORED),
+        kind: builder.add_text_field("kind", STRING | STORED),
+        text: builder.add_text_field("text", TEXT | STORED),
+    };
+    (builder.build(), fields)
+}
+
+fn open_index(dir: &Path, schema: &Schema) -> Result<Index, crate::Error> {
+    std::fs::create_dir_all(dir)?;
+    let directory = MmapDirectory::open(dir).map_err(tantivy::TantivyError::from)?;
+    Ok(Index::open_or_create(directory, schema.clone())?)
+}
+
+/// The fragment on one line, with `**` around the highlighted ranges.
+fn highlight(fragment: &str, ranges: &[std::ops::Range<usize>]) -> String {
+    let mut highlighted = String::new();
+    let mut start = 0;
+    for range in ranges {
+        highlighted.push_str(&fragment[start..range.start]);
+        highlighted.push_str(&format!("**{}**", &fragment[range.clone()]));
+        start = range.end;
+    }
+    highlighted.push_str(&fragment[start..]);
+    highlighted.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_and_search() {
+        let dir = std::env::temp_dir().join("w0rk-test-search");
+        let _ = std::fs::remove_dir_all(&dir);
+        let work_dir = dir.join("work");
+        std::fs::create_dir_all(&work_dir).unwrap();
+        std::fs::write(
+            work_dir.join("2024-06-03.md"),
+            "* [ ] Upgrade the database #infra\n  * [x] Backup the database\n\n\
+             Talked to Alice about the database migration plan.\n",
+        )
+        .unwrap();
+        std::fs::write(work_dir.join("2024-07-01.md"), "* [ ] Rotate keys\n").unwrap();
+        let workspace = Workspace::from_path(&work_dir).unwrap();
+
+        let mut index = SearchIndex::open(&dir.join("state")).unwrap();
+        assert_eq!(index.update(&workspace).unwrap(), 2);
+        assert_eq!(index.update(&workspace).unwrap(), 0);
+
+        // Read with other checkboxes, the days are indexed again.
+        let checkboxes = BTreeMap::from([("?".to_string(), TaskState::Waiting)]);
+        let workspace = Workspace::from_path(&work_dir)
+            .unwrap()
+            .with_checkboxes(checkboxes);
+        let mut index = SearchIndex::open(&dir.join("state")).unwrap();
+        assert_eq!(index.update(&workspace).unwrap(), 2);
+        assert_eq!(index.update(&workspace).unwrap(), 0);
+
+        let hits = index.search("migration", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, HitKind::Notes);
+        assert!(hits[0].snippet.contains("**migration**"));
+        assert_eq!(index.search("database", 10).unwrap().len(), 3);
+
+        std::fs::remove_file(work_dir.join("2024-06-03.md")).unwrap();
+        let workspace = Workspace::from_path(&work_dir).unwrap();
+        let mut index = SearchIndex::open(&dir.join("state")).unwrap();
+        assert_eq!(index.update(&workspace).unwrap(), 1);
+        assert!(index.search("database", 10).unwrap().is_empty());
+        assert_eq!(
+            index.search("keys", 10).unwrap()[0].snippet,
+            "Rotate **keys**"
+        );
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("Deploy the\napp", &[0..6, 11..14]),
+            "**Deploy** the **app**"
+        );
+    }
+}
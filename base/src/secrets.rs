@@ -0,0 +1,21 @@
+//! Tokens stored in the OS keyring (Keychain, Secret Service or the
+//! Credential Manager), referenced from the config with `token_ref`.
+
+const SERVICE: &str = "w0rk";
+
+pub fn get(name: &str) -> Result<String, crate::Error> {
+    keyring::Entry::new(SERVICE, name)?
+        .get_password()
+        .map_err(|err| match err {
+            keyring::Error::NoEntry => crate::Error::MissingSecret(name.to_string()),
+            err => err.into(),
+        })
+}
+
+pub fn set(name: &str, secret: &str) -> Result<(), crate::Error> {
+    Ok(keyring::Entry::new(SERVICE, name)?.set_password(secret)?)
+}
+
+pub fn delete(name: &str) -> Result<(), crate::Error> {
+    Ok(keyring::Entry::new(SERVICE, name)?.delete_credential()?)
+}
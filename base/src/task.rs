@@ -1,32 +1,90 @@
 use std::convert::TryFrom;
 use std::fmt::Display;
 
+use crate::config::{StyleConfig, DAY_FORMAT};
+use crate::recurring_task::Interval;
 use crate::Error;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use time::Date;
 
 lazy_static! {
     static ref TASK_REGEX: Regex =
         Regex::new(r"^[\*|-]\s?\[(?<completed>.?)\]\s?(?<name>.+)$").unwrap();
+    // `📅 2024-07-01` is how the Obsidian Tasks plugin writes due dates.
+    static ref DUE_REGEX: Regex =
+        Regex::new(r"(?:@due:|📅\s*)(?<date>\d{4}-\d{2}-\d{2})").unwrap();
+    static ref SPENT_REGEX: Regex =
+        Regex::new(r"\s*@spent:(?:(?<hours>\d+)h)?(?:(?<minutes>\d+)m)?").unwrap();
+    // Tags start with a letter, so issue references like `#123` aren't tags.
+    pub(crate) static ref TAG_REGEX: Regex = Regex::new(r"(?:^|\s)#(?<tag>[A-Za-z][\w-]*)").unwrap();
+    // Annotations like `@due:2024-07-01` and `@blocked-by:deploy`, the value
+    // may be empty for lint to report it.
+    pub(crate) static ref ANNOTATION_REGEX: Regex =
+        Regex::new(r"(?<space>^|\s)@(?<key>[A-Za-z][\w-]*):(?<value>\S*)").unwrap();
+    // Mentions start with a letter too and can have dots like `@john.doe`,
+    // `@due:` and `@spent:` are annotations. `>@bob` hands the task to Bob.
+    static ref MENTION_REGEX: Regex = Regex::new(
+        r"(?:^|\s)(?<delegate>>)?@(?<person>[A-Za-z][\w-]*(?:\.[\w-]+)*)(?<annotation>[:(])?"
+    )
+    .unwrap();
+    static ref PIN_REGEX: Regex = Regex::new(r"(?:^|\s)!pin(?:\s|$)").unwrap();
+    static ref WEIGHT_REGEX: Regex = Regex::new(r"(?:^|\s)\((?<weight>\d+)\)$").unwrap();
+    static ref ID_REGEX: Regex = Regex::new(r"(?:^|\s)@id:(?<id>\S+)").unwrap();
+    // `@every:monday` on the tasks written from a recurring task.
+    static ref EVERY_REGEX: Regex = Regex::new(r"\s*@every:(?<interval>[\w-]+)").unwrap();
+    // `@blocked-by:deploy` for the task with `@id:deploy`, or the task named
+    // deploy, and `@blocked-by:"Deploy the API"` for names with spaces.
+    static ref BLOCKED_BY_REGEX: Regex =
+        Regex::new(r#"(?:^|\s)@blocked-by:(?:"(?<name>[^"]+)"|(?<id>\S+))"#).unwrap();
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum State {
     Completed,
     Incomplete,
     InProgress,
     Blocked,
+    /// Handed off to someone else, see `Task::delegate`.
+    Waiting,
 }
 impl TryFrom<&str> for State {
     type Error = crate::Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "x" => Ok(State::Completed),
-            " " => Ok(State::Incomplete),
-            "~" => Ok(State::InProgress),
-            "#" => Ok(State::Blocked),
-            _ => Err(Error::InvalidTaskSyntax(value.to_string())),
+        parse_state(value, &BTreeMap::new())
+    }
+}
+
+/// The state of a checkbox, the usual ones or the ones in the table, see
+/// `CheckboxConfig`.
+pub(crate) fn parse_state(
+    value: &str,
+    checkboxes: &BTreeMap<String, State>,
+) -> Result<State, Error> {
+    match value {
+        "x" => Ok(State::Completed),
+        " " | "" => Ok(State::Incomplete),
+        "~" => Ok(State::InProgress),
+        "#" => Ok(State::Blocked),
+        ">" => Ok(State::Waiting),
+        _ => match checkboxes.get(value) {
+            Some(state) => Ok(state.clone()),
+            None => Err(Error::InvalidTaskSyntax(value.to_string())),
+        },
+    }
+}
+
+impl State {
+    /// The bullet and checkbox of a task in the state in the style, like
+    /// `* [x]`.
+    pub fn marker(&self, style: &StyleConfig) -> String {
+        match (self, style.spaced_checkbox) {
+            (State::Incomplete, false) => format!("{} []", style.bullet),
+            _ => format!("{} [{}]", style.bullet, self),
         }
     }
 }
@@ -38,12 +96,13 @@ impl Display for State {
             State::Incomplete => " ",
             State::InProgress => "~",
             State::Blocked => "#",
+            State::Waiting => ">",
         };
         write!(f, "{}", state)
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub name: String,
     pub state: State,
@@ -54,6 +113,14 @@ impl TryFrom<&str> for Task {
     type Error = crate::Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Task::parse(value, &BTreeMap::new())
+    }
+}
+
+impl Task {
+    /// Parses the task line like `try_from`, with the checkboxes in the
+    /// table besides the usual ones, see `CheckboxConfig`.
+    pub fn parse(value: &str, checkboxes: &BTreeMap<String, State>) -> Result<Self, Error> {
         let captures = match TASK_REGEX.captures(value) {
             Some(captures) => captures,
             None => return Err(Error::InvalidTaskSyntax(value.to_string())),
@@ -62,16 +129,14 @@ impl TryFrom<&str> for Task {
         if let (Some(state), Some(name)) = (captures.name("completed"), captures.name("name")) {
             Ok(Task {
                 name: name.as_str().to_string(),
-                state: state.as_str().try_into()?,
+                state: parse_state(state.as_str(), checkboxes)?,
                 subtasks: Vec::new(),
             })
         } else {
             Err(Error::InvalidTaskSyntax(value.to_string()))
         }
     }
-}
 
-impl Task {
     pub fn add_subtask(&mut self, subtask: Task) {
         self.subtasks.push(subtask);
         self.update_state_from_subtasks();
@@ -97,15 +162,28 @@ impl Task {
         }
     }
 
+    /// Sets the state of the task. Completing it completes its subtasks too,
+    /// so they're not carried over without it.
+    pub fn set_state(&mut self, state: State) {
+        if state == State::Completed {
+            for subtask in self.subtasks.iter_mut() {
+                subtask.state = State::Completed;
+            }
+        }
+        self.state = state;
+    }
+
     pub fn update_state_from_subtasks(&mut self) {
         if self.subtasks.is_empty() {
             return;
         }
 
-        let all_complete = self.subtasks.iter().all(|t| t.state == State::Completed);
+        // Not by weight, a subtask weighing 0 isn't dropped when the task is
+        // completed and not carried over.
+        let all_completed = self.subtasks.iter().all(|t| t.state == State::Completed);
         let any_in_progress = self.subtasks.iter().any(|t| t.state == State::InProgress);
 
-        if all_complete {
+        if all_completed {
             self.state = State::Completed;
         } else if any_in_progress {
             self.state = State::InProgress;
@@ -117,15 +195,219 @@ impl Task {
     pub fn has_subtasks(&self) -> bool {
         !self.subtasks.is_empty()
     }
+
+    /// How much effort the task is from a `(3)` at the end of its name,
+    /// before the `@spent` and `@every` annotations, like
+    /// `* [ ] Migrate the data (3)`, 1 without one.
+    pub fn weight(&self) -> usize {
+        WEIGHT_REGEX
+            .captures(&self.recurring_name())
+            .and_then(|captures| captures["weight"].parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// The weight of the completed subtasks and of all subtasks, see
+    /// `weight`, `None` without subtasks. Without weights these are the
+    /// number of subtasks. When all subtasks weigh 0 each counts as 1.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        if self.subtasks.is_empty() {
+            return None;
+        }
+        let weights: Vec<usize> = match sum_weights(self.subtasks.iter().map(Task::weight)) {
+            0 => vec![1; self.subtasks.len()],
+            _ => self.subtasks.iter().map(Task::weight).collect(),
+        };
+        let completed = sum_weights(
+            self.subtasks
+                .iter()
+                .zip(&weights)
+                .filter(|(subtask, _)| subtask.state == State::Completed)
+                .map(|(_, weight)| *weight),
+        );
+        Some((completed, sum_weights(weights.into_iter())))
+    }
+
+    /// The `#tags` in the name, without the `#`.
+    pub fn tags(&self) -> Vec<String> {
+        TAG_REGEX
+            .captures_iter(&self.name)
+            .map(|captures| captures["tag"].to_string())
+            .collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim_start_matches('#');
+        self.tags().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Whether the task starts with 📌 or has `!pin` in its name, to keep it
+    /// at the top of the day.
+    pub fn is_pinned(&self) -> bool {
+        self.name.starts_with('📌') || PIN_REGEX.is_match(&self.name)
+    }
+
+    /// The name as the sinks, calendars and `w0rk list` show it, without
+    /// the `!pin` marker and the `@every` annotation.
+    pub fn display_name(&self) -> String {
+        let name = EVERY_REGEX.replace_all(&self.name, "");
+        PIN_REGEX.replace_all(&name, " ").trim().to_string()
+    }
+
+    /// The task and its subtasks as lines of a day file in the style.
+    pub fn to_markdown(&self, style: &StyleConfig) -> String {
+        let mut text = format!("{} {}\n", self.state.marker(style), self.name);
+        for subtask in &self.subtasks {
+            text.push_str(&format!(
+                "  {} {}\n",
+                subtask.state.marker(style),
+                subtask.name
+            ));
+        }
+        text
+    }
+
+    /// The people mentioned with `@name` in the name, without the `@`.
+    pub fn people(&self) -> Vec<String> {
+        MENTION_REGEX
+            .captures_iter(&self.name)
+            .filter(|captures| captures.name("annotation").is_none())
+            .map(|captures| captures["person"].to_string())
+            .collect()
+    }
+
+    /// The person the task is handed off to with `>@name`, without the `>@`.
+    pub fn delegate(&self) -> Option<String> {
+        MENTION_REGEX
+            .captures_iter(&self.name)
+            .find(|captures| {
+                captures.name("delegate").is_some() && captures.name("annotation").is_none()
+            })
+            .map(|captures| captures["person"].to_string())
+    }
+
+    /// Hands the task off to the person, replacing the `>@name` of whoever
+    /// it was handed to before, and waits on them.
+    pub fn delegate_to(&mut self, person: &str) {
+        let name = MENTION_REGEX.replace_all(&self.name, |captures: &regex::Captures| {
+            match (captures.name("delegate"), captures.name("annotation")) {
+                (Some(_), None) => String::new(),
+                _ => captures[0].to_string(),
+            }
+        });
+        self.name = format!("{} >@{}", name.trim(), person.trim_start_matches('@'));
+        self.state = State::Waiting;
+    }
+
+    pub fn mentions(&self, person: &str) -> bool {
+        let person = person.trim_start_matches('@');
+        self.people().iter().any(|p| p.eq_ignore_ascii_case(person))
+    }
+
+    /// The id from an `@id:deploy` annotation, for other tasks to depend on.
+    pub fn id(&self) -> Option<String> {
+        ID_REGEX
+            .captures(&self.name)
+            .map(|captures| captures["id"].to_string())
+    }
+
+    /// The ids or names of the tasks this one is blocked by, from
+    /// `@blocked-by:` annotations.
+    pub fn blocked_by(&self) -> Vec<String> {
+        BLOCKED_BY_REGEX
+            .captures_iter(&self.name)
+            .filter_map(|captures| captures.name("name").or(captures.name("id")))
+            .map(|dependency| dependency.as_str().to_string())
+            .collect()
+    }
+
+    /// Removes the `@blocked-by:` annotations of the dependency.
+    pub fn remove_dependency(&mut self, dependency: &str) {
+        self.name = BLOCKED_BY_REGEX
+            .replace_all(&self.name, |captures: &regex::Captures| {
+                match captures.name("name").or(captures.name("id")) {
+                    Some(found) if found.as_str() == dependency => String::new(),
+                    _ => captures[0].to_string(),
+                }
+            })
+            .to_string();
+    }
+
+    /// Whether this is the task a `@blocked-by:` refers to, by id or by its
+    /// exact name.
+    pub fn is_dependency(&self, dependency: &str) -> bool {
+        self.id().as_deref() == Some(dependency)
+            || self.name == dependency
+            || self.name_without_spent() == dependency
+    }
+
+    /// The due date from a `@due:YYYY-MM-DD` annotation (or `📅 YYYY-MM-DD`)
+    /// in the name, if any.
+    pub fn due(&self) -> Option<Date> {
+        DUE_REGEX
+            .captures(&self.name)
+            .and_then(|captures| Date::parse(&captures["date"], &DAY_FORMAT).ok())
+    }
+
+    /// The time spent from `@spent:1h30m` annotations in the name, added up.
+    /// Carried over tasks keep the annotation, so this is the total so far.
+    pub fn spent(&self) -> Option<time::Duration> {
+        let mut spent = None;
+        for captures in SPENT_REGEX.captures_iter(&self.name) {
+            let (hours, minutes) = (captures.name("hours"), captures.name("minutes"));
+            if hours.is_none() && minutes.is_none() {
+                continue;
+            }
+            let number = |m: Option<regex::Match>| m.map_or(0, |m| m.as_str().parse().unwrap_or(0));
+            *spent.get_or_insert(time::Duration::ZERO) +=
+                time::Duration::hours(number(hours)) + time::Duration::minutes(number(minutes));
+        }
+        spent
+    }
+
+    /// The name without the `@spent` annotations, the same from day to day.
+    pub fn name_without_spent(&self) -> String {
+        SPENT_REGEX.replace_all(&self.name, "").trim().to_string()
+    }
+
+    /// The interval from an `@every:` annotation, for a task written from a
+    /// recurring task.
+    pub fn recurrence(&self) -> Option<Interval> {
+        EVERY_REGEX
+            .captures(&self.name)
+            .and_then(|captures| Interval::try_from(&captures["interval"]).ok())
+    }
+
+    /// The name without the `@every` and `@spent` annotations, the name of
+    /// the recurring task it was written from. The reports follow tasks from
+    /// day to day by it.
+    pub fn recurring_name(&self) -> String {
+        EVERY_REGEX
+            .replace_all(&self.name_without_spent(), "")
+            .trim()
+            .to_string()
+    }
+
+    /// Adds the duration to the time spent, as one `@spent` at the end of
+    /// the name.
+    pub fn add_spent(&mut self, duration: time::Duration) {
+        let spent = self.spent().unwrap_or(time::Duration::ZERO) + duration;
+        let spent = match (spent.whole_hours(), spent.whole_minutes() % 60) {
+            (0, minutes) => format!("{}m", minutes),
+            (hours, 0) => format!("{}h", hours),
+            (hours, minutes) => format!("{}h{}m", hours, minutes),
+        };
+        self.name = format!("{} @spent:{}", self.name_without_spent(), spent);
+    }
+}
+
+/// The sum of the weights, at most `usize::MAX` for huge weights.
+fn sum_weights(weights: impl Iterator<Item = usize>) -> usize {
+    weights.fold(0, usize::saturating_add)
 }
 
 impl Display for Task {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "* [{}] {}", self.state, self.name)?;
-        for subtask in &self.subtasks {
-            writeln!(f, "  * [{}] {}", subtask.state, subtask.name)?;
-        }
-        Ok(())
+        write!(f, "{}", self.to_markdown(&StyleConfig::default()))
     }
 }
 
@@ -151,6 +433,34 @@ mod tests {
         assert_eq!(task.name, "Water plants");
     }
 
+    #[test]
+    fn test_parse_state() {
+        let checkboxes = crate::CheckboxConfig {
+            lenient: true,
+            states: BTreeMap::from([("?".to_string(), State::Waiting)]),
+        }
+        .table();
+        assert_eq!(parse_state("X", &checkboxes).unwrap(), State::Completed);
+        assert_eq!(parse_state(".", &checkboxes).unwrap(), State::InProgress);
+        assert_eq!(parse_state("?", &checkboxes).unwrap(), State::Waiting);
+        assert_eq!(parse_state("~", &checkboxes).unwrap(), State::InProgress);
+        assert!(parse_state("X", &BTreeMap::new()).is_err());
+        assert!(parse_state("y", &checkboxes).is_err());
+    }
+
+    #[test]
+    fn test_style() {
+        let task: Task = "- [] Water plants".try_into().unwrap();
+        assert_eq!(task.state, State::Incomplete);
+        let style = StyleConfig {
+            bullet: crate::Bullet::Dash,
+            spaced_checkbox: false,
+        };
+        assert_eq!(task.state.marker(&style), "- []");
+        assert_eq!(State::Completed.marker(&style), "- [x]");
+        assert_eq!(task.state.marker(&StyleConfig::default()), "* [ ]");
+    }
+
     #[test]
     fn test_parse_in_progress() {
         let task: Task = "-[~]Water plants".try_into().expect("Could not parse task");
@@ -172,6 +482,170 @@ mod tests {
         assert_eq!(task.name, "Water plants");
     }
 
+    #[test]
+    fn test_due() {
+        let task: Task = "* [ ] Send invoice @due:2024-07-01"
+            .try_into()
+            .expect("Could not parse task");
+        assert_eq!(
+            task.due(),
+            Some(Date::from_calendar_date(2024, time::Month::July, 1).unwrap())
+        );
+
+        let task: Task = "* [ ] Send invoice".try_into().unwrap();
+        assert_eq!(task.due(), None);
+    }
+
+    #[test]
+    fn test_spent() {
+        let task: Task = "* [x] Review PR @spent:1h30m and fix @spent:45m"
+            .try_into()
+            .unwrap();
+        assert_eq!(task.spent(), Some(time::Duration::minutes(135)));
+        assert_eq!(task.name_without_spent(), "Review PR and fix");
+
+        let task: Task = "* [ ] Review PR @spent:2h".try_into().unwrap();
+        assert_eq!(task.spent(), Some(time::Duration::hours(2)));
+
+        let task: Task = "* [ ] Review PR @spent:".try_into().unwrap();
+        assert_eq!(task.spent(), None);
+
+        let mut task: Task = "* [~] Review PR @spent:45m #infra".try_into().unwrap();
+        task.add_spent(time::Duration::minutes(25));
+        assert_eq!(task.name, "Review PR #infra @spent:1h10m");
+        task.add_spent(time::Duration::minutes(50));
+        assert_eq!(task.spent(), Some(time::Duration::hours(2)));
+        assert_eq!(task.name, "Review PR #infra @spent:2h");
+    }
+
+    #[test]
+    fn test_tags() {
+        let task: Task = "* [ ] #infra Upgrade database #public for PR #123"
+            .try_into()
+            .expect("Could not parse task");
+        assert_eq!(task.tags(), vec!["infra", "public"]);
+        assert!(task.has_tag("#Public"));
+        assert!(!task.has_tag("123"));
+    }
+
+    #[test]
+    fn test_people() {
+        let task: Task =
+            "* [#] Budget from @alice and @john.doe. Mail bob@example.com @due:2024-07-01 @spent:1h"
+                .try_into()
+                .unwrap();
+        assert_eq!(task.people(), vec!["alice", "john.doe"]);
+        assert!(task.mentions("@Alice"));
+        assert!(!task.mentions("example.com"));
+        assert_eq!(task.delegate(), None);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let mut task: Task =
+            r#"* [#] Announce @blocked-by:deploy @blocked-by:"Write the post" @spent:1h"#
+                .try_into()
+                .unwrap();
+        assert_eq!(task.blocked_by(), vec!["deploy", "Write the post"]);
+        task.remove_dependency("Write the post");
+        assert_eq!(task.name, "Announce @blocked-by:deploy @spent:1h");
+        assert_eq!(task.id(), None);
+        assert!(task.people().is_empty());
+
+        let deploy: Task = "* [ ] Deploy the API @id:deploy".try_into().unwrap();
+        assert_eq!(deploy.id(), Some("deploy".to_string()));
+        assert!(deploy.is_dependency("deploy"));
+        assert!(deploy.is_dependency("Deploy the API @id:deploy"));
+        let post: Task = "* [ ] Write the post @spent:30m".try_into().unwrap();
+        assert!(post.is_dependency("Write the post"));
+        assert!(!post.is_dependency("Write"));
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut task: Task = "* [~] Release".try_into().unwrap();
+        assert_eq!(task.progress(), None);
+        task.add_subtask("* [x] Changelog".try_into().unwrap());
+        task.add_subtask("* [~] Announce".try_into().unwrap());
+        task.add_subtask("* [ ] Tag".try_into().unwrap());
+        assert_eq!(task.progress(), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_weighted_progress() {
+        let mut task: Task = "* [ ] Migrate".try_into().unwrap();
+        task.add_subtask("* [ ] Migrate the data (3) @spent:1h".try_into().unwrap());
+        task.add_subtask("* [ ] Update the docs".try_into().unwrap());
+        task.add_subtask("* [ ] Tell #123 (maybe) (0)".try_into().unwrap());
+        assert_eq!(task.subtasks[0].weight(), 3);
+        assert_eq!(task.subtasks[2].weight(), 0);
+        assert_eq!(task.progress(), Some((0, 4)));
+        let weight = |line: &str| Task::try_from(line).unwrap().weight();
+        assert_eq!(weight("* [ ] Fix (3) of the bugs"), 1);
+        assert_eq!(weight("* [ ] Standup (2) @every:daily"), 2);
+        assert_eq!(weight(&format!("* [ ] Huge ({})", usize::MAX)), usize::MAX);
+
+        task.mark_subtask_complete(0);
+        assert_eq!(task.progress(), Some((3, 4)));
+        assert_eq!(task.state, State::Incomplete);
+        // The subtask weighing 0 doesn't count, but keeps the task open.
+        task.mark_subtask_complete(1);
+        assert_eq!(task.progress(), Some((4, 4)));
+        assert_eq!(task.state, State::Incomplete);
+        task.mark_subtask_complete(2);
+        assert_eq!(task.state, State::Completed);
+
+        let mut huge: Task = "* [ ] Huge".try_into().unwrap();
+        huge.add_subtask(
+            format!("* [x] One ({})", usize::MAX)
+                .as_str()
+                .try_into()
+                .unwrap(),
+        );
+        huge.add_subtask("* [ ] Two (1)".try_into().unwrap());
+        assert_eq!(huge.progress(), Some((usize::MAX, usize::MAX)));
+        assert_eq!(huge.state, State::Incomplete);
+
+        let mut optional: Task = "* [ ] Extras".try_into().unwrap();
+        optional.add_subtask("* [x] Stickers (0)".try_into().unwrap());
+        optional.add_subtask("* [ ] Mugs (0)".try_into().unwrap());
+        assert_eq!(optional.progress(), Some((1, 2)));
+        assert_eq!(optional.state, State::Incomplete);
+    }
+
+    #[test]
+    fn test_is_pinned() {
+        let pinned = |line: &str| Task::try_from(line).unwrap().is_pinned();
+        assert!(pinned("* [ ] 📌 Review PRs"));
+        assert!(pinned("* [ ] Review PRs !pin"));
+        assert!(pinned("* [~] !pin Review PRs #infra"));
+        assert!(!pinned("* [ ] Review the 📌 emoji"));
+        assert!(!pinned("* [ ] Review !pinned"));
+
+        let name = |line: &str| Task::try_from(line).unwrap().display_name();
+        assert_eq!(name("* [ ] Review PRs !pin"), "Review PRs");
+        assert_eq!(name("* [~] !pin Review PRs #infra"), "Review PRs #infra");
+        assert_eq!(name("* [ ] 📌 Review PRs"), "📌 Review PRs");
+        assert_eq!(
+            name("* [ ] Standup !pin @every:daily @spent:15m"),
+            "Standup @spent:15m"
+        );
+    }
+
+    #[test]
+    fn test_delegate() {
+        let mut task: Task = "* [>] Budget numbers >@bob for @alice".try_into().unwrap();
+        assert_eq!(task.state, State::Waiting);
+        assert_eq!(task.delegate(), Some("bob".to_string()));
+        assert_eq!(task.people(), vec!["bob", "alice"]);
+        assert_eq!(task.to_string(), "* [>] Budget numbers >@bob for @alice\n");
+
+        task.state = State::Incomplete;
+        task.delegate_to("@carol");
+        assert_eq!(task.name, "Budget numbers for @alice >@carol");
+        assert_eq!(task.state, State::Waiting);
+    }
+
     #[test]
     fn test_add_subtask() {
         let mut task: Task = "* [ ] Main task".try_into().unwrap();
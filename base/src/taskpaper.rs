@@ -0,0 +1,195 @@
+//! The TaskPaper dialect of the day files: `- Deploy @infra @done(2024-07-01)`.
+//! Tags are `#tags` and annotations `@due:2024-07-01` in the tasks, the
+//! dialect only changes how they're written.
+use crate::day::Source;
+use crate::task::{State as TaskState, Task, ANNOTATION_REGEX};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use time::Date;
+
+lazy_static! {
+    static ref TASKPAPER_TAG_REGEX: Regex = Regex::new(
+        r"(?<space>^|\s)@(?<tag>[A-Za-z][\w-]*)(?:\((?<value>[^)]*)\)|(?<annotation>:\S*))?"
+    )
+    .unwrap();
+    static ref HASH_TAG_REGEX: Regex =
+        Regex::new(r"(?<space>^|\s)#(?<tag>[A-Za-z][\w-]*)").unwrap();
+    static ref STATE_TAG_REGEX: Regex =
+        Regex::new(r"\s+@(?:done|started|start|inprogress|blocked|waiting)(?:\([^)]*\)|\b)")
+            .unwrap();
+}
+
+/// Tasks start with `- `, subtasks are indented. The lines above the first
+/// task, like a `Project:` line, are the preamble and every other line is
+/// kept as notes. The task lines go in the sources, see `Day::sources`.
+pub(crate) fn parse(content: &str, sources: &mut Vec<Source>) -> (String, Vec<Task>, String) {
+    let mut preamble = String::new();
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut notes = String::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let Some(text) = trimmed.strip_prefix("- ") else {
+            let text = match tasks.is_empty() {
+                true => &mut preamble,
+                false => &mut notes,
+            };
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        };
+
+        let task = task(text);
+        let subtask = trimmed.len() < line.len() && !tasks.is_empty();
+        sources.push(Source {
+            subtask,
+            state: task.state.clone(),
+            name: task.name.clone(),
+            line: line.to_string(),
+            children: String::new(),
+        });
+        match (subtask, tasks.last_mut()) {
+            (true, Some(parent)) => parent.subtasks.push(task),
+            _ => tasks.push(task),
+        }
+    }
+
+    (preamble, tasks, notes)
+}
+
+fn task(text: &str) -> Task {
+    let mut state = TaskState::Incomplete;
+    let name = TASKPAPER_TAG_REGEX.replace_all(text, |captures: &Captures| {
+        let space = &captures["space"];
+        let tag = &captures["tag"];
+        if captures.name("annotation").is_some() {
+            return captures[0].to_string();
+        }
+        match (tag, captures.name("value")) {
+            ("done", _) => state = TaskState::Completed,
+            ("started" | "start" | "inprogress", _) => state = TaskState::InProgress,
+            ("blocked", _) => state = TaskState::Blocked,
+            ("waiting", _) => state = TaskState::Waiting,
+            (tag, Some(value)) => return format!("{}@{}:{}", space, tag, value.as_str()),
+            (tag, None) => return format!("{}#{}", space, tag),
+        }
+        String::new()
+    });
+    Task {
+        name: name.trim().to_string(),
+        state,
+        subtasks: Vec::new(),
+    }
+}
+
+/// The tasks of the day and the notes. The tasks that didn't change are
+/// written like they were read, ones completed since are `@done` on the day.
+pub(crate) fn to_string(tasks: &[Task], notes: &str, date: Date, sources: &[Source]) -> String {
+    let mut sources: Vec<&Source> = sources.iter().collect();
+    let mut content = String::new();
+    for task in tasks {
+        content.push_str(&task_line(task, false, date, &mut sources));
+        for subtask in &task.subtasks {
+            content.push_str(&task_line(subtask, true, date, &mut sources));
+        }
+    }
+    format!("{}\n{}", content, notes.trim_start_matches('\n'))
+}
+
+/// The line the task was read from when it didn't change, that line with
+/// the new state tag when only the state changed, like `Day::to_content`.
+fn task_line(task: &Task, subtask: bool, date: Date, sources: &mut Vec<&Source>) -> String {
+    let same = |source: &&Source| source.subtask == subtask && source.name == task.name;
+    if let Some(index) = sources
+        .iter()
+        .position(|source| same(source) && source.state == task.state)
+    {
+        return format!("{}\n", sources.remove(index).line);
+    }
+    if let Some(index) = sources.iter().position(same) {
+        let line = STATE_TAG_REGEX.replace_all(&sources.remove(index).line, "");
+        return format!("{}{}\n", line.trim_end(), state_tag(&task.state, date));
+    }
+
+    let name = HASH_TAG_REGEX.replace_all(&task.name, "$space@$tag");
+    let name = ANNOTATION_REGEX.replace_all(&name, "$space@$key($value)");
+    format!(
+        "{}- {}{}\n",
+        if subtask { "\t" } else { "" },
+        name,
+        state_tag(&task.state, date)
+    )
+}
+
+fn state_tag(state: &TaskState, date: Date) -> String {
+    match state {
+        TaskState::Completed => format!(" @done({})", date),
+        TaskState::InProgress => " @started".to_string(),
+        TaskState::Blocked => " @blocked".to_string(),
+        TaskState::Waiting => " @waiting".to_string(),
+        TaskState::Incomplete => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::parse_date;
+
+    #[test]
+    fn test_parse() {
+        let (preamble, tasks, notes) = parse(
+            "Work:\n\
+             - Deploy @infra @due(2024-07-12) @done(2024-07-01)\n\
+             \t- Build @started\n\
+             - Review @waiting\n\
+             - Ship @spent:1h\n",
+            &mut Vec::new(),
+        );
+        assert_eq!(tasks[0].name, "Deploy #infra @due:2024-07-12");
+        assert_eq!(tasks[0].state, TaskState::Completed);
+        assert_eq!(tasks[0].subtasks[0].state, TaskState::InProgress);
+        assert_eq!(tasks[1].state, TaskState::Waiting);
+        assert_eq!(tasks[2].name, "Ship @spent:1h");
+        assert_eq!(preamble, "Work:\n");
+        assert_eq!(notes, "");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let date = parse_date("2024-07-01").unwrap();
+        let content = "- Deploy @infra @due(2024-07-12) @done(2024-07-01)\n\
+                       \t- Build @started\n\
+                       - Review\n\
+                       \n\
+                       Shipped it.\n";
+        let mut sources = Vec::new();
+        let (_, tasks, notes) = parse(content, &mut sources);
+        assert_eq!(to_string(&tasks, &notes, date, &sources), content);
+        assert_eq!(to_string(&tasks, &notes, date, &[]), content);
+    }
+
+    #[test]
+    fn test_write_changed() {
+        let date = parse_date("2024-07-02").unwrap();
+        let content = "- Deploy @done(2024-06-28) @infra\n\
+                       \x20\x20- Build @due(2024-07-12) @started\n\
+                       - Review @waiting @alice\n\
+                       \n\
+                       Shipped it.\n";
+        let mut sources = Vec::new();
+        let (_, mut tasks, notes) = parse(content, &mut sources);
+        tasks[1].state = TaskState::Completed;
+        tasks.push(Task::try_from("* [ ] Release #infra").unwrap());
+
+        assert_eq!(
+            to_string(&tasks, &notes, date, &sources),
+            "- Deploy @done(2024-06-28) @infra\n\
+             \x20\x20- Build @due(2024-07-12) @started\n\
+             - Review @alice @done(2024-07-02)\n\
+             - Release @infra\n\
+             \n\
+             Shipped it.\n"
+        );
+    }
+}
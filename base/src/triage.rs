@@ -0,0 +1,100 @@
+//! Today's tasks as a buffer to edit for `w0rk triage`, like the todo list
+//! of `git rebase -i`.
+use crate::config::DAY_FORMAT;
+use crate::{Day, Task};
+
+const HELP: &str = "
+# Triage the tasks of {date}:
+#
+# Reorder the lines to reorder the tasks, remove a line to delete its task
+# and change the state between the brackets to re-state it:
+# [ ] open, [~] in progress, [#] blocked, [>] waiting, [x] completed.
+#
+# Indented lines are subtasks of the task above them. Lines starting with #
+# are ignored. An empty buffer leaves the day as it is.
+";
+
+/// The buffer to edit, the tasks of the day with the instructions below them.
+pub fn to_buffer(day: &Day) -> Result<String, crate::Error> {
+    let tasks: String = day.tasks.iter().map(ToString::to_string).collect();
+    let help = HELP.replace("{date}", &day.date.format(&DAY_FORMAT)?);
+    Ok(format!("{}{}", tasks, help))
+}
+
+/// The day with the tasks of the edited buffer, in their order in the
+/// buffer. The preamble and notes stay as they are. Returns `None` for an
+/// empty buffer, to leave the day alone like `git rebase -i` does.
+pub fn apply(day: &Day, buffer: &str) -> Result<Option<Day>, crate::Error> {
+    if buffer.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let mut tasks: Vec<Task> = Vec::new();
+    for line in buffer.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let subtask = line.starts_with("  ") || line.starts_with('\t');
+        let task = Task::try_from(line.trim_start())?;
+        match tasks.last_mut() {
+            Some(parent) if subtask => parent.subtasks.push(task),
+            _ => tasks.push(task),
+        }
+    }
+
+    Ok(Some(Day {
+        tasks,
+        ..day.clone()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TaskState;
+    use std::path::Path;
+
+    fn day() -> Day {
+        Day::parse(
+            Path::new("2024-07-01.md"),
+            "# Monday\n* [ ] Deploy\n* [~] Review\n  * [ ] Tests\n* [ ] Lunch\n\nNotes\n",
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn test_to_buffer() {
+        let buffer = to_buffer(&day()).unwrap();
+        assert!(buffer.starts_with("* [ ] Deploy\n* [~] Review\n  * [ ] Tests\n* [ ] Lunch\n\n"));
+        assert!(buffer.contains("# Triage the tasks of 2024-07-01:"));
+        let unchanged = apply(&day(), &buffer).unwrap().unwrap();
+        assert_eq!(unchanged.to_content(), day().to_content());
+    }
+
+    #[test]
+    fn test_apply() {
+        let day = day();
+        let triaged = apply(
+            &day,
+            "* [~] Review\n  * [x] Tests\n# * [ ] Lunch\n* [#] Deploy\n\n# Help\n",
+        )
+        .unwrap()
+        .unwrap();
+        let names: Vec<&str> = triaged
+            .tasks
+            .iter()
+            .map(|task| task.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Review", "Deploy"]);
+        assert_eq!(triaged.tasks[0].subtasks[0].state, TaskState::Completed);
+        assert_eq!(triaged.tasks[1].state, TaskState::Blocked);
+        assert_eq!(triaged.preamble, day.preamble);
+        assert_eq!(triaged.notes, day.notes);
+
+        // Only the instructions left, every task is deleted.
+        assert!(apply(&day, "# Help\n").unwrap().unwrap().tasks.is_empty());
+        assert!(apply(&day, "\n").unwrap().is_none());
+        assert!(apply(&day, "* [y] Deploy\n").is_err());
+    }
+}
@@ -1,24 +1,123 @@
-use crate::config::{DAY_EXTENTION, DAY_FORMAT, RECURRING_FILE};
-use crate::day::{Day, DaysList};
-use crate::recurring_task::RecurringTasks;
-use crate::task::State as TaskState;
-use crate::Error;
+use crate::cache::DayCache;
+use crate::config::{
+    Dialect, Layout, Rewrite, StyleConfig, DAY_FORMAT, RECURRING_FILE, RECURRING_STATE_FILE,
+    TRASH_DIR,
+};
+use crate::day::{Day, DaysList, Diagnostic, TaskEntry};
+use crate::planner::Planner;
+use crate::recurring_task::{RecurringState, RecurringTasks};
+use crate::task::{State as TaskState, Task};
+use crate::{Error, Key};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use time::OffsetDateTime;
+use time::format_description::OwnedFormatItem;
+use time::{Date, OffsetDateTime};
 
 pub struct Workspace {
     pub name: String,
     pub path: PathBuf,
     pub recurring_tasks: RecurringTasks,
+    /// The invalid lines of `.recurring.md`, left out of `recurring_tasks`.
+    pub recurring_diagnostics: Vec<Diagnostic>,
+    pub recurring_state: RecurringState,
     pub day_list: DaysList,
+    /// The dialect of new days, by default that of the last day.
+    pub dialect: Dialect,
+    /// The file name of new days, `YYYY-MM-DD` by default.
+    pub file_format: Option<OwnedFormatItem>,
+    /// The folders of new days, in the work dir itself by default.
+    pub layout: Layout,
+    /// The style tasks are written and shown in.
+    pub style: StyleConfig,
+    /// Days more than this many days ago are locked, see `is_locked`.
+    pub lock_after: Option<u32>,
+    /// The rewrites of the names and notes as they're shown, see
+    /// `Day::rewrite`.
+    pub rewrites: Vec<Rewrite>,
+    /// The checkboxes the days are read with besides the usual ones, see
+    /// `CheckboxConfig`.
+    pub checkboxes: BTreeMap<String, TaskState>,
+    /// Parsed days from earlier runs, for `read_days`.
+    cache: Option<DayCache>,
+    /// The key of the encrypted day files, see `open`.
+    key: Option<Key>,
 }
 
 impl Workspace {
     pub fn from_path(path: &Path) -> Result<Self, crate::Error> {
+        Self::open(path, None)
+    }
+
+    /// Reads the workspace like `from_path`, with the key the day files and
+    /// `.recurring.json` are encrypted with. The days it reads and creates
+    /// are written encrypted.
+    pub fn open(path: &Path, key: Option<Key>) -> Result<Self, crate::Error> {
         if !path.is_dir() {
-            return Err(Error::WorkspaceIsNotDirectory);
+            return Err(Error::in_file(path)(Error::WorkspaceIsNotDirectory));
+        }
+
+        let recurring_path = path.join(RECURRING_FILE);
+        let recurring = match std::fs::read_to_string(&recurring_path) {
+            Ok(content) => RecurringTasks::parse_with_diagnostics(&content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(err) => return Err(Error::in_file(&recurring_path)(err)),
+        };
+        let recurring_state =
+            RecurringState::from_path(&path.join(RECURRING_STATE_FILE), key.as_ref())?;
+        Self::new(
+            path,
+            recurring,
+            recurring_state,
+            DaysList::from_path(path)?,
+            key,
+        )
+    }
+
+    /// Reads the workspace like `from_path`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn from_path_async(path: &Path) -> Result<Self, crate::Error> {
+        Self::open_async(path, None).await
+    }
+
+    /// Reads the workspace like `open`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn open_async(path: &Path, key: Option<Key>) -> Result<Self, crate::Error> {
+        if !tokio::fs::metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(Error::in_file(path)(Error::WorkspaceIsNotDirectory));
         }
 
+        let recurring_path = path.join(RECURRING_FILE);
+        let recurring = match tokio::fs::read_to_string(&recurring_path).await {
+            Ok(content) => RecurringTasks::parse_with_diagnostics(&content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(err) => return Err(Error::in_file(&recurring_path)(err)),
+        };
+        let state_path = path.join(RECURRING_STATE_FILE);
+        let recurring_state = match tokio::fs::read(&state_path).await {
+            Ok(content) => RecurringState::parse(&state_path, content, key.as_ref())?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => RecurringState::default(),
+            Err(err) => return Err(Error::in_file(&state_path)(err)),
+        };
+        Self::new(
+            path,
+            recurring,
+            recurring_state,
+            DaysList::from_path_async(path).await?,
+            key,
+        )
+    }
+
+    fn new(
+        path: &Path,
+        (recurring_tasks, recurring_diagnostics): (RecurringTasks, Vec<Diagnostic>),
+        recurring_state: RecurringState,
+        day_list: DaysList,
+        key: Option<Key>,
+    ) -> Result<Self, crate::Error> {
         let name = match path.iter().next_back().and_then(|res| res.to_str()) {
             Some(name) => name.to_string(),
             None => {
@@ -27,60 +126,433 @@ impl Workspace {
                 ))
             }
         };
-        let recurring_tasks = RecurringTasks::from_path(&path.join(RECURRING_FILE));
-        let day_list = DaysList::from_path(path)?;
+        let dialect = day_list
+            .last()
+            .map(|(_, path)| Dialect::from_path(path))
+            .unwrap_or_default();
 
         Ok(Workspace {
             path: path.to_owned(),
             name,
-            recurring_tasks: recurring_tasks.unwrap_or_default(),
+            recurring_tasks,
+            recurring_diagnostics,
+            recurring_state,
             day_list,
+            dialect,
+            file_format: None,
+            layout: Layout::default(),
+            style: StyleConfig::default(),
+            lock_after: None,
+            rewrites: Vec::new(),
+            checkboxes: BTreeMap::new(),
+            cache: None,
+            key,
         })
     }
 
-    pub fn today(&self) -> Option<Day> {
+    /// Creates new days in the dialect from the config.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Names new days in the format, and only reads the days named in it
+    /// or `YYYY-MM-DD`, so other notes with a date aren't days.
+    pub fn with_file_format(mut self, file_format: OwnedFormatItem) -> Self {
+        self.file_format = Some(file_format);
+        let days = std::mem::take(&mut self.day_list);
+        self.day_list = self.named(days);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Writes the tasks of the days in the style from the config.
+    pub fn with_style(mut self, style: StyleConfig) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Shows the names and notes of the days with the rewrites from the
+    /// config.
+    pub fn with_rewrites(mut self, rewrites: Vec<Rewrite>) -> Self {
+        self.rewrites = rewrites;
+        self
+    }
+
+    /// Reads the days with the checkboxes from the config, before
+    /// `with_cache` as the cache is for days read with them.
+    pub fn with_checkboxes(mut self, checkboxes: BTreeMap<String, TaskState>) -> Self {
+        self.checkboxes = checkboxes;
+        self
+    }
+
+    /// Locks the days more than this many days ago, from `lock_after_days`
+    /// in the config.
+    pub fn with_lock_after(mut self, lock_after: Option<u32>) -> Self {
+        self.lock_after = lock_after;
+        self
+    }
+
+    /// Whether the day of the date can't be written, see `with_lock_after`.
+    pub fn is_locked(&self, date: &Date) -> bool {
+        crate::day::is_locked(date, self.lock_after)
+    }
+
+    /// Keeps the parsed days in the state dir between runs, except for
+    /// encrypted day files as the cache is in plain text.
+    pub fn with_cache(mut self, state_dir: &Path) -> Self {
+        if self.is_encrypted() {
+            return self;
+        }
+        self.cache = Some(DayCache::open(state_dir, self.checkboxes.clone()));
+        self
+    }
+
+    /// The path of the day file of the date in the workspace's dialect,
+    /// or of the existing file in the other one.
+    pub fn day_path(&self, date: &Date) -> Result<PathBuf, crate::Error> {
+        if let Some((_, path)) = self.day_list.iter().find(|(day, _)| day == date) {
+            return Ok(path.clone());
+        }
+        let day_file = self.file_name(date, self.dialect.extension())?;
+        Ok(self.path.join(self.layout.folder(date)).join(day_file))
+    }
+
+    /// The file name of a new day file of the date with the extension, in
+    /// the file format of the workspace.
+    pub fn file_name(&self, date: &Date, extension: &str) -> Result<String, crate::Error> {
+        let format = match (&self.file_format, self.dialect) {
+            (Some(file_format), _) => file_format,
+            (None, Dialect::Logseq) => &*crate::logseq::JOURNAL_FORMAT,
+            (None, _) => &*DAY_FORMAT,
+        };
+        Ok(format!("{}.{}", date.format(format)?, extension))
+    }
+
+    /// Reads the days with a date matching the filter, parsing the files in
+    /// parallel, or from the cache when there is one. The days are in order
+    /// and the error is that of the first day that failed, like reading them
+    /// one by one.
+    pub fn read_days(&self, filter: impl Fn(&Date) -> bool + Sync) -> Result<Vec<Day>, Error> {
+        let days = self
+            .day_list
+            .iter()
+            .filter(|(date, _)| filter(date))
+            .collect::<Vec<_>>()
+            .par_iter()
+            .map(|(_, path)| self.read_day(path))
+            .collect::<Vec<Result<Day, Error>>>()
+            .into_iter()
+            .collect::<Result<Vec<Day>, Error>>()?;
+        if let Some(cache) = &self.cache {
+            // A cache that can't be written is only slower next time.
+            let _ = cache.save();
+        }
+        Ok(days)
+    }
+
+    /// Whether the day files are encrypted, see `open`.
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// The key the days are encrypted with, see `Day::read`.
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    /// Gives the day the style, checkboxes, lock and rewrites of the
+    /// workspace.
+    pub(crate) fn configure_day(&self, day: &mut Day) {
+        day.style = self.style;
+        day.checkboxes = self.checkboxes.clone();
+        day.lock_after = self.lock_after;
+        day.rewrites = self.rewrites.clone();
+    }
+
+    /// Reads the day file, from the cache when there is one.
+    pub fn read_day(&self, path: &Path) -> Result<Day, Error> {
+        let mut day = match &self.cache {
+            Some(cache) => cache.day(path)?,
+            None => return self.read_day_with_diagnostics(path).map(|(day, _)| day),
+        };
+        self.configure_day(&mut day);
+        Ok(day)
+    }
+
+    /// Reads the day file like `Day::from_path_with_diagnostics`, decrypted
+    /// when the workspace is encrypted.
+    pub fn read_day_with_diagnostics(&self, path: &Path) -> Result<(Day, Vec<Diagnostic>), Error> {
+        let (mut day, diagnostics) = Day::read(path, self.key(), &self.checkboxes)?;
+        self.configure_day(&mut day);
+        Ok((day, diagnostics))
+    }
+
+    /// Reads the day file like `read_day`, without blocking the runtime.
+    #[cfg(feature = "async")]
+    pub async fn read_day_async(&self, path: &Path) -> Result<Day, Error> {
+        let mut day = Day::read_async(path, self.key(), &self.checkboxes).await?;
+        self.configure_day(&mut day);
+        Ok(day)
+    }
+
+    /// The files that are encrypted with the key: the day files, the state
+    /// of the recurring tasks and the written reports, see `write_file`.
+    pub fn encrypted_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files: Vec<PathBuf> = self.day_list.iter().map(|(_, path)| path.clone()).collect();
+        let state_path = self.path.join(RECURRING_STATE_FILE);
+        if state_path.exists() {
+            files.push(state_path);
+        }
+        let reports = self.path.join("reports");
+        if reports.is_dir() {
+            for entry in std::fs::read_dir(&reports).map_err(Error::in_file(&reports))? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|extension| extension == "md") {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Writes a file in the workspace like a day file, encrypted when the
+    /// workspace is.
+    pub fn write_file(&self, path: &Path, content: String) -> Result<(), Error> {
+        crate::day::write_day_content(path, content, self.key())
+    }
+
+    /// The tasks of all days, to go over with `AllTasks::iter`.
+    pub fn all_tasks(&self) -> Result<AllTasks, Error> {
+        Ok(AllTasks {
+            days: self.read_days(|_| true)?,
+        })
+    }
+
+    /// Today, `None` when there's no day file for it yet.
+    pub fn today(&self) -> Result<Option<Day>, crate::Error> {
         let date = OffsetDateTime::now_utc().date();
         self.day_list
             .iter()
             .find(|(day, _)| day == &date)
-            .map(|(_, path)| Day::from_path(path).unwrap())
+            .map(|(_, path)| self.read_day_with_diagnostics(path).map(|(day, _)| day))
+            .transpose()
+    }
+
+    /// Today, written like `new_day` when there's no day file for it yet.
+    pub fn today_or_create(&self) -> Result<Day, crate::Error> {
+        match self.today()? {
+            Some(today) => Ok(today),
+            None => self.new_day(),
+        }
     }
 
     pub fn new_day(&self) -> Result<Day, crate::Error> {
-        let date = OffsetDateTime::now_utc().date();
-        let day_file = format!("{}.{}", date.format(&DAY_FORMAT)?, DAY_EXTENTION);
-        let day_path = self.path.join(&day_file);
+        self.new_day_for(OffsetDateTime::now_utc().date())
+    }
+
+    /// Writes the day of the date with the open tasks of the last day
+    /// before it, the tasks deferred until then and the recurring tasks of
+    /// the date, like `w0rk plan` does for tomorrow.
+    pub fn new_day_for(&self, date: Date) -> Result<Day, crate::Error> {
+        self.new_day_with(date, &Planner::default())
+    }
+
+    /// Writes the day of the date like `new_day_for`, with the tasks the
+    /// policy of the planner lets in.
+    pub fn new_day_with(&self, date: Date, planner: &Planner) -> Result<Day, crate::Error> {
+        let day_path = self.day_path(&date)?;
         if day_path.exists() {
-            return Err(Error::DayAlreadyExists(day_file));
+            return Err(Error::DayAlreadyExists(
+                day_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            ));
         }
         let mut new_day = Day::new(&day_path)?;
+        new_day.key = self.key.clone();
+        self.configure_day(&mut new_day);
 
-        if let Some((_, path)) = self.day_list.last() {
-            let last_day = Day::from_path(path)?;
-            new_day.tasks = last_day
-                .tasks
-                .iter()
-                .filter(|task| task.state != TaskState::Completed)
-                .cloned()
-                .collect();
+        // The days since the start of the week or month as well, for the
+        // recurring tasks to complete a number of times in it.
+        let since = self
+            .recurring_tasks
+            .iter()
+            .filter_map(|rt| rt.interval.frequency(&date))
+            .map(|(_, since)| since)
+            .min();
+        let previous_days = match self.day_list.iter().rev().find(|(day, _)| day < &date) {
+            Some((last, _)) => self.read_days(|day| {
+                day < &date && (day == last || since.is_some_and(|since| day >= &since))
+            })?,
+            None => Vec::new(),
         };
-
-        for rt in self.recurring_tasks.for_date(&date).iter() {
-            if new_day.tasks.iter().any(|task| task.name == rt.name) {
-                continue;
-            }
-            new_day.tasks.push(rt.into());
-        }
+        let mut recurring_state = self.recurring_state.clone();
+        new_day.tasks = planner.plan(
+            &previous_days,
+            &self.recurring_tasks,
+            &mut recurring_state,
+            &date,
+        );
+        new_day.update_dependencies();
 
         new_day.write()?;
+        // The state is written right after the day, so the deferred tasks
+        // are added to one day only, and stay deferred when the day can't be
+        // written.
+        if recurring_state != self.recurring_state {
+            recurring_state.write(&self.path.join(RECURRING_STATE_FILE), self.key())?;
+        }
         Ok(new_day)
     }
+
+    /// Removes the recurring task at the position (from 1) in today's file
+    /// and leaves it out of new days until the date.
+    pub fn snooze(&mut self, position: usize, until: Date) -> Result<Task, crate::Error> {
+        let mut today = self.today()?.ok_or(Error::NoToday)?;
+        let index = today.task_index(position)?;
+        let task = &today.tasks[index];
+        if !self.recurring_tasks.iter().any(|rt| rt.matches(task)) {
+            return Err(Error::NotRecurring(task.name.clone()));
+        }
+
+        let task = today.tasks.remove(index);
+        self.recurring_state
+            .snooze(&task.recurring_name(), until, today.date);
+        self.recurring_state
+            .write(&self.path.join(RECURRING_STATE_FILE), self.key())?;
+        today.write()?;
+        Ok(task)
+    }
+
+    /// Applies the decisions of `w0rk review` to today's tasks at the
+    /// positions (from 1) and writes today and the deferred tasks. Returns
+    /// today with the names of the tasks that were unblocked.
+    pub fn review(
+        &mut self,
+        decisions: &[(usize, crate::review::Decision)],
+    ) -> Result<(Day, Vec<String>), crate::Error> {
+        let mut today = self.today()?.ok_or(Error::NoToday)?;
+        let unblocked = crate::review::apply(&mut today, &mut self.recurring_state, decisions)?;
+        // Today first, a deferred task stays in it when it can't be written.
+        today.write()?;
+        self.recurring_state
+            .write(&self.path.join(RECURRING_STATE_FILE), self.key())?;
+        Ok((today, unblocked))
+    }
+
+    /// Reads the day of the date, failing when there's none.
+    pub fn read_date(&self, date: Date) -> Result<Day, crate::Error> {
+        let path = self.day_list_path(date).ok_or(Error::NoDay(date))?;
+        self.read_day(&path)
+    }
+
+    /// Moves the day file of the date to the trash, returning its path in
+    /// the trash. Locked days stay, see `is_locked`.
+    pub fn trash(&mut self, date: Date) -> Result<PathBuf, crate::Error> {
+        let path = self.day_list_path(date).ok_or(Error::NoDay(date))?;
+        if self.is_locked(&date) {
+            return Err(Error::DayIsLocked(date));
+        }
+        let trash_dir = self.path.join(TRASH_DIR);
+        std::fs::create_dir_all(&trash_dir).map_err(Error::in_file(&trash_dir))?;
+        let trashed = trash_dir.join(path.file_name().unwrap_or_default());
+        move_day_file(&path, &trashed)?;
+        self.day_list = self.list_days(&self.path)?;
+        Ok(trashed)
+    }
+
+    /// The days in the trash.
+    pub fn trashed(&self) -> Result<DaysList, crate::Error> {
+        let trash_dir = self.path.join(TRASH_DIR);
+        match trash_dir.is_dir() {
+            true => self.list_days(&trash_dir),
+            false => Ok(DaysList::default()),
+        }
+    }
+
+    /// Moves the day of the date back from the trash, returning its path.
+    pub fn restore_trashed(&mut self, date: Date) -> Result<PathBuf, crate::Error> {
+        let trashed = self
+            .trashed()?
+            .iter()
+            .find(|(day, _)| day == &date)
+            .map(|(_, path)| path.clone())
+            .ok_or(Error::NotInTrash(date))?;
+        if self.day_list_path(date).is_some() {
+            return Err(Error::DayAlreadyExists(date.to_string()));
+        }
+        let path = self
+            .path
+            .join(self.layout.folder(&date))
+            .join(trashed.file_name().unwrap_or_default());
+        move_day_file(&trashed, &path)?;
+        self.day_list = self.list_days(&self.path)?;
+        Ok(path)
+    }
+
+    /// The days in the folder with a file name of the workspace.
+    pub(crate) fn list_days(&self, dir: &Path) -> Result<DaysList, crate::Error> {
+        Ok(self.named(DaysList::from_path(dir)?))
+    }
+
+    fn named(&self, days: DaysList) -> DaysList {
+        match &self.file_format {
+            Some(file_format) => days.named(&[file_format, &DAY_FORMAT]),
+            None => days,
+        }
+    }
+
+    fn day_list_path(&self, date: Date) -> Option<PathBuf> {
+        self.day_list
+            .iter()
+            .find(|(day, _)| day == &date)
+            .map(|(_, path)| path.clone())
+    }
+}
+
+/// Moves a day file, without overwriting one that's already there.
+pub(crate) fn move_day_file(from: &Path, to: &Path) -> Result<(), crate::Error> {
+    if to.exists() {
+        return Err(Error::DayAlreadyExists(to.to_string_lossy().to_string()));
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(Error::in_file(parent))?;
+    }
+    std::fs::rename(from, to).map_err(Error::in_file(from))
+}
+
+/// The days of a workspace, read once to go over all of their tasks.
+pub struct AllTasks {
+    pub days: Vec<Day>,
+}
+
+impl AllTasks {
+    /// The tasks of the days in order, each task followed by its subtasks.
+    pub fn iter(&self) -> impl Iterator<Item = TaskEntry<'_>> {
+        self.days.iter().flat_map(Day::task_entries)
+    }
+}
+
+impl<'a> IntoIterator for &'a AllTasks {
+    type Item = TaskEntry<'a>;
+    type IntoIter = Box<dyn Iterator<Item = TaskEntry<'a>> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::task::Task;
+    use crate::task::State as TaskState;
     use crate::tests::helpers::test_fixtures_path;
 
     #[test]
@@ -105,17 +577,18 @@ mod tests {
                     subtasks: Vec::new(),
                 },
                 Task {
-                    name: "Deploy staging with latest changes".to_string(),
+                    name: "Deploy staging with latest changes @every:daily".to_string(),
                     state: TaskState::Incomplete,
                     subtasks: Vec::new(),
                 },
                 Task {
-                    name: "Deploy production with latest changes".to_string(),
+                    name: "Deploy production with latest changes @every:daily".to_string(),
                     state: TaskState::Incomplete,
                     subtasks: Vec::new(),
                 },
                 Task {
-                    name: "Update changelog with latest production changes".to_string(),
+                    name: "Update changelog with latest production changes @every:daily"
+                        .to_string(),
                     state: TaskState::Incomplete,
                     subtasks: Vec::new(),
                 },
@@ -125,8 +598,238 @@ mod tests {
         helpers::clean_fs();
     }
 
+    #[test]
+    fn test_read_days() {
+        let dir = std::env::temp_dir().join("w0rk-test-read-days");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for day in 1..=28 {
+            let content = format!("* [ ] Task {}\n", day);
+            std::fs::write(dir.join(format!("2024-02-{:02}.md", day)), content).unwrap();
+        }
+        let workspace = Workspace::from_path(&dir).unwrap();
+
+        let days = workspace.read_days(|date| date.day() > 10).unwrap();
+        assert_eq!(days.len(), 18);
+        assert!(days.windows(2).all(|days| days[0].date < days[1].date));
+        assert_eq!(days[0].tasks[0].name, "Task 11");
+
+        std::fs::write(dir.join("2024-02-20.md"), [0xff, 0xfe]).unwrap();
+        std::fs::write(dir.join("2024-02-25.md"), [0xff, 0xfe]).unwrap();
+        assert!(workspace.read_days(|date| date.day() < 20).is_ok());
+        assert!(workspace.read_days(|_| true).is_err());
+    }
+
+    #[test]
+    fn test_read_day_checkboxes() {
+        let dir = std::env::temp_dir().join("w0rk-test-read-day-checkboxes");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("2024-07-01.md");
+        std::fs::write(&path, "* [?] Ask Bob\n* [ ] Deploy\n").unwrap();
+
+        // Workspaces with other checkboxes in the same process.
+        let lenient = Workspace::from_path(&dir)
+            .unwrap()
+            .with_checkboxes(BTreeMap::from([("?".to_string(), TaskState::Waiting)]));
+        let strict = Workspace::from_path(&dir).unwrap();
+
+        let day = lenient.read_day(&path).unwrap();
+        assert_eq!(day.tasks[0].state, TaskState::Waiting);
+        let (day, diagnostics) = strict.read_day_with_diagnostics(&path).unwrap();
+        assert_eq!(day.tasks.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_all_tasks() {
+        let dir = std::env::temp_dir().join("w0rk-test-all-tasks");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("2024-03-01.md"),
+            "* [x] Plan the offsite\n  * [x] Book the venue\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("2024-03-04.md"), "* [ ] Send the invites\n").unwrap();
+        let all_tasks = Workspace::from_path(&dir).unwrap().all_tasks().unwrap();
+
+        let entries: Vec<(String, &str, Option<&str>)> = all_tasks
+            .iter()
+            .map(|entry| {
+                (
+                    entry.date.to_string(),
+                    entry.task.name.as_str(),
+                    entry.parent.map(|parent| parent.name.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("2024-03-01".to_string(), "Plan the offsite", None),
+                (
+                    "2024-03-01".to_string(),
+                    "Book the venue",
+                    Some("Plan the offsite")
+                ),
+                ("2024-03-04".to_string(), "Send the invites", None),
+            ]
+        );
+        let completed = (&all_tasks)
+            .into_iter()
+            .filter(|entry| entry.task.state == TaskState::Completed)
+            .count();
+        assert_eq!(completed, 2);
+    }
+
+    #[test]
+    fn test_today_or_create() {
+        let dir = std::env::temp_dir().join("w0rk-test-today-or-create");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+        assert!(workspace.today().unwrap().is_none());
+
+        let today = workspace.today_or_create().unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+        assert_eq!(workspace.today().unwrap().unwrap().path, today.path);
+
+        // A today that can't be read is an error instead of a panic.
+        std::fs::write(&today.path, [0xff, 0xfe]).unwrap();
+        assert!(workspace.today().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snooze() {
+        let dir = std::env::temp_dir().join("w0rk-test-snooze");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(RECURRING_FILE), "* [] @daily Water the plants\n").unwrap();
+        let today = OffsetDateTime::now_utc().date();
+        let today_path = dir.join(format!("{}.md", today.format(&DAY_FORMAT).unwrap()));
+        std::fs::write(&today_path, "* [ ] Deploy\n* [ ] Water the plants\n").unwrap();
+        let mut workspace = Workspace::from_path(&dir).unwrap();
+
+        assert!(matches!(
+            workspace.snooze(1, today),
+            Err(Error::NotRecurring(_))
+        ));
+        assert!(matches!(
+            workspace.snooze(3, today),
+            Err(Error::NoTaskAt(3))
+        ));
+        let until = today.next_day().unwrap().next_day().unwrap();
+        assert_eq!(workspace.snooze(2, until).unwrap().name, "Water the plants");
+        assert_eq!(workspace.today().unwrap().unwrap().tasks.len(), 1);
+
+        let workspace = Workspace::from_path(&dir).unwrap();
+        assert!(workspace
+            .recurring_state
+            .is_snoozed("Water the plants", &today.next_day().unwrap()));
+        std::fs::remove_file(&today_path).unwrap();
+        let workspace = Workspace::from_path(&dir).unwrap();
+        assert!(workspace.new_day().unwrap().tasks.is_empty());
+    }
+
+    #[test]
+    fn test_new_day_for() {
+        let dir = std::env::temp_dir().join("w0rk-test-new-day-for");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [x] Deploy\n* [ ] Review\n").unwrap();
+        std::fs::write(dir.join(RECURRING_FILE), "* [ ] @daily Standup\n").unwrap();
+        let mut workspace = Workspace::from_path(&dir).unwrap();
+
+        let date = crate::parse_date("2024-07-02").unwrap();
+        let taxes = Task::try_from("* [ ] Taxes").unwrap();
+        workspace.recurring_state.defer(taxes, date);
+        let names = |day: Day| -> Vec<String> { day.tasks.into_iter().map(|t| t.name).collect() };
+        assert_eq!(
+            names(workspace.new_day_for(date).unwrap()),
+            vec!["Review", "Taxes", "Standup @every:daily"]
+        );
+        assert!(
+            RecurringState::from_path(&dir.join(RECURRING_STATE_FILE), None)
+                .unwrap()
+                .deferred
+                .is_empty()
+        );
+        assert!(matches!(
+            workspace.new_day_for(date),
+            Err(Error::DayAlreadyExists(_))
+        ));
+        // Only the days before the date are carried over.
+        let date = crate::parse_date("2024-06-30").unwrap();
+        assert_eq!(
+            names(workspace.new_day_for(date).unwrap()),
+            vec!["Standup @every:daily"]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trash() {
+        let dir = std::env::temp_dir().join("w0rk-test-trash");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2024-07-01.md"), "* [ ] Deploy\n").unwrap();
+        std::fs::write(dir.join("2024-07-02.md"), "* [ ] Review\n").unwrap();
+        let mut workspace = Workspace::from_path(&dir).unwrap();
+        let date = crate::parse_date("2024-07-01").unwrap();
+
+        let trashed = workspace.trash(date).unwrap();
+        assert_eq!(trashed, dir.join(".trash/2024-07-01.md"));
+        assert_eq!(workspace.day_list.iter().count(), 1);
+        assert_eq!(
+            Workspace::from_path(&dir).unwrap().day_list.iter().count(),
+            1
+        );
+        assert!(matches!(workspace.trash(date), Err(Error::NoDay(_))));
+        assert_eq!(workspace.trashed().unwrap().iter().count(), 1);
+
+        assert_eq!(
+            workspace.restore_trashed(date).unwrap(),
+            dir.join("2024-07-01.md")
+        );
+        assert_eq!(workspace.day_list.iter().count(), 2);
+        assert!(matches!(
+            workspace.restore_trashed(date),
+            Err(Error::NotInTrash(_))
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_from_path_async() {
+        let dir = std::env::temp_dir().join("w0rk-test-from-path-async");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("2024-03-02.md")).unwrap();
+        std::fs::write(dir.join("2024-03-01.taskpaper"), "- Task 1\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "Not a day\n").unwrap();
+
+        let workspace = Workspace::from_path_async(&dir).await.unwrap();
+        let sync = Workspace::from_path(&dir).unwrap();
+        assert_eq!(workspace.day_list.iter().count(), 1);
+        assert_eq!(workspace.day_list.last(), sync.day_list.last());
+        assert_eq!(workspace.dialect, Dialect::Taskpaper);
+
+        let (_, path) = workspace.day_list.last().unwrap();
+        let day = Day::from_path_async(path).await.unwrap();
+        assert_eq!(day.tasks[0].name, "Task 1");
+
+        let missing = dir.join("missing");
+        assert!(Workspace::from_path_async(&missing).await.is_err());
+    }
+
     pub mod helpers {
         use super::*;
+        use crate::config::DAY_EXTENTION;
         use std::fs::remove_file;
 
         pub(crate) fn clean_fs() {
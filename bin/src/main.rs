@@ -1,49 +1,1784 @@
-use base::{Config, Workspace};
-use clap::{Parser, Subcommand};
-use directories::ProjectDirs;
-use sync::Syncer;
+use base::board::{Board, Direction, COLUMNS};
+use base::export::DateRange;
+use base::planner::{Planner, Policy};
+use base::pomodoro::{Phase, Pomodoro};
+use base::query::TaskQuery;
+use base::report::{
+    DebtReport, PeopleReport, Period, Report, StandupReport, TagReport, TimeReport,
+};
+use base::review::Decision;
+use base::Config;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use directories::{ProjectDirs, UserDirs};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use regex::Regex;
+use server::ServerState;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use sync::daemon::{Daemon, DaemonStatus};
+use sync::reminder::Reminders;
+use sync::{SinkRegistry, Syncer};
+use time::{Date, OffsetDateTime};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// Keep the config and state in this directory, for a portable setup
+    #[arg(long, global = true, env = "W0RK_HOME", value_name = "DIR")]
+    home: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Where the config and the state live, the platform directories unless
+/// a home directory is given.
+struct Dirs {
+    config_dir: PathBuf,
+    state_dir: PathBuf,
+    // The work dir in the config written by `config init`.
+    default_work_dir: PathBuf,
+}
+
+impl Dirs {
+    fn new(home: Option<&Path>) -> anyhow::Result<Self> {
+        if let Some(home) = home {
+            return Ok(Self {
+                config_dir: home.into(),
+                state_dir: home.join("state"),
+                default_work_dir: home.join("work"),
+            });
+        }
+
+        match ProjectDirs::from("com", "matsimitsu", "w0rk") {
+            Some(proj_dirs) => Ok(Self {
+                config_dir: proj_dirs.config_dir().into(),
+                state_dir: proj_dirs.data_local_dir().into(),
+                default_work_dir: UserDirs::new()
+                    .and_then(|dirs| dirs.document_dir().map(|dir| dir.join("Work")))
+                    .unwrap_or_else(|| proj_dirs.data_dir().join("work")),
+            }),
+            None => Err(anyhow::anyhow!("Could not find project directories")),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
-    New,
-    Sync,
+    New {
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Write tomorrow's day file tonight, with what's still open today and
+    /// tomorrow's recurring tasks, and open it in $EDITOR
+    Plan {
+        /// Don't open the day file
+        #[arg(long)]
+        no_edit: bool,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    Sync {
+        /// Sync the day of this date (YYYY-MM-DD) instead of today
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        date: Option<Date>,
+        /// Sync every day since this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date, conflicts_with = "date")]
+        since: Option<Date>,
+        /// Remove the synced messages for this date (YYYY-MM-DD) instead
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date, conflicts_with_all = ["date", "since"])]
+        delete: Option<Date>,
+        /// Write the completions of the sinks to days locked by
+        /// `lock_after_days` too
+        #[arg(long)]
+        force: bool,
+        /// Only sync to these sinks, e.g. `--only slack,notion`
+        #[arg(long, value_name = "SINK", value_delimiter = ',')]
+        only: Vec<String>,
+        /// Sync to every sink except these, e.g. `--except email`
+        #[arg(
+            long,
+            value_name = "SINK",
+            value_delimiter = ',',
+            conflicts_with = "only"
+        )]
+        except: Vec<String>,
+    },
+    /// Post the Slack reminders that are due
+    Remind,
+    /// Keep syncing in the background
+    Daemon {
+        #[command(subcommand)]
+        command: Option<DaemonCommand>,
+    },
+    /// Serve the HTTP API, configured with `server` in the config
+    Serve {
+        /// Only serve the endpoint to add tasks to today's file
+        #[arg(long)]
+        inbound: bool,
+        /// Address to listen on, overrides the config
+        #[arg(long)]
+        addr: Option<String>,
+    },
+    /// Export tasks to other formats
+    Export {
+        #[arg(value_enum, hide = true)]
+        format_arg: Option<ExportFormat>,
+        #[arg(long, value_enum, conflicts_with = "format_arg")]
+        format: Option<ExportFormat>,
+        /// Only export the days in this range (<from>..<to>), for csv and jsonl
+        #[arg(long, value_parser = base::export::parse_range)]
+        range: Option<DateRange>,
+        /// Tags to export as @contexts instead of +projects, for todotxt
+        #[arg(long = "context", value_name = "TAG")]
+        contexts: Vec<String>,
+    },
+    /// Add the open `- [ ]` items of other markdown files to today
+    Collect {
+        /// The files to scan, e.g. "~/projects/*/README.md"
+        pattern: String,
+        /// Only collect the items matching this regex, e.g. "#work"
+        #[arg(long)]
+        filter: Option<Regex>,
+    },
+    /// Import tasks from other apps into the workspace
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+        /// Add completed tasks to days locked by `lock_after_days` too
+        #[arg(long, global = true)]
+        force: bool,
+    },
+    /// Publish the workspace to other places
+    Publish {
+        #[command(subcommand)]
+        target: PublishTarget,
+    },
+    /// Summarize the completed, carried over and blocked tasks of a week
+    Report {
+        /// Report on the month instead of the week
+        #[arg(long)]
+        month: bool,
+        /// Report on the period of this date (YYYY-MM-DD) instead of today
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        date: Option<Date>,
+        /// Write the report to `reports/` in the workspace
+        #[arg(long)]
+        write: bool,
+        /// List the open tasks by how long they have been carried over instead
+        #[arg(long, conflicts_with_all = ["month", "date", "write"])]
+        debt: bool,
+        /// Group the completed tasks and the time spent by tag instead
+        #[arg(long, conflicts_with_all = ["write", "debt"])]
+        by_tag: bool,
+        /// Only report on the tasks matching the filter, see `w0rk list`
+        #[arg(long, conflicts_with = "debt")]
+        filter: Option<TaskQuery>,
+        /// Copy the report to the clipboard as well
+        #[arg(long)]
+        copy: bool,
+    },
+    /// List the tasks of all days, or those matching the filter
+    List {
+        /// Terms like "state:blocked tag:infra due-before:2024-07-01
+        /// range:2024-07-01..2024-07-31", other words are text in the name
+        #[arg(long)]
+        filter: Option<TaskQuery>,
+        /// Only the tasks mentioning the person with @name, like `--filter @alice`
+        #[arg(long)]
+        person: Option<String>,
+    },
+    /// The people mentioned with @name and the open tasks with each of them
+    People {
+        /// Only this person
+        name: Option<String>,
+        /// Copy the list to the clipboard as well
+        #[arg(long)]
+        copy: bool,
+    },
+    /// What got done on the day before, what's planned today and what's
+    /// blocked, for a standup
+    Standup {
+        /// The standup of this date instead of today
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        date: Option<Date>,
+        /// Copy the standup to the clipboard as well
+        #[arg(long)]
+        copy: bool,
+    },
+    /// Print the day of a date, today by default, ready to share
+    Show {
+        #[arg(value_parser = base::parse_natural_date)]
+        date: Option<Date>,
+        /// Copy the day to the clipboard as well
+        #[arg(long)]
+        copy: bool,
+    },
+    /// Move today's tasks between columns per state with the arrow keys
+    Board,
+    /// Complete a task of today
+    Done {
+        /// The number of the task in today's file, the first task is 1. Pick
+        /// the task by typing part of its name when left out
+        index: Option<usize>,
+    },
+    /// Hand a task of today off to someone, marking it waiting with `>@name`
+    Delegate {
+        /// Who you're waiting on now
+        person: String,
+        /// The number of the task in today's file, the first task is 1. Pick
+        /// the task by typing part of its name when left out
+        index: Option<usize>,
+    },
+    /// Run a pomodoro timer for a task of today, adding the finished
+    /// pomodoros to its `@spent`
+    Pomo {
+        /// The number of the task in today's file, the first task is 1. Pick
+        /// the task by typing part of its name when left out
+        index: Option<usize>,
+    },
+    /// Remove a recurring task from today and leave it out of new days for a while
+    Snooze {
+        /// The number of the task in today's file, the first task is 1. Pick
+        /// the task by typing part of its name when left out
+        index: Option<usize>,
+        /// Add the task to new days again from this date
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        until: Date,
+    },
+    /// Encrypt the existing day files with the identity of `encryption` in
+    /// the config, new ones are encrypted when they're written
+    #[cfg(feature = "encryption")]
+    Encrypt {
+        /// Write the day files in plain text again, before removing
+        /// `encryption` from the config
+        #[arg(long)]
+        decrypt: bool,
+    },
+    /// Back up the workspace, the config and the sync state to a .tar.zst
+    Backup {
+        /// The directory to write the backup to, instead of `dir` from the
+        /// `backup` config
+        #[arg(long, value_name = "DIR")]
+        to: Option<PathBuf>,
+    },
+    /// Unpack a backup made with `w0rk backup`
+    Restore {
+        backup: PathBuf,
+        /// Overwrite the files that exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Move the day of a date to the trash, `.trash` in the work dir, and
+    /// remove what was synced for it
+    Rm {
+        #[arg(value_parser = base::parse_natural_date)]
+        date: Date,
+        /// Trash the day even when it's locked by `lock_after_days`
+        #[arg(long)]
+        force: bool,
+    },
+    /// List the days in the trash
+    Trash {
+        #[command(subcommand)]
+        command: Option<TrashCommand>,
+    },
+    /// Move the day files to the file names and layout of the config, or
+    /// upgrade the sync state files
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// Add a meeting block to today's notes, or its action items to today's tasks
+    Meeting {
+        #[command(subcommand)]
+        command: MeetingCommand,
+    },
+    /// Go through today's open tasks at the end of the day to complete,
+    /// carry over, defer, cancel or drop each, then write tomorrow's day file
+    Review {
+        /// Sync today when done
+        #[arg(long)]
+        sync: bool,
+    },
+    /// Reorder, delete and re-state today's tasks in $EDITOR, like `git rebase -i`
+    Triage,
+    /// List the open tasks that have been carried over for a while
+    Stale {
+        /// Only list tasks open for at least this many days
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Find tasks of all days in the task index
+    #[cfg(feature = "index")]
+    Query {
+        /// Text in the name of the task
+        text: Option<String>,
+        #[arg(long, value_enum)]
+        state: Option<QueryState>,
+        /// Only tasks with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only tasks of this date (YYYY-MM-DD) or later
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        from: Option<Date>,
+        /// Only tasks of this date (YYYY-MM-DD) or earlier
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        to: Option<Date>,
+    },
+    /// Search the tasks and notes of all days, best matches first
+    #[cfg(feature = "search")]
+    Search {
+        /// Words, "phrases", +required or -excluded words
+        query: String,
+        /// Show at most this many matches
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Find the lines of all days matching a regex, with the lines around them
+    Grep {
+        /// A regex, e.g. "alice|bob" or "^\* \[#\]"
+        pattern: String,
+        /// Show this many lines before and after each match
+        #[arg(short = 'C', long, default_value_t = 2)]
+        context: usize,
+        /// Match upper and lower case alike
+        #[arg(short, long)]
+        ignore_case: bool,
+    },
+    /// Check the day files and `.recurring.md` for lines that don't parse,
+    /// duplicate days, file names without a date and invalid annotations
+    Lint {
+        /// Fix the problems that have one fix, like `[X]` or `2024-7-1.md`
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Rewrite the day files in their canonical form: the bullets and
+    /// checkboxes of the style, two space indents and no trailing whitespace
+    Fmt {
+        /// Only list the files that aren't formatted, failing when there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Run a language server for the day files on stdin and stdout, for
+    /// diagnostics, completion and code actions in your editor
+    Lsp,
+    /// Track time spent with `@spent:1h30m` on tasks
+    Time {
+        #[command(subcommand)]
+        command: TimeCommand,
+    },
+    /// Manage the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage the tokens in the OS keyring
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommand {
+    /// Store a token, referenced with `"token_ref": "<NAME>"` in the config
+    Set { name: String },
+    /// Remove a token
+    Delete { name: String },
+    /// Generate an age identity for `encryption`, referenced with
+    /// `identity_ref = "<NAME>"` in the config
+    #[cfg(feature = "encryption")]
+    Generate { name: String },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// A Todoist CSV export or the JSON of its sync API, with completed tasks
+    Todoist { file: PathBuf },
+    /// An org-mode file, TODO headlines become tasks
+    Org { file: PathBuf },
+    /// A Things 3 JSON export, projects and areas become tags
+    Things { file: PathBuf },
+    /// An Apple Reminders CSV export, lists become tags
+    Reminders { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum PublishTarget {
+    /// A static site with a calendar, a page per day and tag, and search
+    Html { out_dir: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum TimeCommand {
+    /// Break the time spent down by tag, task and day
+    Report {
+        /// Report on the week, the default
+        #[arg(long, conflicts_with = "month")]
+        week: bool,
+        /// Report on the month instead of the week
+        #[arg(long)]
+        month: bool,
+        /// Report on the period of this date (YYYY-MM-DD) instead of today
+        #[arg(long, value_name = "DATE", value_parser = base::parse_natural_date)]
+        date: Option<Date>,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+        format: ReportFormat,
+    },
+}
+
+/// Which tasks go into a new day, see `base::planner::Policy`.
+#[derive(Args)]
+struct PolicyArgs {
+    /// Leave out the open tasks of the last day
+    #[arg(long)]
+    no_carry_over: bool,
+    /// Leave out the tasks deferred until the day, they stay deferred
+    #[arg(long)]
+    no_deferred: bool,
+    /// Leave out the recurring tasks of the day
+    #[arg(long)]
+    no_recurring: bool,
+}
+
+impl PolicyArgs {
+    fn planner(&self) -> Planner {
+        Planner::new(Policy {
+            carry_over: !self.no_carry_over,
+            deferred: !self.no_deferred,
+            recurring: !self.no_recurring,
+        })
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+#[cfg(feature = "index")]
+#[derive(Clone, Copy, ValueEnum)]
+enum QueryState {
+    Open,
+    InProgress,
+    Blocked,
+    Waiting,
+    Completed,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Check the config for errors, like unknown or misspelled keys
+    Validate,
+    /// Write a commented config to start from
+    Init {
+        /// Overwrite an existing config
+        #[arg(long)]
+        force: bool,
+    },
+    /// Open the config in $EDITOR and check it after saving
+    Edit,
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// Move the day of a date back from the trash
+    Restore {
+        #[arg(value_parser = base::parse_natural_date)]
+        date: Date,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Rename the day files to the date format of the config, e.g. after
+    /// changing `date_format` of `obsidian`
+    Names {
+        /// Only list the renames
+        #[arg(long)]
+        dry_run: bool,
+        /// Rename the files in this Obsidian date format, the previous
+        /// `date_format`, instead of the days in the current one
+        #[arg(long, value_name = "DATE_FORMAT")]
+        from: Option<String>,
+    },
+    /// Move the day files to the folders of `layout` in the config, e.g.
+    /// `2024/07/2024-07-01.md` for `monthly`
+    Layout {
+        /// Only list the moves
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Upgrade the sync state files to the current version
+    State,
+}
+
+#[derive(Subcommand)]
+enum MeetingCommand {
+    /// Add a meeting block to fill in to the end of today's notes
+    New {
+        title: String,
+        /// When the meeting is, like 10:00
+        #[arg(long)]
+        time: Option<String>,
+        /// The attendees, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        with: Vec<String>,
+    },
+    /// Add the open action items of today's meetings to today's tasks,
+    /// tagged with the meeting
+    Actions {
+        /// Only the meeting with this title
+        title: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Show whether the daemon is running and when it last synced
+    Status,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// iCalendar with tasks that have a due date and the recurring tasks
+    Ics,
+    /// Every task of every day, a row per task
+    Csv,
+    /// Every task of every day, a JSON object per line
+    Jsonl,
+    /// The open tasks in todo.txt syntax
+    Todotxt,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let proj_dirs = match ProjectDirs::from("com", "matsimitsu", "w0rk") {
-        Some(proj_dirs) => proj_dirs,
-        None => {
-            return Err(anyhow::anyhow!("Could not find project directories"));
+    let dirs = Dirs::new(cli.home.as_deref())?;
+    let config_path = Config::find_path(&dirs.config_dir);
+    eprintln!("Config path: {:?}", config_path);
+    if let Commands::Config { command } = &cli.command {
+        return match command {
+            ConfigCommand::Validate => {
+                Config::from_path(&config_path)?;
+                println!("Config is valid");
+                Ok(())
+            }
+            ConfigCommand::Init { force } => {
+                let config_path = init_config(&dirs, *force)?;
+                println!("Wrote {:?}", config_path);
+                Ok(())
+            }
+            ConfigCommand::Edit => {
+                let config_path = match config_path.exists() {
+                    true => config_path,
+                    false => init_config(&dirs, false)?,
+                };
+                edit_config(&config_path)
+            }
+        };
+    }
+    if let Commands::Auth { command } = &cli.command {
+        match command {
+            AuthCommand::Set { name } => {
+                let token = rpassword::prompt_password(format!("Token for {}: ", name))?;
+                base::secrets::set(name, token.trim())?;
+                println!("Stored {} in the keyring", name);
+            }
+            AuthCommand::Delete { name } => {
+                base::secrets::delete(name)?;
+                println!("Removed {} from the keyring", name);
+            }
+            #[cfg(feature = "encryption")]
+            AuthCommand::Generate { name } => {
+                base::secrets::set(name, &base::encryption::generate_identity())?;
+                println!("Stored a new identity as {} in the keyring", name);
+            }
         }
-    };
-    let config_path = proj_dirs.config_dir().join("config.json");
-    println!("Config path: {:?}", config_path);
+        return Ok(());
+    }
+    if let Commands::Restore { backup, force } = &cli.command {
+        // The config comes first, it has the work dir to restore to.
+        let mut restored = base::backup::restore(backup, "config", &dirs.config_dir, *force, None)?;
+        let config = Config::from_path(&Config::find_path(&dirs.config_dir))?;
+        restored.extend(base::backup::restore(
+            backup,
+            "work",
+            &config.work_dir,
+            *force,
+            config.lock_after_days,
+        )?);
+        restored.extend(base::backup::restore(
+            backup,
+            "state",
+            &dirs.state_dir,
+            *force,
+            None,
+        )?);
+        println!("Restored {} files from {:?}", restored.len(), backup);
+        return Ok(());
+    }
     let config = Config::from_path(&config_path)?;
-    let workspace = Workspace::from_path(&config.work_dir)?;
+    if let Commands::Lint { fix } = &cli.command {
+        // Without the workspace, which doesn't load with an invalid
+        // `.recurring.md`.
+        let key = config.key()?;
+        let checkboxes = config.checkboxes.table();
+        let mut problems = base::lint::lint(&config.work_dir, key.as_ref(), &checkboxes)?;
+        if *fix {
+            println!(
+                "Fixed {} problems",
+                base::lint::fix(&problems, key.as_ref(), config.lock_after_days)?
+            );
+            problems = base::lint::lint(&config.work_dir, key.as_ref(), &checkboxes)?;
+        }
+        for problem in &problems {
+            match problem.fix {
+                Some(_) => println!("{} (fixable with --fix)", problem),
+                None => println!("{}", problem),
+            }
+        }
+        if !problems.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Commands::Lsp = &cli.command {
+        base::lsp::serve(&config.work_dir, config.key()?, config.checkboxes.table())?;
+        return Ok(());
+    }
+    let mut workspace = config.workspace()?.with_cache(&dirs.state_dir);
+    // The invalid recurring tasks are left out until they're fixed.
+    for diagnostic in &workspace.recurring_diagnostics {
+        eprintln!(
+            "Warning: {}:{}: {}: {}",
+            workspace.path.join(".recurring.md").display(),
+            diagnostic.line,
+            diagnostic.reason,
+            diagnostic.content.trim()
+        );
+    }
 
     match &cli.command {
-        Commands::New => {
-            let new_day = workspace.new_day()?;
-            println!("New day: {:?}", new_day.path);
+        Commands::New { policy } => match workspace.today()? {
+            // A day planned the evening before is already there.
+            Some(today) => println!("Today is there already: {:?}", today.path),
+            None => {
+                // Lines with a typo in the checkbox aren't carried over.
+                let today = OffsetDateTime::now_utc().date();
+                if let Some((_, path)) = workspace.day_list.last_until(&today) {
+                    let (_, diagnostics) = workspace.read_day_with_diagnostics(path)?;
+                    for diagnostic in diagnostics {
+                        eprintln!(
+                            "Warning: {}:{}: {}: {}",
+                            path.display(),
+                            diagnostic.line,
+                            diagnostic.reason,
+                            diagnostic.content.trim()
+                        );
+                    }
+                }
+                let new_day = workspace.new_day_with(today, &policy.planner())?;
+                println!("New day: {:?}", new_day.path);
+            }
+        },
+        Commands::Plan { no_edit, policy } => {
+            let tomorrow = OffsetDateTime::now_utc().date() + time::Duration::days(1);
+            let path = match workspace
+                .day_list
+                .iter()
+                .find(|(date, _)| date == &tomorrow)
+            {
+                Some((_, path)) => path.clone(),
+                None => {
+                    let day = workspace.new_day_with(tomorrow, &policy.planner())?;
+                    println!("Planned tomorrow: {:?}", day.path);
+                    day.path
+                }
+            };
+            if !no_edit {
+                open_editor(&path)?;
+            }
+        }
+        Commands::Sync {
+            date,
+            since,
+            delete,
+            only,
+            except,
+            force,
+        } => {
+            if *force {
+                workspace.lock_after = None;
+            }
+            if let (None, None, None, Some(git)) = (date, since, delete, &config.git) {
+                add_shipped(git, &mut workspace.today_or_create()?)?;
+            }
+            let mut registry = SinkRegistry::default();
+            registry.select(only, except)?;
+            let syncer = Syncer::with_registry(&config, &dirs.state_dir, &workspace, registry)?;
+            let reports = match (date, since, delete) {
+                (_, _, Some(date)) => syncer.delete(*date).await?,
+                (Some(date), _, _) => syncer.sync_date(*date).await?,
+                (_, Some(since), _) => syncer.sync_since(*since).await?,
+                _ => syncer.sync().await?,
+            };
+            print_reports(reports);
+        }
+        Commands::Remind => {
+            let mut reminders = Reminders::new(&config, &dirs.state_dir, &workspace)?;
+            for text in reminders.run(OffsetDateTime::now_utc()).await? {
+                println!("{}", text);
+            }
+            for warning in reminders.take_warnings() {
+                println!("warning: {}", warning);
+            }
+        }
+        Commands::Daemon { command: None } => {
+            let mut daemon =
+                Daemon::new(config.clone(), &dirs.state_dir)?.watch_config(&config_path);
+            daemon.run().await?;
+        }
+        Commands::Daemon {
+            command: Some(DaemonCommand::Status),
+        } => match DaemonStatus::read(&dirs.state_dir)? {
+            Some(status) if status.is_running(OffsetDateTime::now_utc()) => {
+                println!("Running (pid {}) since {}", status.pid, status.started_at);
+                if let Some(last_sync) = status.last_sync {
+                    println!("Last sync: {}", last_sync);
+                }
+                if let Some(error) = status.last_error {
+                    println!("Last error: {}", error);
+                }
+                println!("Next sync: {}", status.next_sync);
+            }
+            _ => println!("Not running"),
+        },
+        Commands::Serve { inbound, addr } => {
+            let server_config = match &config.server {
+                Some(server_config) => server_config,
+                None => return Err(anyhow::anyhow!("Add a `server` section to the config")),
+            };
+            let addr = addr.as_ref().unwrap_or(&server_config.addr);
+            let state = ServerState::new(config.clone(), &server_config.token, &dirs.state_dir)?;
+            let router = match inbound {
+                true => server::inbound_router(state),
+                false => server::api_router(state),
+            };
+            println!("Listening on {}", addr);
+            server::serve(router, addr).await?;
+        }
+        Commands::Export {
+            format_arg,
+            format,
+            range,
+            contexts,
+        } => {
+            let range = range.unwrap_or_default();
+            match format.or(*format_arg) {
+                Some(ExportFormat::Ics) => print!("{}", base::ics::export(&workspace)?),
+                Some(ExportFormat::Csv) => {
+                    let records = base::export::workspace_records(&workspace, &range)?;
+                    print!("{}", base::export::to_csv(&records));
+                }
+                Some(ExportFormat::Jsonl) => {
+                    let records = base::export::workspace_records(&workspace, &range)?;
+                    print!("{}", base::export::to_jsonl(&records)?);
+                }
+                Some(ExportFormat::Todotxt) => {
+                    print!("{}", base::export::workspace_todotxt(&workspace, contexts)?);
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Pass a --format: ics, csv, jsonl or todotxt"
+                    ))
+                }
+            }
+        }
+        Commands::Collect { pattern, filter } => {
+            // Quoted patterns don't get a `~` expanded by the shell.
+            let pattern = match (pattern.strip_prefix("~/"), UserDirs::new()) {
+                (Some(rest), Some(dirs)) => {
+                    dirs.home_dir().join(rest).to_string_lossy().to_string()
+                }
+                _ => pattern.clone(),
+            };
+            let items = base::collect::collect(&workspace, &pattern, filter.as_ref())?;
+            let added = base::collect::add_to_today(&workspace, &items)?;
+            for task in &added {
+                println!("{}", task.name);
+            }
+            println!("Collected {} of {} open items", added.len(), items.len());
+        }
+        Commands::Import { source, force } => {
+            if *force {
+                workspace.lock_after = None;
+            }
+            let today = OffsetDateTime::now_utc().date();
+            let import = match source {
+                ImportSource::Todoist { file } => {
+                    base::import::todoist::parse(&fs::read_to_string(file)?, today)?
+                }
+                ImportSource::Org { file } => {
+                    base::import::org::parse(&fs::read_to_string(file)?, today)?
+                }
+                ImportSource::Things { file } => {
+                    base::import::things::parse(&fs::read_to_string(file)?, today)?
+                }
+                ImportSource::Reminders { file } => {
+                    base::import::reminders::parse(&fs::read_to_string(file)?, today)?
+                }
+            };
+            for warning in &import.warnings {
+                eprintln!("{}", warning);
+            }
+            let summary = import.write(&workspace)?;
+            println!(
+                "Imported {} tasks into {} new and {} existing days, and {} recurring tasks",
+                summary.tasks, summary.days_created, summary.days_updated, summary.recurring
+            );
+        }
+        Commands::Publish {
+            target: PublishTarget::Html { out_dir },
+        } => {
+            let pages = base::html::publish(&workspace, out_dir)?;
+            println!("Wrote {} pages to {:?}", pages, out_dir);
+        }
+        Commands::Report {
+            debt: true, copy, ..
+        } => {
+            let markdown = DebtReport::from_workspace(&workspace, None)?.to_markdown();
+            print!("{}", markdown);
+            copy_if(*copy, &markdown)?;
+        }
+        Commands::Report {
+            month,
+            date,
+            write,
+            by_tag,
+            filter,
+            copy,
+            ..
+        } => {
+            let date = date.unwrap_or_else(|| OffsetDateTime::now_utc().date());
+            let period = match month {
+                true => Period::month(date),
+                false => Period::week(date),
+            };
+            let filter = filter.clone().unwrap_or_default();
+            let days: Vec<_> = period
+                .days(&workspace)?
+                .iter()
+                .map(|day| filter.filter_day(day))
+                .collect();
+            if *by_tag {
+                let markdown = TagReport::new(period, &days).to_markdown();
+                print!("{}", markdown);
+                copy_if(*copy, &markdown)?;
+                return Ok(());
+            }
+            let report = Report::new(period, &days);
+            let markdown = report.to_markdown();
+            match write {
+                true => {
+                    // Encrypted like the day files when they are.
+                    let path = report.path(&workspace);
+                    workspace.write_file(&path, markdown.clone())?;
+                    println!("Wrote {:?}", path);
+                }
+                false => print!("{}", markdown),
+            }
+            copy_if(*copy, &markdown)?;
+        }
+        Commands::List { filter, person } => {
+            let mut filter = filter.clone().unwrap_or_default();
+            if let Some(person) = person {
+                filter = filter.person(person);
+            }
+            for day in filter.days(&workspace)? {
+                let mut tasks: Vec<&base::Task> = day.tasks.iter().collect();
+                tasks.sort_by_key(|task| !task.is_pinned());
+                for task in tasks {
+                    let marker = task.state.marker(&workspace.style);
+                    match task.progress() {
+                        Some((completed, total)) => println!(
+                            "{}  {} {} ({}/{})",
+                            day.date,
+                            marker,
+                            day.display_name(task),
+                            completed,
+                            total
+                        ),
+                        None => println!("{}  {} {}", day.date, marker, day.display_name(task)),
+                    }
+                    for subtask in &task.subtasks {
+                        println!(
+                            "{}    {} {}",
+                            day.date,
+                            subtask.state.marker(&workspace.style),
+                            day.display_name(subtask)
+                        );
+                    }
+                }
+            }
+        }
+        Commands::People { name, copy } => {
+            let mut report = PeopleReport::from_workspace(&workspace)?;
+            if let Some(name) = name {
+                report = report.only(name);
+            }
+            let markdown = report.to_markdown();
+            print!("{}", markdown);
+            copy_if(*copy, &markdown)?;
+        }
+        Commands::Standup { date, copy } => {
+            let date = date.unwrap_or_else(|| OffsetDateTime::now_utc().date());
+            let markdown = StandupReport::from_workspace(&workspace, date)?.to_markdown();
+            print!("{}", markdown);
+            copy_if(*copy, &markdown)?;
+        }
+        Commands::Show { date, copy } => {
+            let markdown = match date {
+                Some(date) => workspace.read_date(*date)?,
+                None => workspace.today()?.ok_or(base::Error::NoToday)?,
+            }
+            .to_markdown();
+            print!("{}", markdown);
+            copy_if(*copy, &markdown)?;
+        }
+        Commands::Board => {
+            let today = workspace.today_or_create()?;
+            run_board(Board::new(today))?;
+        }
+        Commands::Done { index } => {
+            let mut today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            let position = match index {
+                Some(index) => *index,
+                None => match pick_task(&today)? {
+                    Some(position) => position,
+                    None => return Ok(()),
+                },
+            };
+            let index = today.task_index(position)?;
+            today.set_task_state(index, None, base::TaskState::Completed);
+            let opened = today.update_dependencies();
+            today.write()?;
+            println!("Completed \"{}\"", today.tasks[index].name);
+            for name in opened {
+                println!("Unblocked \"{}\"", name);
+            }
+        }
+        Commands::Review { sync } => {
+            let today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            let mut decisions = Vec::new();
+            for position in base::review::open_tasks(&today) {
+                print!("{}. {}", position, today.tasks[position - 1]);
+                decisions.push((position, ask_decision()?));
+            }
+            let (mut today, unblocked) = workspace.review(&decisions)?;
+            for name in unblocked {
+                println!("Unblocked \"{}\"", name);
+            }
+            if let Some(git) = &config.git {
+                add_shipped(git, &mut today)?;
+            }
+            let tomorrow = today.date + time::Duration::days(1);
+            if workspace.day_list.iter().any(|(date, _)| date == &tomorrow) {
+                println!("Tomorrow is planned already");
+            } else {
+                println!(
+                    "Planned tomorrow: {:?}",
+                    workspace.new_day_for(tomorrow)?.path
+                );
+            }
+            if *sync {
+                let syncer = Syncer::new(&config, &dirs.state_dir, &workspace)?;
+                print_reports(syncer.sync_date(today.date).await?);
+            }
+        }
+        Commands::Delegate { person, index } => {
+            let mut today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            let position = match index {
+                Some(index) => *index,
+                None => match pick_task(&today)? {
+                    Some(position) => position,
+                    None => return Ok(()),
+                },
+            };
+            let index = today.task_index(position)?;
+            today.tasks[index].delegate_to(person);
+            let opened = today.update_dependencies();
+            today.write()?;
+            println!("Waiting on \"{}\"", today.tasks[index].name);
+            for name in opened {
+                println!("Unblocked \"{}\"", name);
+            }
+        }
+        Commands::Pomo { index } => {
+            let mut today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            let position = match index {
+                Some(index) => *index,
+                None => match pick_task(&today)? {
+                    Some(position) => position,
+                    None => return Ok(()),
+                },
+            };
+            let index = today.task_index(position)?;
+            if today.tasks[index].state != base::TaskState::Completed {
+                today.set_task_state(index, None, base::TaskState::InProgress);
+                for name in today.update_dependencies() {
+                    println!("Unblocked \"{}\"", name);
+                }
+                today.write()?;
+            }
+            let name = today.tasks[index].name_without_spent();
+            let pomodoro = Pomodoro::new(config.pomodoro.clone());
+            let completed = run_pomodoro(pomodoro, &workspace, &today.path, &name)?;
+            println!("Finished {} pomodoros on \"{}\"", completed, name);
+        }
+        Commands::Snooze { index, until } => {
+            let position = match index {
+                Some(index) => *index,
+                None => {
+                    let today = workspace.today()?.ok_or(base::Error::NoToday)?;
+                    match pick_task(&today)? {
+                        Some(position) => position,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            let task = workspace.snooze(position, *until)?;
+            println!("Snoozed \"{}\" until {}", task.name, until);
+        }
+        #[cfg(feature = "encryption")]
+        Commands::Encrypt { decrypt } => {
+            let key = config.key()?.ok_or_else(|| {
+                base::Error::Config(
+                    "set `encryption` in the config to convert the day files".to_string(),
+                )
+            })?;
+            let mut converted = 0;
+            for path in workspace.encrypted_files()? {
+                if base::encryption::convert_file(&path, &key, !decrypt)? {
+                    converted += 1;
+                }
+            }
+            match decrypt {
+                true => println!("Decrypted {} files", converted),
+                false => {
+                    println!("Encrypted {} files", converted);
+                    for path in base::encryption::remove_plain_text_stores(&dirs.state_dir)? {
+                        println!("Removed {:?}", path);
+                    }
+                }
+            }
+        }
+        Commands::Backup { to } => {
+            let dir = to
+                .clone()
+                .or_else(|| config.backup.dir.clone())
+                .unwrap_or_else(|| dirs.state_dir.join("backups"));
+            let sources = [
+                ("config", config_path.as_path()),
+                ("work", config.work_dir.as_path()),
+                ("state", dirs.state_dir.as_path()),
+            ];
+            let path = base::backup::create(&dir, &sources, OffsetDateTime::now_utc())?;
+            println!("Wrote {:?}", path);
+            if let Some(keep) = config.backup.keep {
+                for removed in base::backup::prune(&dir, keep)? {
+                    println!("Removed {:?}", removed);
+                }
+            }
+        }
+        Commands::Rm { date, force } => {
+            if *force {
+                workspace.lock_after = None;
+            }
+            let trashed = workspace.trash(*date)?;
+            println!("Moved {} to {:?}", date, trashed);
+            // The next sync cleans up as well while the day is in the trash.
+            let syncer = Syncer::new(&config, &dirs.state_dir, &workspace)?;
+            match syncer.delete(*date).await {
+                Ok(reports) => print_reports(reports),
+                Err(err) => eprintln!("Could not remove what was synced for {}: {}", date, err),
+            }
+        }
+        Commands::Trash { command: None } => {
+            for (date, path) in workspace.trashed()?.iter() {
+                println!("{}  {:?}", date, path);
+            }
+        }
+        Commands::Trash {
+            command: Some(TrashCommand::Restore { date }),
+        } => {
+            let path = workspace.restore_trashed(*date)?;
+            println!("Restored {:?}", path);
+        }
+        Commands::Migrate {
+            command: MigrateCommand::Names { dry_run, from },
+        } => {
+            let from = match from {
+                Some(date_format) => Some(
+                    base::ObsidianConfig {
+                        date_format: date_format.clone(),
+                    }
+                    .file_format()?,
+                ),
+                None => None,
+            };
+            let moves = base::migrate::rename_moves(&workspace, from.as_ref())?;
+            migrate_days(&mut workspace, &moves, *dry_run)?;
+        }
+        Commands::Migrate {
+            command: MigrateCommand::Layout { dry_run },
+        } => {
+            let moves = base::migrate::layout_moves(&workspace);
+            migrate_days(&mut workspace, &moves, *dry_run)?;
+        }
+        Commands::Migrate {
+            command: MigrateCommand::State,
+        } => {
+            let migration = sync::migrate_state(&config, &dirs.state_dir, &workspace)?;
+            for key in &migration.imported {
+                println!("Imported {}", key);
+            }
+            for path in &migration.removed {
+                println!("Removed {:?}", path);
+            }
+            for warning in &migration.warnings {
+                println!("warning: {}", warning);
+            }
+        }
+        Commands::Meeting {
+            command: MeetingCommand::New { title, time, with },
+        } => {
+            let mut today = workspace.today_or_create()?;
+            let meeting = base::meeting::Meeting::new(title, time.as_deref(), with);
+            base::meeting::add(&mut today, &meeting);
+            today.write()?;
+            println!("Added {} to {:?}", title, today.path);
+        }
+        Commands::Meeting {
+            command: MeetingCommand::Actions { title },
+        } => {
+            let mut today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            let added = base::meeting::extract_actions(&mut today, title.as_deref())?;
+            if !added.is_empty() {
+                today.write()?;
+            }
+            for task in &added {
+                println!("{}", task.name);
+            }
+            println!("Added {} action items", added.len());
+        }
+        Commands::Triage => {
+            // The buffer is a plain text file outside of the workspace.
+            if workspace.is_encrypted() {
+                return Err(base::Error::NotEncrypted("Triage".to_string()).into());
+            }
+            let today = workspace.today()?.ok_or(base::Error::NoToday)?;
+            match triage(&today)? {
+                Some(mut triaged) => {
+                    for name in triaged.update_dependencies() {
+                        println!("Unblocked \"{}\"", name);
+                    }
+                    triaged.write()?;
+                    println!("Triaged {} tasks", triaged.tasks.len());
+                }
+                None => println!("Empty buffer, today is left as it is"),
+            }
+        }
+        Commands::Stale { days } => {
+            let report = DebtReport::from_workspace(&workspace, None)?;
+            for task in report.stale(*days) {
+                println!(
+                    "{:>4} days  {} (since {})",
+                    task.age, task.display_name, task.first_seen
+                );
+            }
+        }
+        #[cfg(feature = "index")]
+        Commands::Query {
+            text,
+            state,
+            tag,
+            from,
+            to,
+        } => {
+            let mut query = TaskQuery::new();
+            if let Some(state) = state {
+                query = query.state(match state {
+                    QueryState::Open => base::TaskState::Incomplete,
+                    QueryState::InProgress => base::TaskState::InProgress,
+                    QueryState::Blocked => base::TaskState::Blocked,
+                    QueryState::Waiting => base::TaskState::Waiting,
+                    QueryState::Completed => base::TaskState::Completed,
+                });
+            }
+            if let Some(tag) = tag {
+                query = query.tag(tag);
+            }
+            query = match (from, to) {
+                (Some(from), Some(to)) => query.in_range(*from..=*to),
+                (Some(from), None) => query.in_range(*from..),
+                (None, Some(to)) => query.in_range(..=*to),
+                (None, None) => query,
+            };
+            if let Some(text) = text {
+                query = query.text(text);
+            }
+            // Only the days that changed since the last query are indexed
+            // again.
+            let mut index = base::index::TaskIndex::open(&dirs.state_dir)?;
+            index.update(&workspace)?;
+            for task in index.query(&query)? {
+                let indent = if task.parent.is_some() { "  " } else { "" };
+                println!(
+                    "{}  {}{} {}",
+                    task.date,
+                    indent,
+                    task.state.marker(&workspace.style),
+                    task.name
+                );
+            }
+        }
+        #[cfg(feature = "search")]
+        Commands::Search { query, limit } => {
+            let mut index = base::search::SearchIndex::open(&dirs.state_dir)?;
+            index.update(&workspace)?;
+            for hit in index.search(query, *limit)? {
+                let kind = match hit.kind {
+                    base::search::HitKind::Task => "task ",
+                    base::search::HitKind::Notes => "notes",
+                };
+                println!("{}  {}  {}", hit.date, kind, hit.snippet);
+            }
         }
-        Commands::Sync => {
-            let syncer = Syncer::new(&config, proj_dirs.data_local_dir(), &workspace)?;
-            syncer.sync().await?;
+        Commands::Grep {
+            pattern,
+            context,
+            ignore_case,
+        } => {
+            let regex = regex::RegexBuilder::new(pattern)
+                .case_insensitive(*ignore_case)
+                .build()?;
+            for (index, file) in base::grep::grep(&workspace, &regex, *context)?
+                .iter()
+                .enumerate()
+            {
+                if index > 0 {
+                    println!();
+                }
+                println!("{}  {}", file.date, file.path.display());
+                for (index, hunk) in file.hunks.iter().enumerate() {
+                    if index > 0 {
+                        println!("--");
+                    }
+                    for line in hunk {
+                        let separator = if line.matched { ':' } else { '-' };
+                        let line = format!("{:>4}{} {}", line.number, separator, line.content);
+                        println!("{}", line.trim_end());
+                    }
+                }
+            }
+        }
+        Commands::Fmt { check } => {
+            let changed = base::format::format(&workspace, *check)?;
+            for path in &changed {
+                match check {
+                    true => println!("{}", path.display()),
+                    false => println!("Formatted {}", path.display()),
+                }
+            }
+            if *check && !changed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Time {
+            command:
+                TimeCommand::Report {
+                    week: _,
+                    month,
+                    date,
+                    format,
+                },
+        } => {
+            let date = date.unwrap_or_else(|| OffsetDateTime::now_utc().date());
+            let period = match month {
+                true => Period::month(date),
+                false => Period::week(date),
+            };
+            let report = TimeReport::from_workspace(&workspace, period)?;
+            match format {
+                ReportFormat::Markdown => print!("{}", report.to_markdown()),
+                ReportFormat::Json => println!("{:#}", report.to_json()),
+            }
+        }
+        Commands::Config { .. } | Commands::Auth { .. } | Commands::Restore { .. } => {
+            unreachable!("handled before loading the config")
+        }
+        Commands::Lint { .. } | Commands::Lsp => {
+            unreachable!("handled before loading the workspace")
+        }
+    }
 
-            println!("Syncing...");
+    Ok(())
+}
+
+/// Puts the text on the clipboard when `--copy` is passed.
+fn copy_if(copy: bool, text: &str) -> anyhow::Result<()> {
+    if !copy {
+        return Ok(());
+    }
+    let copied = arboard::Clipboard::new().and_then(|mut clipboard| {
+        // On Linux the text is only on the clipboard while w0rk runs, so
+        // give a clipboard manager a moment to take it over.
+        #[cfg(target_os = "linux")]
+        {
+            use arboard::SetExtLinux;
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(1);
+            clipboard.set().wait_until(deadline).text(text)
         }
+        #[cfg(not(target_os = "linux"))]
+        clipboard.set_text(text)
+    });
+    match copied {
+        Ok(()) => eprintln!("Copied to the clipboard"),
+        Err(err) => return Err(anyhow::anyhow!("Could not copy to the clipboard: {}", err)),
     }
+    Ok(())
+}
 
+/// Lets the user pick a task of the day by typing part of its name, the
+/// best matches first. Returns its position in the file (from 1), or `None`
+/// when cancelled with Esc.
+fn pick_task(day: &base::Day) -> anyhow::Result<Option<usize>> {
+    if day.tasks.is_empty() {
+        return Err(anyhow::anyhow!("There are no tasks today"));
+    }
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen)?;
+    let result = picker_loop(day, &mut stdout);
+    execute!(stdout, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn picker_loop(day: &base::Day, stdout: &mut io::Stdout) -> anyhow::Result<Option<usize>> {
+    let matcher = SkimMatcherV2::default();
+    let mut query = String::new();
+    let mut selected = 0;
+    loop {
+        let mut matches: Vec<(i64, usize)> = day
+            .tasks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, task)| {
+                let score = matcher.fuzzy_match(&task.name, &query)?;
+                Some((score, index + 1))
+            })
+            .collect();
+        matches.sort_by_key(|(score, position)| (-score, *position));
+        selected = selected.min(matches.len().saturating_sub(1));
+
+        let (width, height) = terminal::size()?;
+        queue!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0),
+            Print(format!("> {}", query))
+        )?;
+        let rows = height.saturating_sub(1) as usize;
+        for (row, (_, position)) in matches.iter().enumerate().take(rows) {
+            let task = &day.tasks[position - 1];
+            let line = format!("{:>3}  [{}] {}", position, task.state, task.name);
+            queue!(stdout, cursor::MoveTo(0, row as u16 + 1))?;
+            if row == selected {
+                queue!(stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                stdout,
+                Print(line.chars().take(width as usize).collect::<String>()),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        queue!(stdout, cursor::MoveTo(query.chars().count() as u16 + 2, 0))?;
+        stdout.flush()?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Enter => {
+                if let Some((_, position)) = matches.get(selected) {
+                    return Ok(Some(*position));
+                }
+            }
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Runs the timer until `q`, returning the number of finished pomodoros.
+fn run_pomodoro(
+    mut pomodoro: Pomodoro,
+    workspace: &base::Workspace,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<u32> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+    let result = pomodoro_loop(&mut pomodoro, workspace, path, name, &mut stdout);
+    execute!(stdout, cursor::Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result.map(|_| pomodoro.completed)
+}
+
+fn pomodoro_loop(
+    pomodoro: &mut Pomodoro,
+    workspace: &base::Workspace,
+    path: &Path,
+    name: &str,
+    stdout: &mut io::Stdout,
+) -> anyhow::Result<()> {
+    let mut started = Instant::now();
+    let mut paused: Option<Instant> = None;
+    loop {
+        let elapsed = paused.unwrap_or_else(Instant::now) - started;
+        let duration = std::time::Duration::try_from(pomodoro.duration())?;
+        if elapsed >= duration {
+            if pomodoro.phase == Phase::Work {
+                // Read the day again, it may have changed since the start.
+                let mut day = workspace.read_day(path)?;
+                if base::pomodoro::log(&mut day, name, pomodoro.duration()) {
+                    day.write()?;
+                }
+            }
+            pomodoro.next();
+            started = Instant::now();
+            // Ring the terminal bell at the end of every phase.
+            execute!(stdout, Print("\x07"))?;
+            continue;
+        }
+        draw_pomodoro(pomodoro, name, duration - elapsed, paused.is_some(), stdout)?;
+
+        if !event::poll(std::time::Duration::from_millis(250))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('s') => {
+                    pomodoro.skip();
+                    started = Instant::now();
+                    paused = None;
+                }
+                KeyCode::Char('p') | KeyCode::Char(' ') => match paused.take() {
+                    Some(at) => started += at.elapsed(),
+                    None => paused = Some(Instant::now()),
+                },
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+fn draw_pomodoro(
+    pomodoro: &Pomodoro,
+    name: &str,
+    remaining: std::time::Duration,
+    paused: bool,
+    stdout: &mut io::Stdout,
+) -> anyhow::Result<()> {
+    let phase = match pomodoro.phase {
+        Phase::Work => "Work",
+        Phase::ShortBreak => "Short break",
+        Phase::LongBreak => "Long break",
+    };
+    let seconds = remaining.as_secs();
+    let mut clock = format!("{:02}:{:02}", seconds / 60, seconds % 60);
+    if paused {
+        clock.push_str(" (paused)");
+    }
+    queue!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        SetAttribute(Attribute::Bold),
+        Print(format!("{}  {}", phase, clock)),
+        SetAttribute(Attribute::Reset),
+        cursor::MoveTo(0, 2),
+        Print(name),
+        cursor::MoveTo(0, 4),
+        Print(format!(
+            "Pomodoros: {}   p pause, s skip, q quit",
+            pomodoro.completed
+        ))
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Shows the board until `q`, writing today's file after every move.
+fn run_board(mut board: Board) -> anyhow::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+    let result = board_loop(&mut board, &mut stdout);
+    execute!(stdout, cursor::Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn board_loop(board: &mut Board, stdout: &mut io::Stdout) -> anyhow::Result<()> {
+    loop {
+        draw_board(board, stdout)?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let (direction, move_task) = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => (Direction::Up, false),
+            KeyCode::Down | KeyCode::Char('j') => (Direction::Down, false),
+            KeyCode::Left | KeyCode::Char('h') => (Direction::Left, shift),
+            KeyCode::Right | KeyCode::Char('l') => (Direction::Right, shift),
+            KeyCode::Char('H') => (Direction::Left, true),
+            KeyCode::Char('L') => (Direction::Right, true),
+            _ => continue,
+        };
+        match move_task {
+            true if board.move_selected(direction) => board.day.write()?,
+            true => {}
+            false => board.select(direction),
+        }
+    }
+}
+
+fn draw_board(board: &Board, stdout: &mut io::Stdout) -> anyhow::Result<()> {
+    let (width, height) = terminal::size()?;
+    let column_width = width as usize / COLUMNS.len();
+    // The text of a column leaves a space before the next one.
+    let fit =
+        |text: &str| -> String { text.chars().take(column_width.saturating_sub(1)).collect() };
+    queue!(stdout, terminal::Clear(ClearType::All))?;
+    for (column, state) in COLUMNS.iter().enumerate() {
+        let x = (column * column_width) as u16;
+        let tasks = board.column_tasks(column);
+        let title = match state {
+            base::TaskState::Incomplete => "Open",
+            base::TaskState::InProgress => "In progress",
+            base::TaskState::Blocked => "Blocked",
+            base::TaskState::Waiting => "Waiting",
+            base::TaskState::Completed => "Completed",
+        };
+        queue!(
+            stdout,
+            cursor::MoveTo(x, 0),
+            SetAttribute(Attribute::Bold),
+            Print(fit(&format!("{} ({})", title, tasks.len()))),
+            SetAttribute(Attribute::Reset)
+        )?;
+        let rows = height.saturating_sub(3) as usize;
+        for (row, index) in tasks.iter().enumerate().take(rows) {
+            queue!(stdout, cursor::MoveTo(x, row as u16 + 2))?;
+            if board.cursor() == (column, row) {
+                queue!(stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                stdout,
+                Print(fit(&board.day.tasks[*index].name)),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+    }
+    queue!(
+        stdout,
+        cursor::MoveTo(0, height.saturating_sub(1)),
+        Print("arrows or hjkl: select  shift+arrows or H/L: move  q: quit")
+    )?;
+    stdout.flush()?;
     Ok(())
 }
+
+/// Writes the commits of the day in the repositories of `git` to its notes.
+fn add_shipped(git: &base::GitConfig, day: &mut base::Day) -> anyhow::Result<()> {
+    let (commits, errors) = base::git::commits(git, day.date);
+    for err in errors {
+        eprintln!("Skipped a repository: {}", err);
+    }
+    if base::git::set_shipped(day, &commits) {
+        day.write()?;
+        println!("Shipped {} commits", commits.len());
+    }
+    Ok(())
+}
+
+fn init_config(dirs: &Dirs, force: bool) -> anyhow::Result<PathBuf> {
+    let existing = Config::find_path(&dirs.config_dir);
+    if existing.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{:?} already exists, use --force to overwrite it",
+            existing
+        ));
+    }
+
+    let config_path = dirs.config_dir.join("config.toml");
+    fs::create_dir_all(&dirs.config_dir)?;
+    fs::write(&config_path, Config::template(&dirs.default_work_dir))?;
+    // The template is TOML, which comes first, a config in another format
+    // isn't read from now on.
+    for other in Config::shadowed_paths(&dirs.config_dir) {
+        eprintln!("{:?} is no longer read, remove it", other);
+    }
+    Ok(config_path)
+}
+
+/// Lists the moves of `w0rk migrate` and makes them, unless it's a dry run.
+fn migrate_days(
+    workspace: &mut base::Workspace,
+    moves: &[base::migrate::Move],
+    dry_run: bool,
+) -> Result<(), base::Error> {
+    for (from, to) in moves {
+        println!("{:?} -> {:?}", from, to);
+    }
+    if !dry_run {
+        base::migrate::apply(workspace, moves)?;
+        println!("Moved {} day files", moves.len());
+    }
+    Ok(())
+}
+
+fn print_reports(reports: Vec<sync::SinkReport>) {
+    for report in reports {
+        match report.date {
+            Some(date) => println!("{} {}: {:?}", date, report.sink, report.action),
+            None => println!("{}: {:?}", report.sink, report.action),
+        }
+        for name in report.completed {
+            println!("  completed: {}", name);
+        }
+        for warning in report.warnings {
+            println!("  warning: {}", warning);
+        }
+    }
+}
+
+fn open_editor(path: &Path) -> anyhow::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // The editor may come with arguments, like `code --wait`.
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("vi");
+    let status = Command::new(program).args(args).arg(path).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", editor, status));
+    }
+    Ok(())
+}
+
+fn edit_again() -> anyhow::Result<bool> {
+    Ok(!prompt("Edit again? [Y/n] ")?.eq_ignore_ascii_case("n"))
+}
+
+/// Asks what to do with a task in `w0rk review`, until the answer is one of
+/// the options. Carrying it over is the default.
+fn ask_decision() -> anyhow::Result<Decision> {
+    loop {
+        match prompt("   [c]omplete, ca[r]ry, [d]efer, ca[n]cel or dro[p]? [r] ")?.as_str() {
+            "c" => return Ok(Decision::Complete),
+            "" | "r" => return Ok(Decision::Carry),
+            "d" => match base::parse_natural_date(&prompt("   Defer until: ")?) {
+                Ok(date) => return Ok(Decision::Defer(date)),
+                Err(err) => eprintln!("   {}", err),
+            },
+            "n" => return Ok(Decision::Cancel),
+            "p" => return Ok(Decision::Drop),
+            _ => {}
+        }
+    }
+}
+
+/// The answer to the question on stdin, trimmed and in lowercase.
+fn prompt(question: &str) -> anyhow::Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_lowercase())
+}
+
+// Opens the editor until the config is valid, or the user gives up.
+fn edit_config(config_path: &Path) -> anyhow::Result<()> {
+    loop {
+        open_editor(config_path)?;
+
+        match Config::from_path(config_path) {
+            Ok(_) => {
+                println!("Config is valid");
+                return Ok(());
+            }
+            Err(err) => {
+                println!("{}", err);
+                if !edit_again()? {
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+}
+
+// Opens today's tasks in the editor until the buffer parses, or the user
+// gives up. The buffer is kept in a temporary file.
+fn triage(today: &base::Day) -> anyhow::Result<Option<base::Day>> {
+    let path = std::env::temp_dir().join(format!("w0rk-triage-{}.md", today.date));
+    fs::write(&path, base::triage::to_buffer(today)?)?;
+
+    let result = loop {
+        open_editor(&path)?;
+
+        match base::triage::apply(today, &fs::read_to_string(&path)?) {
+            Ok(triaged) => break Ok(triaged),
+            Err(err) => {
+                println!("{}", err);
+                if !edit_again()? {
+                    break Err(err.into());
+                }
+            }
+        }
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
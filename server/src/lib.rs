@@ -0,0 +1,414 @@
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, patch, post};
+use axum::{Json, Router};
+use base::{Config, Day, Task, TaskState, Workspace};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use sync::{SinkAction, SinkReport, SyncError, Syncer};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Base(#[from] base::Error),
+    #[error("{0}")]
+    Sync(#[from] SyncError),
+    #[error("Invalid task name: \"{0}\"")]
+    InvalidTaskName(String),
+    #[error("Invalid date: \"{0}\"")]
+    InvalidDate(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Set a `token` in the `server` section of the config")]
+    EmptyToken,
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ServerError::InvalidTaskName(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServerError::InvalidDate(_) => StatusCode::BAD_REQUEST,
+            ServerError::NotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (
+            status,
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+pub struct ServerState {
+    config: Config,
+    token: String,
+    state_dir: PathBuf,
+    // Requests that write to the day files take turns.
+    lock: Mutex<()>,
+}
+
+impl ServerState {
+    /// Refuses an empty token, which would let every request in.
+    pub fn new(
+        config: Config,
+        token: &str,
+        state_dir: &std::path::Path,
+    ) -> Result<Arc<Self>, ServerError> {
+        if token.trim().is_empty() {
+            return Err(ServerError::EmptyToken);
+        }
+        Ok(Arc::new(Self {
+            config,
+            token: token.to_string(),
+            state_dir: state_dir.into(),
+            lock: Mutex::new(()),
+        }))
+    }
+
+    async fn workspace(&self) -> Result<Workspace, ServerError> {
+        Ok(self.config.workspace_async().await?)
+    }
+
+    async fn day(&self, date: &str) -> Result<Day, ServerError> {
+        let date =
+            base::parse_date(date).map_err(|_| ServerError::InvalidDate(date.to_string()))?;
+        let workspace = self.workspace().await?;
+        match workspace.day_list.iter().find(|(day, _)| day == &date) {
+            Some((_, path)) => Ok(workspace.read_day_async(path).await?),
+            None => Err(ServerError::NotFound(format!("day {}", date))),
+        }
+    }
+}
+
+/// The inbound endpoint plus read and write access to the workspace:
+///
+/// - `GET /days` lists the dates of the days
+/// - `GET /days/:date` returns the tasks and notes of a day
+/// - `PATCH /days/:date/tasks/:index` sets the state of a task, with
+///   `/subtasks/:subtask` for a subtask, e.g. `{"state": "completed"}`
+/// - `POST /sync` syncs today to the configured sinks
+pub fn api_router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/days", get(list_days))
+        .route("/days/:date", get(get_day))
+        .route("/days/:date/tasks/:index", patch(update_task))
+        .route(
+            "/days/:date/tasks/:index/subtasks/:subtask",
+            patch(update_subtask),
+        )
+        .route("/sync", post(sync))
+        .merge(inbound_routes())
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+/// `POST /tasks {"name": "..."}` appends a task to today's file, creating it
+/// when needed, so tasks can be captured from a phone or an email rule.
+pub fn inbound_router(state: Arc<ServerState>) -> Router {
+    inbound_routes()
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+fn inbound_routes() -> Router<Arc<ServerState>> {
+    Router::new().route("/tasks", post(add_task))
+}
+
+pub async fn serve(router: Router, addr: &str) -> Result<(), ServerError> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+async fn authenticate(
+    State(state): State<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // In constant time, so the token can't be guessed from the timing.
+        .is_some_and(|token| bool::from(token.as_bytes().ct_eq(state.token.as_bytes())));
+
+    match authorized {
+        true => next.run(request).await,
+        false => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewTask {
+    name: String,
+}
+
+#[derive(Serialize, Debug)]
+struct DayResponse {
+    date: String,
+    tasks: Vec<Task>,
+    notes: String,
+}
+
+impl From<Day> for DayResponse {
+    fn from(day: Day) -> Self {
+        DayResponse {
+            date: day.date.to_string(),
+            tasks: day.tasks,
+            notes: day.notes,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TaskUpdate {
+    state: TaskState,
+}
+
+#[derive(Serialize)]
+struct ReportResponse {
+    sink: String,
+    action: SinkAction,
+    date: Option<String>,
+    completed: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl From<SinkReport> for ReportResponse {
+    fn from(report: SinkReport) -> Self {
+        ReportResponse {
+            sink: report.sink,
+            action: report.action,
+            date: report.date.map(|date| date.to_string()),
+            completed: report.completed,
+            warnings: report.warnings,
+        }
+    }
+}
+
+async fn list_days(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<String>>, ServerError> {
+    let workspace = state.workspace().await?;
+    Ok(Json(
+        workspace
+            .day_list
+            .iter()
+            .map(|(date, _)| date.to_string())
+            .collect(),
+    ))
+}
+
+async fn get_day(
+    State(state): State<Arc<ServerState>>,
+    Path(date): Path<String>,
+) -> Result<Json<DayResponse>, ServerError> {
+    Ok(Json(state.day(&date).await?.into()))
+}
+
+async fn update_task(
+    State(state): State<Arc<ServerState>>,
+    Path((date, index)): Path<(String, usize)>,
+    Json(update): Json<TaskUpdate>,
+) -> Result<Json<DayResponse>, ServerError> {
+    set_task_state(&state, &date, index, None, update.state).await
+}
+
+async fn update_subtask(
+    State(state): State<Arc<ServerState>>,
+    Path((date, index, subtask)): Path<(String, usize, usize)>,
+    Json(update): Json<TaskUpdate>,
+) -> Result<Json<DayResponse>, ServerError> {
+    set_task_state(&state, &date, index, Some(subtask), update.state).await
+}
+
+async fn set_task_state(
+    state: &ServerState,
+    date: &str,
+    index: usize,
+    subtask: Option<usize>,
+    task_state: TaskState,
+) -> Result<Json<DayResponse>, ServerError> {
+    let _lock = state.lock.lock().await;
+    let mut day = state.day(date).await?;
+    if !day.set_task_state(index, subtask, task_state) {
+        return Err(ServerError::NotFound(format!("task {}", index)));
+    }
+    day.update_dependencies();
+    day.write()?;
+    Ok(Json(day.into()))
+}
+
+async fn sync(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<ReportResponse>>, ServerError> {
+    let _lock = state.lock.lock().await;
+    let workspace = state.workspace().await?;
+    let syncer = Syncer::new(&state.config, &state.state_dir, &workspace)?;
+    let reports = syncer.sync().await?;
+    Ok(Json(
+        reports.into_iter().map(ReportResponse::from).collect(),
+    ))
+}
+
+async fn add_task(
+    State(state): State<Arc<ServerState>>,
+    Json(new_task): Json<NewTask>,
+) -> Result<(StatusCode, Json<Task>), ServerError> {
+    let name = new_task.name.trim();
+    if name.is_empty() || name.contains('\n') {
+        return Err(ServerError::InvalidTaskName(new_task.name));
+    }
+    let task: Task = format!("* [ ] {}", name)
+        .as_str()
+        .try_into()
+        .map_err(|_| ServerError::InvalidTaskName(new_task.name.clone()))?;
+
+    let _lock = state.lock.lock().await;
+    let workspace = state.workspace().await?;
+    let mut today: Day = workspace.today_or_create()?;
+    today.tasks.push(task.clone());
+    today.write()?;
+
+    Ok((StatusCode::CREATED, Json(task)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn request(token: &str, body: &str) -> Request {
+        Request::post("/tasks")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api() {
+        let dir = std::env::temp_dir().join("w0rk-test-api");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("2024-07-01.md"),
+            "* [ ] Deploy\n* [ ] Release\n  * [ ] Changelog\n",
+        )
+        .unwrap();
+        let config = Config {
+            work_dir: dir.clone(),
+            ..Config::default()
+        };
+        assert!(matches!(
+            ServerState::new(config.clone(), "", &dir.join("state")),
+            Err(ServerError::EmptyToken)
+        ));
+        let router = api_router(ServerState::new(config, "secret", &dir.join("state")).unwrap());
+        let authorized = |request: axum::http::request::Builder| {
+            request.header(header::AUTHORIZATION, "Bearer secret")
+        };
+
+        let response = router
+            .clone()
+            .oneshot(
+                authorized(Request::get("/days"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json(response).await, serde_json::json!(["2024-07-01"]));
+
+        let response = router
+            .clone()
+            .oneshot(
+                authorized(Request::patch("/days/2024-07-01/tasks/1/subtasks/0"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"state": "completed"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["tasks"][1]["state"], "completed");
+
+        let response = router
+            .clone()
+            .oneshot(
+                authorized(Request::get("/days/2024-07-02"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = router
+            .oneshot(Request::get("/days").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_add_task() {
+        let dir = std::env::temp_dir().join("w0rk-test-inbound");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            work_dir: dir.clone(),
+            ..Config::default()
+        };
+        let router =
+            inbound_router(ServerState::new(config, "secret", &dir.join("state")).unwrap());
+
+        let response = router
+            .clone()
+            .oneshot(request("wrong", r#"{"name": "Call the bank"}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .clone()
+            .oneshot(request("secret", r#"{"name": "  "}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = router
+            .oneshot(request("secret", r#"{"name": "Call the bank"}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let today = Workspace::from_path(&dir)
+            .unwrap()
+            .today()
+            .unwrap()
+            .unwrap();
+        assert_eq!(today.tasks[0].name, "Call the bank");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
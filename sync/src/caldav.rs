@@ -0,0 +1,161 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::SyncError;
+use async_trait::async_trait;
+use base::{ics, CaldavConfig, Day, Rewrite, Task, TaskState};
+use std::path::Path;
+use time::OffsetDateTime;
+
+pub type CaldavSyncState = Vec<CaldavTaskState>;
+
+/// Maps a task (by name) to the UID of its `VTODO` on the server. Carried
+/// over tasks keep their name, so they keep pointing at the same `VTODO`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CaldavTaskState {
+    pub url: String,
+    pub uid: String,
+    pub name: String,
+}
+
+pub struct Caldav {
+    client: reqwest::Client,
+    config: CaldavConfig,
+    store: Store,
+    state: CaldavSyncState,
+}
+
+impl Caldav {
+    pub fn new(
+        state_dir: &Path,
+        config: &CaldavConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "caldav")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    fn todo_url(&self, uid: &str) -> String {
+        format!("{}/{}.ics", self.config.url.trim_end_matches('/'), uid)
+    }
+
+    /// Pushes the state of every task that isn't done yet (or was completed
+    /// locally) to the server, returning the tasks completed on the server.
+    pub async fn sync_tasks(&mut self, day: &Day) -> Result<Vec<String>, SyncError> {
+        let mut completed = Vec::new();
+
+        for task in day.tasks.iter() {
+            let leaves = match task.has_subtasks() {
+                true => task.subtasks.iter().collect(),
+                false => vec![task],
+            };
+            for leaf in leaves {
+                if self.sync_task(leaf, &day.rewrites).await? {
+                    completed.push(leaf.name.clone());
+                }
+            }
+        }
+
+        self.write_state()?;
+        Ok(completed)
+    }
+
+    // Returns whether the task was completed on the server.
+    async fn sync_task(&mut self, task: &Task, rewrites: &[Rewrite]) -> Result<bool, SyncError> {
+        let uid = self
+            .state
+            .iter()
+            .find(|state| state.name == task.display_name() && state.url == self.config.url)
+            .map(|state| state.uid.clone());
+
+        match uid {
+            Some(uid) => {
+                let remote_state = self.fetch_state(&uid).await?;
+                if remote_state == Some(TaskState::Completed) && task.state != TaskState::Completed
+                {
+                    return Ok(true);
+                }
+                self.put(task, rewrites, &uid).await?;
+            }
+            None if task.state != TaskState::Completed => {
+                let uid = format!(
+                    "w0rk-{}-{}",
+                    OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                    self.state.len()
+                );
+                self.put(task, rewrites, &uid).await?;
+                self.state.push(CaldavTaskState {
+                    url: self.config.url.clone(),
+                    uid,
+                    name: task.display_name(),
+                });
+            }
+            None => {}
+        }
+
+        Ok(false)
+    }
+
+    async fn fetch_state(&self, uid: &str) -> Result<Option<TaskState>, SyncError> {
+        let response = self
+            .client
+            .get(self.todo_url(uid))
+            .basic_auth(&self.config.username, Some(&self.config.password))
+            .send()
+            .await?;
+
+        // Removed on the server, it'll be recreated by the next put.
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let calendar = response.error_for_status()?.text().await?;
+        Ok(ics::todo_state(&calendar))
+    }
+
+    async fn put(&self, task: &Task, rewrites: &[Rewrite], uid: &str) -> Result<(), SyncError> {
+        let calendar = ics::todo_calendar(task, rewrites, uid, OffsetDateTime::now_utc())?;
+
+        self.client
+            .put(self.todo_url(uid))
+            .basic_auth(&self.config.username, Some(&self.config.password))
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .body(calendar)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for Caldav {
+    fn name(&self) -> &str {
+        "caldav"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let mut report = SinkReport::new(self.name(), SinkAction::Updated);
+        report.completed = self.sync_tasks(day).await?;
+        Ok(report)
+    }
+
+    // The tasks on the server are the open ones of today.
+    async fn backfill(&mut self, _day: &Day) -> Result<SinkReport, SyncError> {
+        Ok(SinkReport::new(self.name(), SinkAction::Skipped))
+    }
+}
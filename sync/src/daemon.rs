@@ -0,0 +1,345 @@
+use super::notification::Notifications;
+use super::reminder::Reminders;
+use super::{SinkReport, SyncError, Syncer};
+use base::Config;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use time::OffsetDateTime;
+
+// How often the daemon wakes up to check for changes and due syncs.
+const TICK: Duration = Duration::from_secs(2);
+// A status without a heartbeat for this long belongs to a daemon that's gone.
+const STALE_AFTER: time::Duration = time::Duration::seconds(15);
+
+/// Written by the running daemon on every tick, read by `w0rk daemon status`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub heartbeat: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_sync: Option<OffsetDateTime>,
+    pub last_error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_sync: OffsetDateTime,
+}
+
+impl DaemonStatus {
+    pub fn read(state_dir: &Path) -> Result<Option<Self>, SyncError> {
+        let path = status_path(state_dir);
+        match path.exists() {
+            true => Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?)),
+            false => Ok(None),
+        }
+    }
+
+    fn write(&self, state_dir: &Path) -> Result<(), SyncError> {
+        let state_file = fs::File::create(status_path(state_dir))?;
+        serde_json::to_writer(state_file, self)?;
+        Ok(())
+    }
+
+    pub fn is_running(&self, now: OffsetDateTime) -> bool {
+        now - self.heartbeat < STALE_AFTER
+    }
+}
+
+fn status_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("daemon.json")
+}
+
+/// Syncs at the configured interval and whenever a day file changes, and
+/// posts the reminders that are due. Only one daemon runs at a time.
+pub struct Daemon {
+    config: Config,
+    state_dir: PathBuf,
+    // The config file to reload on changes, with its last modification time.
+    config_path: Option<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Daemon {
+    pub fn new(config: Config, state_dir: &Path) -> Result<Self, SyncError> {
+        fs::create_dir_all(state_dir)?;
+
+        Ok(Self {
+            config,
+            state_dir: state_dir.into(),
+            config_path: None,
+        })
+    }
+
+    /// Reloads the config when the file changes, a config with errors is
+    /// reported and the current one is kept.
+    pub fn watch_config(mut self, path: &Path) -> Self {
+        self.config_path = Some((path.into(), modified(path)));
+        self
+    }
+
+    // Returns whether a changed config was loaded.
+    fn reload_config(&mut self) -> Result<bool, base::Error> {
+        let (path, last_modified) = match &mut self.config_path {
+            Some(config_path) => config_path,
+            None => return Ok(false),
+        };
+        let current = modified(path);
+        if current == *last_modified {
+            return Ok(false);
+        }
+        *last_modified = current;
+
+        self.config = Config::from_path(path)?;
+        Ok(true)
+    }
+
+    /// Runs until interrupted, the sync in progress is finished before exiting.
+    pub async fn run(&mut self) -> Result<(), SyncError> {
+        let now = OffsetDateTime::now_utc();
+        if let Some(status) = DaemonStatus::read(&self.state_dir)? {
+            if status.is_running(now) {
+                return Err(SyncError::DaemonRunning(status.pid));
+            }
+        }
+
+        let mut status = DaemonStatus {
+            pid: std::process::id(),
+            started_at: now,
+            heartbeat: now,
+            last_sync: None,
+            last_error: None,
+            next_sync: now,
+        };
+        let mut files = fingerprint(&self.config.work_dir);
+        // Kept until the config is reloaded, the old config is in use until then.
+        let mut reload_error: Option<String> = None;
+
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+
+        loop {
+            let now = OffsetDateTime::now_utc();
+            let reloaded = match self.reload_config() {
+                Ok(reloaded) => reloaded,
+                Err(err) => {
+                    eprintln!("{} config not reloaded: {}", now, err);
+                    reload_error = Some(format!("config not reloaded: {}", err));
+                    status.last_error = reload_error.clone();
+                    false
+                }
+            };
+            if reloaded {
+                reload_error = None;
+                println!("{} reloaded the config", now);
+                files = fingerprint(&self.config.work_dir);
+            }
+            let changed = reloaded
+                || self.config.daemon.watch && {
+                    let current = fingerprint(&self.config.work_dir);
+                    let changed = current != files;
+                    files = current;
+                    changed
+                };
+
+            if now >= status.next_sync || changed {
+                match self.sync_once(now).await {
+                    Ok(reports) => {
+                        for report in reports {
+                            println!("{} {}: {:?}", now, report.sink, report.action);
+                            for warning in report.warnings {
+                                eprintln!("{} {}: {}", now, report.sink, warning);
+                            }
+                        }
+                        status.last_error = reload_error.clone();
+                    }
+                    Err(err) => {
+                        eprintln!("{} sync failed: {}", now, err);
+                        status.last_error = Some(err.to_string());
+                    }
+                }
+                status.last_sync = Some(now);
+                status.next_sync = now + interval(&self.config);
+                // Don't pick up our own changes, like tasks completed by a sink.
+                files = fingerprint(&self.config.work_dir);
+            }
+
+            status.heartbeat = OffsetDateTime::now_utc();
+            status.write(&self.state_dir)?;
+
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = tokio::time::sleep(TICK) => {}
+            }
+        }
+
+        fs::remove_file(status_path(&self.state_dir))?;
+        Ok(())
+    }
+
+    async fn sync_once(&self, now: OffsetDateTime) -> Result<Vec<SinkReport>, SyncError> {
+        // Reloaded every time to pick up new day files.
+        let workspace = self.config.workspace_async().await?;
+
+        // A reminder that can't be posted doesn't hold up the sync.
+        let reminded = match Reminders::new(&self.config, &self.state_dir, &workspace) {
+            Ok(mut reminders) => {
+                let run = reminders.run(now).await.map(|_| ());
+                for warning in reminders.take_warnings() {
+                    eprintln!("{} reminders: {}", now, warning);
+                }
+                run
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = reminded {
+            eprintln!("{} reminders failed: {}", now, err);
+        }
+
+        let syncer = Syncer::new(&self.config, &self.state_dir, &workspace)?;
+        // Nothing to sync until the day is created, the daemon doesn't
+        // create it in the middle of the night.
+        let reports = match workspace.today()? {
+            Some(_) => syncer.sync().await?,
+            None => Vec::new(),
+        };
+
+        // After syncing, and like the reminders a desktop without
+        // notifications doesn't fail it.
+        if let Some(config) = &self.config.notifications {
+            let notified = Notifications::new(config, &self.state_dir, &workspace).and_then(
+                |mut notifications| {
+                    for warning in notifications.take_warnings() {
+                        eprintln!("{} notifications: {}", now, warning);
+                    }
+                    notifications.run(now)
+                },
+            );
+            if let Err(err) = notified {
+                eprintln!("{} notifications failed: {}", now, err);
+            }
+        }
+        Ok(reports)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn interval(config: &Config) -> time::Duration {
+    let jitter = match config.daemon.jitter {
+        0 => 0,
+        max => {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.subsec_nanos() as u64)
+                .unwrap_or_default();
+            nanos % (max + 1)
+        }
+    };
+    time::Duration::seconds((config.daemon.interval + jitter) as i64)
+}
+
+// The names and modification times of the files in the work dir, and in
+// the year and month folders of the `monthly` layout.
+fn fingerprint(work_dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files = Vec::new();
+    let mut dirs = vec![work_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let is_date_folder = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.chars().all(|c| c.is_ascii_digit()));
+            if path.is_dir() && is_date_folder {
+                dirs.push(path.clone());
+            }
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok();
+            files.push((path, modified));
+        }
+    }
+    files.sort();
+    files
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        let mut config = Config::default();
+        config.daemon.interval = 60;
+        config.daemon.jitter = 10;
+        let interval = interval(&config);
+        assert!(interval >= time::Duration::seconds(60));
+        assert!(interval <= time::Duration::seconds(70));
+
+        config.daemon.jitter = 0;
+        assert_eq!(super::interval(&config), time::Duration::seconds(60));
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let dir = std::env::temp_dir().join("w0rk-test-daemon-reload");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let touch = |content: &str, seconds: u64| {
+            fs::write(&path, content).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+                .unwrap();
+        };
+
+        touch("work_dir = \"/tmp/a\"\n", 1);
+        let config = Config::from_path(&path).unwrap();
+        let mut daemon = Daemon::new(config, &dir.join("state"))
+            .unwrap()
+            .watch_config(&path);
+        assert!(!daemon.reload_config().unwrap());
+
+        touch("work_dir = \"/tmp/b\"\n", 2);
+        assert!(daemon.reload_config().unwrap());
+        assert_eq!(daemon.config.work_dir, PathBuf::from("/tmp/b"));
+
+        // A broken config keeps the current one.
+        touch("work_dir = \"/tmp/c\"\nintervall = 1\n", 3);
+        assert!(daemon.reload_config().is_err());
+        assert_eq!(daemon.config.work_dir, PathBuf::from("/tmp/b"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_running() {
+        let now = OffsetDateTime::now_utc();
+        let status = DaemonStatus {
+            pid: 1,
+            started_at: now,
+            heartbeat: now - time::Duration::seconds(5),
+            last_sync: None,
+            last_error: None,
+            next_sync: now,
+        };
+        assert!(status.is_running(now));
+        assert!(!status.is_running(now + time::Duration::minutes(1)));
+    }
+}
@@ -0,0 +1,271 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::slack::content_hash;
+use super::state::{self, Store};
+use super::{render_text, split_text, SyncError, UnicodeEmoji};
+use async_trait::async_trait;
+use base::{Day, DiscordConfig, Rewrite, TaskState};
+use serde::Deserialize;
+use std::path::Path;
+use time::Date;
+
+pub trait DiscordMessage {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String;
+    fn date(&self) -> Date;
+}
+
+impl DiscordMessage for Day {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String {
+        render_text(self, rewrites, TaskState::to_emoji, |name| {
+            format!("**{}**", name)
+        })
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+// Discord doesn't take messages with more characters than this.
+const MAX_CONTENT: usize = 2000;
+
+pub type DiscordSyncState = Vec<DiscordDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiscordDayState {
+    pub message_id: String,
+    pub date: Date,
+    /// Messages with the lines that didn't fit in the first one.
+    #[serde(default)]
+    pub continuation_ids: Vec<String>,
+}
+
+pub struct Discord {
+    client: reqwest::Client,
+    webhook_url: String,
+    rewrites: Vec<Rewrite>,
+    store: Store,
+    state: DiscordSyncState,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Message {
+    pub id: String,
+}
+
+/// The key of the state of the webhook in the state database, every webhook
+/// keeps its own.
+fn state_key(config: &DiscordConfig) -> String {
+    let url = config.webhook_url.trim_end_matches('/');
+    format!("discord-{}", content_hash(url, &[]))
+}
+
+impl Discord {
+    pub fn new(
+        state_dir: &Path,
+        config: &DiscordConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (mut store, mut state) = state::open(state_dir, &state_key(config))?;
+        // Before a key per webhook the state was under `discord`, the first
+        // webhook that syncs takes it over.
+        if !store.exists()? {
+            let (legacy, legacy_state) = state::open(state_dir, "discord")?;
+            if legacy.exists()? {
+                state = legacy_state;
+                store.write(0, &state)?;
+                legacy.remove()?;
+            }
+            store.warnings.extend(legacy.warnings);
+        }
+
+        Ok(Self {
+            client,
+            webhook_url: config.webhook_url.trim_end_matches('/').to_string(),
+            rewrites: config.rewrites.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    pub async fn sync_message<M>(
+        &mut self,
+        message: &M,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError>
+    where
+        M: DiscordMessage,
+    {
+        let date = message.date();
+        let index = self.state.iter().position(|state| state.date == date);
+        // Busy days don't fit in one message, the rest is posted as continuations.
+        let mut parts = split_text(&message.to_message(rewrites), MAX_CONTENT).into_iter();
+        let first = parts.next().unwrap_or_default();
+        let continued: Vec<String> = parts.collect();
+
+        match index {
+            Some(index) => {
+                self.update_message(&self.state[index].message_id, first)
+                    .await?;
+                self.sync_continuations(index, continued).await?;
+                Ok(SinkAction::Updated)
+            }
+            None => {
+                let result = self.send_message(first).await?;
+                self.state.push(DiscordDayState {
+                    message_id: result.id,
+                    date,
+                    continuation_ids: Vec::new(),
+                });
+                self.write_state()?;
+                self.sync_continuations(self.state.len() - 1, continued)
+                    .await?;
+                Ok(SinkAction::Created)
+            }
+        }
+    }
+
+    // Updates the continuation messages of the day, posting new ones and
+    // deleting the ones that aren't needed anymore.
+    async fn sync_continuations(
+        &mut self,
+        index: usize,
+        parts: Vec<String>,
+    ) -> Result<(), SyncError> {
+        let existing = self.state[index].continuation_ids.clone();
+        let mut continuation_ids = Vec::new();
+
+        for (part_index, part) in parts.iter().enumerate() {
+            match existing.get(part_index) {
+                Some(id) => {
+                    self.update_message(id, part.clone()).await?;
+                    continuation_ids.push(id.clone());
+                }
+                None => continuation_ids.push(self.send_message(part.clone()).await?.id),
+            }
+        }
+        for id in existing.iter().skip(parts.len()) {
+            self.delete_message(id).await?;
+        }
+
+        self.state[index].continuation_ids = continuation_ids;
+        self.write_state()
+    }
+
+    async fn send_message(&self, message: String) -> Result<Message, reqwest::Error> {
+        // `wait=true` makes Discord return the created message, we need its id
+        // to edit the message on the next sync.
+        self.client
+            .post(format!("{}?wait=true", self.webhook_url))
+            .json(&serde_json::json!({ "content": message }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Message>()
+            .await
+    }
+
+    async fn update_message(
+        &self,
+        message_id: &str,
+        message: String,
+    ) -> Result<(), reqwest::Error> {
+        self.client
+            .patch(format!("{}/messages/{}", self.webhook_url, message_id))
+            .json(&serde_json::json!({ "content": message }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn delete_message(&self, message_id: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .delete(format!("{}/messages/{}", self.webhook_url, message_id))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for Discord {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let rewrites = self.rewrites.clone();
+        let action = self.sync_message(day, &rewrites).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(webhook_url: &str) -> DiscordConfig {
+        serde_json::from_value(serde_json::json!({ "webhook_url": webhook_url })).unwrap()
+    }
+
+    #[test]
+    fn test_state_key() {
+        let dir = std::env::temp_dir().join("w0rk-test-discord-state");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = vec![DiscordDayState {
+            message_id: "1".to_string(),
+            date: base::parse_date("2024-07-01").unwrap(),
+            continuation_ids: Vec::new(),
+        }];
+        std::fs::write(
+            dir.join("discord.json"),
+            serde_json::to_string(&legacy).unwrap(),
+        )
+        .unwrap();
+        let client = reqwest::Client::new();
+
+        // The first webhook takes over the state from before, the other
+        // one starts without.
+        let first = config("https://discord.example/api/webhooks/1/a");
+        let other = config("https://discord.example/api/webhooks/2/b");
+        assert_ne!(state_key(&first), state_key(&other));
+        assert_eq!(
+            state_key(&first),
+            state_key(&config("https://discord.example/api/webhooks/1/a/"))
+        );
+        let discord = Discord::new(&dir, &first, client.clone()).unwrap();
+        assert_eq!(discord.state[0].message_id, "1");
+        assert!(Discord::new(&dir, &other, client.clone())
+            .unwrap()
+            .state
+            .is_empty());
+        assert_eq!(Discord::new(&dir, &first, client).unwrap().state.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_long_message() {
+        let content: String = (0..200)
+            .map(|index| format!("* [ ] Task number {} with a longer name\n", index))
+            .collect();
+        let (day, _) = Day::parse(Path::new("2024-07-01.md"), &content).unwrap();
+
+        let parts = split_text(&day.to_message(&[]), MAX_CONTENT);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.chars().count() <= MAX_CONTENT));
+        assert!(parts.last().unwrap().contains("Task number 199 "));
+    }
+}
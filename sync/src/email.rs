@@ -0,0 +1,177 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::{display_name, rewrite_name, SyncError};
+use async_trait::async_trait;
+use base::{Day, DigestPeriod, EmailConfig, Key, Rewrite, Workspace};
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::{Path, PathBuf};
+use time::{Date, Duration, Weekday};
+
+pub trait EmailDigest {
+    fn to_digest(&self, rewrites: &[Rewrite]) -> String;
+}
+
+impl EmailDigest for Day {
+    fn to_digest(&self, rewrites: &[Rewrite]) -> String {
+        let mut text = format!("# {}\n\n", self.date);
+        for task in &self.tasks {
+            text.push_str(&format!(
+                "{} {}\n",
+                task.state.marker(&self.style),
+                display_name(self, task, rewrites)
+            ));
+            for subtask in &task.subtasks {
+                text.push_str(&format!(
+                    "  {} {}\n",
+                    subtask.state.marker(&self.style),
+                    display_name(self, subtask, rewrites)
+                ));
+            }
+        }
+        if !self.notes.trim().is_empty() {
+            text.push_str(&format!(
+                "\n{}\n",
+                rewrite_name(&self.rewrite(self.notes.trim()), rewrites)
+            ));
+        }
+        text
+    }
+}
+
+pub type EmailSyncState = Vec<EmailDigestState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EmailDigestState {
+    pub period: String,
+}
+
+/// Sends a digest of the period once, the first time `sync` runs when the
+/// period is due: every day for daily digests, from Friday on for weekly ones.
+pub struct Email {
+    config: EmailConfig,
+    days: Vec<(Date, PathBuf)>,
+    key: Option<Key>,
+    store: Store,
+    state: EmailSyncState,
+}
+
+impl Email {
+    pub fn new(
+        state_dir: &Path,
+        config: &EmailConfig,
+        workspace: &Workspace,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "email")?;
+
+        Ok(Self {
+            config: config.clone(),
+            days: workspace.day_list.iter().cloned().collect(),
+            key: workspace.key().cloned(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    pub async fn send_digest(&mut self, today: &Day) -> Result<SinkAction, SyncError> {
+        let date = today.date;
+        let (period, start) = match self.config.digest {
+            DigestPeriod::Daily => (date.to_string(), date),
+            DigestPeriod::Weekly => {
+                if date.weekday().number_from_monday() < Weekday::Friday.number_from_monday() {
+                    return Ok(SinkAction::Skipped);
+                }
+                let (year, week, _) = date.to_iso_week_date();
+                let monday = date - Duration::days(date.weekday().number_days_from_monday().into());
+                (format!("{}-W{:02}", year, week), monday)
+            }
+        };
+
+        if self.state.iter().any(|state| state.period == period) {
+            return Ok(SinkAction::Skipped);
+        }
+
+        let body = digest_body(
+            &self.days,
+            self.key.as_ref(),
+            today,
+            start,
+            &self.config.rewrites,
+        )?;
+        self.send(&format!("w0rk digest {}", period), body).await?;
+
+        self.state.push(EmailDigestState { period });
+        self.write_state()?;
+        Ok(SinkAction::Created)
+    }
+
+    async fn send(&self, subject: &str, body: String) -> Result<(), SyncError> {
+        let mut message = Message::builder()
+            .from(self.config.from.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.config.to {
+            message = message.to(to.parse()?);
+        }
+        let message = message.body(body)?;
+
+        let mut mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.smtp_host)?;
+        if let Some(port) = self.config.smtp_port {
+            mailer = mailer.port(port);
+        }
+        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
+            mailer = mailer.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        mailer.build().send(message).await?;
+        Ok(())
+    }
+}
+
+fn digest_body(
+    days: &[(Date, PathBuf)],
+    key: Option<&Key>,
+    today: &Day,
+    start: Date,
+    rewrites: &[Rewrite],
+) -> Result<String, SyncError> {
+    let mut sections = Vec::new();
+    for (date, path) in days {
+        if *date < start || *date >= today.date {
+            continue;
+        }
+        let (mut day, _) = Day::read(path, key, &today.checkboxes)?;
+        // In the style and with the rewrites of the workspace, like today.
+        day.style = today.style;
+        day.rewrites = today.rewrites.clone();
+        sections.push(day.to_digest(rewrites));
+    }
+    sections.push(today.to_digest(rewrites));
+
+    Ok(sections.join("\n"))
+}
+
+#[async_trait]
+impl Sink for Email {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let action = self.send_digest(day).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+
+    async fn backfill(&mut self, _day: &Day) -> Result<SinkReport, SyncError> {
+        Ok(SinkReport::new(self.name(), SinkAction::Skipped))
+    }
+}
@@ -1,8 +1,24 @@
+mod caldav;
+pub mod daemon;
+mod discord;
+mod email;
+mod matrix;
+mod mattermost;
+pub mod notification;
+mod notion;
+pub mod reminder;
+mod sink;
 mod slack;
-use base::{Config, Workspace};
+mod state;
+mod teams;
+mod telegram;
+mod webhook;
+use base::{Config, Day, Rewrite, Task, TaskState, Workspace};
+pub use sink::{Sink, SinkAction, SinkContext, SinkFactory, SinkRegistry, SinkReport};
 use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
+use time::{Date, OffsetDateTime};
 
 #[derive(Error, Debug)]
 pub enum SyncError {
@@ -10,16 +26,91 @@ pub enum SyncError {
     Io(#[from] std::io::Error),
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
+    #[error("{0}")]
+    Base(#[from] base::Error),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
-    #[error("No today found")]
-    NoToday,
+    #[error("Email error: {0}")]
+    Email(#[from] lettre::error::Error),
+    #[error("Email address error: {0}")]
+    EmailAddress(#[from] lettre::address::AddressError),
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("State database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("Slack error: {0}")]
+    Slack(String),
+    #[error("Telegram error: {0}")]
+    Telegram(String),
+    #[error("Could not show a notification: {0}")]
+    Notification(String),
+    #[error("State was written by a newer version of w0rk (version {0})")]
+    StateVersion(u32),
+    #[error("Daemon is already running (pid {0})")]
+    DaemonRunning(u32),
+    #[error("No day found for {0}")]
+    NoDay(Date),
+    #[error("Unknown sink \"{0}\", expected one of: {1}")]
+    UnknownSink(String, String),
+}
+
+/// The state imported into the state database by `migrate_state`, by key,
+/// and the files it was imported from.
+#[derive(Debug, Default)]
+pub struct StateMigration {
+    pub imported: Vec<String>,
+    pub removed: Vec<PathBuf>,
+    /// State that couldn't be read and started over.
+    pub warnings: Vec<String>,
+}
+
+/// The sinks with a JSON state file named after them from before the state
+/// database.
+const STATE_FILES: [&str; 10] = [
+    "caldav",
+    "discord",
+    "email",
+    "matrix",
+    "mattermost",
+    "notifications",
+    "notion",
+    "reminders",
+    "teams",
+    "telegram",
+];
+
+/// Imports the JSON state files of the sinks into the state database at the
+/// current version, instead of on their next sync.
+pub fn migrate_state(
+    config: &Config,
+    state_dir: &Path,
+    workspace: &Workspace,
+) -> Result<StateMigration, SyncError> {
+    let mut migration = slack::migrate_state_files(config, state_dir, workspace)?;
+    for key in STATE_FILES {
+        let path = state_dir.join(format!("{}.json", key));
+        if !path.exists() {
+            continue;
+        }
+        // The file is left alone when there's state in the database already.
+        let (store, _) = state::open::<serde_json::Value>(state_dir, key)?;
+        migration.warnings.extend(store.warnings);
+        if !path.exists() {
+            migration.imported.push(key.to_string());
+            migration.removed.push(path);
+        }
+    }
+    Ok(migration)
 }
 
 pub struct Syncer<'a> {
     config: &'a Config,
     workspace: &'a Workspace,
+    client: reqwest::Client,
     state_dir: PathBuf,
+    registry: SinkRegistry,
 }
 
 impl<'a> Syncer<'a> {
@@ -27,30 +118,443 @@ impl<'a> Syncer<'a> {
         config: &'a Config,
         state_dir: &Path,
         workspace: &'a Workspace,
+    ) -> Result<Self, SyncError> {
+        Self::with_registry(config, state_dir, workspace, SinkRegistry::default())
+    }
+
+    pub fn with_registry(
+        config: &'a Config,
+        state_dir: &Path,
+        workspace: &'a Workspace,
+        registry: SinkRegistry,
     ) -> Result<Self, SyncError> {
         fs::create_dir_all(state_dir)?;
 
         Ok(Self {
             config,
             workspace,
+            client: http_client(config)?,
             state_dir: state_dir.into(),
+            registry,
         })
     }
 
-    pub async fn sync(&self) -> Result<(), SyncError> {
-        let today = match self.workspace.today() {
-            Some(today) => today,
-            None => {
-                return Err(SyncError::NoToday);
+    pub async fn sync(&self) -> Result<Vec<SinkReport>, SyncError> {
+        self.sync_days(vec![self.workspace.today_or_create()?])
+            .await
+    }
+
+    /// Syncs the day of the given date, creating or updating its messages.
+    pub async fn sync_date(&self, date: Date) -> Result<Vec<SinkReport>, SyncError> {
+        let path = match self.workspace.day_list.iter().find(|(day, _)| day == &date) {
+            Some((_, path)) => path,
+            None => return Err(SyncError::NoDay(date)),
+        };
+
+        self.sync_days(vec![self.workspace.read_day(path)?]).await
+    }
+
+    /// Syncs every day from the given date up to and including today.
+    pub async fn sync_since(&self, date: Date) -> Result<Vec<SinkReport>, SyncError> {
+        let days = self.workspace.read_days(|day| day >= &date)?;
+
+        self.sync_days(days).await
+    }
+
+    async fn sync_days(&self, mut days: Vec<Day>) -> Result<Vec<SinkReport>, SyncError> {
+        let context = SinkContext {
+            config: self.config,
+            client: &self.client,
+            state_dir: &self.state_dir,
+            workspace: self.workspace,
+        };
+        let mut reports = Vec::new();
+        let trashed = self.workspace.trashed()?;
+        let today = OffsetDateTime::now_utc().date();
+
+        for mut sink in self.registry.build(&context)? {
+            for day in days.iter_mut() {
+                let mut report = match day.date < today {
+                    true => sink.backfill(day).await?,
+                    false => sink.sync(day).await?,
+                };
+                report.date = Some(day.date);
+                report.warnings.extend(sink.take_warnings());
+
+                // Apply completions right away, so the sinks after this one see them.
+                // A locked day can't be written, the other days and sinks
+                // are synced anyway.
+                if day.is_locked() && !report.completed.is_empty() {
+                    report.warnings.push(format!(
+                        "{} is locked, not completed: {}",
+                        day.date,
+                        report.completed.join(", ")
+                    ));
+                    report.completed.clear();
+                }
+                let mut changed = false;
+                for name in &report.completed {
+                    changed |= day.complete_task(name);
+                }
+                if changed {
+                    day.update_dependencies();
+                    day.write()?;
+                }
+
+                reports.push(report);
+            }
+
+            // Clean up what was synced for days moved to the trash with `w0rk
+            // rm`, a day file that's missing for another reason, like a
+            // rename or an unmounted folder, keeps its messages.
+            for date in sink.synced_dates() {
+                if trashed.iter().any(|(day, _)| day == &date)
+                    && !self.workspace.day_list.iter().any(|(day, _)| day == &date)
+                {
+                    let mut report = sink.delete(date).await?;
+                    report.date = Some(date);
+                    report.warnings.extend(sink.take_warnings());
+                    reports.push(report);
+                }
             }
+        }
+
+        Ok(reports)
+    }
+
+    /// Removes what was synced for the date from every sink.
+    pub async fn delete(&self, date: Date) -> Result<Vec<SinkReport>, SyncError> {
+        let context = SinkContext {
+            config: self.config,
+            client: &self.client,
+            state_dir: &self.state_dir,
+            workspace: self.workspace,
         };
+        let mut reports = Vec::new();
 
-        if let Some(slack_config) = &self.config.slack {
-            let mut slack =
-                slack::Slack::new(&self.state_dir, &slack_config.token, &slack_config.channel)?;
-            slack.sync_message(today, &slack_config.rewrites).await?;
+        for mut sink in self.registry.build(&context)? {
+            let mut report = sink.delete(date).await?;
+            report.date = Some(date);
+            report.warnings.extend(sink.take_warnings());
+            reports.push(report);
         }
 
-        Ok(())
+        Ok(reports)
+    }
+}
+
+/// The HTTP client for the sinks. Without a proxy in the config reqwest
+/// uses the `HTTPS_PROXY`/`HTTP_PROXY` environment variables, if set.
+pub(crate) fn http_client(config: &Config) -> Result<reqwest::Client, SyncError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Stock Unicode emoji for sinks that don't support custom emoji.
+pub(crate) trait UnicodeEmoji {
+    fn to_emoji(&self) -> String;
+}
+
+impl UnicodeEmoji for TaskState {
+    fn to_emoji(&self) -> String {
+        match self {
+            TaskState::Blocked => "⏸️",
+            TaskState::Completed => "✅",
+            TaskState::InProgress => "🔄",
+            TaskState::Incomplete => "⬜",
+            TaskState::Waiting => "⏳",
+        }
+        .to_string()
+    }
+}
+
+pub(crate) fn rewrite_name(name: &str, rewrites: &[Rewrite]) -> String {
+    let mut name = name.to_string();
+    for rewrite in rewrites {
+        rewrite.rewrite(&mut name);
+    }
+    name
+}
+
+/// The name of the task as the sinks post it, see `Day::display_name`, with
+/// the rewrites of the sink applied after those of the config.
+pub(crate) fn display_name(day: &Day, task: &Task, rewrites: &[Rewrite]) -> String {
+    rewrite_name(&day.display_name(task), rewrites)
+}
+
+/// Renders the tasks of a day as lines of `<emoji> <name>`, grouping tasks
+/// with subtasks under a heading with their progress like `(3/5)` and
+/// pinned tasks first. Shared by the sinks that post plain text.
+pub(crate) fn render_text<E, H>(day: &Day, rewrites: &[Rewrite], emoji: E, heading: H) -> String
+where
+    E: Fn(&TaskState) -> String,
+    H: Fn(&str) -> String,
+{
+    let mut text = "".to_string();
+    let mut tasks: Vec<&Task> = day.tasks.iter().collect();
+    tasks.sort_by_key(|task| !task.is_pinned());
+
+    for task in tasks {
+        if task.subtasks.is_empty() {
+            text.push_str(&format!(
+                "{} {}\n",
+                emoji(&task.state),
+                display_name(day, task, rewrites)
+            ));
+        } else {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            let name = display_name(day, task, rewrites);
+            let title = match task.progress() {
+                Some((completed, total)) => format!("{} ({}/{})", name, completed, total),
+                None => name,
+            };
+            text.push_str(&format!("{}\n", heading(&title)));
+            for subtask in &task.subtasks {
+                text.push_str(&format!(
+                    "{} {}\n",
+                    emoji(&subtask.state),
+                    display_name(day, subtask, rewrites)
+                ));
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Splits the text on line boundaries into parts of at most `max`
+/// characters, lines that are too long on their own are cut off.
+pub(crate) fn split_text(text: &str, max: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let line: String = line.chars().take(max).collect();
+        let length = current.chars().count();
+        if length > 0 && length + 1 + line.chars().count() > max {
+            parts.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct RecordingSink;
+
+    #[async_trait]
+    impl Sink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn sync(&mut self, _day: &Day) -> Result<SinkReport, SyncError> {
+            Ok(SinkReport::new(self.name(), SinkAction::Created))
+        }
+    }
+
+    /// Has synced two days, one of them in the trash.
+    struct SyncedSink;
+
+    #[async_trait]
+    impl Sink for SyncedSink {
+        fn name(&self) -> &str {
+            "synced"
+        }
+
+        async fn sync(&mut self, _day: &Day) -> Result<SinkReport, SyncError> {
+            Ok(SinkReport::new(self.name(), SinkAction::Updated))
+        }
+
+        fn synced_dates(&self) -> Vec<Date> {
+            vec![
+                base::parse_date("2024-06-29").unwrap(),
+                base::parse_date("2024-06-30").unwrap(),
+            ]
+        }
+
+        async fn delete(&mut self, _date: Date) -> Result<SinkReport, SyncError> {
+            Ok(SinkReport::new(self.name(), SinkAction::Deleted))
+        }
+    }
+
+    /// Keeps its state in the store, like the built-in sinks.
+    struct StateSink {
+        store: state::Store,
+    }
+
+    #[async_trait]
+    impl Sink for StateSink {
+        fn name(&self) -> &str {
+            "state"
+        }
+
+        fn take_warnings(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.store.warnings)
+        }
+
+        async fn sync(&mut self, _day: &Day) -> Result<SinkReport, SyncError> {
+            Ok(SinkReport::new(self.name(), SinkAction::Updated))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_warnings() {
+        let dir = std::env::temp_dir().join("w0rk-test-sync-state-warnings");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("state")).unwrap();
+        fs::write(dir.join("state/state.json"), "[\"c\", ").unwrap();
+        for date in ["2024-06-30", "2024-07-01"] {
+            fs::write(dir.join(format!("{}.md", date)), "* [ ] Deploy\n").unwrap();
+        }
+
+        let config = Config {
+            work_dir: dir.clone(),
+            ..Config::default()
+        };
+        let workspace = Workspace::from_path(&dir).unwrap();
+        let mut registry = SinkRegistry::default();
+        registry.register("state", |context| {
+            let (store, _) = state::open::<Vec<String>>(context.state_dir, "state")?;
+            Ok(vec![Box::new(StateSink { store })])
+        });
+        registry.select(&["state".to_string()], &[]).unwrap();
+        let syncer =
+            Syncer::with_registry(&config, &dir.join("state"), &workspace, registry).unwrap();
+
+        // The state that started over is on the first report, once.
+        let reports = syncer
+            .sync_since(base::parse_date("2024-06-30").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(reports[0].warnings.len(), 1);
+        assert!(reports[0].warnings[0].starts_with("Could not read state"));
+        assert!(reports[1].warnings.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clean_up_trashed() {
+        let dir = std::env::temp_dir().join("w0rk-test-sync-trashed");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".trash")).unwrap();
+        fs::write(dir.join(".trash/2024-06-30.md"), "* [ ] Deploy\n").unwrap();
+        fs::write(dir.join("2024-07-01.md"), "* [ ] Deploy\n").unwrap();
+
+        let config = Config {
+            work_dir: dir.clone(),
+            ..Config::default()
+        };
+        let workspace = Workspace::from_path(&dir).unwrap();
+        let mut registry = SinkRegistry::default();
+        registry.register("synced", |_| Ok(vec![Box::new(SyncedSink)]));
+        registry.select(&["synced".to_string()], &[]).unwrap();
+        let syncer =
+            Syncer::with_registry(&config, &dir.join("state"), &workspace, registry).unwrap();
+
+        let reports = syncer
+            .sync_date(base::parse_date("2024-07-01").unwrap())
+            .await
+            .unwrap();
+        let actions: Vec<(String, SinkAction)> = reports
+            .iter()
+            .map(|report| (report.date.unwrap().to_string(), report.action))
+            .collect();
+        // The missing 2024-06-29 isn't cleaned up, it wasn't removed with `w0rk rm`.
+        assert_eq!(
+            actions,
+            vec![
+                ("2024-07-01".to_string(), SinkAction::Updated),
+                ("2024-06-30".to_string(), SinkAction::Deleted),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_text() {
+        assert_eq!(split_text("aaa\nbb\ncccc", 6), vec!["aaa\nbb", "cccc"]);
+        assert_eq!(split_text("aaaaaaaa\nb", 6), vec!["aaaaaa", "b"]);
+        assert_eq!(split_text("", 6), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_render_text_rewrites() {
+        let config = Config::from_content(
+            r##"{"work_dir": "/tmp",
+                "discord": {"webhook_url": "https://discord.example/hook",
+                            "rewrites": [{"from": "PR", "to": "Pull"}]}}"##,
+            "json",
+        )
+        .unwrap();
+        let rewrites = &config.discord.unwrap().rewrites;
+        let mut day = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [ ] Review #12\n  * [x] Tests #13\n",
+        )
+        .unwrap()
+        .0;
+        day.rewrites = serde_json::from_str(r##"[{"from": "#(\\d+)", "to": "PR $1"}]"##).unwrap();
+
+        // The rewrites of the sink run after those of the config.
+        assert_eq!(
+            render_text(&day, rewrites, TaskState::to_emoji, |name| name.to_string()),
+            "Review Pull 12 (1/1)\n✅ Tests Pull 13\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_since() {
+        let dir = std::env::temp_dir().join("w0rk-test-sync-since");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for date in ["2024-06-30", "2024-07-01", "2024-07-02"] {
+            fs::write(dir.join(format!("{}.md", date)), "* [ ] Deploy\n").unwrap();
+        }
+
+        let config = Config {
+            work_dir: dir.clone(),
+            ..Config::default()
+        };
+        let workspace = Workspace::from_path(&dir).unwrap();
+        let mut registry = SinkRegistry::default();
+        registry.register("recording", |_| Ok(vec![Box::new(RecordingSink)]));
+        let syncer =
+            Syncer::with_registry(&config, &dir.join("state"), &workspace, registry).unwrap();
+
+        let reports = syncer
+            .sync_since(base::parse_date("2024-07-01").unwrap())
+            .await
+            .unwrap();
+        let dates: Vec<String> = reports
+            .iter()
+            .map(|report| report.date.unwrap().to_string())
+            .collect();
+        assert_eq!(dates, vec!["2024-07-01", "2024-07-02"]);
+
+        let result = syncer
+            .sync_date(base::parse_date("2024-08-01").unwrap())
+            .await;
+        assert!(matches!(result, Err(SyncError::NoDay(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
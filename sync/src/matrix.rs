@@ -0,0 +1,196 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::{display_name, render_text, SyncError, UnicodeEmoji};
+use async_trait::async_trait;
+use base::{Day, MatrixConfig, Rewrite, TaskState};
+use serde::Deserialize;
+use std::path::Path;
+use time::{Date, OffsetDateTime};
+
+pub trait MatrixMessage {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String;
+    fn to_html(&self, rewrites: &[Rewrite]) -> String;
+    fn date(&self) -> Date;
+}
+
+impl MatrixMessage for Day {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String {
+        render_text(self, rewrites, TaskState::to_emoji, |name| name.to_string())
+    }
+
+    fn to_html(&self, rewrites: &[Rewrite]) -> String {
+        let mut lines = Vec::new();
+
+        for task in &self.tasks {
+            if task.subtasks.is_empty() {
+                lines.push(format!(
+                    "{} {}",
+                    task.state.to_emoji(),
+                    escape_html(&display_name(self, task, rewrites))
+                ));
+            } else {
+                lines.push(format!(
+                    "<strong>{}</strong>",
+                    escape_html(&display_name(self, task, rewrites))
+                ));
+                for subtask in &task.subtasks {
+                    lines.push(format!(
+                        "{} {}",
+                        subtask.state.to_emoji(),
+                        escape_html(&display_name(self, subtask, rewrites))
+                    ));
+                }
+            }
+        }
+        lines.join("<br>")
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub type MatrixSyncState = Vec<MatrixDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MatrixDayState {
+    pub room_id: String,
+    pub event_id: String,
+    pub date: Date,
+}
+
+pub struct Matrix {
+    client: reqwest::Client,
+    config: MatrixConfig,
+    store: Store,
+    state: MatrixSyncState,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SendResponse {
+    pub event_id: String,
+}
+
+impl Matrix {
+    pub fn new(
+        state_dir: &Path,
+        config: &MatrixConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "matrix")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    pub async fn sync_message<M>(
+        &mut self,
+        message: &M,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError>
+    where
+        M: MatrixMessage,
+    {
+        let date = message.date();
+        let state = self
+            .state
+            .iter()
+            .find(|state| state.date == date && state.room_id == self.config.room_id);
+        let content = serde_json::json!({
+            "msgtype": "m.text",
+            "body": message.to_message(rewrites),
+            "format": "org.matrix.custom.html",
+            "formatted_body": message.to_html(rewrites),
+        });
+
+        match state {
+            Some(state) => {
+                // Edits are new events that replace the original through a relation,
+                // the fallback body is what clients without edit support show.
+                let mut edit = content.clone();
+                edit["body"] = format!("* {}", content["body"].as_str().unwrap_or_default()).into();
+                edit["m.new_content"] = content;
+                edit["m.relates_to"] = serde_json::json!({
+                    "rel_type": "m.replace",
+                    "event_id": state.event_id,
+                });
+                self.send_event(edit).await?;
+                Ok(SinkAction::Updated)
+            }
+            None => {
+                let result = self.send_event(content).await?;
+                self.state.push(MatrixDayState {
+                    room_id: self.config.room_id.clone(),
+                    event_id: result.event_id,
+                    date,
+                });
+                self.write_state()?;
+                Ok(SinkAction::Created)
+            }
+        }
+    }
+
+    async fn send_event(&self, content: serde_json::Value) -> Result<SendResponse, SyncError> {
+        let txn_id = format!("w0rk-{}", OffsetDateTime::now_utc().unix_timestamp_nanos());
+        let mut url = reqwest::Url::parse(&self.config.homeserver)
+            .map_err(|_| SyncError::InvalidUrl(self.config.homeserver.clone()))?;
+        url.path_segments_mut()
+            .map_err(|_| SyncError::InvalidUrl(self.config.homeserver.clone()))?
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.config.room_id,
+                "send",
+                "m.room.message",
+                &txn_id,
+            ]);
+
+        let result = self
+            .client
+            .put(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&content)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SendResponse>()
+            .await?;
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl Sink for Matrix {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let rewrites = self.config.rewrites.clone();
+        let action = self.sync_message(day, &rewrites).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
@@ -0,0 +1,148 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::{render_text, SyncError, UnicodeEmoji};
+use async_trait::async_trait;
+use base::{Day, MattermostConfig, Rewrite, TaskState};
+use serde::Deserialize;
+use std::path::Path;
+use time::Date;
+
+pub trait MattermostMessage {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String;
+    fn date(&self) -> Date;
+}
+
+impl MattermostMessage for Day {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String {
+        render_text(self, rewrites, TaskState::to_emoji, |name| {
+            format!("**{}**", name)
+        })
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+pub type MattermostSyncState = Vec<MattermostDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MattermostDayState {
+    pub channel_id: String,
+    pub post_id: String,
+    pub date: Date,
+}
+
+pub struct Mattermost {
+    client: reqwest::Client,
+    config: MattermostConfig,
+    store: Store,
+    state: MattermostSyncState,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Post {
+    pub id: String,
+}
+
+impl Mattermost {
+    pub fn new(
+        state_dir: &Path,
+        config: &MattermostConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "mattermost")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!("{}/api/v4{}", self.config.url.trim_end_matches('/'), path)
+    }
+
+    pub async fn sync_message<M>(
+        &mut self,
+        message: &M,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError>
+    where
+        M: MattermostMessage,
+    {
+        let date = message.date();
+        let state = self
+            .state
+            .iter()
+            .find(|state| state.date == date && state.channel_id == self.config.channel_id);
+        let text = message.to_message(rewrites);
+
+        match state {
+            Some(state) => {
+                self.update_post(&state.post_id, text).await?;
+                Ok(SinkAction::Updated)
+            }
+            None => {
+                let result = self.create_post(text).await?;
+                self.state.push(MattermostDayState {
+                    channel_id: self.config.channel_id.clone(),
+                    post_id: result.id,
+                    date,
+                });
+                self.write_state()?;
+                Ok(SinkAction::Created)
+            }
+        }
+    }
+
+    async fn create_post(&self, message: String) -> Result<Post, reqwest::Error> {
+        self.client
+            .post(self.api_url("/posts"))
+            .bearer_auth(&self.config.token)
+            .json(&serde_json::json!({
+                "channel_id": &self.config.channel_id,
+                "message": message,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Post>()
+            .await
+    }
+
+    async fn update_post(&self, post_id: &str, message: String) -> Result<Post, reqwest::Error> {
+        self.client
+            .put(self.api_url(&format!("/posts/{}/patch", post_id)))
+            .bearer_auth(&self.config.token)
+            .json(&serde_json::json!({ "message": message }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Post>()
+            .await
+    }
+}
+
+#[async_trait]
+impl Sink for Mattermost {
+    fn name(&self) -> &str {
+        "mattermost"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let rewrites = self.config.rewrites.clone();
+        let action = self.sync_message(day, &rewrites).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
@@ -0,0 +1,241 @@
+use super::state::{self, Store};
+use super::SyncError;
+use base::{Day, NotificationConfig, TaskState, Workspace};
+use std::path::Path;
+use time::{Date, OffsetDateTime};
+
+/// The keys of the notifications that were shown, so each is shown once.
+pub type NotificationSyncState = Vec<String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// What the notification is about, like `due:<date>:<task>`.
+    pub key: String,
+    pub summary: String,
+    pub body: String,
+}
+
+/// Shows desktop notifications about the tasks of today, meant to be run
+/// periodically by the daemon.
+pub struct Notifications<'a> {
+    config: &'a NotificationConfig,
+    workspace: &'a Workspace,
+    store: Store,
+    state: NotificationSyncState,
+}
+
+impl<'a> Notifications<'a> {
+    pub fn new(
+        config: &'a NotificationConfig,
+        state_dir: &Path,
+        workspace: &'a Workspace,
+    ) -> Result<Self, SyncError> {
+        std::fs::create_dir_all(state_dir)?;
+        let (store, state) = state::open(state_dir, "notifications")?;
+
+        Ok(Self {
+            config,
+            workspace,
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    /// Problems that didn't stop the notifications, like state that
+    /// couldn't be read.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    /// Shows the notifications that are due at `now` and weren't shown
+    /// before, returning them. The ones that aren't due anymore are
+    /// forgotten, so the state only holds the current ones.
+    pub fn run(&mut self, now: OffsetDateTime) -> Result<Vec<Notification>, SyncError> {
+        let today = now.date();
+        // Only the blocked tasks need the days before today.
+        let days = match self.config.blocked_after {
+            Some(_) => self.workspace.read_days(|date| date <= &today)?,
+            None => self.workspace.read_days(|date| date == &today)?,
+        };
+
+        let notifications = notifications(self.config, now, &days);
+        let count = self.state.len();
+        self.state.retain(|key| {
+            notifications
+                .iter()
+                .any(|notification| &notification.key == key)
+        });
+        if self.state.len() != count {
+            self.write_state()?;
+        }
+
+        let mut shown = Vec::new();
+        for notification in notifications {
+            if self.state.contains(&notification.key) {
+                continue;
+            }
+            notify_rust::Notification::new()
+                .appname("w0rk")
+                .summary(&notification.summary)
+                .body(&notification.body)
+                .show()
+                .map_err(|err| SyncError::Notification(err.to_string()))?;
+            self.state.push(notification.key.clone());
+            self.write_state()?;
+            shown.push(notification);
+        }
+        Ok(shown)
+    }
+}
+
+/// The notifications for today at `now`, from the days up to today in order.
+pub fn notifications(
+    config: &NotificationConfig,
+    now: OffsetDateTime,
+    days: &[Day],
+) -> Vec<Notification> {
+    let date = now.date();
+    let today = match days.last() {
+        Some(day) if day.date == date => day,
+        _ => return Vec::new(),
+    };
+    let mut notifications = Vec::new();
+
+    for entry in today.task_entries() {
+        let task = entry.task;
+        match (&task.state, task.due()) {
+            (TaskState::Completed, _) => continue,
+            (_, Some(due)) if config.due && due <= date => notifications.push(Notification {
+                key: format!("due:{}:{}", due, task.display_name()),
+                summary: match due == date {
+                    true => "Due today".to_string(),
+                    false => format!("Overdue since {}", due),
+                },
+                body: today.display_name(task),
+            }),
+            _ => {}
+        }
+        if let (TaskState::Blocked, Some(blocked_after)) = (&task.state, config.blocked_after) {
+            let since = blocked_since(days, &task.name);
+            let blocked = (date - since).whole_days();
+            if blocked > blocked_after {
+                notifications.push(Notification {
+                    key: format!("blocked:{}:{}", since, task.display_name()),
+                    summary: format!("Blocked for {} days", blocked),
+                    body: today.display_name(task),
+                });
+            }
+        }
+    }
+
+    if config.unblocked {
+        for entry in today.task_entries() {
+            let task = entry.task;
+            if task.state == TaskState::Completed
+                || task.blocked_by().is_empty()
+                || !today.open_dependencies(task).is_empty()
+            {
+                continue;
+            }
+            notifications.push(Notification {
+                key: format!("unblocked:{}", task.display_name()),
+                summary: "Unblocked".to_string(),
+                body: today.display_name(task),
+            });
+        }
+    }
+
+    if let Some(end_of_day) = config.end_of_day {
+        let open = today
+            .task_entries()
+            .filter(|entry| entry.parent.is_none() && entry.task.state != TaskState::Completed)
+            .map(|entry| today.display_name(entry.task))
+            .collect::<Vec<_>>();
+        if now.time() >= end_of_day && !open.is_empty() {
+            notifications.push(Notification {
+                key: format!("end-of-day:{}", date),
+                summary: "Still open today".to_string(),
+                body: open.join("\n"),
+            });
+        }
+    }
+    notifications
+}
+
+/// The first of the last days in a row on which the task was blocked.
+fn blocked_since(days: &[Day], name: &str) -> Date {
+    let mut since = days.last().map(|day| day.date).unwrap_or(Date::MIN);
+    for day in days.iter().rev() {
+        let blocked = day
+            .task_entries()
+            .any(|entry| entry.task.name == name && entry.task.state == TaskState::Blocked);
+        if !blocked {
+            break;
+        }
+        since = day.date;
+    }
+    since
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::tests::helpers::day;
+
+    fn at(date: &str, hour: u8, minute: u8) -> OffsetDateTime {
+        base::parse_date(date)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn test_notifications() {
+        let days = vec![
+            day("2024-06-28", "* [#] Upgrade the database\n"),
+            day(
+                "2024-07-01",
+                "* [#] Upgrade the database\n* [#] Rotate keys\n",
+            ),
+            day(
+                "2024-07-02",
+                "* [#] Upgrade the database\n* [#] Rotate keys\n\
+                 * [ ] Deploy @due:2024-07-02\n* [x] Review @due:2024-07-01\n\
+                 * [ ] Plan the offsite\n  * [ ] Book the venue @due:2024-06-30\n\
+                 * [x] Ship @id:ship\n* [#] Announce @blocked-by:ship\n",
+            ),
+        ];
+        let config = NotificationConfig {
+            due: true,
+            blocked_after: Some(3),
+            unblocked: true,
+            end_of_day: Some(time::Time::from_hms(17, 30, 0).unwrap()),
+        };
+
+        let summaries = |now| {
+            notifications(&config, now, &days)
+                .into_iter()
+                .map(|notification| format!("{}: {}", notification.summary, notification.body))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summaries(at("2024-07-02", 9, 0)),
+            vec![
+                "Blocked for 4 days: Upgrade the database",
+                "Due today: Deploy @due:2024-07-02",
+                "Overdue since 2024-06-30: Book the venue @due:2024-06-30",
+                "Unblocked: Announce @blocked-by:ship",
+            ]
+        );
+        assert_eq!(
+            summaries(at("2024-07-02", 17, 30))[4],
+            "Still open today: Upgrade the database\nRotate keys\nDeploy @due:2024-07-02\nPlan the offsite\nAnnounce @blocked-by:ship"
+        );
+        assert!(summaries(at("2024-07-03", 9, 0)).is_empty());
+    }
+}
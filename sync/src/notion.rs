@@ -0,0 +1,255 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::SyncError;
+use async_trait::async_trait;
+use base::{Day, NotionConfig, Task, TaskState};
+use serde::Deserialize;
+use std::path::Path;
+use time::Date;
+
+const API_URL: &str = "https://api.notion.com/v1";
+const API_VERSION: &str = "2022-06-28";
+
+pub trait NotionPage {
+    fn to_blocks(&self) -> Vec<serde_json::Value>;
+    fn date(&self) -> Date;
+}
+
+impl NotionPage for Day {
+    fn to_blocks(&self) -> Vec<serde_json::Value> {
+        let mut blocks: Vec<serde_json::Value> = self
+            .tasks
+            .iter()
+            .map(|task| to_do_block(self, task))
+            .collect();
+
+        if !self.notes.trim().is_empty() {
+            blocks.push(serde_json::json!({
+                "object": "block",
+                "type": "paragraph",
+                "paragraph": {
+                    "rich_text": [{ "type": "text", "text": { "content": self.rewrite(self.notes.trim()) } }]
+                }
+            }));
+        }
+        blocks
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+fn to_do_block(day: &Day, task: &Task) -> serde_json::Value {
+    let mut to_do = serde_json::json!({
+        "rich_text": [{ "type": "text", "text": { "content": day.display_name(task) } }],
+        "checked": task.state == TaskState::Completed,
+    });
+    if task.has_subtasks() {
+        to_do["children"] = task
+            .subtasks
+            .iter()
+            .map(|subtask| to_do_block(day, subtask))
+            .collect();
+    }
+
+    serde_json::json!({
+        "object": "block",
+        "type": "to_do",
+        "to_do": to_do,
+    })
+}
+
+pub type NotionSyncState = Vec<NotionDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotionDayState {
+    pub database_id: String,
+    pub page_id: String,
+    pub date: Date,
+}
+
+pub struct Notion {
+    client: reqwest::Client,
+    config: NotionConfig,
+    store: Store,
+    state: NotionSyncState,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BlockChildren {
+    results: Vec<Page>,
+    has_more: bool,
+}
+
+impl Notion {
+    pub fn new(
+        state_dir: &Path,
+        config: &NotionConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "notion")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", API_URL, path))
+            .header("Authorization", "Bearer ".to_string() + &self.config.token)
+            .header("Notion-Version", API_VERSION)
+    }
+
+    pub async fn sync_page<P>(&mut self, page: &P) -> Result<SinkAction, SyncError>
+    where
+        P: NotionPage,
+    {
+        let date = page.date();
+        let state = self
+            .state
+            .iter()
+            .find(|state| state.date == date && state.database_id == self.config.database_id);
+        let blocks = page.to_blocks();
+
+        match state {
+            Some(state) => {
+                self.replace_blocks(&state.page_id, blocks).await?;
+                Ok(SinkAction::Updated)
+            }
+            None => {
+                let result = self.create_page(date, blocks).await?;
+                self.state.push(NotionDayState {
+                    database_id: self.config.database_id.clone(),
+                    page_id: result.id,
+                    date,
+                });
+                self.write_state()?;
+                Ok(SinkAction::Created)
+            }
+        }
+    }
+
+    async fn create_page(
+        &self,
+        date: Date,
+        blocks: Vec<serde_json::Value>,
+    ) -> Result<Page, reqwest::Error> {
+        let mut properties = serde_json::json!({});
+        properties[&self.config.title_property] =
+            serde_json::json!({ "title": [{ "text": { "content": date.to_string() } }] });
+        if let Some(date_property) = &self.config.date_property {
+            properties[date_property] =
+                serde_json::json!({ "date": { "start": date.to_string() } });
+        }
+
+        self.request(reqwest::Method::POST, "/pages")
+            .json(&serde_json::json!({
+                "parent": { "database_id": &self.config.database_id },
+                "properties": properties,
+                "children": blocks,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Page>()
+            .await
+    }
+
+    // Notion has no "replace content" call, so we remove the existing blocks
+    // before appending the freshly rendered ones.
+    async fn replace_blocks(
+        &self,
+        page_id: &str,
+        blocks: Vec<serde_json::Value>,
+    ) -> Result<(), reqwest::Error> {
+        loop {
+            let children = self
+                .request(
+                    reqwest::Method::GET,
+                    &format!("/blocks/{}/children?page_size=100", page_id),
+                )
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<BlockChildren>()
+                .await?;
+
+            for block in &children.results {
+                self.request(reqwest::Method::DELETE, &format!("/blocks/{}", block.id))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+
+            if !children.has_more {
+                break;
+            }
+        }
+
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("/blocks/{}/children", page_id),
+        )
+        .json(&serde_json::json!({ "children": blocks }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for Notion {
+    fn name(&self) -> &str {
+        "notion"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let action = self.sync_page(day).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_blocks_rewrites() {
+        let mut day = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [x] Review #123\n    * [ ] Merge #124\n\nAbout #123.\n",
+        )
+        .unwrap()
+        .0;
+        day.rewrites = serde_json::from_str(r##"[{"from": "#(\\d+)", "to": "PR $1"}]"##).unwrap();
+
+        let blocks = day.to_blocks();
+        let content = |block: &serde_json::Value| block["rich_text"][0]["text"]["content"].clone();
+        assert_eq!(content(&blocks[0]["to_do"]), "Review PR 123");
+        assert_eq!(
+            content(&blocks[0]["to_do"]["children"][0]["to_do"]),
+            "Merge PR 124"
+        );
+        assert_eq!(content(&blocks[1]["paragraph"]), "About PR 123.");
+    }
+}
@@ -0,0 +1,174 @@
+use super::slack::Slack;
+use super::state::{self, Store};
+use super::{http_client, render_text, Sink, SyncError};
+use base::{Config, Day, EmojiConfig, ReminderConfig, ReminderKind, Rewrite, Workspace};
+use std::path::{Path, PathBuf};
+use time::{Date, OffsetDateTime, Time};
+
+pub type ReminderSyncState = Vec<SentReminder>;
+
+/// A reminder that was handled for the day, so it's only posted once.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SentReminder {
+    pub channel: String,
+    pub at: Time,
+    pub date: Date,
+}
+
+/// Posts the reminders configured for the Slack destinations once their
+/// time has passed. Meant to be run periodically, by cron or the daemon.
+pub struct Reminders<'a> {
+    config: &'a Config,
+    workspace: &'a Workspace,
+    client: reqwest::Client,
+    state_dir: PathBuf,
+    store: Store,
+    state: ReminderSyncState,
+    /// State that couldn't be read, of the reminders and the channels.
+    warnings: Vec<String>,
+}
+
+impl<'a> Reminders<'a> {
+    pub fn new(
+        config: &'a Config,
+        state_dir: &Path,
+        workspace: &'a Workspace,
+    ) -> Result<Self, SyncError> {
+        std::fs::create_dir_all(state_dir)?;
+        let (mut store, state) = state::open(state_dir, "reminders")?;
+
+        Ok(Self {
+            config,
+            workspace,
+            client: http_client(config)?,
+            state_dir: state_dir.into(),
+            warnings: std::mem::take(&mut store.warnings),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    /// Problems that didn't stop the reminders, like state that couldn't
+    /// be read.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Posts the reminders that are due at `now`, returning the posted texts.
+    pub async fn run(&mut self, now: OffsetDateTime) -> Result<Vec<String>, SyncError> {
+        let today = self.workspace.today()?;
+        let mut posted = Vec::new();
+
+        for slack_config in self.config.slack.iter().filter(|config| config.enabled) {
+            for reminder in &slack_config.reminders {
+                let sent = SentReminder {
+                    channel: slack_config.key().to_string(),
+                    at: reminder.at,
+                    date: now.date(),
+                };
+                if !is_due(reminder, now) || self.state.contains(&sent) {
+                    continue;
+                }
+
+                if let Some(text) = reminder_text(
+                    reminder.kind,
+                    now.date(),
+                    today.as_ref(),
+                    &slack_config.rewrites,
+                    &slack_config.emoji,
+                ) {
+                    let mut slack = Slack::new(
+                        &self.state_dir,
+                        self.workspace,
+                        slack_config,
+                        self.client.clone(),
+                    )?;
+                    self.warnings.extend(slack.take_warnings());
+                    slack.post_text(text.clone()).await?;
+                    posted.push(text);
+                }
+
+                // Reminders without anything to say are handled as well,
+                // a plan reminder shouldn't fire once the day is created.
+                self.state.push(sent);
+                self.write_state()?;
+            }
+        }
+
+        Ok(posted)
+    }
+}
+
+fn is_due(reminder: &ReminderConfig, now: OffsetDateTime) -> bool {
+    now.time() >= reminder.at
+}
+
+/// The text of the reminder, or `None` when there's nothing to remind about.
+pub fn reminder_text(
+    kind: ReminderKind,
+    date: Date,
+    today: Option<&Day>,
+    rewrites: &[Rewrite],
+    emoji: &EmojiConfig,
+) -> Option<String> {
+    match (kind, today) {
+        (ReminderKind::Plan, None) => Some(format!(
+            "Time to plan your day, there's no file for {} yet.",
+            date
+        )),
+        (ReminderKind::Plan, Some(_)) => None,
+        (ReminderKind::Incomplete, None) => None,
+        (ReminderKind::Incomplete, Some(day)) => {
+            let open = day.filtered(|task| task.state != base::TaskState::Completed);
+            if open.tasks.is_empty() {
+                return None;
+            }
+            let text = render_text(
+                &open,
+                rewrites,
+                |state| emoji.for_state(state).to_string(),
+                |name| format!("*{}*", name),
+            );
+            Some(format!("Still open today:\n{}", text.trim_end()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::Task;
+
+    #[test]
+    fn test_reminder_text() {
+        let date = Date::from_calendar_date(2024, time::Month::July, 1).unwrap();
+        let mut day = Day::new(Path::new("2024-07-01.md")).expect("Could not create day");
+        let task: Task = "* [x] Deploy".try_into().unwrap();
+        day.tasks.push(task);
+        let emoji = EmojiConfig::default();
+
+        assert_eq!(
+            reminder_text(ReminderKind::Plan, date, None, &[], &emoji),
+            Some("Time to plan your day, there's no file for 2024-07-01 yet.".to_string())
+        );
+        assert_eq!(
+            reminder_text(ReminderKind::Plan, date, Some(&day), &[], &emoji),
+            None
+        );
+        assert_eq!(
+            reminder_text(ReminderKind::Incomplete, date, Some(&day), &[], &emoji),
+            None
+        );
+
+        let task: Task = "* [~] Review".try_into().unwrap();
+        day.tasks.push(task);
+        assert_eq!(
+            reminder_text(ReminderKind::Incomplete, date, Some(&day), &[], &emoji),
+            Some("Still open today:\n🔄 Review".to_string())
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use super::SyncError;
+use super::{caldav, discord, email, matrix, mattermost, notion, slack, teams, telegram, webhook};
+use async_trait::async_trait;
+use base::{Config, Day, Workspace};
+use std::path::Path;
+use time::Date;
+
+/// A destination the day gets synced to.
+#[async_trait]
+pub trait Sink: Send {
+    fn name(&self) -> &str;
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError>;
+
+    /// Problems setting the sink up that didn't stop it, like state that
+    /// couldn't be read, the syncer adds them to its next report.
+    fn take_warnings(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Syncs a day before today, for `sync --date` and `--since`. Sinks of
+    /// what's going on now, like a digest, skip it.
+    async fn backfill(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        self.sync(day).await
+    }
+
+    /// The dates this sink has synced, used to clean up after days moved
+    /// to the trash.
+    fn synced_dates(&self) -> Vec<Date> {
+        Vec::new()
+    }
+
+    /// Removes what was synced for the date. Sinks that can't remove
+    /// anything skip it.
+    async fn delete(&mut self, _date: Date) -> Result<SinkReport, SyncError> {
+        Ok(SinkReport::new(self.name(), SinkAction::Skipped))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkAction {
+    Created,
+    Updated,
+    Deleted,
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinkReport {
+    pub sink: String,
+    pub action: SinkAction,
+    /// The day this report is about, set by the syncer.
+    pub date: Option<Date>,
+    /// Names of tasks that were completed on the other end, the syncer
+    /// marks these as completed in the day file.
+    pub completed: Vec<String>,
+    /// Problems that didn't stop the sync, like a lookup that failed.
+    pub warnings: Vec<String>,
+}
+
+impl SinkReport {
+    pub fn new(sink: &str, action: SinkAction) -> Self {
+        Self {
+            sink: sink.to_string(),
+            action,
+            date: None,
+            completed: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+pub struct SinkContext<'a> {
+    pub config: &'a Config,
+    /// Shared by all sinks, configured with the proxy from the config.
+    pub client: &'a reqwest::Client,
+    pub state_dir: &'a Path,
+    pub workspace: &'a Workspace,
+}
+
+/// Builds the sinks for every configured destination, or none when it isn't configured.
+pub type SinkFactory = fn(&SinkContext) -> Result<Vec<Box<dyn Sink>>, SyncError>;
+
+pub struct SinkRegistry {
+    factories: Vec<(String, SinkFactory)>,
+}
+
+impl SinkRegistry {
+    pub fn register(&mut self, name: &str, factory: SinkFactory) {
+        self.factories.push((name.to_string(), factory));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.factories
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Keeps the sinks named in `only`, or all of them when it's empty, minus
+    /// the ones named in `except`.
+    pub fn select(&mut self, only: &[String], except: &[String]) -> Result<(), SyncError> {
+        let names = self.names();
+        if let Some(unknown) = only
+            .iter()
+            .chain(except)
+            .find(|name| !names.contains(&name.as_str()))
+        {
+            return Err(SyncError::UnknownSink(unknown.clone(), names.join(", ")));
+        }
+        self.factories
+            .retain(|(name, _)| (only.is_empty() || only.contains(name)) && !except.contains(name));
+        Ok(())
+    }
+
+    pub fn build(&self, context: &SinkContext) -> Result<Vec<Box<dyn Sink>>, SyncError> {
+        let mut sinks = Vec::new();
+        for (_, factory) in &self.factories {
+            sinks.extend(factory(context)?);
+        }
+        Ok(sinks)
+    }
+}
+
+/// The registry of the built-in sinks, more can be added with `register`.
+impl Default for SinkRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: Vec::new(),
+        };
+
+        // CalDAV can complete tasks, it goes first so the other sinks see the changes.
+        registry.register("caldav", |context| {
+            context
+                .config
+                .caldav
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(caldav::Caldav::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("slack", |context| {
+            context
+                .config
+                .slack
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(slack::Slack::new(
+                        context.state_dir,
+                        context.workspace,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("notion", |context| {
+            context
+                .config
+                .notion
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(notion::Notion::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("discord", |context| {
+            context
+                .config
+                .discord
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(discord::Discord::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("teams", |context| {
+            context
+                .config
+                .teams
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(teams::Teams::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("mattermost", |context| {
+            context
+                .config
+                .mattermost
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(mattermost::Mattermost::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("matrix", |context| {
+            context
+                .config
+                .matrix
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(matrix::Matrix::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("telegram", |context| {
+            context
+                .config
+                .telegram
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(telegram::Telegram::new(
+                        context.state_dir,
+                        config,
+                        context.client.clone(),
+                    )?))
+                })
+                .collect()
+        });
+        registry.register("webhook", |context| {
+            Ok(context
+                .config
+                .webhook
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| boxed(webhook::Webhook::new(config, context.client.clone())))
+                .collect())
+        });
+        registry.register("email", |context| {
+            context
+                .config
+                .email
+                .iter()
+                .filter(|config| config.enabled)
+                .map(|config| {
+                    Ok(boxed(email::Email::new(
+                        context.state_dir,
+                        config,
+                        context.workspace,
+                    )?))
+                })
+                .collect()
+        });
+
+        registry
+    }
+}
+
+fn boxed<S: Sink + 'static>(sink: S) -> Box<dyn Sink> {
+    Box::new(sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(only: &[&str], except: &[&str]) -> Result<Vec<String>, SyncError> {
+        let strings = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut registry = SinkRegistry::default();
+        registry.select(&strings(only), &strings(except))?;
+        Ok(strings(&registry.names()))
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(names(&["slack"], &[]).unwrap(), vec!["slack"]);
+        assert_eq!(
+            names(&["slack", "caldav"], &[]).unwrap(),
+            vec!["caldav", "slack"]
+        );
+        let all = names(&[], &[]).unwrap();
+        let except = names(&[], &["notion", "email"]).unwrap();
+        assert_eq!(except.len(), all.len() - 2);
+        assert!(!except.contains(&"notion".to_string()));
+        assert_eq!(
+            names(&["slak"], &[]).unwrap_err().to_string(),
+            "Unknown sink \"slak\", expected one of: caldav, slack, notion, discord, teams, mattermost, matrix, telegram, webhook, email"
+        );
+    }
+}
@@ -1,57 +1,48 @@
-use super::SyncError;
-use base::{Day, Rewrite, TaskState};
+mod blocks;
+
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::{display_name, render_text, rewrite_name, StateMigration, SyncError};
+use async_trait::async_trait;
+use base::query::TaskQuery;
+use base::report::{CarriedTask, DebtReport};
+use base::{
+    Config, Day, EmojiConfig, Key, Rewrite, SlackConfig, SlackMode, Task, TaskState, Workspace,
+};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use time::Date;
 
 pub trait SlackMessage {
-    fn to_message(&self, rewrites: &[Rewrite]) -> String;
+    fn to_message(&self, rewrites: &[Rewrite], emoji: &EmojiConfig) -> String;
+    fn to_blocks(&self, rewrites: &[Rewrite], emoji: &EmojiConfig) -> Vec<serde_json::Value>;
+    fn to_notes(&self, rewrites: &[Rewrite]) -> Option<String>;
     fn date(&self) -> Date;
 }
 
-pub trait SlackEmoji {
-    fn to_emoji(&self) -> String;
-}
+impl SlackMessage for Day {
+    fn to_message(&self, rewrites: &[Rewrite], emoji: &EmojiConfig) -> String {
+        render_text(
+            self,
+            rewrites,
+            |state| emoji.for_state(state).to_string(),
+            |name| format!("*{}*", name),
+        )
+    }
 
-impl SlackEmoji for TaskState {
-    fn to_emoji(&self) -> String {
-        match self {
-            TaskState::Blocked => ":todo_paused:",
-            TaskState::Completed => ":todo_done:",
-            TaskState::InProgress => ":todo_doing:",
-            TaskState::Incomplete => ":todo:",
-        }
-        .to_string()
+    fn to_blocks(&self, rewrites: &[Rewrite], emoji: &EmojiConfig) -> Vec<serde_json::Value> {
+        blocks::day_blocks(self, rewrites, emoji)
     }
-}
 
-impl SlackMessage for Day {
-    fn to_message(&self, rewrites: &[Rewrite]) -> String {
-        let mut text = "".to_string();
-
-        for task in &self.tasks {
-            if task.subtasks.is_empty() {
-                text.push_str(&format!(
-                    "{} {}\n",
-                    task.state.to_emoji(),
-                    rewrite_name(&task.name, rewrites)
-                ));
-            } else {
-                if !text.is_empty() {
-                    text.push('\n');
-                }
-                text.push_str(&format!("*{}*\n", task.name));
-                for subtask in &task.subtasks {
-                    text.push_str(&format!(
-                        "{} {}\n",
-                        subtask.state.to_emoji(),
-                        rewrite_name(&subtask.name, rewrites)
-                    ));
-                }
-                text.push('\n');
-            }
+    fn to_notes(&self, rewrites: &[Rewrite]) -> Option<String> {
+        match self.notes.trim() {
+            "" => None,
+            notes => Some(rewrite_name(&self.rewrite(notes), rewrites)),
         }
-        text
     }
 
     fn date(&self) -> Date {
@@ -59,14 +50,6 @@ impl SlackMessage for Day {
     }
 }
 
-fn rewrite_name(name: &str, rewrites: &[Rewrite]) -> String {
-    let mut name = name.to_string();
-    for rewrite in rewrites {
-        rewrite.rewrite(&mut name);
-    }
-    name
-}
-
 pub type SlackSyncState = Vec<SlackDayState>;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -74,149 +57,1281 @@ pub struct SlackDayState {
     pub channel_id: String,
     pub ts: String,
     pub date: Date,
+    #[serde(default)]
+    pub notes_ts: Option<String>,
+    /// The status text we set, so a status set by hand is never cleared.
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub task_replies: Vec<TaskReply>,
+    /// Hash of the last posted message, to skip updates that don't change anything.
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// Messages with the blocks that didn't fit in the first one.
+    #[serde(default)]
+    pub continuation_ts: Vec<String>,
+    /// The message of every task in task mode.
+    #[serde(default)]
+    pub task_messages: Vec<TaskMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaskMessage {
+    /// The task the message is of, see `task_messages`.
+    pub name: String,
+    pub ts: String,
+    pub hash: String,
+}
+
+/// A task posted as a thread reply, to complete it by reacting.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaskReply {
+    pub name: String,
+    pub ts: String,
 }
 
 pub struct Slack {
     client: reqwest::Client,
+    name: String,
+    target: String,
+    // Resolved on the first sync when the target is a user.
     channel_id: String,
     token: String,
-    state_path: PathBuf,
+    rewrites: Vec<Rewrite>,
+    thread_notes: bool,
+    tags: Vec<String>,
+    tombstone: bool,
+    emoji: EmojiConfig,
+    /// Whether to look up which of the custom emoji exist before the first
+    /// message, see `resolve_emoji`.
+    check_emoji: bool,
+    /// For the report of the sync, see `resolve_emoji`.
+    warnings: Vec<String>,
+    status: bool,
+    status_emoji: String,
+    reactions: bool,
+    mode: SlackMode,
+    stale_after: Option<i64>,
+    shipped: bool,
+    work_dir: PathBuf,
+    // To read the encrypted days of the workspace for the aging tasks.
+    key: Option<Key>,
+    // The aging tasks of the day that's being synced.
+    aging: Vec<CarriedTask>,
+    // The Shipped section of the day that's being synced, with `shipped`.
+    shipped_lines: Vec<String>,
+    // The owner of the token, resolved when first needed.
+    user_id: Option<String>,
+    store: Store,
     state: SlackSyncState,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Response {
-    pub ok: bool,
-    #[allow(dead_code)]
-    pub error: Option<String>,
     pub ts: Option<String>,
 }
 
-impl Slack {
-    pub fn new(state_dir: &Path, token: &str, channel_id: &str) -> Result<Self, SyncError> {
-        let state_path = state_dir.join("slack.json");
+#[derive(Deserialize, Debug)]
+pub struct UsersResponse {
+    #[serde(default)]
+    pub members: Vec<User>,
+    pub response_metadata: Option<ResponseMetadata>,
+}
 
-        let state = match Path::new(&state_path).exists() {
-            true => {
-                let state_file = std::fs::read_to_string(&state_path)?;
-                serde_json::from_str(&state_file)?
+#[derive(Deserialize, Debug)]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub profile: UserProfile,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct UserProfile {
+    #[serde(default)]
+    pub display_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResponseMetadata {
+    #[serde(default)]
+    pub next_cursor: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConversationResponse {
+    pub channel: Conversation,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Conversation {
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AuthResponse {
+    pub user_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EmojiListResponse {
+    /// The custom emoji by name.
+    pub emoji: std::collections::HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepliesResponse {
+    #[serde(default)]
+    pub messages: Vec<ReplyMessage>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReplyMessage {
+    pub ts: String,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Reaction {
+    pub name: String,
+    #[serde(default)]
+    pub users: Vec<String>,
+}
+
+// The name Slack uses for ✅.
+const COMPLETE_REACTION: &str = "white_check_mark";
+
+/// The tasks with a ✅ reaction from the user on their reply.
+fn reacted_tasks(replies: &[TaskReply], messages: &[ReplyMessage], user_id: &str) -> Vec<String> {
+    replies
+        .iter()
+        .filter(|reply| {
+            messages.iter().any(|message| {
+                message.ts == reply.ts
+                    && message.reactions.iter().any(|reaction| {
+                        reaction.name == COMPLETE_REACTION
+                            && reaction.users.iter().any(|user| user == user_id)
+                    })
+            })
+        })
+        .map(|reply| reply.name.clone())
+        .collect()
+}
+
+const STATE_VERSION: u32 = 1;
+
+// Version 0 is the unversioned list of days, fields added since then have defaults.
+fn migrate_state(_version: u32, state: serde_json::Value) -> serde_json::Value {
+    state
+}
+
+const LEGACY_STATE_FILE: &str = "slack.json";
+
+/// The key of the state of the destination in the state database, by its
+/// name or channel.
+fn state_key(workspace: &Workspace, config: &SlackConfig) -> String {
+    format!("{}/slack-{}", workspace.name, config.key())
+}
+
+/// The state of the channel, imported from the JSON file it lived in before
+/// the state database the first time. Returns the files it was imported from,
+/// which are removed.
+fn read_state(
+    store: &mut Store,
+    state_dir: &Path,
+    workspace: &Workspace,
+    channel: &str,
+) -> Result<(SlackSyncState, Vec<PathBuf>), SyncError> {
+    if let Some(state) = store.read(STATE_VERSION, migrate_state)? {
+        return Ok((state, Vec::new()));
+    }
+
+    // A file per workspace and channel, before namespacing a file per channel
+    // in the state dir itself, and before multiple destinations all of it
+    // lived in `slack.json`.
+    let file_name = format!("slack-{}.json", channel);
+    for path in [
+        state_dir.join(&workspace.name).join(&file_name),
+        state_dir.join(&file_name),
+    ] {
+        if path.exists() {
+            let state = store.read_or_import(&path, STATE_VERSION, migrate_state)?;
+            return Ok((state, vec![path]));
+        }
+    }
+    let legacy: SlackSyncState = state::read_file(
+        &state_dir.join(LEGACY_STATE_FILE),
+        STATE_VERSION,
+        migrate_state,
+        &mut store.warnings,
+    )?;
+    let state: SlackSyncState = legacy
+        .into_iter()
+        .filter(|state| state.channel_id == channel)
+        .collect();
+    if !state.is_empty() {
+        store.write(STATE_VERSION, &state)?;
+    }
+    Ok((state, Vec::new()))
+}
+
+/// Imports the state of the channels in the config into the state database
+/// and removes the files it was in. `slack.json` stays while it has the state
+/// of a channel that's not in the config.
+pub(crate) fn migrate_state_files(
+    config: &Config,
+    state_dir: &Path,
+    workspace: &Workspace,
+) -> Result<StateMigration, SyncError> {
+    let mut migration = StateMigration::default();
+    // A disabled sink keeps its state files as they are until it's enabled.
+    let enabled = || config.slack.iter().filter(|slack| slack.enabled);
+    for slack in enabled() {
+        let key = state_key(workspace, slack);
+        let mut store = Store::open(state_dir, &key)?;
+        let imported = !store.exists()?;
+        let (_, removed) = read_state(&mut store, state_dir, workspace, &slack.channel)?;
+        migration.warnings.append(&mut store.warnings);
+        if imported && store.exists()? {
+            migration.imported.push(key);
+        }
+        migration.removed.extend(removed);
+        for path in [
+            state_dir
+                .join(&workspace.name)
+                .join(format!("slack-{}.json", slack.channel)),
+            state_dir.join(format!("slack-{}.json", slack.channel)),
+        ] {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+                migration.removed.push(path);
             }
-            false => Vec::new(),
-        };
+        }
+    }
+    // Only removed when empty.
+    let _ = std::fs::remove_dir(state_dir.join(&workspace.name));
+
+    let legacy_state_path = state_dir.join(LEGACY_STATE_FILE);
+    let legacy: SlackSyncState = state::read_file(
+        &legacy_state_path,
+        STATE_VERSION,
+        migrate_state,
+        &mut migration.warnings,
+    )?;
+    let migrated = legacy
+        .iter()
+        .all(|state| enabled().any(|slack| slack.channel == state.channel_id));
+    if legacy_state_path.exists() && migrated {
+        std::fs::remove_file(&legacy_state_path)?;
+        migration.removed.push(legacy_state_path);
+    }
+    Ok(migration)
+}
+
+const MAX_ATTEMPTS: u32 = 5;
+
+// Slack truncates the text of a message after this many characters.
+const MAX_TEXT: usize = 40000;
+
+/// How long to wait before retrying a request, or `None` when it shouldn't
+/// be retried. Slack tells us how long to back off when we're rate limited,
+/// server errors are retried with an exponential backoff.
+fn retry_delay(status: StatusCode, retry_after: Option<&str>, attempt: u32) -> Option<Duration> {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let seconds = retry_after
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1);
+        return Some(Duration::from_secs(seconds));
+    }
+    if status.is_server_error() {
+        return Some(Duration::from_millis(500 * 2u64.pow(attempt - 1)));
+    }
+    None
+}
+
+impl Slack {
+    /// Every workspace and destination keeps its own state, so several
+    /// destinations can be synced without overwriting each other's state.
+    pub fn new(
+        state_dir: &Path,
+        workspace: &Workspace,
+        config: &SlackConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let mut store = Store::open(state_dir, &state_key(workspace, config))?;
+        let (state, _) = read_state(&mut store, state_dir, workspace, &config.channel)?;
 
         Ok(Self {
-            client: reqwest::Client::new(),
-            channel_id: channel_id.to_string(),
-            token: token.to_string(),
-            state_path,
+            client,
+            name: format!("slack ({})", config.key()),
+            target: config.channel.clone(),
+            channel_id: match config.channel.starts_with('@') {
+                true => String::new(),
+                false => config.channel.clone(),
+            },
+            token: config.token.clone(),
+            rewrites: config.rewrites.clone(),
+            thread_notes: config.thread_notes,
+            tags: config.tags.clone(),
+            tombstone: config.tombstone,
+            emoji: match config.unicode_emoji {
+                Some(true) => config.emoji.with_fallback(|_| false),
+                _ => config.emoji.clone(),
+            },
+            // Only when one of the emoji is a `:name:` one.
+            check_emoji: config.unicode_emoji.is_none()
+                && config.emoji.with_fallback(|_| false) != config.emoji,
+            warnings: Vec::new(),
+            status: config.status,
+            status_emoji: config.status_emoji.clone(),
+            reactions: config.reactions,
+            mode: config.mode,
+            stale_after: config.stale_after,
+            shipped: config.shipped,
+            work_dir: workspace.path.clone(),
+            key: workspace.key().cloned(),
+            aging: Vec::new(),
+            shipped_lines: Vec::new(),
+            user_id: None,
+            store,
             state,
         })
     }
 
     fn write_state(&self) -> Result<(), SyncError> {
-        let state_file = std::fs::File::create(&self.state_path)?;
-        serde_json::to_writer(state_file, &self.state)?;
+        self.store.write(STATE_VERSION, &self.state)
+    }
+
+    /// Sends the request, retrying when rate limited or on server errors,
+    /// and turns responses that aren't `ok` into a `SyncError::Slack`.
+    async fn call<T>(&self, request: reqwest::RequestBuilder) -> Result<T, SyncError>
+    where
+        T: DeserializeOwned,
+    {
+        let request = request.header("Authorization", "Bearer ".to_string() + &self.token);
+        let mut attempt = 1;
+
+        loop {
+            let response = request
+                .try_clone()
+                .ok_or_else(|| SyncError::Slack("request can't be retried".to_string()))?
+                .send()
+                .await?;
+
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok());
+            if attempt < MAX_ATTEMPTS {
+                if let Some(delay) = retry_delay(response.status(), retry_after, attempt) {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            let body: serde_json::Value = response.error_for_status()?.json().await?;
+            if body["ok"] != serde_json::Value::Bool(true) {
+                let error = body["error"].as_str().unwrap_or("unknown error");
+                return Err(SyncError::Slack(error.to_string()));
+            }
+            return Ok(serde_json::from_value(body)?);
+        }
+    }
+
+    async fn post(&self, path: &str, content: serde_json::Value) -> Result<Response, SyncError> {
+        self.call(self.client.post(path).json(&content)).await
+    }
+
+    /// Swaps the custom emoji that don't exist in the workspace for stock
+    /// ones, looked up once. Without the `emoji:read` scope they're kept,
+    /// with a warning in the report.
+    async fn resolve_emoji(&mut self) {
+        if !std::mem::take(&mut self.check_emoji) {
+            return;
+        }
+        let result: Result<EmojiListResponse, SyncError> = self
+            .call(self.client.get("https://slack.com/api/emoji.list"))
+            .await;
+        match result {
+            Ok(result) => {
+                self.emoji = self
+                    .emoji
+                    .with_fallback(|name| result.emoji.contains_key(name))
+            }
+            Err(err) => self.warnings.push(format!(
+                "could not list the emoji of the workspace, keeping the configured ones: {}",
+                err
+            )),
+        }
+    }
+
+    /// Looks up the user by name and opens the direct message with them.
+    async fn resolve_channel(&mut self) -> Result<(), SyncError> {
+        if !self.channel_id.is_empty() {
+            return Ok(());
+        }
+        let username = self.target.trim_start_matches('@');
+        let user_id = self.find_user(username).await?;
+
+        let result: ConversationResponse = self
+            .call(
+                self.client
+                    .post("https://slack.com/api/conversations.open")
+                    .json(&serde_json::json!({ "users": user_id })),
+            )
+            .await?;
+        self.channel_id = result.channel.id;
         Ok(())
     }
 
-    async fn post(
-        &self,
-        path: &str,
-        content: serde_json::Value,
-    ) -> Result<Response, reqwest::Error> {
-        self.client
-            .request(reqwest::Method::POST, path)
-            .header("Content-Type", "application/json")
-            .header("Authorization", "Bearer ".to_string() + &self.token)
-            .json(&content)
-            .send()
-            .await?
-            .json::<Response>()
-            .await
+    async fn find_user(&self, username: &str) -> Result<String, SyncError> {
+        let mut cursor = String::new();
+        loop {
+            let result: UsersResponse = self
+                .call(
+                    self.client
+                        .get("https://slack.com/api/users.list")
+                        .query(&[("limit", "200"), ("cursor", cursor.as_str())]),
+                )
+                .await?;
+
+            if let Some(user) = result
+                .members
+                .iter()
+                .find(|user| user.name == username || user.profile.display_name == username)
+            {
+                return Ok(user.id.clone());
+            }
+
+            cursor = result
+                .response_metadata
+                .map(|metadata| metadata.next_cursor)
+                .unwrap_or_default();
+            if cursor.is_empty() {
+                return Err(SyncError::Slack(format!("user @{} not found", username)));
+            }
+        }
     }
 
     pub async fn sync_message<M>(
         &mut self,
-        message: M,
+        message: &M,
         rewrites: &[Rewrite],
-    ) -> Result<(), SyncError>
+    ) -> Result<SinkAction, SyncError>
     where
         M: SlackMessage,
     {
+        self.resolve_channel().await?;
+        self.resolve_emoji().await;
         let date = message.date();
-        let state = self.state.iter().find(|state| state.date == date);
-        let text = message.to_message(rewrites);
+        let index = self
+            .state
+            .iter()
+            .position(|state| state.date == date && state.channel_id == self.channel_id);
+        let text: String = message
+            .to_message(rewrites, &self.emoji)
+            .chars()
+            .take(MAX_TEXT)
+            .collect();
+        let mut blocks = message.to_blocks(rewrites, &self.emoji);
+        blocks.extend(blocks::aging_blocks(
+            &self.aging.iter().collect::<Vec<_>>(),
+            rewrites,
+        ));
+        blocks.extend(blocks::shipped_blocks(&self.shipped_lines, rewrites));
+        let hash = content_hash(&text, &blocks);
+
+        // Busy days don't fit in one message, the rest is posted as continuations.
+        let mut pages = blocks::paginate(blocks).into_iter();
+        let first = pages.next().unwrap_or_default();
+        let continued: Vec<_> = pages.collect();
 
-        match state {
-            Some(state) => {
-                self.update_message(state.ts.to_owned(), text).await?;
+        let action = match index {
+            // Nothing changed since the last sync, save the API call.
+            Some(index) if self.state[index].hash.as_ref() == Some(&hash) => SinkAction::Skipped,
+            Some(index) => {
+                self.update_message(self.state[index].ts.clone(), text, first)
+                    .await?;
+                self.state[index].hash = Some(hash);
+                self.sync_continuations(index, continued).await?;
+                SinkAction::Updated
             }
             None => {
-                let result = self.send_message(text).await?;
-                if result.ok {
+                let result = self.send_message(text, first).await?;
+                if let Some(ts) = result.ts {
                     self.state.push(SlackDayState {
                         channel_id: self.channel_id.clone(),
-                        ts: result.ts.unwrap(),
+                        ts,
                         date,
+                        notes_ts: None,
+                        status: None,
+                        task_replies: Vec::new(),
+                        hash: Some(hash),
+                        continuation_ts: Vec::new(),
+                        task_messages: Vec::new(),
                     });
-                    self.write_state()?;
+                    self.sync_continuations(self.state.len() - 1, continued)
+                        .await?;
                 }
+                SinkAction::Created
             }
+        };
+
+        if self.thread_notes {
+            self.sync_notes(date, message.to_notes(rewrites)).await?;
+        }
+
+        Ok(action)
+    }
+
+    /// Posts the date as a message of its own, with the aging tasks and
+    /// the Shipped section, followed by a message per top-level task that's
+    /// updated in place when the task changes.
+    pub async fn sync_task_messages(
+        &mut self,
+        day: &Day,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError> {
+        self.resolve_channel().await?;
+        self.resolve_emoji().await;
+        let existing = self
+            .state
+            .iter()
+            .position(|state| state.date == day.date && state.channel_id == self.channel_id);
+        let text = format!("*{}*", day.date);
+        let blocks = blocks::date_blocks(
+            day,
+            &self.aging.iter().collect::<Vec<_>>(),
+            &self.shipped_lines,
+            rewrites,
+        );
+        let hash = content_hash(&text, &blocks);
+
+        let (index, mut action) = match existing {
+            Some(index) if self.state[index].hash.as_ref() == Some(&hash) => {
+                (index, SinkAction::Skipped)
+            }
+            Some(index) => {
+                self.update_message(self.state[index].ts.clone(), text, blocks)
+                    .await?;
+                self.state[index].hash = Some(hash);
+                self.write_state()?;
+                (index, SinkAction::Updated)
+            }
+            None => {
+                let result = self.send_message(text, blocks).await?;
+                let ts = match result.ts {
+                    Some(ts) => ts,
+                    None => return Ok(SinkAction::Skipped),
+                };
+                self.state.push(SlackDayState {
+                    channel_id: self.channel_id.clone(),
+                    ts,
+                    date: day.date,
+                    notes_ts: None,
+                    status: None,
+                    task_replies: Vec::new(),
+                    hash: Some(hash),
+                    continuation_ts: Vec::new(),
+                    task_messages: Vec::new(),
+                });
+                self.write_state()?;
+                (self.state.len() - 1, SinkAction::Created)
+            }
+        };
+
+        let messages = task_messages(day, rewrites, &self.emoji);
+        let keys: Vec<String> = messages.iter().map(|(key, _, _)| key.clone()).collect();
+        let previous = reused_messages(&self.state[index].task_messages, &keys);
+        let mut task_messages = Vec::new();
+        for ((name, text, blocks), previous) in messages.into_iter().zip(previous) {
+            let hash = content_hash(&text, &blocks);
+            let ts = match previous {
+                Some(previous) if previous.hash == hash => previous.ts,
+                Some(previous) => {
+                    self.update_message(previous.ts.clone(), text, blocks)
+                        .await?;
+                    if action == SinkAction::Skipped {
+                        action = SinkAction::Updated;
+                    }
+                    previous.ts
+                }
+                None => {
+                    let result = self.send_message(text, blocks).await?;
+                    if action == SinkAction::Skipped {
+                        action = SinkAction::Updated;
+                    }
+                    match result.ts {
+                        Some(ts) => ts,
+                        None => continue,
+                    }
+                }
+            };
+            task_messages.push(TaskMessage { name, ts, hash });
+        }
+
+        // Tasks that were removed, and the messages posted again in order.
+        for message in &self.state[index].task_messages {
+            if !task_messages.iter().any(|task| task.ts == message.ts) {
+                self.delete_message(message.ts.clone()).await?;
+                if action == SinkAction::Skipped {
+                    action = SinkAction::Updated;
+                }
+            }
+        }
+
+        self.state[index].task_messages = task_messages;
+        self.write_state()?;
+
+        if self.thread_notes {
+            self.sync_notes(day.date, day.to_notes(rewrites)).await?;
+        }
+
+        Ok(action)
+    }
+
+    // Updates the continuation messages of the day, posting new ones and
+    // deleting the ones that aren't needed anymore.
+    async fn sync_continuations(
+        &mut self,
+        index: usize,
+        pages: Vec<Vec<serde_json::Value>>,
+    ) -> Result<(), SyncError> {
+        let existing = self.state[index].continuation_ts.clone();
+        let text = format!("{} (continued)", self.state[index].date);
+        let mut continuation_ts = Vec::new();
+
+        for (page_index, page) in pages.iter().enumerate() {
+            match existing.get(page_index) {
+                Some(ts) => {
+                    self.update_message(ts.clone(), text.clone(), page.clone())
+                        .await?;
+                    continuation_ts.push(ts.clone());
+                }
+                None => {
+                    let result = self.send_message(text.clone(), page.clone()).await?;
+                    continuation_ts.extend(result.ts);
+                }
+            }
+        }
+        for ts in existing.iter().skip(pages.len()) {
+            self.delete_message(ts.clone()).await?;
+        }
+
+        self.state[index].continuation_ts = continuation_ts;
+        self.write_state()
+    }
+
+    async fn sync_notes(&mut self, date: Date, notes: Option<String>) -> Result<(), SyncError> {
+        let index = match self
+            .state
+            .iter()
+            .position(|state| state.date == date && state.channel_id == self.channel_id)
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let state = &self.state[index];
+
+        match (notes, state.notes_ts.clone()) {
+            (Some(notes), Some(notes_ts)) => {
+                self.update_text(notes_ts, notes).await?;
+            }
+            (Some(notes), None) => {
+                let result = self.reply(state.ts.clone(), notes).await?;
+                self.state[index].notes_ts = result.ts;
+                self.write_state()?;
+            }
+            (None, Some(notes_ts)) => {
+                self.delete_message(notes_ts).await?;
+                self.state[index].notes_ts = None;
+                self.write_state()?;
+            }
+            (None, None) => {}
         }
 
         Ok(())
     }
 
-    async fn send_message(&self, message: String) -> Result<Response, SyncError> {
-        let result = self
-            .post(
-                "https://slack.com/api/chat.postMessage",
-                serde_json::json!({
-                    "channel": &self.channel_id,
-                    "blocks": [
-                        {
-                            "type": "context",
-                            "elements": [
-                                {
-                                    "type": "mrkdwn",
-                                    "text": message
-                                }
-                            ]
+    /// Posts a standalone message, not tied to any day.
+    pub async fn post_text(&mut self, text: String) -> Result<(), SyncError> {
+        self.resolve_channel().await?;
+        self.post(
+            "https://slack.com/api/chat.postMessage",
+            serde_json::json!({
+                "channel": &self.channel_id,
+                "text": text,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the tasks completed with a reaction on their reply, and posts
+    /// replies for the open tasks that don't have one yet.
+    async fn sync_reactions(&mut self, day: &Day) -> Result<Vec<String>, SyncError> {
+        let index = match self
+            .state
+            .iter()
+            .position(|state| state.date == day.date && state.channel_id == self.channel_id)
+        {
+            Some(index) => index,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut completed = Vec::new();
+        if !self.state[index].task_replies.is_empty() {
+            let user_id = self.user_id().await?;
+            let replies: RepliesResponse = self
+                .call(
+                    self.client
+                        .get("https://slack.com/api/conversations.replies")
+                        .query(&[
+                            ("channel", self.channel_id.as_str()),
+                            ("ts", self.state[index].ts.as_str()),
+                        ]),
+                )
+                .await?;
+            completed = reacted_tasks(&self.state[index].task_replies, &replies.messages, &user_id);
+        }
+
+        let leaves = day.tasks.iter().flat_map(|task| match task.has_subtasks() {
+            true => task.subtasks.iter().collect(),
+            false => vec![task],
+        });
+        for task in leaves {
+            let replied = self.state[index]
+                .task_replies
+                .iter()
+                .any(|reply| reply.name == task.name);
+            if replied || task.state == TaskState::Completed || completed.contains(&task.name) {
+                continue;
+            }
+            let text = display_name(day, task, &self.rewrites);
+            let result = self.reply(self.state[index].ts.clone(), text).await?;
+            if let Some(ts) = result.ts {
+                self.state[index].task_replies.push(TaskReply {
+                    name: task.name.clone(),
+                    ts,
+                });
+                self.write_state()?;
+            }
+        }
+
+        Ok(completed)
+    }
+
+    async fn user_id(&mut self) -> Result<String, SyncError> {
+        if let Some(user_id) = &self.user_id {
+            return Ok(user_id.clone());
+        }
+        let result: AuthResponse = self
+            .call(self.client.post("https://slack.com/api/auth.test"))
+            .await?;
+        self.user_id = Some(result.user_id.clone());
+        Ok(result.user_id)
+    }
+
+    /// Sets the status to the first in-progress task, or clears the status
+    /// we set earlier when nothing is in progress anymore.
+    async fn sync_status(&mut self, day: &Day) -> Result<(), SyncError> {
+        let index = match self
+            .state
+            .iter()
+            .position(|state| state.date == day.date && state.channel_id == self.channel_id)
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let text = current_task(day).map(|task| {
+            display_name(day, task, &self.rewrites)
+                .chars()
+                .take(100)
+                .collect::<String>()
+        });
+        if text == self.state[index].status {
+            return Ok(());
+        }
+
+        let emoji = match text {
+            Some(_) => self.status_emoji.clone(),
+            None => String::new(),
+        };
+        let _: serde_json::Value = self
+            .call(
+                self.client
+                    .post("https://slack.com/api/users.profile.set")
+                    .json(&serde_json::json!({
+                        "profile": {
+                            "status_text": text.clone().unwrap_or_default(),
+                            "status_emoji": emoji,
                         }
-                    ]
-                }),
+                    })),
             )
             .await?;
 
-        Ok(result)
+        self.state[index].status = text;
+        self.write_state()
+    }
+
+    /// Deletes the message of the day, or replaces it with a tombstone, and
+    /// forgets about it.
+    pub async fn delete_day(&mut self, date: Date) -> Result<SinkAction, SyncError> {
+        let index = match self.state.iter().position(|state| state.date == date) {
+            Some(index) => index,
+            None => return Ok(SinkAction::Skipped),
+        };
+        let state = self.state[index].clone();
+        // The channel isn't resolved yet when only deleting, the state knows it.
+        self.channel_id = state.channel_id.clone();
+
+        if let Some(notes_ts) = state.notes_ts {
+            self.delete_message(notes_ts).await?;
+        }
+        for reply in state.task_replies {
+            self.delete_message(reply.ts).await?;
+        }
+        for ts in state.continuation_ts {
+            self.delete_message(ts).await?;
+        }
+        for message in state.task_messages {
+            self.delete_message(message.ts).await?;
+        }
+        match self.tombstone {
+            true => {
+                self.update_message(state.ts, format!("~{}~ removed", date), Vec::new())
+                    .await?;
+            }
+            false => {
+                self.delete_message(state.ts).await?;
+            }
+        }
+
+        self.state.remove(index);
+        self.write_state()?;
+        Ok(SinkAction::Deleted)
+    }
+
+    async fn reply(&self, thread_ts: String, text: String) -> Result<Response, SyncError> {
+        self.post(
+            "https://slack.com/api/chat.postMessage",
+            serde_json::json!({
+                "channel": &self.channel_id,
+                "thread_ts": thread_ts,
+                "text": text,
+            }),
+        )
+        .await
+    }
+
+    async fn update_text(&self, ts: String, text: String) -> Result<Response, SyncError> {
+        self.post(
+            "https://slack.com/api/chat.update",
+            serde_json::json!({
+                "channel": &self.channel_id,
+                "ts": ts,
+                "text": text,
+            }),
+        )
+        .await
+    }
+
+    async fn delete_message(&self, ts: String) -> Result<Response, SyncError> {
+        self.post(
+            "https://slack.com/api/chat.delete",
+            serde_json::json!({
+                "channel": &self.channel_id,
+                "ts": ts,
+            }),
+        )
+        .await
+    }
+
+    // The text is used as the fallback for notifications and clients that can't show blocks.
+    async fn send_message(
+        &self,
+        message: String,
+        blocks: Vec<serde_json::Value>,
+    ) -> Result<Response, SyncError> {
+        self.post(
+            "https://slack.com/api/chat.postMessage",
+            serde_json::json!({
+                "channel": &self.channel_id,
+                "text": message,
+                "blocks": blocks,
+            }),
+        )
+        .await
     }
 
     async fn update_message(
         &self,
         ts: String,
         message: String,
-    ) -> Result<Response, reqwest::Error> {
-        let result = self
-            .post(
-                "https://slack.com/api/chat.update",
-                serde_json::json!({
-                  "channel": &self.channel_id,
-                  "ts": ts,
-                  "blocks": [
-                      {
-                          "type": "context",
-                          "elements": [
-                              {
-                                  "type": "mrkdwn",
-                                  "text": message
-                              }
-                          ]
-                      }
-                  ],
-                }),
+        blocks: Vec<serde_json::Value>,
+    ) -> Result<Response, SyncError> {
+        self.post(
+            "https://slack.com/api/chat.update",
+            serde_json::json!({
+              "channel": &self.channel_id,
+              "ts": ts,
+              "text": message,
+              "blocks": blocks,
+            }),
+        )
+        .await
+    }
+
+    // The tasks of the day that have been open for `stale_after` days or more.
+    fn aging_tasks(&self, day: &Day) -> Result<Vec<CarriedTask>, SyncError> {
+        let stale_after = match self.stale_after {
+            Some(stale_after) => stale_after,
+            None => return Ok(Vec::new()),
+        };
+        let workspace =
+            Workspace::open(&self.work_dir, self.key.clone())?.with_rewrites(day.rewrites.clone());
+        let names: Vec<String> = day.tasks.iter().map(Task::recurring_name).collect();
+        Ok(DebtReport::from_workspace(&workspace, Some(day.date))?
+            .stale(stale_after)
+            .into_iter()
+            .filter(|task| names.contains(&task.name))
+            .cloned()
+            .collect())
+    }
+
+    /// Syncs the messages of the day, and the status when `status` is set.
+    async fn sync_day(&mut self, day: &Day, status: bool) -> Result<SinkReport, SyncError> {
+        let rewrites = self.rewrites.clone();
+        let synced = TaskQuery::new().any_tag(&self.tags).filter_day(day);
+        self.aging = self.aging_tasks(&synced)?;
+        if self.shipped {
+            self.shipped_lines = base::git::shipped(day)
+                .iter()
+                .map(|line| day.rewrite(line))
+                .collect();
+        }
+
+        let action = match self.mode {
+            SlackMode::Day => self.sync_message(&synced, &rewrites).await?,
+            SlackMode::Task => self.sync_task_messages(&synced, &rewrites).await?,
+        };
+        let mut report = SinkReport::new(self.name(), action);
+        report.warnings = std::mem::take(&mut self.warnings);
+        if self.reactions {
+            report.completed = self.sync_reactions(&synced).await?;
+        }
+        if status {
+            self.sync_status(day).await?;
+        }
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl Sink for Slack {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        self.sync_day(day, self.status).await
+    }
+
+    // The status is of what's in progress now.
+    async fn backfill(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        self.sync_day(day, false).await
+    }
+
+    fn synced_dates(&self) -> Vec<Date> {
+        self.state.iter().map(|state| state.date).collect()
+    }
+
+    async fn delete(&mut self, date: Date) -> Result<SinkReport, SyncError> {
+        let action = self.delete_day(date).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
+
+/// A 64-bit FNV-1a hash of the message. Unlike the std hasher it's stable
+/// between Rust versions, so it can be kept in the state.
+pub(crate) fn content_hash(text: &str, blocks: &[serde_json::Value]) -> String {
+    let blocks = serde_json::Value::Array(blocks.to_vec()).to_string();
+    let hash = text
+        .bytes()
+        .chain(blocks.bytes())
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// The messages of the top-level tasks in task mode, pinned ones first, as
+/// their key, text and blocks. The key is the `@id` of the task or its name,
+/// with how many tasks with the same one come before it, so tasks that are
+/// shown the same keep a message of their own.
+fn task_messages(
+    day: &Day,
+    rewrites: &[Rewrite],
+    emoji: &EmojiConfig,
+) -> Vec<(String, String, Vec<serde_json::Value>)> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut tasks: Vec<(String, &Task)> = day
+        .tasks
+        .iter()
+        .map(|task| {
+            let key = match task.id() {
+                Some(id) => format!("@id:{}", id),
+                None => task.name.clone(),
+            };
+            let count = seen.iter().filter(|other| *other == &key).count();
+            seen.push(key.clone());
+            match count {
+                0 => (key, task),
+                count => (format!("{} ({})", key, count + 1), task),
+            }
+        })
+        .collect();
+    tasks.sort_by_key(|(_, task)| !task.is_pinned());
+    tasks
+        .into_iter()
+        .map(|(key, task)| {
+            (
+                key,
+                blocks::task_text(day, task, rewrites, emoji),
+                blocks::task_blocks(day, task, rewrites, emoji),
             )
-            .await?;
+        })
+        .collect()
+}
+
+/// The message of every key that can be updated in place. Messages stay in
+/// the order they were posted in, so from the first task that's new or
+/// moved up the messages are posted again, after the ones before it.
+fn reused_messages(previous: &[TaskMessage], keys: &[String]) -> Vec<Option<TaskMessage>> {
+    let mut previous = previous
+        .iter()
+        .filter(|message| keys.contains(&message.name))
+        .peekable();
+    let mut in_order = true;
+    keys.iter()
+        .map(|key| {
+            in_order &= previous.peek().map(|message| &message.name) == Some(key);
+            match in_order {
+                true => previous.next().cloned(),
+                false => None,
+            }
+        })
+        .collect()
+}
+
+/// The first task (or subtask) that's in progress.
+fn current_task(day: &Day) -> Option<&Task> {
+    day.tasks
+        .iter()
+        .flat_map(|task| std::iter::once(task).chain(task.subtasks.iter()))
+        .find(|task| task.state == TaskState::InProgress && !task.has_subtasks())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_state_files() {
+        let dir = std::env::temp_dir().join("w0rk-test-slack-migrate");
+        let _ = std::fs::remove_dir_all(&dir);
+        let (work_dir, state_dir) = (dir.join("work"), dir.join("state"));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        std::fs::create_dir_all(&state_dir).unwrap();
+        let day_state = |channel_id: &str| SlackDayState {
+            channel_id: channel_id.to_string(),
+            ts: "1720000000.000100".to_string(),
+            date: base::parse_date("2024-07-01").unwrap(),
+            notes_ts: None,
+            status: None,
+            task_replies: Vec::new(),
+            hash: None,
+            continuation_ts: Vec::new(),
+            task_messages: Vec::new(),
+        };
+        // Unversioned, from before multiple channels.
+        std::fs::write(
+            state_dir.join(LEGACY_STATE_FILE),
+            serde_json::to_string(&vec![day_state("C1"), day_state("C2")]).unwrap(),
+        )
+        .unwrap();
+        let mut config = Config::from_content(
+            "work_dir = \"work\"\n[[slack]]\ntoken = \"xoxb\"\nchannel = \"C1\"\n",
+            "toml",
+        )
+        .unwrap();
+        let workspace = Workspace::from_path(&work_dir).unwrap();
+
+        let migration = migrate_state_files(&config, &state_dir, &workspace).unwrap();
+        assert_eq!(migration.imported, vec!["work/slack-C1"]);
+        // C2 isn't in the config, so its state stays where it is.
+        assert!(migration.removed.is_empty());
+        let mut store = Store::open(&state_dir, "work/slack-C1").unwrap();
+        let state: SlackSyncState = store.read(STATE_VERSION, migrate_state).unwrap().unwrap();
+        assert_eq!(state.len(), 1);
+
+        // A file per channel from before namespacing.
+        std::fs::write(
+            state_dir.join("slack-C3.json"),
+            serde_json::to_string(&vec![day_state("C3")]).unwrap(),
+        )
+        .unwrap();
+
+        config.slack.push(SlackConfig {
+            channel: "C2".to_string(),
+            enabled: false,
+            ..config.slack[0].clone()
+        });
+        config.slack.push(SlackConfig {
+            channel: "C3".to_string(),
+            ..config.slack[0].clone()
+        });
+        // C2 is disabled, so its state stays in the old file.
+        let migration = migrate_state_files(&config, &state_dir, &workspace).unwrap();
+        assert_eq!(migration.imported, vec!["work/slack-C3"]);
+        assert_eq!(migration.removed, vec![state_dir.join("slack-C3.json")]);
+
+        config.slack[1].enabled = true;
+        let migration = migrate_state_files(&config, &state_dir, &workspace).unwrap();
+        assert_eq!(migration.imported, vec!["work/slack-C2"]);
+        assert_eq!(migration.removed, vec![state_dir.join(LEGACY_STATE_FILE)]);
+        assert!(migrate_state_files(&config, &state_dir, &workspace)
+            .unwrap()
+            .imported
+            .is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_task_messages() {
+        let (day, _) = Day::parse(
+            Path::new("2024-07-01.md"),
+            "* [ ] Review\n* [x] Review\n* [ ] Deploy @id:api\n* [ ] Standup !pin\n* [#] Review\n",
+        )
+        .unwrap();
+        let messages = task_messages(&day, &[], &EmojiConfig::default());
+
+        let keys: Vec<&str> = messages.iter().map(|(key, _, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "Standup !pin",
+                "Review",
+                "Review (2)",
+                "@id:api",
+                "Review (3)"
+            ]
+        );
+        assert_eq!(messages[2].1, "✅ Review");
+    }
+
+    #[test]
+    fn test_reused_messages() {
+        let message = |name: &str| TaskMessage {
+            name: name.to_string(),
+            ts: format!("ts-{}", name),
+            hash: String::new(),
+        };
+        let previous = vec![message("a"), message("b"), message("c")];
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        let reused = |keys: &[String]| {
+            reused_messages(&previous, keys)
+                .into_iter()
+                .map(|message| message.map(|message| message.name))
+                .collect::<Vec<_>>()
+        };
+
+        // Removed and added at the end, the rest stays.
+        assert_eq!(
+            reused(&keys(&["a", "c", "d"])),
+            vec![Some("a".to_string()), Some("c".to_string()), None]
+        );
+        // Moved up, it and the messages after it are posted again.
+        assert_eq!(reused(&keys(&["c", "a", "b"])), vec![None, None, None]);
+        assert_eq!(
+            reused(&keys(&["a", "d", "b"])),
+            vec![Some("a".to_string()), None, None]
+        );
+    }
+
+    #[test]
+    fn test_current_task() {
+        let mut day = Day::new(Path::new("2024-07-01.md")).expect("Could not create day");
+        day.tasks = vec![
+            "* [x] Deploy".try_into().unwrap(),
+            "* [~] Release".try_into().unwrap(),
+        ];
+        day.tasks[1].subtasks = vec![
+            "* [ ] Changelog".try_into().unwrap(),
+            "* [~] Announce".try_into().unwrap(),
+        ];
+        assert_eq!(
+            current_task(&day).map(|task| task.name.as_str()),
+            Some("Announce")
+        );
+
+        day.tasks[1].subtasks[1].state = TaskState::Completed;
+        assert_eq!(current_task(&day), None);
+    }
+
+    #[test]
+    fn test_reacted_tasks() {
+        let replies = vec![
+            TaskReply {
+                name: "Deploy".to_string(),
+                ts: "1.1".to_string(),
+            },
+            TaskReply {
+                name: "Review".to_string(),
+                ts: "1.2".to_string(),
+            },
+        ];
+        let messages: Vec<ReplyMessage> = serde_json::from_value(serde_json::json!([
+            { "ts": "1.0", "reactions": [{ "name": "white_check_mark", "users": ["U1"] }] },
+            { "ts": "1.1", "reactions": [{ "name": "white_check_mark", "users": ["U2", "U1"] }] },
+            { "ts": "1.2", "reactions": [{ "name": "white_check_mark", "users": ["U2"] }] },
+        ]))
+        .unwrap();
+
+        assert_eq!(reacted_tasks(&replies, &messages, "U1"), vec!["Deploy"]);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let blocks = vec![serde_json::json!({ "type": "divider" })];
+        assert_eq!(content_hash("", &[]), "09612b07b5ecb5a5");
+        assert_eq!(content_hash("a", &blocks), content_hash("a", &blocks));
+        assert_ne!(content_hash("a", &blocks), content_hash("b", &blocks));
+        assert_ne!(content_hash("a", &blocks), content_hash("a", &[]));
+    }
 
-        Ok(result)
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(
+            retry_delay(StatusCode::TOO_MANY_REQUESTS, Some("30"), 1),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_delay(StatusCode::TOO_MANY_REQUESTS, None, 1),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, None, 3),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(retry_delay(StatusCode::OK, None, 1), None);
+        assert_eq!(retry_delay(StatusCode::NOT_FOUND, None, 1), None);
     }
 }
@@ -0,0 +1,345 @@
+use crate::{display_name, rewrite_name, split_text};
+use base::report::CarriedTask;
+use base::{Day, EmojiConfig, Rewrite, Task, TaskState};
+use serde_json::{json, Value};
+
+// Slack's limits for the text of a section and the blocks in a message.
+const MAX_SECTION_TEXT: usize = 3000;
+const MAX_BLOCKS: usize = 50;
+
+/// Renders the day as Block Kit blocks: a header with the date, a section per
+/// group of tasks separated by dividers, and the task counts as fields.
+pub fn day_blocks(day: &Day, rewrites: &[Rewrite], emoji: &EmojiConfig) -> Vec<Value> {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": day.date.to_string(),
+        }
+    })];
+
+    let mut tasks = day.tasks.clone();
+    tasks.sort_by_key(|task| !task.is_pinned());
+    for (index, group) in task_groups(day, &tasks, rewrites, emoji)
+        .into_iter()
+        .enumerate()
+    {
+        if index > 0 {
+            blocks.push(json!({ "type": "divider" }));
+        }
+        blocks.extend(
+            split_text(&group, MAX_SECTION_TEXT)
+                .iter()
+                .map(|text| section(text)),
+        );
+    }
+
+    blocks.push(json!({ "type": "divider" }));
+    blocks.push(counts(&day.tasks));
+    blocks
+}
+
+// Consecutive tasks without subtasks form one group, every task with
+// subtasks gets a group of its own with its name as the title.
+fn task_groups(
+    day: &Day,
+    tasks: &[Task],
+    rewrites: &[Rewrite],
+    emoji: &EmojiConfig,
+) -> Vec<String> {
+    let mut groups = Vec::new();
+    let mut current = Vec::new();
+
+    for task in tasks {
+        if task.has_subtasks() {
+            if !current.is_empty() {
+                groups.push(current.join("\n"));
+                current = Vec::new();
+            }
+            let mut title = format!("*{}*", display_name(day, task, rewrites));
+            if let Some((completed, total)) = task.progress() {
+                title.push_str(&format!(" ({}/{})", completed, total));
+            }
+            let mut lines = vec![title];
+            lines.extend(
+                task.subtasks
+                    .iter()
+                    .map(|subtask| line(day, subtask, rewrites, emoji)),
+            );
+            groups.push(lines.join("\n"));
+        } else {
+            current.push(line(day, task, rewrites, emoji));
+        }
+    }
+    if !current.is_empty() {
+        groups.push(current.join("\n"));
+    }
+
+    groups
+}
+
+fn line(day: &Day, task: &Task, rewrites: &[Rewrite], emoji: &EmojiConfig) -> String {
+    format!(
+        "{} {}",
+        emoji.for_state(&task.state),
+        display_name(day, task, rewrites)
+    )
+}
+
+/// Renders a single top-level task of the day, with its subtasks, as blocks.
+pub fn task_blocks(
+    day: &Day,
+    task: &Task,
+    rewrites: &[Rewrite],
+    emoji: &EmojiConfig,
+) -> Vec<Value> {
+    split_text(&task_text(day, task, rewrites, emoji), MAX_SECTION_TEXT)
+        .iter()
+        .map(|text| section(text))
+        .collect()
+}
+
+/// The text of a single top-level task, the fallback for `task_blocks`.
+pub fn task_text(day: &Day, task: &Task, rewrites: &[Rewrite], emoji: &EmojiConfig) -> String {
+    task_groups(day, std::slice::from_ref(task), rewrites, emoji).join("\n")
+}
+
+/// The message with the date before the tasks in task mode, with the
+/// aging tasks and the Shipped section that come after the tasks of a day
+/// message.
+pub fn date_blocks(
+    day: &Day,
+    aging: &[&CarriedTask],
+    shipped: &[String],
+    rewrites: &[Rewrite],
+) -> Vec<Value> {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": day.date.to_string(),
+        }
+    })];
+    blocks.extend(aging_blocks(aging, rewrites));
+    blocks.extend(shipped_blocks(shipped, rewrites));
+    blocks
+}
+
+/// Splits the blocks over as many messages as needed to stay within the
+/// number of blocks Slack allows in one message.
+pub fn paginate(blocks: Vec<Value>) -> Vec<Vec<Value>> {
+    blocks
+        .chunks(MAX_BLOCKS)
+        .map(|chunk| chunk.to_vec())
+        .collect()
+}
+
+/// The open tasks that keep getting carried over, with how long they've been open.
+pub fn aging_blocks(tasks: &[&CarriedTask], rewrites: &[Rewrite]) -> Vec<Value> {
+    if tasks.is_empty() {
+        return Vec::new();
+    }
+    let mut lines = vec!["*Aging*".to_string()];
+    lines.extend(tasks.iter().map(|task| {
+        format!(
+            "⏳ {} – open for {} days",
+            rewrite_name(&task.display_name, rewrites),
+            task.age
+        )
+    }));
+    split_text(&lines.join("\n"), MAX_SECTION_TEXT)
+        .iter()
+        .map(|text| section(text))
+        .collect()
+}
+
+/// The commits of the Shipped section of the notes, see `base::git`.
+pub fn shipped_blocks(lines: &[String], rewrites: &[Rewrite]) -> Vec<Value> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let mut text = vec!["*Shipped*".to_string()];
+    text.extend(lines.iter().map(|line| {
+        let line = line.trim_start_matches("- ");
+        format!("🚢 {}", rewrite_name(line, rewrites))
+    }));
+    split_text(&text.join("\n"), MAX_SECTION_TEXT)
+        .iter()
+        .map(|text| section(text))
+        .collect()
+}
+
+fn section(text: &str) -> Value {
+    json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": text,
+        }
+    })
+}
+
+fn counts(tasks: &[Task]) -> Value {
+    let leaves: Vec<&Task> = tasks
+        .iter()
+        .flat_map(|task| match task.has_subtasks() {
+            true => task.subtasks.iter().collect(),
+            false => vec![task],
+        })
+        .collect();
+    let count = |state: TaskState| leaves.iter().filter(|task| task.state == state).count();
+
+    let mut fields = vec![
+        json!({ "type": "mrkdwn", "text": format!("*Completed*\n{}", count(TaskState::Completed)) }),
+        json!({ "type": "mrkdwn", "text": format!("*In progress*\n{}", count(TaskState::InProgress)) }),
+        json!({ "type": "mrkdwn", "text": format!("*Blocked*\n{}", count(TaskState::Blocked)) }),
+        json!({ "type": "mrkdwn", "text": format!("*Todo*\n{}", count(TaskState::Incomplete)) }),
+    ];
+    // Only for those who hand off tasks.
+    match count(TaskState::Waiting) {
+        0 => {}
+        waiting => {
+            fields.push(json!({ "type": "mrkdwn", "text": format!("*Waiting*\n{}", waiting) }))
+        }
+    }
+    json!({ "type": "section", "fields": fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn day(tasks: &[&str]) -> Day {
+        let mut day = Day::new(Path::new("2024-07-01.md")).expect("Could not create day");
+        for line in tasks {
+            let subtask = line.starts_with("  ");
+            let task: Task = line.trim_start().try_into().expect("Could not parse task");
+            match (subtask, day.tasks.last_mut()) {
+                (true, Some(parent)) => parent.subtasks.push(task),
+                _ => day.tasks.push(task),
+            }
+        }
+        day
+    }
+
+    #[test]
+    fn test_day_blocks() {
+        let day = day(&[
+            "* [x] Deploy",
+            "* [ ] Review #123",
+            "* [~] Release",
+            "  * [x] Changelog",
+            "  * [#] Announce",
+            "* [ ] Lunch",
+            "* [ ] 📌 Standup notes",
+        ]);
+        let rewrites: Vec<Rewrite> =
+            serde_json::from_value(json!([{ "from": r"#(\d+)", "to": "PR $1" }])).unwrap();
+        let emoji = EmojiConfig {
+            completed: ":todo_done:".to_string(),
+            in_progress: ":todo_doing:".to_string(),
+            blocked: ":todo_paused:".to_string(),
+            incomplete: ":todo:".to_string(),
+            waiting: ":todo_waiting:".to_string(),
+        };
+
+        assert_eq!(
+            Value::Array(day_blocks(&day, &rewrites, &emoji)),
+            json!([
+                { "type": "header", "text": { "type": "plain_text", "text": "2024-07-01" } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": ":todo: 📌 Standup notes\n:todo_done: Deploy\n:todo: Review PR 123" } },
+                { "type": "divider" },
+                { "type": "section", "text": { "type": "mrkdwn", "text": "*Release* (1/2)\n:todo_done: Changelog\n:todo_paused: Announce" } },
+                { "type": "divider" },
+                { "type": "section", "text": { "type": "mrkdwn", "text": ":todo: Lunch" } },
+                { "type": "divider" },
+                { "type": "section", "fields": [
+                    { "type": "mrkdwn", "text": "*Completed*\n2" },
+                    { "type": "mrkdwn", "text": "*In progress*\n0" },
+                    { "type": "mrkdwn", "text": "*Blocked*\n1" },
+                    { "type": "mrkdwn", "text": "*Todo*\n3" },
+                ] },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_task_blocks() {
+        let day = day(&["* [~] Release", "  * [x] Changelog"]);
+
+        assert_eq!(
+            task_blocks(&day, &day.tasks[0], &[], &EmojiConfig::default()),
+            vec![
+                json!({ "type": "section", "text": { "type": "mrkdwn", "text": "*Release* (1/1)\n✅ Changelog" } })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_date_blocks() {
+        let shipped = vec!["- w0rk: Fix the parser (a1b2c3d)".to_string()];
+
+        assert_eq!(
+            date_blocks(&day(&[]), &[], &shipped, &[]),
+            vec![
+                json!({ "type": "header", "text": { "type": "plain_text", "text": "2024-07-01" } }),
+                section("*Shipped*\n🚢 w0rk: Fix the parser (a1b2c3d)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_day_blocks() {
+        let names: Vec<String> = (0..400)
+            .map(|index| format!("* [ ] Task number {} with a longer name", index))
+            .collect();
+        let day = day(&names.iter().map(|name| name.as_str()).collect::<Vec<_>>());
+
+        let blocks = day_blocks(&day, &[], &EmojiConfig::default());
+        assert!(blocks.len() > 4);
+        for block in &blocks {
+            let text = block["text"]["text"].as_str().unwrap_or_default();
+            assert!(text.chars().count() <= MAX_SECTION_TEXT);
+        }
+
+        let pages = paginate((0..120).map(|_| json!({ "type": "divider" })).collect());
+        assert_eq!(
+            pages.iter().map(|page| page.len()).collect::<Vec<_>>(),
+            vec![50, 50, 20]
+        );
+    }
+
+    #[test]
+    fn test_day_blocks_without_tasks() {
+        let blocks = day_blocks(&day(&[]), &[], &EmojiConfig::default());
+
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[1], json!({ "type": "divider" }));
+    }
+
+    #[test]
+    fn test_aging_blocks() {
+        let task = CarriedTask {
+            name: "Deploy".to_string(),
+            display_name: "Deploy".to_string(),
+            first_seen: day(&[]).date,
+            carried: 6,
+            age: 9,
+        };
+        assert!(aging_blocks(&[], &[]).is_empty());
+        assert_eq!(
+            aging_blocks(&[&task], &[]),
+            vec![section("*Aging*\n⏳ Deploy – open for 9 days")]
+        );
+    }
+
+    #[test]
+    fn test_shipped_blocks() {
+        assert!(shipped_blocks(&[], &[]).is_empty());
+        assert_eq!(
+            shipped_blocks(&["- w0rk: Fix the parser (a1b2c3d)".to_string()], &[]),
+            vec![section("*Shipped*\n🚢 w0rk: Fix the parser (a1b2c3d)")]
+        );
+    }
+}
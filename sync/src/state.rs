@@ -0,0 +1,321 @@
+//! The sync state of the sinks, a row per sink in `state.sqlite` in the
+//! state dir. Every write is a transaction of its own, so a crash halfway
+//! through a sync leaves the state of the last write behind.
+use super::SyncError;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+const STATE_FILE: &str = "state.sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS state (
+        key TEXT PRIMARY KEY,
+        version INTEGER NOT NULL,
+        value TEXT NOT NULL
+    );
+";
+
+/// The state of one sink, like `caldav` or `work/slack-C123`. It opens the
+/// database for every read and write, as connections can't be shared
+/// between the tasks of a sync.
+pub(crate) struct Store {
+    path: PathBuf,
+    key: String,
+    /// State that couldn't be read and started over, for the report of
+    /// the sink.
+    pub(crate) warnings: Vec<String>,
+}
+
+impl Store {
+    pub(crate) fn open(state_dir: &Path, key: &str) -> Result<Self, SyncError> {
+        fs::create_dir_all(state_dir)?;
+        let store = Self {
+            path: state_dir.join(STATE_FILE),
+            key: key.to_string(),
+            warnings: Vec::new(),
+        };
+        store.connection()?;
+        Ok(store)
+    }
+
+    fn connection(&self) -> Result<Connection, SyncError> {
+        let connection = Connection::open(&self.path)?;
+        // The daemon and a command can sync at the same time.
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(connection)
+    }
+
+    /// Reads the state, `None` when there's none yet. `migrate` upgrades the
+    /// state of older versions to the current one.
+    ///
+    /// State that can't be read is kept aside under a `.bak` key and the
+    /// state starts over, so one bad row doesn't break syncing for good.
+    pub(crate) fn read<T>(
+        &mut self,
+        version: u32,
+        migrate: fn(u32, Value) -> Value,
+    ) -> Result<Option<T>, SyncError>
+    where
+        T: DeserializeOwned + Default,
+    {
+        let connection = self.connection()?;
+        let row: Option<(u32, String)> = connection
+            .query_row(
+                "SELECT version, value FROM state WHERE key = ?1",
+                [&self.key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (row_version, value) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        if row_version > version {
+            return Err(SyncError::StateVersion(row_version));
+        }
+        let state = serde_json::from_str(&value).map(|state| match row_version < version {
+            true => migrate(row_version, state),
+            false => state,
+        });
+        match state.and_then(serde_json::from_value) {
+            Ok(state) => Ok(Some(state)),
+            Err(err) => {
+                let backup = format!(
+                    "{}.{}.bak",
+                    self.key,
+                    OffsetDateTime::now_utc().unix_timestamp()
+                );
+                connection.execute(
+                    "UPDATE state SET key = ?1 WHERE key = ?2",
+                    params![backup, self.key],
+                )?;
+                self.warnings.push(format!(
+                    "Could not read state {} ({}), moved it to {} and starting over",
+                    self.key, err, backup
+                ));
+                Ok(Some(T::default()))
+            }
+        }
+    }
+
+    /// Reads the state like `read`, or the JSON file it's imported from
+    /// the first time, which is removed once it's in the database.
+    pub(crate) fn read_or_import<T>(
+        &mut self,
+        file: &Path,
+        version: u32,
+        migrate: fn(u32, Value) -> Value,
+    ) -> Result<T, SyncError>
+    where
+        T: DeserializeOwned + Serialize + Default,
+    {
+        if let Some(state) = self.read(version, migrate)? {
+            return Ok(state);
+        }
+        let state: T = read_file(file, version, migrate, &mut self.warnings)?;
+        if file.exists() {
+            self.write(version, &state)?;
+            fs::remove_file(file)?;
+        }
+        Ok(state)
+    }
+
+    pub(crate) fn write<T>(&self, version: u32, state: &T) -> Result<(), SyncError>
+    where
+        T: Serialize,
+    {
+        self.connection()?.execute(
+            "INSERT OR REPLACE INTO state (key, version, value) VALUES (?1, ?2, ?3)",
+            params![self.key, version, serde_json::to_string(state)?],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn remove(&self) -> Result<(), SyncError> {
+        self.connection()?
+            .execute("DELETE FROM state WHERE key = ?1", [&self.key])?;
+        Ok(())
+    }
+
+    pub(crate) fn exists(&self) -> Result<bool, SyncError> {
+        Ok(self
+            .connection()?
+            .query_row(
+                "SELECT 1 FROM state WHERE key = ?1",
+                [&self.key],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+}
+
+/// Opens the state of a sink that had no versions in its JSON file, and
+/// reads it, importing `<key>.json` in the state dir the first time.
+pub(crate) fn open<T>(state_dir: &Path, key: &str) -> Result<(Store, T), SyncError>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let mut store = Store::open(state_dir, key)?;
+    let file = state_dir.join(format!("{}.json", key));
+    let state = store.read_or_import(&file, 0, unversioned)?;
+    Ok((store, state))
+}
+
+fn unversioned(_version: u32, state: Value) -> Value {
+    state
+}
+
+#[derive(Deserialize)]
+struct VersionedValue {
+    version: u32,
+    state: Value,
+}
+
+/// Reads a versioned JSON state file from before the database, `migrate`
+/// upgrades the state of older versions to the current one. Files from
+/// before versioning are version 0.
+///
+/// A file that can't be read is moved aside to a backup and the state starts
+/// over, so one bad file doesn't break syncing for good. That's added to
+/// `warnings`.
+pub(crate) fn read_file<T>(
+    path: &Path,
+    version: u32,
+    migrate: fn(u32, Value) -> Value,
+    warnings: &mut Vec<String>,
+) -> Result<T, SyncError>
+where
+    T: DeserializeOwned + Default,
+{
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(path)?;
+    match parse(&content, version, migrate) {
+        Ok(state) => Ok(state),
+        // Written by a newer w0rk, leave it alone.
+        Err(err @ SyncError::StateVersion(_)) => Err(err),
+        Err(err) => {
+            let backup = backup_path(path);
+            fs::rename(path, &backup)?;
+            warnings.push(format!(
+                "Could not read state {:?} ({}), moved it to {:?} and starting over",
+                path, err, backup
+            ));
+            Ok(T::default())
+        }
+    }
+}
+
+fn parse<T>(content: &str, version: u32, migrate: fn(u32, Value) -> Value) -> Result<T, SyncError>
+where
+    T: DeserializeOwned,
+{
+    let value: Value = serde_json::from_str(content)?;
+    let (file_version, state) = match value {
+        Value::Object(ref object) if object.contains_key("version") => {
+            let versioned: VersionedValue = serde_json::from_value(value)?;
+            (versioned.version, versioned.state)
+        }
+        value => (0, value),
+    };
+
+    if file_version > version {
+        return Err(SyncError::StateVersion(file_version));
+    }
+    let state = match file_version < version {
+        true => migrate(file_version, state),
+        false => state,
+    };
+    Ok(serde_json::from_value(state)?)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(
+        ".{}.bak",
+        OffsetDateTime::now_utc().unix_timestamp()
+    ));
+    PathBuf::from(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate(version: u32, state: Value) -> Value {
+        match version {
+            0 => Value::Array(vec![state]),
+            _ => state,
+        }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let dir = std::env::temp_dir().join("w0rk-test-state");
+        let _ = fs::remove_dir_all(&dir);
+        let mut store = Store::open(&dir, "sink").unwrap();
+
+        let state: Option<Vec<String>> = store.read(1, migrate).unwrap();
+        assert!(state.is_none());
+        assert!(!store.exists().unwrap());
+
+        store.write(1, &vec!["a".to_string()]).unwrap();
+        let state: Option<Vec<String>> = store.read(1, migrate).unwrap();
+        assert_eq!(state.unwrap(), vec!["a"]);
+
+        store.write(0, &"b").unwrap();
+        let state: Option<Vec<String>> = store.read(1, migrate).unwrap();
+        assert_eq!(state.unwrap(), vec!["b"]);
+
+        store.write(2, &"c").unwrap();
+        assert!(matches!(
+            store.read::<Vec<String>>(1, migrate),
+            Err(SyncError::StateVersion(2))
+        ));
+
+        // State that can't be read is moved aside.
+        store.write(1, &"d").unwrap();
+        let state: Option<Vec<String>> = store.read(1, migrate).unwrap();
+        assert!(state.unwrap().is_empty());
+        assert!(!store.exists().unwrap());
+        assert_eq!(store.warnings.len(), 1);
+        assert!(store.warnings[0].starts_with("Could not read state sink ("));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_or_import() {
+        let dir = std::env::temp_dir().join("w0rk-test-state-import");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sink.json");
+
+        // Unversioned files are migrated from version 0.
+        fs::write(&path, r#""b""#).unwrap();
+        let mut store = Store::open(&dir, "sink").unwrap();
+        let state: Vec<String> = store.read_or_import(&path, 1, migrate).unwrap();
+        assert_eq!(state, vec!["b"]);
+        assert!(!path.exists());
+        assert!(store.exists().unwrap());
+
+        // Corrupt files are moved aside.
+        fs::write(dir.join("other.json"), "[\"c\", ").unwrap();
+        let (store, state): (Store, Vec<String>) = open(&dir, "other").unwrap();
+        assert!(state.is_empty());
+        assert_eq!(store.warnings.len(), 1);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
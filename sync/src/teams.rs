@@ -0,0 +1,173 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::slack::content_hash;
+use super::state::{self, Store};
+use super::{display_name, SyncError, UnicodeEmoji};
+use async_trait::async_trait;
+use base::{Day, Rewrite, TeamsConfig};
+use std::path::Path;
+use time::Date;
+
+pub trait TeamsCard {
+    fn to_card(&self, rewrites: &[Rewrite]) -> serde_json::Value;
+    fn date(&self) -> Date;
+}
+
+impl TeamsCard for Day {
+    fn to_card(&self, rewrites: &[Rewrite]) -> serde_json::Value {
+        let mut body = vec![serde_json::json!({
+            "type": "TextBlock",
+            "text": self.date.to_string(),
+            "weight": "Bolder",
+            "size": "Medium",
+        })];
+
+        for task in &self.tasks {
+            if task.subtasks.is_empty() {
+                body.push(text_block(format!(
+                    "{} {}",
+                    task.state.to_emoji(),
+                    display_name(self, task, rewrites)
+                )));
+            } else {
+                body.push(serde_json::json!({
+                    "type": "TextBlock",
+                    "text": display_name(self, task, rewrites),
+                    "weight": "Bolder",
+                    "spacing": "Medium",
+                    "wrap": true,
+                }));
+                for subtask in &task.subtasks {
+                    body.push(text_block(format!(
+                        "{} {}",
+                        subtask.state.to_emoji(),
+                        display_name(self, subtask, rewrites)
+                    )));
+                }
+            }
+        }
+
+        serde_json::json!({
+            "type": "AdaptiveCard",
+            "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+            "version": "1.4",
+            "body": body,
+        })
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+fn text_block(text: String) -> serde_json::Value {
+    serde_json::json!({
+        "type": "TextBlock",
+        "text": text,
+        "spacing": "None",
+        "wrap": true,
+    })
+}
+
+pub type TeamsSyncState = Vec<TeamsDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TeamsDayState {
+    pub date: Date,
+    /// The hash of the card that was posted last, see `content_hash`.
+    #[serde(default)]
+    pub hash: String,
+}
+
+/// Posts the day as an Adaptive Card to an incoming webhook of a channel.
+/// Webhooks can't edit what they posted, so a day that changed is posted
+/// again as a new card, and one that didn't isn't posted at all.
+pub struct Teams {
+    client: reqwest::Client,
+    config: TeamsConfig,
+    store: Store,
+    state: TeamsSyncState,
+}
+
+impl Teams {
+    pub fn new(
+        state_dir: &Path,
+        config: &TeamsConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "teams")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    pub async fn sync_card<C>(
+        &mut self,
+        card: &C,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError>
+    where
+        C: TeamsCard,
+    {
+        let date = card.date();
+        let card = card.to_card(rewrites);
+        let hash = content_hash("", std::slice::from_ref(&card));
+        let index = self.state.iter().position(|state| state.date == date);
+        let action = match index {
+            Some(index) if self.state[index].hash == hash => return Ok(SinkAction::Skipped),
+            Some(_) => SinkAction::Updated,
+            None => SinkAction::Created,
+        };
+
+        self.client
+            .post(&self.config.webhook_url)
+            .json(&message_body(card))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        match index {
+            Some(index) => self.state[index].hash = hash,
+            None => self.state.push(TeamsDayState { date, hash }),
+        }
+        self.write_state()?;
+        Ok(action)
+    }
+}
+
+fn message_body(card: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "type": "message",
+        "attachments": [
+            {
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "contentUrl": null,
+                "content": card,
+            }
+        ]
+    })
+}
+
+#[async_trait]
+impl Sink for Teams {
+    fn name(&self) -> &str {
+        "teams"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let rewrites = self.config.rewrites.clone();
+        let action = self.sync_card(day, &rewrites).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
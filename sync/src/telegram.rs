@@ -0,0 +1,171 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::state::{self, Store};
+use super::{render_text, SyncError, UnicodeEmoji};
+use async_trait::async_trait;
+use base::{Day, Rewrite, TaskState, TelegramConfig};
+use serde::Deserialize;
+use std::path::Path;
+use time::Date;
+
+pub trait TelegramMessage {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String;
+    fn date(&self) -> Date;
+}
+
+impl TelegramMessage for Day {
+    fn to_message(&self, rewrites: &[Rewrite]) -> String {
+        let text = render_text(self, rewrites, TaskState::to_emoji, |name| name.to_string());
+        format!("{}\n\n{}", self.date, text.trim_end())
+    }
+
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+pub type TelegramSyncState = Vec<TelegramDayState>;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TelegramDayState {
+    pub chat_id: String,
+    pub message_id: i64,
+    pub date: Date,
+}
+
+pub struct Telegram {
+    client: reqwest::Client,
+    config: TelegramConfig,
+    store: Store,
+    state: TelegramSyncState,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub ok: bool,
+    pub description: Option<String>,
+    pub result: Option<Message>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Message {
+    pub message_id: i64,
+}
+
+impl Telegram {
+    pub fn new(
+        state_dir: &Path,
+        config: &TelegramConfig,
+        client: reqwest::Client,
+    ) -> Result<Self, SyncError> {
+        let (store, state) = state::open(state_dir, "telegram")?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            store,
+            state,
+        })
+    }
+
+    fn write_state(&self) -> Result<(), SyncError> {
+        self.store.write(0, &self.state)
+    }
+
+    async fn call(&self, method: &str, content: serde_json::Value) -> Result<Response, SyncError> {
+        let result = self
+            .client
+            .post(format!(
+                "https://api.telegram.org/bot{}/{}",
+                self.config.token, method
+            ))
+            .json(&content)
+            .send()
+            .await?
+            .json::<Response>()
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn sync_message<M>(
+        &mut self,
+        message: &M,
+        rewrites: &[Rewrite],
+    ) -> Result<SinkAction, SyncError>
+    where
+        M: TelegramMessage,
+    {
+        let date = message.date();
+        let state = self
+            .state
+            .iter()
+            .find(|state| state.date == date && state.chat_id == self.config.chat_id);
+        let text = message.to_message(rewrites);
+
+        match state {
+            Some(state) => {
+                let result = self
+                    .call(
+                        "editMessageText",
+                        serde_json::json!({
+                            "chat_id": &self.config.chat_id,
+                            "message_id": state.message_id,
+                            "text": text,
+                        }),
+                    )
+                    .await?;
+
+                // Telegram refuses edits that don't change anything, that's fine for us.
+                let unchanged = result
+                    .description
+                    .as_deref()
+                    .is_some_and(|description| description.contains("message is not modified"));
+                if !result.ok && !unchanged {
+                    return Err(SyncError::Telegram(result.description.unwrap_or_default()));
+                }
+                Ok(SinkAction::Updated)
+            }
+            None => {
+                let result = self
+                    .call(
+                        "sendMessage",
+                        serde_json::json!({
+                            "chat_id": &self.config.chat_id,
+                            "text": text,
+                        }),
+                    )
+                    .await?;
+
+                match result.result {
+                    Some(sent) if result.ok => {
+                        self.state.push(TelegramDayState {
+                            chat_id: self.config.chat_id.clone(),
+                            message_id: sent.message_id,
+                            date,
+                        });
+                        self.write_state()?;
+                        Ok(SinkAction::Created)
+                    }
+                    _ => Err(SyncError::Telegram(result.description.unwrap_or_default())),
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.store.warnings)
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        let rewrites = self.config.rewrites.clone();
+        let action = self.sync_message(day, &rewrites).await?;
+        Ok(SinkReport::new(self.name(), action))
+    }
+}
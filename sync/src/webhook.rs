@@ -0,0 +1,65 @@
+use super::sink::{Sink, SinkAction, SinkReport};
+use super::SyncError;
+use async_trait::async_trait;
+use base::{Day, Task, WebhookConfig};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct Payload<'a> {
+    pub date: String,
+    pub tasks: &'a [Task],
+    pub notes: &'a str,
+}
+
+impl<'a> From<&'a Day> for Payload<'a> {
+    fn from(day: &'a Day) -> Self {
+        Payload {
+            date: day.date.to_string(),
+            tasks: &day.tasks,
+            notes: &day.notes,
+        }
+    }
+}
+
+/// Posts the full day as JSON on every sync. There's no state to keep track
+/// of, the receiving end decides what to do with repeated deliveries.
+pub struct Webhook {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl Webhook {
+    pub fn new(config: &WebhookConfig, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            config: config.clone(),
+        }
+    }
+
+    pub async fn post(&self, day: &Day) -> Result<(), SyncError> {
+        let mut request = self.client.post(&self.config.url);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+
+        request
+            .json(&Payload::from(day))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn sync(&mut self, day: &Day) -> Result<SinkReport, SyncError> {
+        self.post(day).await?;
+        Ok(SinkReport::new(self.name(), SinkAction::Created))
+    }
+}